@@ -1,17 +1,109 @@
+use super::output::Output;
 use super::*;
-use crate::utils::auth::get_current_username;
+use crate::session::Session;
 use crate::utils::prompt::UserPrompter;
+use serde::Serialize;
 use std::path::{Path, PathBuf};
 
 /// CommandResult enum to represent the result of a command execution
 pub enum CommandResult {
-    Output(String),
-    ChangeDirectory(PathBuf, String),
+    Output(Output),
+    /// The command failed, the output says why
+    Error(Output),
+    ChangeDirectory(PathBuf, Output),
+    /// Image or audio object to show next to the output: (path relative to HOME, MIME type, message)
+    ShowAsset(PathBuf, String, Output),
     Clear,
     Exit,
     NotFound,
 }
 
+impl CommandResult {
+    /// Result of a command which tells what it did, or why it failed.
+    pub fn from_message(result: Result<String, String>) -> Self {
+        match result {
+            Ok(msg) => CommandResult::Output(msg.into()),
+            Err(msg) => CommandResult::Error(Output::new().error(msg)),
+        }
+    }
+
+    /// What frontends show for `NotFound`.
+    pub fn not_found_output() -> Output {
+        Output::new()
+            .error("Command not found.")
+            .hint(" Try `help`.")
+    }
+}
+
+/// CommandResult as sent to the web frontend, by the server or the browser build.
+#[derive(Serialize)]
+pub struct CommandResponse {
+    /// Plain text of the output, for clients which do not show the segments
+    pub output: String,
+    pub segments: Output,
+    /// The command failed
+    pub error: bool,
+    /// The terminal is to be cleared, or closed
+    pub clear: bool,
+    pub exit: bool,
+    pub new_current_dir: Option<String>,
+    /// Image or audio to show inline, see `Session::read_asset`
+    pub asset: Option<AssetResponse>,
+}
+
+#[derive(Serialize)]
+pub struct AssetResponse {
+    pub path: String,
+    pub mime: String,
+}
+
+impl From<CommandResult> for CommandResponse {
+    fn from(result: CommandResult) -> Self {
+        let mut response = Self {
+            output: String::new(),
+            segments: Output::new(),
+            error: false,
+            clear: false,
+            exit: false,
+            new_current_dir: None,
+            asset: None,
+        };
+        let segments = match result {
+            CommandResult::Output(output) => output,
+            CommandResult::Error(output) => {
+                response.error = true;
+                output
+            }
+            CommandResult::ChangeDirectory(new_dir, message) => {
+                response.new_current_dir = Some(new_dir.display().to_string());
+                message
+            }
+            CommandResult::ShowAsset(path, mime, message) => {
+                response.asset = Some(AssetResponse {
+                    path: path.display().to_string(),
+                    mime,
+                });
+                message
+            }
+            CommandResult::Clear => {
+                response.clear = true;
+                Output::new()
+            }
+            CommandResult::Exit => {
+                response.exit = true;
+                Output::new()
+            }
+            CommandResult::NotFound => {
+                response.error = true;
+                CommandResult::not_found_output()
+            }
+        };
+        response.output = segments.to_string();
+        response.segments = segments;
+        response
+    }
+}
+
 pub static RESTRICTED_FILES: [&str; 5] = [
     ".dir_info",
     "info.json",
@@ -46,74 +138,108 @@ pub fn check_dir_info(path: &Path) -> bool {
     RESTRICTED_FILES.iter().any(|&file| path_str.contains(file))
 }
 
-/// Command manager that processes commands and processed to return appropriate outputs
+/// Command manager that processes commands and processed to return appropriate outputs.
+/// Commands run where the session is and as its user, asking through the prompter.
 pub fn cmd_manager(
+    session: &Session,
     parts: &[&str],
-    current_dir: &Path,
-    root_dir: &Path,
     prompter: &mut dyn UserPrompter,
 ) -> CommandResult {
     if parts.is_empty() {
         return CommandResult::NotFound;
     }
+    let fs = session.fs();
+    let current_dir = session.current_dir.as_path();
+    let root_dir = session.root();
 
-    match parts[0] {
-        "echo" => CommandResult::Output(echo(&parts[1..])),
-        "whoami" => match get_current_username() {
-            Some(name) => CommandResult::Output(format!("Current user: {name}")),
-            None => {
-                CommandResult::Output("Current user: [Not logged in] (Default User)".to_string())
-            }
-        },
-        "go" => {
-            let (new_dir, msg) = go(&parts[1..], &current_dir.to_path_buf(), root_dir);
-            CommandResult::ChangeDirectory(new_dir, msg)
-        }
-        "ls" => CommandResult::Output(ls(&parts[1..], current_dir, root_dir)),
-        "read" => CommandResult::Output(read(&parts[1..], current_dir, root_dir)),
-        "copy" => {
-            let msg = copy::copy(&parts[1..], current_dir, root_dir, prompter);
-            CommandResult::Output(msg)
-        }
-        "tap" => {
-            let msg = tap(&parts[1..], current_dir, root_dir);
-            CommandResult::Output(msg)
+    // Enforce the event policy of the Sekai, if it has one
+    let policy = match session.sekai.event_policy() {
+        Ok(policy) => policy,
+        Err(e) => return CommandResult::Error(Output::new().error(format!("{}: {e}", parts[0]))),
+    };
+    if let Some(policy) = policy {
+        if policy.is_restricted()
+            && !session
+                .username()
+                .is_some_and(|name| policy.is_user_allowed(name))
+        {
+            return CommandResult::Error(Output::new().error(format!(
+                "{}: Only allowed users can play this Sekai.",
+                parts[0]
+            )));
         }
-        "del" => {
-            let msg = del(&parts[1..], current_dir, root_dir, prompter);
-            CommandResult::Output(msg)
+        if let Err(msg) = policy.check_command(parts[0]) {
+            return CommandResult::Error(Output::new().error(format!("{}: {msg}", parts[0])));
         }
-        "whereami" => CommandResult::Output(whereami(current_dir, root_dir)),
+    }
+
+    match parts[0] {
+        "echo" => CommandResult::Output(echo(&parts[1..]).into()),
+        "whoami" => match session.username() {
+            Some(name) => CommandResult::Output(format!("Current user: {name}").into()),
+            None => CommandResult::Output("Current user: [Not logged in] (Default User)".into()),
+        },
+        "go" => match go(fs, &parts[1..], &current_dir.to_path_buf(), root_dir) {
+            Ok((new_dir, msg)) => CommandResult::ChangeDirectory(new_dir, msg.into()),
+            Err(msg) => CommandResult::Error(Output::new().error(msg)),
+        },
+        "ls" => match ls(fs, &parts[1..], current_dir, root_dir) {
+            Ok(output) => CommandResult::Output(output),
+            Err(output) => CommandResult::Error(output),
+        },
+        "read" => read(fs, &parts[1..], current_dir, root_dir),
+        "copy" => CommandResult::from_message(copy::copy(
+            fs,
+            &parts[1..],
+            current_dir,
+            root_dir,
+            prompter,
+        )),
+        "tap" => CommandResult::from_message(tap(fs, &parts[1..], current_dir, root_dir)),
+        "del" => CommandResult::from_message(del(fs, &parts[1..], current_dir, root_dir, prompter)),
+        "whereami" => CommandResult::Output(Output::new().path(whereami(current_dir, root_dir))),
         "help" => {
             if parts.len() > 1 {
                 match help::get_command_help(parts[1]) {
-                    Some(msg) => CommandResult::Output(msg.to_string()),
-                    None => CommandResult::Output(format!("No help available for '{}'", parts[1])),
+                    Some(msg) => CommandResult::Output(msg.into()),
+                    None => CommandResult::Error(
+                        Output::new().error(format!("No help available for '{}'", parts[1])),
+                    ),
                 }
             } else {
-                CommandResult::Output(help(""))
+                CommandResult::Output(help("").into())
             }
         }
         "clear" => CommandResult::Clear,
         "exit" => match exit(prompter) {
             (true, _) => CommandResult::Exit,
-            (false, msg) => CommandResult::Output(msg),
+            (false, msg) => CommandResult::Output(msg.into()),
         },
-        "restore" => CommandResult::Output(restore::restore(&parts[1..], root_dir, prompter)),
-        "save" => CommandResult::Output(save::save(&parts[1..], root_dir)),
+        "restore" => {
+            CommandResult::from_message(restore::restore(fs, &parts[1..], root_dir, prompter))
+        }
+        "save" => CommandResult::from_message(save::save(fs, &parts[1..], root_dir)),
         "solve" => {
-            let msg = solve::solve(&parts[1..], current_dir, root_dir, prompter);
-            CommandResult::Output(msg)
+            let user = session.user.as_ref();
+            let msg = solve::solve(fs, &parts[1..], current_dir, root_dir, user, prompter);
+            CommandResult::from_message(msg)
         }
         "unlock" => {
-            let msg = unlock::unlock(&parts[1..], current_dir, root_dir, prompter);
-            CommandResult::Output(msg)
+            let user = session.user.as_ref();
+            let msg = unlock::unlock(fs, &parts[1..], current_dir, root_dir, user, prompter);
+            CommandResult::from_message(msg)
         }
+        "scores" => CommandResult::Output(scores::scores(&parts[1..], session.username()).into()),
         "dev" => {
-            //check if dev mode allowed else give error-to be added
+            if !session.dev_mode {
+                return CommandResult::Error(
+                    Output::new().error("dev: Developer commands are only available in Dev Mode."),
+                );
+            }
             //dev is a directory inside commands inside it dev.rs contains fn dev
-            let msg = dev::dev_main::dev(&parts[1..], current_dir, root_dir, prompter);
-            CommandResult::Output(msg)
+            let user = session.user.as_ref();
+            let msg = dev::dev_main::dev(fs, &parts[1..], current_dir, root_dir, user, prompter);
+            CommandResult::from_message(msg)
         }
         _ => CommandResult::NotFound,
     }
@@ -6,8 +6,8 @@ use crate::epr_log_error;
 use crate::metainfo::info_reader::*;
 use crate::metainfo::lock_perm;
 use crate::metainfo::valid_sekai::create_dir_info;
+use crate::sekai_fs::SekaiFs;
 use crate::utils::{log, prompt::UserPrompter};
-use std::fs;
 use std::io::{self, Error};
 use std::path::{Path, PathBuf};
 
@@ -29,11 +29,10 @@ Examples:
 - copy -f /path/to/existing_file.txt /path/to/new_file.txt  # Force overwrite existing file
 "#;
 
-fn _print_dir_contents(path: &Path) {
-    if let Ok(entries) = fs::read_dir(path) {
-        for entry in entries.filter_map(Result::ok) {
-            let entry_path = entry.path();
-            if entry_path.is_dir() {
+fn _print_dir_contents(fs: &dyn SekaiFs, path: &Path) {
+    if let Ok(entries) = fs.read_dir(path) {
+        for entry_path in entries {
+            if fs.is_dir(&entry_path) {
                 println!("{} (directory)", entry_path.display());
             } else {
                 println!("{}", entry_path.display());
@@ -46,6 +45,7 @@ fn _print_dir_contents(path: &Path) {
 
 /// Validates paths for copy/move operations
 fn validate_paths(
+    fs: &dyn SekaiFs,
     src: &Path,
     dest: &Path,
     current_dir: &Path,
@@ -82,7 +82,7 @@ fn validate_paths(
     }
 
     // Check if source exists
-    if !src_normalized.exists() {
+    if !fs.exists(&src_normalized) {
         return Err(format!(
             "copy: {}: No such file or directory",
             src.display()
@@ -93,7 +93,7 @@ fn validate_paths(
 }
 
 /// Moves object metadata from source to destination in their respective info.json files
-fn move_obj_info(src: &Path, dest: &Path, cut: bool) -> Result<(), String> {
+fn move_obj_info(fs: &dyn SekaiFs, src: &Path, dest: &Path, cut: bool) -> Result<(), String> {
     // Get source and destination parent directories
     let src_parent = src.parent().ok_or("Source has no parent directory")?;
     let dest_parent = dest.parent().ok_or("Destination has no parent directory")?;
@@ -115,19 +115,19 @@ fn move_obj_info(src: &Path, dest: &Path, cut: bool) -> Result<(), String> {
         .ok_or("Invalid destination object name")?;
 
     // Read source object info (if exists)
-    let src_obj_info = match read_get_obj_info(&src_info_path, src_obj_name) {
+    let src_obj_info = match read_get_obj_info(fs, &src_info_path, src_obj_name) {
         Ok(info) => info,
         Err(_) => return Ok(()), // No metadata to move
     };
 
     // Remove source object info
     if cut {
-        del_obj_from_info(src, src_obj_name)
+        del_obj_from_info(fs, src, src_obj_name)
             .map_err(|e| format!("Failed to remove source metadata: {e}"))?;
     }
 
     // Add destination object info with same properties
-    add_obj_to_info(dest, dest_obj_name, Some(src_obj_info.properties))
+    add_obj_to_info(fs, dest, dest_obj_name, Some(src_obj_info.properties))
         .map_err(|e| format!("Failed to add destination metadata: {e}"))?;
 
     Ok(())
@@ -135,25 +135,26 @@ fn move_obj_info(src: &Path, dest: &Path, cut: bool) -> Result<(), String> {
 
 /// Copies a file from source to destination
 fn copy_file(
+    fs: &dyn SekaiFs,
     src: &Path,
     dest: &Path,
     root_dir: &Path,
     force: bool,
     cut: bool,
 ) -> io::Result<String> {
-    if dest.exists() {
+    if fs.exists(dest) {
         if !force {
             return Err(Error::new(
                 io::ErrorKind::AlreadyExists,
                 "Destination file exists (use -f to overwrite)",
             ));
         }
-        fs::remove_file(dest)?;
+        fs.remove_file(dest)?;
     }
-    fs::copy(src, dest)?;
+    fs.copy(src, dest)?;
 
     // Copy metadata
-    if let Err(e) = move_obj_info(src, dest, false) {
+    if let Err(e) = move_obj_info(fs, src, dest, false) {
         // false = copy operation
         log::log_warning(
             "copy",
@@ -171,25 +172,26 @@ fn copy_file(
 
 /// Recursively copies a directory
 fn copy_directory(
+    fs: &dyn SekaiFs,
     src: &Path,
     dest: &Path,
     root_dir: &Path,
     force: bool,
     cut: bool,
 ) -> io::Result<String> {
-    if dest.exists() {
+    if fs.exists(dest) {
         if !force {
             return Err(Error::new(
                 io::ErrorKind::AlreadyExists,
                 "Destination directory exists (use -f to overwrite)",
             ));
         }
-        fs::remove_dir_all(dest)?;
+        fs.remove_dir_all(dest)?;
     }
-    fs::create_dir_all(dest)?;
+    fs.create_dir_all(dest)?;
 
     // First copy directory metadata
-    if let Err(e) = move_obj_info(src, dest, false) {
+    if let Err(e) = move_obj_info(fs, src, dest, false) {
         // false = copy operation
         log::log_warning(
             "copy",
@@ -198,20 +200,20 @@ fn copy_directory(
     }
 
     // Then copy contents
-    for entry in fs::read_dir(src)? {
-        let entry = entry?;
-        let entry_path = entry.path();
-        let entry_name = entry.file_name();
+    for entry_path in fs.read_dir(src)? {
+        let Some(entry_name) = entry_path.file_name() else {
+            continue;
+        };
 
         if entry_name == ".dir_info" {
             continue;
         }
 
         let new_path = dest.join(entry_name);
-        if entry_path.is_dir() {
-            copy_directory(&entry_path, &new_path, root_dir, force, cut)?;
+        if fs.is_dir(&entry_path) {
+            copy_directory(fs, &entry_path, &new_path, root_dir, force, cut)?;
         } else {
-            copy_file(&entry_path, &new_path, root_dir, force, cut)?;
+            copy_file(fs, &entry_path, &new_path, root_dir, force, cut)?;
         }
     }
 
@@ -225,6 +227,7 @@ fn copy_directory(
 
 /// Moves a file or directory (cut/paste)
 fn move_item(
+    fs: &dyn SekaiFs,
     src: &Path,
     dest: &Path,
     root_dir: &Path,
@@ -238,21 +241,21 @@ fn move_item(
         ));
     }
 
-    if src.is_dir() {
+    if fs.is_dir(src) {
         if !recursive {
             return Err(Error::other("Use -r for directories"));
         }
 
-        if dest.exists() && !force {
+        if fs.exists(dest) && !force {
             return Err(Error::new(
                 io::ErrorKind::AlreadyExists,
                 "Use -f to overwrite",
             ));
         }
-        fs::create_dir_all(dest)?;
+        fs.create_dir_all(dest)?;
 
         // Create .dir_info for destination first
-        if !create_dir_info(dest, false) {
+        if !create_dir_info(fs, dest, root_dir) {
             return Err(Error::other(format!(
                 "Failed to create .dir_info in {}",
                 display_relative_path(dest, root_dir)
@@ -260,7 +263,7 @@ fn move_item(
         }
 
         // First move the directory metadata
-        if let Err(e) = move_obj_info(src, dest, true) {
+        if let Err(e) = move_obj_info(fs, src, dest, true) {
             log::log_warning(
                 "move",
                 &format!(
@@ -272,10 +275,10 @@ fn move_item(
         }
 
         // Process all contents
-        for entry in fs::read_dir(src)? {
-            let entry = entry?;
-            let entry_path = entry.path();
-            let entry_name = entry.file_name();
+        for entry_path in fs.read_dir(src)? {
+            let Some(entry_name) = entry_path.file_name() else {
+                continue;
+            };
 
             if entry_name == ".dir_info" {
                 continue;
@@ -284,7 +287,7 @@ fn move_item(
             let new_path = dest.join(entry_name);
 
             // Handle metadata first for each item
-            if let Err(e) = move_obj_info(&entry_path, &new_path, true) {
+            if let Err(e) = move_obj_info(fs, &entry_path, &new_path, true) {
                 log::log_warning(
                     "move",
                     &format!("Failed to move metadata for {}: {}", new_path.display(), e),
@@ -292,9 +295,9 @@ fn move_item(
             }
 
             // Then handle the actual file/directory move
-            if entry_path.is_dir() {
-                fs::create_dir_all(&new_path)?;
-                if !create_dir_info(&new_path, false) {
+            if fs.is_dir(&entry_path) {
+                fs.create_dir_all(&new_path)?;
+                if !create_dir_info(fs, &new_path, root_dir) {
                     return Err(Error::other(format!(
                         "Failed to create .dir_info in {}",
                         display_relative_path(&new_path, root_dir)
@@ -302,21 +305,21 @@ fn move_item(
                 }
 
                 // Recursively move subdirectory
-                move_item(&entry_path, &new_path, root_dir, true, force)?;
-                fs::remove_dir_all(&entry_path)?;
+                move_item(fs, &entry_path, &new_path, root_dir, true, force)?;
+                fs.remove_dir_all(&entry_path)?;
             } else {
-                fs::rename(&entry_path, &new_path).or_else(|_| {
-                    copy_file(&entry_path, &new_path, root_dir, force, true)?;
-                    fs::remove_file(&entry_path)
+                fs.rename(&entry_path, &new_path).or_else(|_| {
+                    copy_file(fs, &entry_path, &new_path, root_dir, force, true)?;
+                    fs.remove_file(&entry_path)
                 })?;
             }
         }
 
         // Clean up source directory
-        fs::remove_dir_all(src)?;
+        fs.remove_dir_all(src)?;
     } else {
         // Handle file move
-        if dest.exists() && !force {
+        if fs.exists(dest) && !force {
             return Err(Error::new(
                 io::ErrorKind::AlreadyExists,
                 "Use -f to overwrite",
@@ -324,7 +327,7 @@ fn move_item(
         }
 
         // Move metadata first
-        if let Err(e) = move_obj_info(src, dest, true) {
+        if let Err(e) = move_obj_info(fs, src, dest, true) {
             log::log_warning(
                 "move",
                 &format!("Failed to move file metadata for {}: {}", dest.display(), e),
@@ -332,9 +335,9 @@ fn move_item(
         }
 
         // Then move the file
-        fs::rename(src, dest).or_else(|_| {
-            copy_file(src, dest, root_dir, force, true)?;
-            fs::remove_file(src)
+        fs.rename(src, dest).or_else(|_| {
+            copy_file(fs, src, dest, root_dir, force, true)?;
+            fs.remove_file(src)
         })?;
     }
 
@@ -346,21 +349,17 @@ fn move_item(
 }
 
 /// Helper to delete directory contents except .dir_info
-fn delete_directory_contents(path: &Path) -> io::Result<()> {
-    for entry in fs::read_dir(path)? {
-        let entry = entry?;
-        let entry_path = entry.path();
-        let entry_name = entry.file_name();
-
+fn delete_directory_contents(fs: &dyn SekaiFs, path: &Path) -> io::Result<()> {
+    for entry_path in fs.read_dir(path)? {
         // Skip .dir_info
-        if entry_name == ".dir_info" {
+        if entry_path.ends_with(".dir_info") {
             continue;
         }
 
-        if entry_path.is_dir() {
-            fs::remove_dir_all(&entry_path)?;
+        if fs.is_dir(&entry_path) {
+            fs.remove_dir_all(&entry_path)?;
         } else {
-            fs::remove_file(&entry_path)?;
+            fs.remove_file(&entry_path)?;
         }
     }
     Ok(())
@@ -368,11 +367,12 @@ fn delete_directory_contents(path: &Path) -> io::Result<()> {
 
 /// Main copy command function
 pub fn copy(
+    fs: &dyn SekaiFs,
     args: &[&str],
     current_dir: &Path,
     root_dir: &Path,
     prompter: &mut dyn UserPrompter,
-) -> String {
+) -> Result<String, String> {
     let valid_flags = vec![
         "-x",
         "--cut",
@@ -406,7 +406,7 @@ pub fn copy(
                 .collect();
 
             if paths.len() != 2 {
-                return "copy: Requires exactly two paths (source and destination)".to_string();
+                return Err("copy: Requires exactly two paths (source and destination)".to_string());
             }
 
             // handle source and destination paths, and flags
@@ -418,58 +418,53 @@ pub fn copy(
             // If restricted file/directory used in paths, return error
             for pth in [&src, &dest] {
                 if check_dir_info(pth) {
-                    return "copy: Cannot copy/refer restricted file or directory. Operation Not Allowed."
-                        .to_string();
+                    return Err("copy: Cannot copy/refer restricted file or directory. Operation Not Allowed."
+                        .to_string());
                 }
             }
 
             // Prompt for force confirmation
             if force && !prompter.confirm("Are you sure you want to force overwrite files?") {
-                return "Operation of force overwriting cancelled. No files copied/moved."
-                    .to_string();
+                return Ok(
+                    "Operation of force overwriting cancelled. No files copied/moved.".to_string(),
+                );
             }
 
             // Validate paths and perform operations
-            match validate_paths(src, dest, current_dir, root_dir) {
-                Ok((src_path, dest_path)) => {
-                    // Operation allowed only if paths are not locked
-                    for pth in [&src_path, &dest_path] {
-                        if let Err(e) = lock_perm::operation_locked_perm(
-                            pth,
-                            "copy",
-                            "Cannot copy/move locked file/directory. Unlock it first.",
-                        ) {
-                            return e;
-                        }
-                    }
-                    let result = if cut {
-                        move_item(&src_path, &dest_path, root_dir, recursive, force)
-                    } else if src_path.is_dir() && !recursive {
-                        Err(Error::other("Cannot copy directory without -r flag"))
-                    } else if src_path.is_dir() {
-                        copy_directory(&src_path, &dest_path, root_dir, force, false)
-                    } else {
-                        copy_file(&src_path, &dest_path, root_dir, force, false).map(|_| {
-                            format!(
-                                "Copied {} to {}",
-                                display_relative_path(&src_path, root_dir),
-                                display_relative_path(&dest_path, root_dir)
-                            )
-                        })
-                    };
-
-                    match result {
-                        Ok(msg) => msg,
-                        Err(e) => format!("copy: {e}"),
-                    }
-                }
-                Err(e) => e,
+            let (src_path, dest_path) = validate_paths(fs, src, dest, current_dir, root_dir)?;
+            // Operation allowed only if paths are not locked
+            for pth in [&src_path, &dest_path] {
+                lock_perm::operation_locked_perm(
+                    fs,
+                    pth,
+                    root_dir,
+                    "copy",
+                    "Cannot copy/move locked file/directory. Unlock it first.",
+                )?;
             }
+            let result = if cut {
+                move_item(fs, &src_path, &dest_path, root_dir, recursive, force)
+            } else if fs.is_dir(&src_path) && !recursive {
+                Err(Error::other("Cannot copy directory without -r flag"))
+            } else if fs.is_dir(&src_path) {
+                copy_directory(fs, &src_path, &dest_path, root_dir, force, false)
+            } else {
+                copy_file(fs, &src_path, &dest_path, root_dir, force, false).map(|_| {
+                    format!(
+                        "Copied {} to {}",
+                        display_relative_path(&src_path, root_dir),
+                        display_relative_path(&dest_path, root_dir)
+                    )
+                })
+            };
+            result.map_err(|e| format!("copy: {e}"))
         }
         Err(e) => match &e[..] {
-            "help" => HELP_TXT.to_string(),
-            "unknown" => "copy: unknown flag\nTry 'help copy' for more information.".to_string(),
-            _ => "Error parsing arguments. Try 'help copy' for more information.".to_string(),
+            "help" => Ok(HELP_TXT.to_string()),
+            "unknown" => {
+                Err("copy: unknown flag\nTry 'help copy' for more information.".to_string())
+            }
+            _ => Err("Error parsing arguments. Try 'help copy' for more information.".to_string()),
         },
     }
 }
@@ -3,8 +3,8 @@ use super::cmds::{check_dir_info, normalize_path};
 use super::display_relative_path;
 use crate::metainfo::info_reader::del_obj_from_info;
 use crate::metainfo::lock_perm;
+use crate::sekai_fs::SekaiFs;
 use crate::utils::{log, prompt::UserPrompter};
-use std::fs;
 use std::path::{Path, PathBuf};
 
 pub const HELP_TXT: &str = r#"
@@ -23,53 +23,65 @@ Examples:
 "#;
 
 /// Delete a file at the given path
-pub fn delete_file(path: &Path, root_dir: &Path) -> String {
-    if !path.exists() {
-        return format!(
+pub fn delete_file(fs: &dyn SekaiFs, path: &Path, root_dir: &Path) -> Result<String, String> {
+    if !fs.exists(path) {
+        return Err(format!(
             "del: {}: No such file",
             display_relative_path(path, root_dir)
-        );
+        ));
     }
 
-    if path.is_dir() {
-        return format!(
+    if fs.is_dir(path) {
+        return Err(format!(
             "del: {}: Is a directory (use -d flag)",
             display_relative_path(path, root_dir)
-        );
+        ));
     }
 
-    match std::fs::remove_file(path) {
+    match fs.remove_file(path) {
         Ok(_) => {
             // Delete the object from info.json
             let obj_name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
-            let result = del_obj_from_info(path, obj_name).map_err(|e| e.to_string()); // Convert InfoError to String
+            let result = del_obj_from_info(fs, path, obj_name).map_err(|e| e.to_string()); // Convert InfoError to String
 
             log::log_result("del", result, "Deleting object from info.json");
-            format!("Deleted file: {}", display_relative_path(path, root_dir))
+            Ok(format!(
+                "Deleted file: {}",
+                display_relative_path(path, root_dir)
+            ))
         }
-        Err(e) => format!("del: {}: {}", display_relative_path(path, root_dir), e),
+        Err(e) => Err(format!(
+            "del: {}: {}",
+            display_relative_path(path, root_dir),
+            e
+        )),
     }
 }
 
 /// Delete a directory at the given path
-pub fn delete_directory(path: &Path, root_dir: &Path, force: bool) -> String {
-    if !path.exists() {
-        return format!(
+pub fn delete_directory(
+    fs: &dyn SekaiFs,
+    path: &Path,
+    root_dir: &Path,
+    force: bool,
+) -> Result<String, String> {
+    if !fs.exists(path) {
+        return Err(format!(
             "del: {}: No such directory",
             display_relative_path(path, root_dir)
-        );
+        ));
     }
-    if !path.is_dir() {
-        return format!(
+    if !fs.is_dir(path) {
+        return Err(format!(
             "del: {}: Not a directory",
             display_relative_path(path, root_dir)
-        );
+        ));
     }
 
     // Check if directory only contains .dir_info
     let only_has_dir_info = || {
-        if let Ok(entries) = fs::read_dir(path) {
-            entries.count() == 1 && path.join(".dir_info").exists()
+        if let Ok(entries) = fs.read_dir(path) {
+            entries.len() == 1 && fs.exists(&path.join(".dir_info"))
         } else {
             false
         }
@@ -77,41 +89,50 @@ pub fn delete_directory(path: &Path, root_dir: &Path, force: bool) -> String {
 
     // Handle special case for just .dir_info
     if only_has_dir_info() {
-        let _ = fs::remove_dir_all(path.join(".dir_info"));
-        return match fs::remove_dir(path) {
+        let _ = fs.remove_dir_all(&path.join(".dir_info"));
+        return match fs.remove_dir(path) {
             Ok(_) => {
                 // Delete the object from info.json
                 let obj_name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
-                let result = del_obj_from_info(path, obj_name).map_err(|e| e.to_string()); // Convert InfoError to String
+                let result = del_obj_from_info(fs, path, obj_name).map_err(|e| e.to_string()); // Convert InfoError to String
 
                 log::log_result("del", result, "Deleting object from info.json");
-                format!(
+                Ok(format!(
                     "Deleted directory: {}",
                     display_relative_path(path, root_dir)
-                )
+                ))
             }
-            Err(e) => format!("del: {}: {}", display_relative_path(path, root_dir), e),
+            Err(e) => Err(format!(
+                "del: {}: {}",
+                display_relative_path(path, root_dir),
+                e
+            )),
         };
     }
 
     // Normal deletion
     let result = if force {
-        fs::remove_dir_all(path)
+        fs.remove_dir_all(path)
     } else {
-        fs::remove_dir(path)
+        fs.remove_dir(path)
     };
 
     match result {
-        Ok(_) => format!(
+        Ok(_) => Ok(format!(
             "Deleted directory: {}",
             display_relative_path(path, root_dir)
-        ),
-        Err(e) => format!("del: {}: {}", display_relative_path(path, root_dir), e),
+        )),
+        Err(e) => Err(format!(
+            "del: {}: {}",
+            display_relative_path(path, root_dir),
+            e
+        )),
     }
 }
 
 /// Checks the validity of the deletion path
 fn validate_deletion_path(
+    fs: &dyn SekaiFs,
     path: &Path,
     current_dir: &Path,
     root_dir: &Path,
@@ -131,7 +152,7 @@ fn validate_deletion_path(
     }
 
     // Check if path exists
-    if !full_path.exists() {
+    if !fs.exists(&full_path) {
         log::log_warning("del", "Path does not exist");
         return Err(format!(
             "del: {}: No such file or directory",
@@ -144,11 +165,12 @@ fn validate_deletion_path(
 
 /// Main delete command function
 pub fn del(
+    fs: &dyn SekaiFs,
     args: &[&str],
     current_dir: &Path,
     root_dir: &Path,
     prompter: &mut dyn UserPrompter,
-) -> String {
+) -> Result<String, String> {
     let mut parser = ArgParser::new(&["-d", "--dir", "-f", "--force"]);
 
     let args_string: Vec<String> = args.iter().map(|s| s.to_string()).collect();
@@ -170,45 +192,45 @@ pub fn del(
                 .unwrap_or(&"");
 
             if destination.is_empty() {
-                return "del: No destination specified. Use 'del --help' for usage.".to_string();
+                return Err(
+                    "del: No destination specified. Use 'del --help' for usage.".to_string()
+                );
             }
             if check_dir_info(Path::new(destination)) {
-                return "del: Cannot delete/refer restricted file or directory. Operation Not Allowed."
-                    .to_string();
+                return Err(
+                    "del: Cannot delete/refer restricted file or directory. Operation Not Allowed."
+                        .to_string(),
+                );
             }
 
             if !prompter.confirm(&format!(
                 "Are you sure you want to delete '{destination}'? This action cannot be undone."
             )) {
-                return "Deletion cancelled by user.".to_string();
+                return Ok("Deletion cancelled by user.".to_string());
             }
 
             let destination_path = Path::new(destination);
-            match validate_deletion_path(destination_path, current_dir, root_dir) {
-                Ok(full_path) => {
-                    // Operation permitted only if not locked
-                    if let Err(e) = lock_perm::operation_locked_perm(
-                        &full_path,
-                        "del",
-                        "Cannot delete locked file/directory. Unlock it first.",
-                    ) {
-                        return e;
-                    }
-
-                    let force = args.contains(&"-f") || args.contains(&"--force");
-                    if args.contains(&"-d") || args.contains(&"--dir") || full_path.is_dir() {
-                        delete_directory(&full_path, root_dir, force)
-                    } else {
-                        delete_file(&full_path, root_dir)
-                    }
-                }
-                Err(e) => e,
+            let full_path = validate_deletion_path(fs, destination_path, current_dir, root_dir)?;
+            // Operation permitted only if not locked
+            lock_perm::operation_locked_perm(
+                fs,
+                &full_path,
+                root_dir,
+                "del",
+                "Cannot delete locked file/directory. Unlock it first.",
+            )?;
+
+            let force = args.contains(&"-f") || args.contains(&"--force");
+            if args.contains(&"-d") || args.contains(&"--dir") || fs.is_dir(&full_path) {
+                delete_directory(fs, &full_path, root_dir, force)
+            } else {
+                delete_file(fs, &full_path, root_dir)
             }
         }
         Err(e) => match &e[..] {
-            "help" => HELP_TXT.to_string(),
-            "unknown" => "del: unknown flag\nTry 'help del' for more information.".to_string(),
-            _ => "Error parsing arguments. Try 'help del' for more information.".to_string(),
+            "help" => Ok(HELP_TXT.to_string()),
+            "unknown" => Err("del: unknown flag\nTry 'help del' for more information.".to_string()),
+            _ => Err("Error parsing arguments. Try 'help del' for more information.".to_string()),
         },
     }
 }
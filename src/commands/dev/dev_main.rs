@@ -1,32 +1,24 @@
 use super::{init_info, lock};
+use crate::sekai_fs::SekaiFs;
+use crate::utils::globals::UserInfo;
 use crate::utils::prompt::UserPrompter;
 use std::path::Path;
 pub fn dev(
+    fs: &dyn SekaiFs,
     parts: &[&str],
     current_dir: &Path,
     root_dir: &Path,
+    user: Option<&UserInfo>,
     prompter: &mut dyn UserPrompter,
-) -> String {
+) -> Result<String, String> {
     if parts.is_empty() {
-        return "Command not found".into();
+        return Err("Command not found".into());
     }
 
     match parts[0] {
-        "lock" => {
-            let msg = lock::dev_lock(&parts[1..], current_dir, root_dir, prompter);
-            if msg.is_err() {
-                return msg.err().unwrap();
-            }
-            msg.unwrap()
-        }
-        "info" => {
-            let msg = init_info::dev_info(&parts[1..], current_dir, root_dir);
-            if msg.is_err() {
-                return msg.err().unwrap();
-            }
-            msg.unwrap()
-        }
+        "lock" => lock::dev_lock(fs, &parts[1..], current_dir, root_dir, user, prompter),
+        "info" => init_info::dev_info(fs, &parts[1..], current_dir, root_dir),
 
-        _ => "Invalid dev command".to_string(),
+        _ => Err("Invalid dev command".to_string()),
     }
 }
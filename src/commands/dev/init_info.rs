@@ -1,5 +1,6 @@
 use super::super::argparser::ArgParser;
 use crate::metainfo::info_reader::{read_about, read_location, write_about};
+use crate::sekai_fs::SekaiFs;
 use std::path::Path;
 pub const HELP_TEXT: &str = r#"
 Usage: dev info [OPTIONS_1] <PROPERTY_NAME> <PROPERTY_VALUE(if writing)> 
@@ -14,7 +15,12 @@ Option_1:
 
 Examples:
 "#;
-pub fn dev_info(args: &[&str], current_dir: &Path, root_dir: &Path) -> Result<String, String> {
+pub fn dev_info(
+    fs: &dyn SekaiFs,
+    args: &[&str],
+    current_dir: &Path,
+    root_dir: &Path,
+) -> Result<String, String> {
     // Check if the path is valid and get info path
     let mut write_mode = false;
     let mut read_mode = false;
@@ -52,10 +58,10 @@ pub fn dev_info(args: &[&str], current_dir: &Path, root_dir: &Path) -> Result<St
     if read_mode {
         match args[1] {
             "-a" | "--about" => {
-                return read_about(&info_path);
+                return read_about(fs, &info_path);
             }
             "-l" | "--location" => {
-                return read_location(&info_path);
+                return read_location(fs, &info_path);
             }
             _ => {
                 return Err("Invalid field. Use -a/--about or -l/--location.".to_string());
@@ -70,7 +76,7 @@ pub fn dev_info(args: &[&str], current_dir: &Path, root_dir: &Path) -> Result<St
                 }
                 //join args[2..] to a string
                 property_value = Some(args[2..].join(" "));
-                write_about(&info_path, property_value.unwrap())
+                write_about(fs, &info_path, property_value.unwrap())
             }
             "-l" | "--location" => Err("Cannot write to location field.".to_string()),
             _ => Err("Invalid field. Use -a/--about or -l/--location.".to_string()),
@@ -1,13 +1,18 @@
 use super::super::argparser::ArgParser;
 use super::super::cmds::normalize_path;
+use crate::metainfo::authoring::{Authoring, ChestSolution, LevelSolution, update_authoring};
 use crate::metainfo::info_reader::{
-    del_compare_me_from_info, del_decrypt_me_from_info, read_get_obj_info, set_as_default_obj,
-    update_obj_status,
+    InfoTransaction, read_get_obj_info, set_as_default_obj, update_obj_status,
 };
 use crate::metainfo::read_lock_perm;
-use crate::rns::security::{argonhash, characterise_enc_key, decrypt, encrypt};
-use crate::utils::{auth::get_current_user, log, prompt::UserPrompter};
-use argon2::password_hash::SaltString;
+use crate::rns::security::{
+    argonhash, characterise_enc_key, chest_compare_me, encrypt, level_decrypt_me, read_user_flag,
+};
+use crate::sekai_fs::SekaiFs;
+use crate::utils::globals::UserInfo;
+use crate::utils::relative_deemak_path;
+use crate::utils::{log, prompt::UserPrompter};
+use argon2::password_hash::{SaltString, rand_core::OsRng};
 use std::path::Path;
 
 pub const HELP_TEXT: &str = r#"
@@ -24,9 +29,11 @@ Option_1:
                         Remove level status from a level and delete decrypt_me and compare_me files if they exist:
 "#;
 pub fn dev_lock(
+    fs: &dyn SekaiFs,
     args: &[&str],
     current_dir: &Path,
     root_dir: &Path,
+    user: Option<&UserInfo>,
     prompter: &mut dyn UserPrompter,
 ) -> Result<String, String> {
     // Parse arguments
@@ -46,8 +53,8 @@ pub fn dev_lock(
             }
 
             match args[1] {
-                "-l" | "--level" => dev_make_level(args[2], current_dir, root_dir),
-                "-c" | "--chest" => dev_make_chest(args[2], current_dir, root_dir),
+                "-l" | "--level" => dev_make_level(fs, args[2], current_dir, root_dir),
+                "-c" | "--chest" => dev_make_chest(fs, args[2], current_dir, root_dir),
                 _ => {
                     err_msg += "Invalid type provided. Expected -l for level or -c for chest.";
                     log::log_info("dev_lock", err_msg.as_str());
@@ -66,9 +73,9 @@ pub fn dev_lock(
                     //prompt for solution
 
                     let solution = prompter.input("> Enter your solution for the lock : ");
-                    dev_lock_chest(args[2], &solution, current_dir, root_dir)
+                    dev_lock_chest(fs, args[2], &solution, current_dir, root_dir)
                 }
-                "-u" | "--unlock" => dev_unlock_chest(args[2], current_dir, root_dir),
+                "-u" | "--unlock" => dev_unlock_chest(fs, args[2], current_dir, root_dir),
                 _ => {
                     err_msg += "Invalid status provided. Expected -l for lock or -u for unlock.";
                     log::log_info("dev_lock", err_msg.as_str());
@@ -87,9 +94,12 @@ pub fn dev_lock(
             let solution = prompter.input("> Enter your solution for the level lock : ");
             let flag = prompter.input("> Enter your flag for the lock : ");
             dev_create_level_lock(
+                fs,
                 &solve_from_path,
                 &set_lock_to_path,
                 current_dir,
+                root_dir,
+                user,
                 &solution,
                 &flag,
             )
@@ -101,7 +111,7 @@ pub fn dev_lock(
                 return Err(err_msg);
             }
             let path_to_level = normalize_path(&current_dir.join(args[1]));
-            dev_remove_level_lock(&path_to_level, current_dir, root_dir)
+            dev_remove_level_lock(fs, &path_to_level, current_dir, root_dir)
         }
         _ => Err(format!(
             "Invalid option: {}. Use -t, --type, -s, --status, -ll, --level-lock or -rm, --rm-level-lock",
@@ -110,6 +120,7 @@ pub fn dev_lock(
     }
 }
 fn dev_make_level(
+    fs: &dyn SekaiFs,
     path_to_obj: &str,
     current_dir: &Path,
     root_dir: &Path,
@@ -120,18 +131,18 @@ fn dev_make_level(
         .file_name()
         .and_then(|s| s.to_str())
         .ok_or("Invalid object name")?;
-    if !path.exists() {
+    if !fs.exists(&path) {
         return Err(format!("Path does not exist: {path_to_obj}"));
     }
     let info_path = path.parent().unwrap().join(".dir_info/info.json");
-    if !info_path.exists() {
+    if !fs.exists(&info_path) {
         return Err(format!(
             "Info file does not exist at: {}",
             info_path.display()
         ));
     }
 
-    let lock_perm = read_lock_perm(&path);
+    let lock_perm = read_lock_perm(fs, &path);
     if lock_perm.is_err() {
         return Err(format!(
             "Failed to read lock permissions for the level: {}",
@@ -142,38 +153,26 @@ fn dev_make_level(
     if is_level {
         return Err(format!("Object at {} is already a level.", path.display()));
     }
-    let level_name = &path
-        .parent()
-        .and_then(|s| s.file_name())
-        .and_then(|s| s.to_str())
-        .ok_or("Invalid level name")?;
-
-    let attempt = update_obj_status(
-        &path,
-        obj_name,
-        "locked",
-        serde_json::Value::String("10".into()),
-    );
+    //insert a default decrypt_me along with the level permissions
+    let attempt = InfoTransaction::new(fs, &path)
+        .set(obj_name, "locked", serde_json::Value::String("10".into()))
+        .set(
+            obj_name,
+            "decrypt_me",
+            serde_json::Value::String("default_flag".to_string()),
+        )
+        .commit();
     if attempt.is_err() {
         return Err(format!(
             "Failed to update lock permissions for the level: {}",
             path.display()
         ));
     }
-    //insert empty string at compare me
-    let attempt_2 = update_obj_status(
-        &path,
-        level_name,
-        "decrypt_me",
-        serde_json::Value::String("default_flag".to_string()),
-    );
-    if attempt_2.is_err() {
-        return Err("unable to set default decrypt_me ".to_string());
-    }
     Ok("Level created successfully".into())
 }
 
 fn dev_make_chest(
+    fs: &dyn SekaiFs,
     path_to_obj: &str,
     current_dir: &Path,
     root_dir: &Path,
@@ -184,18 +183,18 @@ fn dev_make_chest(
         .file_name()
         .and_then(|s| s.to_str())
         .ok_or("Invalid object name")?;
-    if !path.exists() {
+    if !fs.exists(&path) {
         return Err(format!("Path does not exist: {path_to_obj}"));
     }
     let info_path = path.parent().unwrap().join(".dir_info/info.json");
-    if !info_path.exists() {
+    if !fs.exists(&info_path) {
         return Err(format!(
             "Info file does not exist at: {}",
             info_path.display()
         ));
     }
     //read_lock_perm
-    let lock_perm = read_lock_perm(&path);
+    let lock_perm = read_lock_perm(fs, &path);
     if lock_perm.is_err() {
         return Err(format!(
             "Failed to read lock permissions for the level: {}",
@@ -207,22 +206,12 @@ fn dev_make_chest(
         Ok("Object is already a chest.".to_string())
     } else {
         //is level
-        //remove decrypt_me and compare_me from object info  if they exist
-        let attempt1 = del_compare_me_from_info(&path, obj_name);
-        let attempt2 = del_decrypt_me_from_info(&path, obj_name);
-        if attempt1.is_err() || attempt2.is_err() {
-            return Err(format!(
-                "Failed to remove compare_me or decrypt_me from info for the level: {}",
-                path.display()
-            ));
-        }
-        //set lock perm to "00"
-        let attempt = update_obj_status(
-            &path,
-            obj_name,
-            "locked",
-            serde_json::Value::String("00".into()),
-        );
+        //remove decrypt_me and compare_me from object info if they exist, and set lock perm to "00"
+        let attempt = InfoTransaction::new(fs, &path)
+            .remove(obj_name, "compare_me")
+            .remove(obj_name, "decrypt_me")
+            .set(obj_name, "locked", serde_json::Value::String("00".into()))
+            .commit();
         if attempt.is_err() {
             return Err(format!(
                 "Failed to update lock permissions for the level: {}",
@@ -235,6 +224,7 @@ fn dev_make_chest(
 }
 
 fn dev_unlock_chest(
+    fs: &dyn SekaiFs,
     path_to_obj: &str,
     current_dir: &Path,
     root_dir: &Path,
@@ -245,18 +235,18 @@ fn dev_unlock_chest(
         .file_name()
         .and_then(|s| s.to_str())
         .ok_or("Invalid object name")?;
-    if !path.exists() {
+    if !fs.exists(&path) {
         return Err(format!("Path does not exist: {path_to_obj}"));
     }
     let info_path = path.parent().unwrap().join(".dir_info/info.json");
-    if !info_path.exists() {
+    if !fs.exists(&info_path) {
         return Err(format!(
             "Info file does not exist at: {}",
             info_path.display()
         ));
     }
     //check if is chest
-    let lock_perm = read_lock_perm(&path);
+    let lock_perm = read_lock_perm(fs, &path);
     if lock_perm.is_err() {
         return Err(format!(
             "Failed to read lock permissions for the chest: {}",
@@ -275,31 +265,23 @@ fn dev_unlock_chest(
         return Ok(format!("Chest {} is already unlocked.", path.display()));
     }
 
-    //remove compare_me
-    let attempt1 = del_compare_me_from_info(&path, obj_name);
-    if attempt1.is_err() {
-        return Err(format!(
-            "Failed to remove compare_me from info for the chest: {}",
-            path.display()
-        ));
-    }
-    //set lock perm to "00"
-    let attempt = update_obj_status(
-        &path,
-        obj_name,
-        "locked",
-        serde_json::Value::String("00".into()),
-    );
+    //remove compare_me and set lock perm to "00"
+    let attempt = InfoTransaction::new(fs, &path)
+        .remove(obj_name, "compare_me")
+        .set(obj_name, "locked", serde_json::Value::String("00".into()))
+        .commit();
     if attempt.is_err() {
         return Err(format!(
             "Failed to update lock permissions for the chest: {}",
             path.display()
         ));
     }
+    record_solution(fs, root_dir, &path, |authoring, rel| authoring.remove(&rel));
     Ok(format!("Chest {} unlocked successfully.", path.display()))
 }
 
 fn dev_lock_chest(
+    fs: &dyn SekaiFs,
     path_to_obj: &str,
     solution: &str,
     current_dir: &Path,
@@ -311,18 +293,18 @@ fn dev_lock_chest(
         .file_name()
         .and_then(|s| s.to_str())
         .ok_or("Invalid object name")?;
-    if !path.exists() {
+    if !fs.exists(&path) {
         return Err(format!("Path does not exist: {path_to_obj}"));
     }
     let info_path = path.parent().unwrap().join(".dir_info/info.json");
-    if !info_path.exists() {
+    if !fs.exists(&info_path) {
         return Err(format!(
             "Info file does not exist at: {}",
             info_path.display()
         ));
     }
     //check if is chest
-    let lock_perm = read_lock_perm(&path);
+    let lock_perm = read_lock_perm(fs, &path);
     if lock_perm.is_err() {
         return Err(format!(
             "Failed to read lock permissions for the chest: {}",
@@ -338,65 +320,72 @@ fn dev_lock_chest(
     }
     //check if is locked
 
-    //create compare_me
-    let attempt1 = update_obj_status(
-        &path,
-        obj_name,
-        "compare_me",
-        serde_json::Value::String(solution.to_string()),
-    );
-    if attempt1.is_err() {
-        return Err(format!(
-            "Failed to remove compare_me from info for the chest: {}",
-            path.display()
-        ));
-    }
-    //set lock perm to "00"
-    let attempt = update_obj_status(
-        &path,
-        obj_name,
-        "locked",
-        serde_json::Value::String("01".into()),
-    );
+    //create obj_salt and compare_me, the same way `unlock` checks them
+    //and set lock perm to "01" along with them
+    let obj_salt = SaltString::generate(&mut OsRng);
+    let attempt = InfoTransaction::new(fs, &path)
+        .set(
+            obj_name,
+            "obj_salt",
+            serde_json::Value::String(obj_salt.as_str().to_string()),
+        )
+        .set(
+            obj_name,
+            "compare_me",
+            serde_json::Value::String(chest_compare_me(obj_name, &obj_salt, solution)),
+        )
+        .set(obj_name, "locked", serde_json::Value::String("01".into()))
+        .commit();
     if attempt.is_err() {
         return Err(format!(
             "Failed to update lock permissions for the chest: {}",
             path.display()
         ));
     }
-    Ok(format!("Chest {} unlocked successfully.", path.display()))
+    record_solution(fs, root_dir, &path, |authoring, rel| {
+        authoring.set_chest(ChestSolution {
+            path: rel,
+            solution: solution.to_string(),
+            points: 0,
+        })
+    });
+    Ok(format!("Chest {} locked successfully.", path.display()))
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn dev_create_level_lock(
+    fs: &dyn SekaiFs,
     solve_from_path: &Path,
     set_lock_to_path: &Path,
     current_dir: &Path,
+    root_dir: &Path,
+    user: Option<&UserInfo>,
     solution: &str,
     flag: &str,
 ) -> Result<String, String> {
     //validate solve_from_path, set_lock_to_path
     let mut err_msg = String::new();
     let path_1 = normalize_path(&current_dir.join(solve_from_path));
-    if !path_1.exists() {
+    if !fs.exists(&path_1) {
         err_msg += &format!("Invalid `solve_from_path`:{solve_from_path:?} given");
         log::log_info("solve", err_msg.as_str());
         return Err(err_msg);
     }
     let path_2 = normalize_path(&current_dir.join(set_lock_to_path));
-    if !path_2.exists() {
+    if !fs.exists(&path_2) {
         err_msg += &format!("Invalid `set_lock_to_path`:{set_lock_to_path:?} path given");
         log::log_info("solve", err_msg.as_str());
         return Err(err_msg);
     }
 
     //check if solve_from_path  and set_lock_to_path is a level since only a level allows command `solve`
-    let lock_perm_path_1 = read_lock_perm(&path_1);
+    let lock_perm_path_1 = read_lock_perm(fs, &path_1);
     if lock_perm_path_1.is_err() {
         return Ok(format!("unable to read `locked` for `{solve_from_path:?}`"));
     }
     let (path_1_is_level, path_1_is_locked) = lock_perm_path_1.unwrap();
 
-    let lock_perm_path_2 = read_lock_perm(&path_2);
+    let lock_perm_path_2 = read_lock_perm(fs, &path_2);
     if lock_perm_path_2.is_err() {
         return Ok(format!(
             "unable to read `locked` for `{set_lock_to_path:?}`"
@@ -428,22 +417,18 @@ pub fn dev_create_level_lock(
         return Ok(err_msg);
     };
     let level_2_info=//read salt from info.json
-        read_get_obj_info(&path_2, level_2_name);
+        read_get_obj_info(fs, &path_2.parent().unwrap().join(".dir_info/info.json"), level_2_name);
     if level_2_info.is_err() {
         err_msg += &format!("Failed to read salt for level 2: {level_2_name}");
         log::log_error("solve", err_msg.as_str());
         return Err(err_msg);
     }
     let level_2_info = level_2_info.unwrap();
-    let level_2_salt = level_2_info.properties.get("obj_salt");
-
-    if level_2_salt.is_none() {
-        err_msg += &format!("Salt not found for level 2: {level_2_name}");
-        log::log_error("solve", err_msg.as_str());
-        return Err(err_msg);
-    }
-    let level_2_salt = level_2_salt.unwrap().as_str().unwrap();
-    let level_2_salt = SaltString::from_b64(level_2_salt);
+    // A level gets its salt when it is locked for the first time
+    let level_2_salt = match level_2_info.properties.get("obj_salt") {
+        Some(salt) => SaltString::from_b64(salt.as_str().unwrap_or_default()),
+        None => Ok(SaltString::generate(&mut OsRng)),
+    };
     if level_2_salt.is_err() {
         err_msg += "Failed to convert level 2 salt from base64.";
         log::log_error("solve", err_msg.as_str());
@@ -453,9 +438,9 @@ pub fn dev_create_level_lock(
 
     //create decrypt_me and compare_me
 
-    let decrypt_me_path_1 = encrypt(&characterise_enc_key(level_1_name, solution), flag);
+    let decrypt_me_path_1 = level_decrypt_me(level_1_name, solution, flag);
     // compare_me_path_2=?
-    let user_info = match get_current_user() {
+    let user_info = match user {
         Some(info) => info,
         None => {
             err_msg += "User not authenticated. Please log in.";
@@ -463,7 +448,6 @@ pub fn dev_create_level_lock(
             return Err(err_msg);
         }
     };
-    let username = &user_info.username;
     let user_salt_hex = &user_info.salt;
     let user_salt = SaltString::from_b64(user_salt_hex);
     let user_salt = match user_salt {
@@ -474,13 +458,7 @@ pub fn dev_create_level_lock(
             return Err(err_msg);
         }
     };
-    let decrypted_user_flag = decrypt(
-        &characterise_enc_key(
-            &format!("{}_{}", username, username.len()),
-            &format!("{username}_{level_1_name}"),
-        ),
-        flag,
-    );
+    let decrypted_user_flag = read_user_flag(user_info.flag_name(), level_1_name, flag);
     let l1_hashed_user_flag = argonhash(&level_2_salt, decrypted_user_flag);
     let hashed_with_usersalt = argonhash(&user_salt, l1_hashed_user_flag);
     let compare_me_path_2 = encrypt(
@@ -488,25 +466,41 @@ pub fn dev_create_level_lock(
         &hashed_with_usersalt,
     );
     //write compare_me and decrypt_me to info.json along with level permissions
-    let attempt1 = update_obj_status(
-        &path_2,
-        level_2_name,
-        "locked",
-        serde_json::Value::String("11".into()),
-    );
+    // Record where the flag comes from, so that `dev check` can follow the locks
+    let attempt1 = InfoTransaction::new(fs, &path_2)
+        .set(
+            level_2_name,
+            "obj_salt",
+            serde_json::Value::String(level_2_salt.as_str().to_string()),
+        )
+        .set(
+            level_2_name,
+            "compare_me",
+            serde_json::Value::String(compare_me_path_2),
+        )
+        .set(
+            level_2_name,
+            "unlocked_by",
+            serde_json::Value::String(
+                relative_deemak_path(&path_1, Some(root_dir))
+                    .display()
+                    .to_string(),
+            ),
+        )
+        .set(
+            level_2_name,
+            "locked",
+            serde_json::Value::String("11".into()),
+        )
+        .commit();
     let attempt2 = update_obj_status(
+        fs,
         &path_1,
         level_1_name,
         "decrypt_me",
         serde_json::Value::String(decrypt_me_path_1),
     );
-    let attempt3 = update_obj_status(
-        &path_2,
-        level_2_name,
-        "compare_me",
-        serde_json::Value::String(compare_me_path_2),
-    );
-    if attempt1.is_err() || attempt2.is_err() || attempt3.is_err() {
+    if attempt1.is_err() || attempt2.is_err() {
         err_msg += &format!(
             "Failed to update lock info at the desired locations for the lock from: {} to :{}",
             path_1.display(),
@@ -515,10 +509,25 @@ pub fn dev_create_level_lock(
         log::log_error("solve", err_msg.as_str());
         return Err(err_msg);
     }
+    let unlocks = path_2
+        .strip_prefix(root_dir)
+        .unwrap_or(&path_2)
+        .display()
+        .to_string();
+    record_solution(fs, root_dir, &path_1, |authoring, rel| {
+        authoring.set_level(LevelSolution {
+            path: rel,
+            solution: solution.to_string(),
+            flag: flag.to_string(),
+            unlocks: Some(unlocks),
+            points: 0,
+        })
+    });
     Ok("created lock successfully".to_string())
 }
 
 pub fn dev_remove_level_lock(
+    fs: &dyn SekaiFs,
     path_to_level: &Path,
     current_dir: &Path,
     root_dir: &Path,
@@ -526,12 +535,12 @@ pub fn dev_remove_level_lock(
     //create path and validate
 
     let path = normalize_path(&current_dir.join(path_to_level));
-    if !path.exists() {
+    if !fs.exists(&path) {
         return Err(format!("Path does not exist: {path_to_level:?}. try again"));
     }
     //info_path
     let info_path = path.parent().unwrap().join(".dir_info/info.json");
-    if !info_path.exists() {
+    if !fs.exists(&info_path) {
         return Err(format!(
             "Info file does not exist at: {}",
             info_path.display()
@@ -544,7 +553,7 @@ pub fn dev_remove_level_lock(
     }
     let obj_name = obj_name.unwrap();
     //read lock perm to validate if level
-    let lock_perm = read_lock_perm(&path);
+    let lock_perm = read_lock_perm(fs, &path);
     if lock_perm.is_err() {
         return Err(format!(
             "Failed to read lock permissions for the level: {}",
@@ -555,7 +564,7 @@ pub fn dev_remove_level_lock(
     if !is_level {
         return Err(format!("Object {} is not a level.", path.display()));
     }
-    let attempt_set_as_default = set_as_default_obj(&path, obj_name);
+    let attempt_set_as_default = set_as_default_obj(fs, &path, obj_name);
     //change lock perm
     if attempt_set_as_default.is_err() {
         return Err(format!(
@@ -563,8 +572,27 @@ pub fn dev_remove_level_lock(
             path.display()
         ));
     }
+    record_solution(fs, root_dir, &path, |authoring, rel| authoring.remove(&rel));
     Ok(format!(
         "Removed level lock from {} successfully.",
         path.display()
     ))
 }
+
+/// Keeps the plaintext solution in the authoring file of the Sekai, the object is given
+/// by its path relative to HOME.
+fn record_solution(
+    fs: &dyn SekaiFs,
+    root_dir: &Path,
+    path: &Path,
+    update: impl FnOnce(&mut Authoring, String),
+) {
+    let rel = path
+        .strip_prefix(root_dir)
+        .unwrap_or(path)
+        .display()
+        .to_string();
+    if let Err(e) = update_authoring(fs, root_dir, |authoring| update(authoring, rel)) {
+        log::log_warning("dev_lock", &format!("Failed to record the solution: {e}"));
+    }
+}
@@ -1,7 +1,8 @@
 use super::argparser::ArgParser;
-use super::cmds::check_dir_info;
+use super::cmds::{check_dir_info, normalize_path};
 use super::whereami::display_relative_path;
 use crate::metainfo::{info_reader, lock_perm};
+use crate::sekai_fs::SekaiFs;
 use crate::utils::log;
 use std::path::{Path, PathBuf};
 
@@ -16,27 +17,18 @@ Navigate to different directories:
 
 "#;
 
-/// Remove /private prefix from paths on macOS
-fn remove_private_prefix(path: &Path) -> PathBuf {
-    let mut new_path: PathBuf;
-    if cfg!(target_os = "macos") && path.starts_with("/private") {
-        new_path = path.strip_prefix("/private").unwrap_or(path).to_path_buf();
-    } else {
-        new_path = path.to_path_buf()
-    }
-    Path::new("/").join(new_path)
-}
-
-pub fn navigate(destination: &str, current_dir: &PathBuf, root_dir: &Path) -> (PathBuf, String) {
+pub fn navigate(
+    fs: &dyn SekaiFs,
+    destination: &str,
+    current_dir: &PathBuf,
+    root_dir: &Path,
+) -> Result<(PathBuf, String), String> {
     let new_path = match destination {
         "HOME" | "home" => root_dir.to_path_buf(),
         ".." | "back" => {
             if current_dir == root_dir {
                 log::log_warning("go", "Attempted to go back from root directory");
-                return (
-                    current_dir.clone(),
-                    "You are at the root. Cannot go back further".to_string(),
-                );
+                return Err("You are at the root. Cannot go back further".to_string());
             }
             current_dir.parent().unwrap().to_path_buf()
         }
@@ -48,12 +40,9 @@ pub fn navigate(destination: &str, current_dir: &PathBuf, root_dir: &Path) -> (P
                         "Attempted to go to/refers a restricted directory: {destination}. Operation Not Permitted."
                     ),
                 );
-                return (
-                    current_dir.clone(),
-                    format!(
-                        "go: Attempted to go to/refers a restricted directory: {destination}. Operation Not Permitted"
-                    ),
-                );
+                return Err(format!(
+                    "go: Attempted to go to/refers a restricted directory: {destination}. Operation Not Permitted"
+                ));
             } else {
                 current_dir.join(destination)
             }
@@ -61,21 +50,15 @@ pub fn navigate(destination: &str, current_dir: &PathBuf, root_dir: &Path) -> (P
     };
 
     // Normalize path and verify it exists
-    let canonical_path = match new_path.canonicalize() {
-        Ok(p) => p,
-        Err(_) => {
-            log::log_error(
-                "go",
-                &format!("No such directory, path: {}", new_path.display()),
-            );
-            return (
-                current_dir.clone(),
-                format!("go: {destination}: No such directory"),
-            );
-        }
-    };
+    let canonical_path = normalize_path(&new_path);
+    if !fs.exists(&canonical_path) {
+        log::log_error(
+            "go",
+            &format!("No such directory, path: {}", new_path.display()),
+        );
+        return Err(format!("go: {destination}: No such directory"));
+    }
 
-    let canonical_path = remove_private_prefix(&canonical_path);
     // Verify it's within root and is a directory
     if !canonical_path.starts_with(root_dir) {
         log::log_warning(
@@ -85,14 +68,11 @@ pub fn navigate(destination: &str, current_dir: &PathBuf, root_dir: &Path) -> (P
                 canonical_path.display()
             ),
         );
-        return (
-            current_dir.clone(),
-            "go: Access denied: Cannot go outside root".to_string(),
-        );
+        return Err("go: Access denied: Cannot go outside root".to_string());
     }
     assert!(canonical_path.starts_with(root_dir)); // It should always be true
 
-    if canonical_path.is_file() {
+    if fs.is_file(&canonical_path) {
         log::log_warning(
             "go",
             &format!(
@@ -100,26 +80,27 @@ pub fn navigate(destination: &str, current_dir: &PathBuf, root_dir: &Path) -> (P
                 canonical_path.display()
             ),
         );
-        return (
-            current_dir.clone(),
-            format!("go: {destination}: Is a file (try 'read {destination}')"),
-        );
+        return Err(format!(
+            "go: {destination}: Is a file (try 'read {destination}')"
+        ));
     }
 
     // Check if directory is locked
     if canonical_path != root_dir
         && let Err(e) = lock_perm::operation_locked_perm(
+            fs,
             &canonical_path,
+            root_dir,
             "go",
             "Cannot enter locked directory. Unlock it first",
         )
     {
-        return (current_dir.clone(), e);
+        return Err(e);
     }
 
     // Get directory info if available
     let info_path = canonical_path.join(".dir_info/info.json");
-    let message = match info_reader::read_validate_info(&info_path) {
+    let message = match info_reader::read_validate_info(fs, &info_path) {
         Ok(info) => format!(
             "You have entered {}\n\nAbout:\n{}",
             display_relative_path(&canonical_path, root_dir),
@@ -131,10 +112,15 @@ pub fn navigate(destination: &str, current_dir: &PathBuf, root_dir: &Path) -> (P
         ),
     };
 
-    (canonical_path, message)
+    Ok((canonical_path, message))
 }
 
-pub fn go(args: &[&str], current_dir: &PathBuf, root_dir: &Path) -> (PathBuf, String) {
+pub fn go(
+    fs: &dyn SekaiFs,
+    args: &[&str],
+    current_dir: &PathBuf,
+    root_dir: &Path,
+) -> Result<(PathBuf, String), String> {
     let mut parser = ArgParser::new(&[]);
 
     let args_string: Vec<String> = args.iter().map(|s| s.to_string()).collect();
@@ -154,31 +140,23 @@ pub fn go(args: &[&str], current_dir: &PathBuf, root_dir: &Path) -> (PathBuf, St
             let pos_args = parser.get_positional_args();
 
             if pos_args.is_empty() {
-                return (
-                    current_dir.clone(),
+                return Err(
                     "go: missing directory operand\nTry 'help go' for more information."
                         .to_string(),
                 );
             }
             if pos_args.len() > 1 {
-                return (
-                    current_dir.clone(),
+                return Err(
                     "go: too many arguments\nTry 'help go' for more information.".to_string(),
                 );
             }
             let target = pos_args[0].as_str();
-            navigate(target, current_dir, root_dir)
+            navigate(fs, target, current_dir, root_dir)
         }
         Err(e) => match &e[..] {
-            "help" => (current_dir.clone(), HELP_TXT.to_string()),
-            "unknown" => (
-                current_dir.clone(),
-                "go: unknown flag\nTry 'help go' for more information.".to_string(),
-            ),
-            _ => (
-                current_dir.clone(),
-                "Error parsing arguments. Try 'help go' for more information.".to_string(),
-            ),
+            "help" => Ok((current_dir.clone(), HELP_TXT.to_string())),
+            "unknown" => Err("go: unknown flag\nTry 'help go' for more information.".to_string()),
+            _ => Err("Error parsing arguments. Try 'help go' for more information.".to_string()),
         },
     }
 }
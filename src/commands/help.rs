@@ -18,6 +18,7 @@ pub fn get_command_help(command: &str) -> Option<&'static str> {
         "save" => Some(save::HELP_TEXT),
         "solve" => Some(solve::HELP_TEXT),
         "unlock" => Some(unlock::HELP_TXT),
+        "scores" => Some(scores::HELP_TXT),
         _ => Some("No help available for this command. Check if the command is valid."),
     }
 }
@@ -43,6 +44,7 @@ Welcome to DBD Deemak Help. You can use the following commands:
 - save: Saves your current progress of the Sekai.
 -solve: after completing a level, use this command to submit your answer and obtain the flag.
 -unlock: use flag to unlock levels and chests.
+- scores: Shows your standing in the leaderboard.
 - help <command>: Displays help for the specified command.
 "#;
         help_text.to_string()
@@ -1,7 +1,9 @@
 use super::argparser::ArgParser;
 use super::cmds::{RESTRICTED_FILES, check_dir_info};
+use super::output::Output;
 use super::whereami::display_relative_path;
 use crate::metainfo::lock_perm;
+use crate::sekai_fs::SekaiFs;
 use crate::utils::log;
 use std::path::Path;
 
@@ -17,8 +19,12 @@ Example:
 
 /// Lists all files and directories in the given path, excluding .dir_info and info.json
 /// Returns a tuple of (files, directories) as String vectors with lock status
-pub fn list_directory_entries(target_path: &Path, root_dir: &Path) -> (Vec<String>, Vec<String>) {
-    let entries = match std::fs::read_dir(target_path) {
+pub fn list_directory_entries(
+    fs: &dyn SekaiFs,
+    target_path: &Path,
+    root_dir: &Path,
+) -> (Vec<String>, Vec<String>) {
+    let entries = match fs.read_dir(target_path) {
         Ok(entries) => entries,
         Err(_) => return (Vec::new(), Vec::new()),
     };
@@ -26,10 +32,10 @@ pub fn list_directory_entries(target_path: &Path, root_dir: &Path) -> (Vec<Strin
     let mut files = Vec::new();
     let mut directories = Vec::new();
 
-    for entry in entries {
-        let Ok(entry) = entry else { continue };
-        let path = entry.path();
-        let name = entry.file_name().to_string_lossy().into_owned();
+    for path in entries {
+        let Some(name) = path.file_name().map(|n| n.to_string_lossy().into_owned()) else {
+            continue;
+        };
 
         // if name == ".dir_info" || name == "info.json" {
         //     continue;
@@ -38,7 +44,7 @@ pub fn list_directory_entries(target_path: &Path, root_dir: &Path) -> (Vec<Strin
             continue;
         }
 
-        if path.is_dir() {
+        if fs.is_dir(&path) {
             directories.push(name.to_string());
         } else {
             files.push(name);
@@ -50,7 +56,12 @@ pub fn list_directory_entries(target_path: &Path, root_dir: &Path) -> (Vec<Strin
     (files, directories)
 }
 
-pub fn ls(args: &[&str], current_dir: &Path, root_dir: &Path) -> String {
+pub fn ls(
+    fs: &dyn SekaiFs,
+    args: &[&str],
+    current_dir: &Path,
+    root_dir: &Path,
+) -> Result<Output, Output> {
     let args_string: Vec<String> = args.iter().map(|s| s.to_string()).collect();
     let mut parser = ArgParser::new(&["--all", "-a"]);
 
@@ -60,7 +71,9 @@ pub fn ls(args: &[&str], current_dir: &Path, root_dir: &Path) -> String {
 
             // Handle directory argument
             if positional_args.len() > 1 {
-                return "ls: too many arguments\nTry 'help ls' for more information.".to_string();
+                return Err(Output::new()
+                    .error("ls: too many arguments")
+                    .help_hint("ls"));
             }
 
             let target_path = if positional_args.is_empty() {
@@ -70,10 +83,12 @@ pub fn ls(args: &[&str], current_dir: &Path, root_dir: &Path) -> String {
 
                 // Check if directory is locked
                 let dir_path = current_dir.join(dir_name);
-                if let Ok((_, is_locked)) = lock_perm::read_lock_perm(&dir_path)
+                if let Ok((_, is_locked)) = lock_perm::read_lock_perm(fs, &dir_path)
                     && is_locked
                 {
-                    return format!("{dir_name} is locked. To list contents, unlock it first.");
+                    return Err(Output::new()
+                        .locked(dir_name)
+                        .error(" is locked. To list contents, unlock it first."));
                 }
 
                 if check_dir_info(Path::new(dir_name)) {
@@ -83,21 +98,21 @@ pub fn ls(args: &[&str], current_dir: &Path, root_dir: &Path) -> String {
                             "Attempted to list restricted directory: {dir_name} Operation Not Permitted"
                         ),
                     );
-                    return format!(
+                    return Err(Output::new().error(format!(
                         "Attempted to list restricted directory: {dir_name} Operation Not Permitted"
-                    );
+                    )));
                 }
 
                 let joined = current_dir.join(dir_name);
                 if joined.starts_with(root_dir) {
                     joined
                 } else {
-                    return "ls: Access denied outside root directory".to_string();
+                    return Err(Output::new().error("ls: Access denied outside root directory"));
                 }
             };
 
             let (mut files_vec, mut directories_vec) =
-                list_directory_entries(&target_path, root_dir);
+                list_directory_entries(fs, &target_path, root_dir);
 
             if !args.contains(&"--all") || !args.contains(&"-a") {
                 // Remove all hidden files and directories starting with '.'
@@ -108,60 +123,61 @@ pub fn ls(args: &[&str], current_dir: &Path, root_dir: &Path) -> String {
 
             if files_vec.is_empty()
                 && directories_vec.is_empty()
-                && let Err(e) = std::fs::read_dir(&target_path)
+                && let Err(e) = fs.read_dir(&target_path)
             {
                 let error_msg = if e.kind() == std::io::ErrorKind::NotFound {
                     "No such file or directory".to_string()
                 } else {
                     e.to_string()
                 };
-                return format!(
+                return Err(Output::new().error(format!(
                     "ls: cannot access '{}': {}",
                     display_relative_path(&target_path, root_dir),
                     error_msg
-                );
+                )));
             }
-            // Check lock status
-            // Check lock status and format display names
-            let files = if files_vec.is_empty() {
-                "   (none)\n".to_string()
-            } else {
-                files_vec
-                    .iter()
-                    .map(|f| {
-                        let is_locked = match lock_perm::read_lock_perm(&current_dir.join(f)) {
-                            Ok((_, locked)) => locked,
-                            Err(_) => false,
-                        };
-                        format!("   {}{}\n", f, if is_locked { " (locked)" } else { "" })
-                    })
-                    .collect()
-            };
 
-            let directories = if directories_vec.is_empty() {
-                "   (none)\n".to_string()
-            } else {
-                directories_vec
-                    .iter()
-                    .map(|d| {
-                        let dir_name = d.trim_end_matches('/');
-                        let is_locked = match lock_perm::read_lock_perm(&current_dir.join(dir_name))
-                        {
-                            Ok((_, locked)) => locked,
-                            Err(_) => false,
-                        };
-                        format!("   {}{}\n", d, if is_locked { " (locked)" } else { "" })
-                    })
-                    .collect()
+            // Check lock status and format display names
+            let entries = |output: Output, names: &[String]| {
+                if names.is_empty() {
+                    return output.text("   (none)\n");
+                }
+                names.iter().fold(output, |output, name| {
+                    let is_locked = match lock_perm::read_lock_perm(
+                        fs,
+                        &current_dir.join(name.trim_end_matches('/')),
+                    ) {
+                        Ok((_, locked)) => locked,
+                        Err(_) => false,
+                    };
+                    let output = output.text("   ");
+                    if is_locked {
+                        output.locked(name).hint(" (locked)").text("\n")
+                    } else {
+                        output.path(name).text("\n")
+                    }
+                })
             };
 
-            format!("\nObjects:\n{files}\nFrom inside here, you can go to:\n{directories}")
+            let output = entries(
+                Output::new().text("\n").heading("Objects:").text("\n"),
+                &files_vec,
+            );
+            let output = entries(
+                output
+                    .text("\n")
+                    .heading("From inside here, you can go to:")
+                    .text("\n"),
+                &directories_vec,
+            );
+            Ok(output)
         }
         Err(e) => match &e[..] {
-            "help" => HELP_TXT,
-            "unknown" => "ls: unknown flag\nTry 'help ls' for more information.",
-            _ => "Error parsing arguments. Try 'help ls' for more information.",
-        }
-        .to_string(),
+            "help" => Ok(HELP_TXT.into()),
+            "unknown" => Err(Output::new().error("ls: unknown flag").help_hint("ls")),
+            _ => Err(Output::new()
+                .error("Error parsing arguments.")
+                .hint(" Try 'help ls' for more information.")),
+        },
     }
 }
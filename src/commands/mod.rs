@@ -1,4 +1,5 @@
 pub mod cmds;
+pub mod output;
 mod tests;
 
 mod echo;
@@ -27,7 +28,7 @@ pub use exit::exit;
 mod whereami;
 pub use whereami::{display_relative_path, whereami};
 
-mod read;
+pub mod read;
 pub use read::read;
 
 mod argparser;
@@ -40,4 +41,6 @@ mod solve;
 
 mod unlock;
 
+mod scores;
+
 mod dev;
@@ -0,0 +1,175 @@
+use serde::Serialize;
+use std::fmt;
+
+/*
+COMMAND OUTPUT EXPLANATION:
+
+Commands give their output as segments of text, each with a `Style`, instead of plain text, so
+every frontend shows it its own way:
+- The GUI colours the segments with the current theme, see `Theme::style`.
+- The web gets them as JSON in `CommandResponse` and gives each a CSS class.
+- Anything which only shows text uses the plain text, which is `to_string()`.
+
+Lines are separated by '\n' inside the text of the segments, as in the plain text. Failed commands
+give `CommandResult::Error`, whose segments without a style are shown as errors.
+*/
+
+/// What a segment of the output is, see the explanation above.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Style {
+    Text,
+    /// Places and objects of the Sekai
+    Path,
+    /// Objects which have to be unlocked first
+    Locked,
+    Error,
+    /// What to try next, like the help of the command
+    Hint,
+    Heading,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Segment {
+    pub style: Style,
+    pub text: String,
+}
+
+/// Output of a command, see the explanation above.
+///
+/// Example:
+///     Output::new().heading("Objects:").text("\n   ").locked("chest").hint(" (locked)")
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+#[serde(transparent)]
+pub struct Output {
+    segments: Vec<Segment>,
+}
+
+impl Output {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the text with the style, joined to the last segment if it has the same style.
+    pub fn push(mut self, style: Style, text: impl Into<String>) -> Self {
+        let text = text.into();
+        if text.is_empty() {
+            return self;
+        }
+        match self.segments.last_mut() {
+            Some(last) if last.style == style => last.text.push_str(&text),
+            _ => self.segments.push(Segment { style, text }),
+        }
+        self
+    }
+
+    pub fn text(self, text: impl Into<String>) -> Self {
+        self.push(Style::Text, text)
+    }
+
+    pub fn path(self, text: impl Into<String>) -> Self {
+        self.push(Style::Path, text)
+    }
+
+    pub fn locked(self, text: impl Into<String>) -> Self {
+        self.push(Style::Locked, text)
+    }
+
+    pub fn error(self, text: impl Into<String>) -> Self {
+        self.push(Style::Error, text)
+    }
+
+    pub fn hint(self, text: impl Into<String>) -> Self {
+        self.push(Style::Hint, text)
+    }
+
+    pub fn heading(self, text: impl Into<String>) -> Self {
+        self.push(Style::Heading, text)
+    }
+
+    /// Adds the hint to read the help of the command, on a new line.
+    pub fn help_hint(self, command: &str) -> Self {
+        self.text("\n")
+            .hint(format!("Try 'help {command}' for more information."))
+    }
+
+    pub fn segments(&self) -> &[Segment] {
+        &self.segments
+    }
+
+    /// The segments of each line, without the '\n' between them. There are as many lines as in
+    /// the plain text, empty lines have no segments.
+    pub fn lines(&self) -> Vec<Vec<Segment>> {
+        let mut lines = vec![Vec::new()];
+        for segment in &self.segments {
+            for (i, part) in segment.text.split('\n').enumerate() {
+                if i > 0 {
+                    lines.push(Vec::new());
+                }
+                if !part.is_empty() {
+                    lines.last_mut().unwrap().push(Segment {
+                        style: segment.style,
+                        text: part.to_string(),
+                    });
+                }
+            }
+        }
+        lines
+    }
+}
+
+/// The plain text of the output.
+impl fmt::Display for Output {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.segments
+            .iter()
+            .try_for_each(|segment| f.write_str(&segment.text))
+    }
+}
+
+impl From<String> for Output {
+    fn from(text: String) -> Self {
+        Self::new().text(text)
+    }
+}
+
+impl From<&str> for Output {
+    fn from(text: &str) -> Self {
+        Self::new().text(text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_output_segments() {
+        let output = Output::new()
+            .heading("Objects:")
+            .text("\n   ")
+            .locked("chest")
+            .hint(" (locked)")
+            .text("\n   ")
+            .text("key\n");
+        assert_eq!(output.to_string(), "Objects:\n   chest (locked)\n   key\n");
+        assert_eq!(output.segments().len(), 5);
+
+        let lines = output.lines();
+        assert_eq!(lines.len(), 4);
+        assert_eq!(lines[0][0].style, Style::Heading);
+        let styles: Vec<Style> = lines[1].iter().map(|s| s.style).collect();
+        assert_eq!(styles, [Style::Text, Style::Locked, Style::Hint]);
+        assert_eq!(lines[2][0].text, "   key");
+        assert!(lines[3].is_empty());
+
+        assert_eq!(
+            serde_json::to_value(Output::new().error("ls: no").help_hint("ls")).unwrap(),
+            serde_json::json!([
+                {"style": "error", "text": "ls: no"},
+                {"style": "text", "text": "\n"},
+                {"style": "hint", "text": "Try 'help ls' for more information."},
+            ])
+        );
+    }
+}
@@ -1,62 +1,205 @@
-use super::cmds::check_dir_info;
+use super::argparser::ArgParser;
+use super::cmds::{CommandResult, check_dir_info, normalize_path};
+use super::output::Output;
 use super::whereami::display_relative_path;
 use crate::metainfo::lock_perm;
-use std::fs;
-use std::path::Path;
+use crate::metainfo::mime::{ObjectKind, object_mime};
+use crate::sekai_fs::SekaiFs;
+use std::path::{Path, PathBuf};
 
 pub const HELP_TXT: &str = r#"
-Usage: read <object_name>
+Usage: read [options] <object_name>
 
 Read the contents of a file and display it.
+Images and sounds are shown in the panel next to the terminal.
+Options:
+- read -x | --hex <object_name>       : Shows the bytes of the file in hex, with the readable characters.
+- read -s | --strings <object_name>   : Shows only the readable text inside a binary file.
 "#;
 
-/// Read and display file contents (similar to 'cat' command)
-pub fn read(args: &[&str], current_dir: &Path, root_dir: &Path) -> String {
-    if args.is_empty() {
-        return "read: missing file operand".to_string();
+/// Bytes shown by `read --hex`, the rest is cut off.
+const HEX_LIMIT: usize = 4096;
+const HEX_ROW: usize = 16;
+
+/// Shortest run of readable characters shown by `read --strings`.
+const MIN_STRING_LEN: usize = 4;
+
+fn io_error_msg(e: &std::io::Error) -> &'static str {
+    match e.kind() {
+        std::io::ErrorKind::NotFound => "No such file",
+        std::io::ErrorKind::PermissionDenied => "Permission denied",
+        _ => "Could not read file",
     }
+}
 
-    let file_path = current_dir.join(args[0]);
+/// Resolves the file object to read, checking that the player is allowed to read it.
+/// Returns the error message to show otherwise.
+pub fn resolve_readable(
+    fs: &dyn SekaiFs,
+    name: &str,
+    current_dir: &Path,
+    root_dir: &Path,
+) -> Result<PathBuf, String> {
+    let file_path = normalize_path(&current_dir.join(name));
 
     // Check if file is doesn't refer a restricted one
     if check_dir_info(&file_path) {
-        return format!(
+        return Err(format!(
             "read: Attempted to read/refer restricted files: {} Operation Not Permitted",
             display_relative_path(&file_path, root_dir)
-        );
+        ));
     }
 
     // Security check - must stay within root directory
     if !file_path.starts_with(root_dir) {
-        return "read: Access denied outside root directory".to_string();
+        return Err("read: Access denied outside root directory".to_string());
     }
 
     // Check if path is a directory
-    if file_path.is_dir() {
-        return format!(
+    if fs.is_dir(&file_path) {
+        return Err(format!(
             "read: {}: Is a directory",
             display_relative_path(&file_path, root_dir)
-        );
+        ));
     }
 
     // Check if locked or not
-    if let Err(e) = lock_perm::operation_locked_perm(
+    lock_perm::operation_locked_perm(
+        fs,
         &file_path,
+        root_dir,
         "read",
         "Cannot read locked file. Unlock it first",
-    ) {
-        return e;
-    }
-    match fs::read_to_string(&file_path) {
-        Ok(content) => content,
-        Err(e) => format!(
-            "read: {}: {}",
-            display_relative_path(&file_path, root_dir),
-            match e.kind() {
-                std::io::ErrorKind::NotFound => "No such file",
-                std::io::ErrorKind::PermissionDenied => "Permission denied",
-                _ => "Could not read file",
+    )?;
+    if !fs.exists(&file_path) {
+        return Err(format!(
+            "read: {}: No such file",
+            display_relative_path(&file_path, root_dir)
+        ));
+    }
+    Ok(file_path)
+}
+
+/// Hex dump like `xxd`, with the offset, 16 bytes per row and the readable characters.
+pub fn hex_dump(bytes: &[u8]) -> String {
+    let mut lines = Vec::new();
+    for (row, chunk) in bytes[..bytes.len().min(HEX_LIMIT)]
+        .chunks(HEX_ROW)
+        .enumerate()
+    {
+        let hex: Vec<String> = chunk
+            .chunks(2)
+            .map(|pair| pair.iter().map(|b| format!("{b:02x}")).collect())
+            .collect();
+        let ascii: String = chunk
+            .iter()
+            .map(|&b| {
+                if b.is_ascii_graphic() || b == b' ' {
+                    b as char
+                } else {
+                    '.'
+                }
+            })
+            .collect();
+        lines.push(format!(
+            "{:08x}: {:<39}  {ascii}",
+            row * HEX_ROW,
+            hex.join(" ")
+        ));
+    }
+    if bytes.len() > HEX_LIMIT {
+        lines.push(format!("... {} more bytes", bytes.len() - HEX_LIMIT));
+    }
+    lines.join("\n")
+}
+
+/// Runs of readable ASCII characters, like `strings`.
+pub fn printable_strings(bytes: &[u8]) -> String {
+    bytes
+        .split(|&b| !(b.is_ascii_graphic() || b == b' ' || b == b'\t'))
+        .filter(|run| run.len() >= MIN_STRING_LEN)
+        .map(|run| String::from_utf8_lossy(run).into_owned())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Read and display file contents (similar to 'cat' command)
+pub fn read(fs: &dyn SekaiFs, args: &[&str], current_dir: &Path, root_dir: &Path) -> CommandResult {
+    let args_string: Vec<String> = args.iter().map(|s| s.to_string()).collect();
+    let mut parser = ArgParser::new(&["--hex", "-x", "--strings", "-s"]);
+    if let Err(e) = parser.parse(&args_string, "read") {
+        return match &e[..] {
+            "help" => CommandResult::Output(HELP_TXT.into()),
+            "unknown" => {
+                CommandResult::Error(Output::new().error("read: unknown flag").help_hint("read"))
             }
+            _ => CommandResult::Error(
+                Output::new()
+                    .error("Error parsing arguments.")
+                    .hint(" Try 'help read' for more information."),
+            ),
+        };
+    }
+    let positional_args = parser.get_positional_args();
+    match positional_args.len() {
+        0 => return CommandResult::Error(Output::new().error("read: missing file operand")),
+        1 => {}
+        _ => {
+            return CommandResult::Error(
+                Output::new()
+                    .error("read: too many arguments")
+                    .help_hint("read"),
+            );
+        }
+    }
+
+    let file_path = match resolve_readable(fs, positional_args[0], current_dir, root_dir) {
+        Ok(path) => path,
+        Err(e) => return CommandResult::Error(Output::new().error(e)),
+    };
+    let rel_path = display_relative_path(&file_path, root_dir);
+    let read_error = |e: &std::io::Error| {
+        CommandResult::Error(Output::new().error(format!("read: {rel_path}: {}", io_error_msg(e))))
+    };
+
+    if parser.has_flag("--hex") || parser.has_flag("-x") {
+        return match fs.read(&file_path) {
+            Ok(bytes) => CommandResult::Output(hex_dump(&bytes).into()),
+            Err(e) => read_error(&e),
+        };
+    }
+    if parser.has_flag("--strings") || parser.has_flag("-s") {
+        return match fs.read(&file_path) {
+            Ok(bytes) => CommandResult::Output(printable_strings(&bytes).into()),
+            Err(e) => read_error(&e),
+        };
+    }
+
+    let mime = object_mime(fs, &file_path);
+    match ObjectKind::from_mime(&mime) {
+        ObjectKind::Text => match fs.read_to_string(&file_path) {
+            Ok(content) => CommandResult::Output(content.into()),
+            Err(e) if e.kind() == std::io::ErrorKind::InvalidData => CommandResult::Output(
+                Output::new()
+                    .text("read: ")
+                    .path(&rel_path)
+                    .text(": Not a text file.")
+                    .hint(" Try `read --strings` to look inside."),
+            ),
+            Err(e) => read_error(&e),
+        },
+        ObjectKind::Image | ObjectKind::Audio => {
+            let message = Output::new().path(&rel_path).text(format!(" ({mime})"));
+            // Frontends ask for the object by this path, it must not give away the server paths
+            let home_path = file_path.strip_prefix(root_dir).unwrap_or(&file_path);
+            CommandResult::ShowAsset(home_path.to_path_buf(), mime, message)
+        }
+        ObjectKind::Binary => CommandResult::Output(
+            Output::new()
+                .text("read: ")
+                .path(&rel_path)
+                .text(format!(": Binary file ({mime})."))
+                .hint(" Try `read --hex` or `read --strings` to look inside."),
         ),
     }
 }
@@ -1,5 +1,6 @@
 use super::argparser::ArgParser;
 use crate::rns::restore_comp::{backup_sekai, can_restore, can_save, restore_sekai};
+use crate::sekai_fs::SekaiFs;
 use crate::utils::log;
 use crate::utils::prompt::UserPrompter;
 use std::path::Path;
@@ -11,7 +12,12 @@ Usage: restore
 - restore -f | --force        : This means all your progress is gone, and you will have to start over from scratch.
 "#;
 
-pub fn restore(args: &[&str], root_path: &Path, prompter: &mut dyn UserPrompter) -> String {
+pub fn restore(
+    fs: &dyn SekaiFs,
+    args: &[&str],
+    root_path: &Path,
+    prompter: &mut dyn UserPrompter,
+) -> Result<String, String> {
     let mut parser = ArgParser::new(&["-f", "--force"]);
     let args_string: Vec<String> = args.iter().map(|s| s.to_string()).collect();
 
@@ -22,7 +28,7 @@ pub fn restore(args: &[&str], root_path: &Path, prompter: &mut dyn UserPrompter)
             if !pos_args.is_empty() {
                 err_msg += "Too many positional arguments provided. No arguments expected.";
                 log::log_info("restore", err_msg.as_str());
-                return err_msg;
+                return Err(err_msg);
             }
             // Ask for confirmation
 
@@ -30,59 +36,63 @@ pub fn restore(args: &[&str], root_path: &Path, prompter: &mut dyn UserPrompter)
                 if !prompter
                     .confirm("Are you sure you want to restore? This will erase all progress.")
                 {
-                    return "Restore cancelled by user.".to_string();
+                    return Ok("Restore cancelled by user.".to_string());
                 }
-                if can_restore(root_path) {
+                if can_restore(fs, root_path) {
                     // Restore file already exists.
                     log::log_info(
                         "restore",
                         "Restore file found. Proceeding with restoration.",
                     );
-                    if restore_sekai("restore", root_path).is_err() {
+                    if restore_sekai(fs, "restore", root_path).is_err() {
                         err_msg +=
                             "Failed to restore Sekai. Please check the logs for more details.";
                         log::log_error("restore", err_msg.as_str());
-                        return err_msg;
+                        return Err(err_msg);
                     }
-                    "Sekai restored successfully.\n".to_string()
+                    Ok("Sekai restored successfully.\n".to_string())
                 } else {
                     err_msg += "No restore file found. ";
                     // If restore file is not found, backup the current state
                     log::log_info("restore", err_msg.as_str());
-                    if backup_sekai("restore", root_path).is_err() {
+                    if backup_sekai(fs, "restore", root_path).is_err() {
                         err_msg += "Failed to backup current state. Please check the logs for more details.";
                         log::log_error("restore", err_msg.as_str());
-                        return err_msg;
+                        return Err(err_msg);
                     }
-                    err_msg + "Backup created successfully."
+                    Ok(err_msg + "Backup created successfully.")
                 }
             } else {
                 if !prompter.confirm("Are you sure you want to restore to the last saved version?")
                 {
-                    return "Restore cancelled by user.".to_string();
+                    return Ok("Restore cancelled by user.".to_string());
                 }
                 log::log_info("restore", "SAVE PARSED");
                 // Restore operation
-                if can_save(root_path) {
+                if can_save(fs, root_path) {
                     // `save_me` file already exists.
                     log::log_info("save", "Saved file found. Proceeding with restoration.");
-                    if restore_sekai("save", root_path).is_err() {
+                    if restore_sekai(fs, "save", root_path).is_err() {
                         err_msg +=
                             "Failed to restore Sekai. Please check the logs for more details.";
                         log::log_error("save", err_msg.as_str());
-                        return err_msg;
+                        return Err(err_msg);
                     }
-                    "Sekai restored successfully from previously saved progress.\n".to_string()
+                    Ok("Sekai restored successfully from previously saved progress.\n".to_string())
                 } else {
-                    (err_msg + "No restore file found. Please save your progress first with `save` command.")
-                        .to_string()
+                    Err(err_msg
+                        + "No restore file found. Please save your progress first with `save` command.")
                 }
             }
         }
         Err(e) => match &e[..] {
-            "help" => HELP_TEXT.to_string(),
-            "unknown" => "restore: unknown flag\nTry 'help save' for more information.".to_string(),
-            _ => "Error parsing arguments. Try 'help restore' for more information.".to_string(),
+            "help" => Ok(HELP_TEXT.to_string()),
+            "unknown" => {
+                Err("restore: unknown flag\nTry 'help save' for more information.".to_string())
+            }
+            _ => {
+                Err("Error parsing arguments. Try 'help restore' for more information.".to_string())
+            }
         },
     }
 }
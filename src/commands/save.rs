@@ -1,5 +1,6 @@
 use super::argparser::ArgParser;
 use crate::rns::restore_comp::backup_sekai;
+use crate::sekai_fs::SekaiFs;
 use crate::utils::log;
 use std::path::Path;
 
@@ -10,7 +11,7 @@ Saves the progress of your sekai progress. This means all your progress is saved
 - save  : Save your current progress of the Sekai.
 "#;
 
-pub fn save(args: &[&str], root_path: &Path) -> String {
+pub fn save(fs: &dyn SekaiFs, args: &[&str], root_path: &Path) -> Result<String, String> {
     let mut parser = ArgParser::new(&[]);
     let args_string: Vec<String> = args.iter().map(|s| s.to_string()).collect();
 
@@ -22,21 +23,21 @@ pub fn save(args: &[&str], root_path: &Path) -> String {
             if !pos_args.is_empty() {
                 err_msg += "Too many positional arguments provided. No arguments expected.";
                 log::log_info("save", err_msg.as_str());
-                return err_msg;
+                return Err(err_msg);
             }
-            if backup_sekai("save", root_path).is_err() {
+            if backup_sekai(fs, "save", root_path).is_err() {
                 err_msg += "Failed to save Sekai. Please check the logs for more details.";
                 log::log_error("save", err_msg.as_str());
-                return err_msg;
+                return Err(err_msg);
             } else {
                 log::log_info("save", "Sekai saved successfully.");
             }
-            "Sekai saved successfully \n".to_string()
+            Ok("Sekai saved successfully \n".to_string())
         }
         Err(e) => match &e[..] {
-            "help" => HELP_TEXT.to_string(),
+            "help" => Ok(HELP_TEXT.to_string()),
 
-            _ => "Error parsing arguments. Try 'help save' for more information.".to_string(),
+            _ => Err("Error parsing arguments. Try 'help save' for more information.".to_string()),
         },
     }
 }
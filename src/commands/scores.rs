@@ -0,0 +1,110 @@
+use super::argparser::ArgParser;
+use crate::utils::log;
+use crate::utils::progress::{EventKind, get_freeze_time, load_progress};
+use chrono::{DateTime, Local};
+
+pub const HELP_TXT: &str = r#"
+Usage: scores [OPTIONS]
+
+Shows your standing in the leaderboard, with the levels and chests you have solved or unlocked.
+Options:
+    -a, --all         Show the full leaderboard
+
+Examples:
+- scores              # Show your own standing
+- scores --all        # Show the standing of all players
+"#;
+
+fn format_time(timestamp: i64) -> String {
+    DateTime::from_timestamp(timestamp, 0)
+        .map(|t| {
+            t.with_timezone(&Local)
+                .format("%Y-%m-%d %H:%M:%S")
+                .to_string()
+        })
+        .unwrap_or_else(|| "unknown time".to_string())
+}
+
+pub fn scores(args: &[&str], username: Option<&str>) -> String {
+    let mut parser = ArgParser::new(&["-a", "--all"]);
+    let args_string: Vec<String> = args.iter().map(|s| s.to_string()).collect();
+    let mut err_msg: String = "scores: ".to_string();
+
+    match parser.parse(&args_string, "scores") {
+        Ok(_) => {
+            if !parser.get_positional_args().is_empty() {
+                err_msg += "No positional arguments expected. Try 'help scores'.";
+                log::log_info("scores", err_msg.as_str());
+                return err_msg;
+            }
+            let Some(username) = username else {
+                err_msg += "User not authenticated. Please log in or play as guest.";
+                log::log_info("scores", err_msg.as_str());
+                return err_msg;
+            };
+
+            let progress = load_progress();
+            let freeze = get_freeze_time();
+            let frozen = freeze.is_some_and(|f| chrono::Utc::now().timestamp() > f);
+            let board = progress.leaderboard(freeze);
+            let mut output = String::new();
+            if frozen {
+                output += "The leaderboard is frozen, ranks do not include the latest events.\n";
+            }
+
+            if parser.has_flag("-a") || parser.has_flag("--all") {
+                if board.is_empty() {
+                    return "No scores recorded yet.".to_string();
+                }
+                output += "\nRank  Points  Player\n";
+                for entry in &board {
+                    let marker = if entry.username == username {
+                        " <- you"
+                    } else {
+                        ""
+                    };
+                    output += &format!(
+                        "{:>4}  {:>6}  {}{marker}\n",
+                        entry.rank, entry.points, entry.username
+                    );
+                }
+                return output;
+            }
+
+            let events = progress.user_events(username);
+            let live_points = progress
+                .leaderboard(None)
+                .into_iter()
+                .find(|e| e.username == username)
+                .map(|e| e.points)
+                .unwrap_or(0);
+            output += &format!("Player: {username}\nPoints: {live_points}\n");
+            match board.iter().find(|e| e.username == username) {
+                Some(entry) => output += &format!("Rank: {} of {}\n", entry.rank, board.len()),
+                None => output += "Rank: unranked\n",
+            }
+            if events.is_empty() {
+                output += "\nNothing solved or unlocked yet. Good luck!";
+                return output;
+            }
+            output += "\nHistory:\n";
+            for event in events {
+                let kind = match event.kind {
+                    EventKind::Solve => "solved",
+                    EventKind::Unlock => "unlocked",
+                };
+                output += &format!(
+                    "  [{}] {kind} {} ({} points)\n",
+                    format_time(event.timestamp),
+                    event.object,
+                    event.points
+                );
+            }
+            output
+        }
+        Err(e) => match &e[..] {
+            "help" => HELP_TXT.to_string(),
+            _ => "Error parsing arguments. Try 'help scores' for more information.".to_string(),
+        },
+    }
+}
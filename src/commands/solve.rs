@@ -1,9 +1,13 @@
 use super::argparser::ArgParser;
 use super::cmds::normalize_path;
+use super::display_relative_path;
 use crate::metainfo::info_reader::read_get_obj_info;
 use crate::metainfo::lock_perm::read_lock_perm;
-use crate::rns::security::{characterise_enc_key, decrypt, encrypt};
-use crate::utils::{auth::get_current_username, log, prompt::UserPrompter};
+use crate::rns::security::{characterise_enc_key, decrypt, user_flag};
+use crate::sekai_fs::SekaiFs;
+use crate::utils::globals::UserInfo;
+use crate::utils::progress::{self, EventKind};
+use crate::utils::{log, prompt::UserPrompter};
 use std::path::Path;
 pub const HELP_TEXT: &str = r#"
 Usage: solve [OPTIONS] <LEVEL_NAME> <
@@ -15,11 +19,13 @@ Examples:
 "#;
 
 pub fn solve(
+    fs: &dyn SekaiFs,
     args: &[&str],
     current_dir: &Path,
     root_dir: &Path,
+    user: Option<&UserInfo>,
     prompter: &mut dyn UserPrompter,
-) -> String {
+) -> Result<String, String> {
     //only 1 argumen :path to level
     let mut parser = ArgParser::new(&[]);
     let args_string: Vec<String> = args.iter().map(|s| s.to_string()).collect();
@@ -38,37 +44,37 @@ pub fn solve(
             if pos_args.len() > 1 {
                 err_msg += "Too many positional arguments provided. Only 1 argument expected.";
                 log::log_info("solve", err_msg.as_str());
-                return err_msg;
+                return Err(err_msg);
             }
             if pos_args.is_empty() {
                 err_msg += "No positional argument provided. Expected path to level.";
                 log::log_info("solve", err_msg.as_str());
-                return err_msg;
+                return Err(err_msg);
             }
             //now we know only 1 argument is there
             //test for valid level name
             let target = normalize_path(&current_dir.join(pos_args[0]));
-            if !target.exists() {
+            if !fs.exists(&target) {
                 err_msg += "Invalid path given";
                 log::log_info("solve", err_msg.as_str());
-                return err_msg;
+                return Err(err_msg);
             }
             //validated path. now check if it is a protected thing
-            if let Ok((is_level, is_locked)) = read_lock_perm(&target) {
+            if let Ok((is_level, is_locked)) = read_lock_perm(fs, &target) {
                 if !is_level {
                     err_msg += "This is not a level. Cannot solve.";
                     log::log_info("solve", err_msg.as_str());
-                    return err_msg;
+                    return Err(err_msg);
                 }
                 if is_locked {
                     err_msg += "Level is locked. You must first unlock it.";
                     log::log_info("solve", err_msg.as_str());
-                    return err_msg;
+                    return Err(err_msg);
                 }
             } else {
                 err_msg += "Failed to read lock permissions for the level.";
                 log::log_error("solve", err_msg.as_str());
-                return err_msg;
+                return Err(err_msg);
             }
 
             let Ok(level_name) = target
@@ -78,7 +84,7 @@ pub fn solve(
             else {
                 err_msg += "Failed to get level name from path.";
                 log::log_error("solve", err_msg.as_str());
-                return err_msg;
+                return Err(err_msg);
             };
             log::log_info("solve", &format!("Level name: {level_name}"));
             let user_input =
@@ -86,44 +92,64 @@ pub fn solve(
             if user_input.is_empty() {
                 err_msg += "No input provided. Cannot solve.";
                 log::log_info("solve", err_msg.as_str());
-                err_msg
+                Err(err_msg)
             } else {
-                let username = get_current_username().unwrap_or("default_user");
-                let user_flag = check_solve_input(user_input, &target, level_name, username);
+                let Some(user) = user else {
+                    err_msg += "User not authenticated. Please log in or play as guest.";
+                    log::log_error("solve", err_msg.as_str());
+                    return Err(err_msg);
+                };
+                let username = user.get_username();
+                let user_flag =
+                    check_solve_input(fs, user_input, &target, level_name, user.flag_name());
                 match user_flag {
                     Ok(flag) => {
                         log::log_info(
                             "solve",
                             &format!("Successfully generated User flag: {flag}"),
                         );
-                        format!("User flag: {flag}")
+                        let points = read_get_obj_info(
+                            fs,
+                            &target.parent().unwrap().join(".dir_info/info.json"),
+                            level_name,
+                        )
+                        .map(|info| info.get_points())
+                        .unwrap_or(0);
+                        progress::record_event(
+                            username,
+                            EventKind::Solve,
+                            &display_relative_path(&target, root_dir),
+                            points,
+                        );
+                        Ok(format!("User flag: {flag}"))
                     }
                     Err(e) => {
                         err_msg += &format!("Error solving {level_name}: {e}");
                         log::log_error("solve", err_msg.as_str());
-                        err_msg
+                        Err(err_msg)
                     }
                 }
             }
         }
 
         Err(e) => match &e[..] {
-            "help" => HELP_TEXT.to_string(),
-            _ => "Error parsing arguments. Try 'help solve' for more information.".to_string(),
+            "help" => Ok(HELP_TEXT.to_string()),
+            _ => Err("Error parsing arguments. Try 'help solve' for more information.".to_string()),
         },
     }
 }
 
 fn check_solve_input(
+    fs: &dyn SekaiFs,
     user_input: String,
     path_to_level: &Path,
     level_name: &str,
-    username: &str,
+    flag_name: &str,
 ) -> Result<String, String> {
     let info_path = path_to_level.parent().unwrap().join(".dir_info/info.json");
     println!("info_path: {}", info_path.display());
     println!("level_name: {level_name}");
-    if let Some(text_decrypt_me) = read_get_obj_info(&info_path, level_name)
+    if let Some(text_decrypt_me) = read_get_obj_info(fs, &info_path, level_name)
         .unwrap()
         .properties
         .get("decrypt_me")
@@ -131,8 +157,8 @@ fn check_solve_input(
         .map(|s| s.to_string())
     {
         println!("text_decrypt_me: {text_decrypt_me}");
-        println!("Username: {username}");
-        let user_inp_enc_key = characterise_enc_key(username, level_name);
+        println!("Flag name: {flag_name}");
+        let user_inp_enc_key = characterise_enc_key(flag_name, level_name);
         let decrypted_user_input = decrypt(&user_inp_enc_key, &user_input);
         println!("decrypted _user input: {decrypted_user_input}");
 
@@ -143,14 +169,7 @@ fn check_solve_input(
             &text_decrypt_me,
         );
         println!("decrypted _decrypt me : {decrypted_decrypt_me}");
-        let user_flag: String = encrypt(
-            &characterise_enc_key(
-                &format!("{}_{}", username, username.len()),
-                &format!("{username}_{level_name}"),
-            ),
-            &decrypted_decrypt_me,
-        );
-        Ok(user_flag)
+        Ok(user_flag(flag_name, level_name, &decrypted_decrypt_me))
     } else {
         //unable to read decrypt_me property
         println!("Unable to read decrypt_me property from info.json for level: {level_name}");
@@ -3,6 +3,7 @@ use super::cmds::{check_dir_info, normalize_path};
 use super::display_relative_path;
 use crate::metainfo::info_reader::add_obj_to_info;
 use crate::metainfo::valid_sekai::create_dir_info;
+use crate::sekai_fs::SekaiFs;
 use crate::utils::log;
 use std::path::{Path, PathBuf};
 
@@ -20,69 +21,86 @@ Examples:
 - tap new_dir/    # usage of trailing slash to create a directory
 "#;
 
-pub fn create_file(destination: &str, current_dir: &Path, root_dir: &Path) -> String {
+pub fn create_file(
+    fs: &dyn SekaiFs,
+    destination: &str,
+    current_dir: &Path,
+    root_dir: &Path,
+) -> Result<String, String> {
     let new_path: &Path = &current_dir.join(destination);
 
     // Check if the path already exists
-    if new_path.exists() {
-        return format!("tap: {destination}: File or directory already exists");
+    if fs.exists(new_path) {
+        return Err(format!(
+            "tap: {destination}: File or directory already exists"
+        ));
     }
 
     // Create the file or directory
-    match std::fs::File::create(new_path) {
+    match fs.write(new_path, &[]) {
         Ok(_) => {
             // Add the object to info.json
             let obj_name = new_path.file_name().and_then(|n| n.to_str()).unwrap_or("");
-            let result = add_obj_to_info(new_path, obj_name, None).map_err(|e| e.to_string()); // Convert InfoError to String
+            let result = add_obj_to_info(fs, new_path, obj_name, None).map_err(|e| e.to_string()); // Convert InfoError to String
 
             log::log_result("tap", result, "Adding object to info.json");
-            format!(
+            Ok(format!(
                 "Created file: {}",
                 display_relative_path(new_path, root_dir)
-            )
+            ))
         }
-        Err(e) => format!("tap: {destination}: {e}"),
+        Err(e) => Err(format!("tap: {destination}: {e}")),
     }
 }
 
-pub fn create_directory(destination: &str, current_dir: &Path, root_dir: &Path) -> String {
+pub fn create_directory(
+    fs: &dyn SekaiFs,
+    destination: &str,
+    current_dir: &Path,
+    root_dir: &Path,
+) -> Result<String, String> {
     let new_path: &PathBuf = &current_dir.join(destination);
 
     // Check if the path already exists
-    if new_path.exists() {
-        return format!(
+    if fs.exists(new_path) {
+        return Err(format!(
             "tap: {}: Directory already exists",
             display_relative_path(new_path, root_dir)
-        );
+        ));
     }
 
     // Create the directory
-    match std::fs::create_dir(new_path) {
+    match fs.create_dir(new_path) {
         Ok(_) => {
             // create .dir_info automatically
-            if !create_dir_info(new_path, false) {
-                return format!(
+            if !create_dir_info(fs, new_path, root_dir) {
+                return Err(format!(
                     "tap: Failed to create .dir_info: {}",
                     display_relative_path(new_path, root_dir)
-                );
+                ));
             }
 
             // Add the object to info.json
             let obj_name = new_path.file_name().and_then(|n| n.to_str()).unwrap_or("");
-            let result = add_obj_to_info(new_path, obj_name, None).map_err(|e| e.to_string()); // Convert InfoError to String
+            let result = add_obj_to_info(fs, new_path, obj_name, None).map_err(|e| e.to_string()); // Convert InfoError to String
 
             log::log_result("tap", result, "Adding object to info.json");
-            format!(
+            Ok(format!(
                 "Created directory: {}",
                 display_relative_path(new_path, root_dir)
-            )
+            ))
         }
-        Err(e) => format!("tap: {}: {}", display_relative_path(new_path, root_dir), e),
+        Err(e) => Err(format!(
+            "tap: {}: {}",
+            display_relative_path(new_path, root_dir),
+            e
+        )),
     }
 }
 
 /// Checks the validity of the destination path based on root_dir and current_dir.
 fn handle_destination(
+    fs: &dyn SekaiFs,
     destination: &Path,
     current_dir: &Path,
     root_dir: &Path,
@@ -101,7 +119,7 @@ fn handle_destination(
         ));
     }
     // Check if path already exists
-    if new_path.exists() {
+    if fs.exists(&new_path) {
         log::log_warning("tap", "File or directory already exists");
         return Err(format!(
             "tap: {}: File or directory already exists",
@@ -113,7 +131,12 @@ fn handle_destination(
 }
 
 // Check if the destination is within the root directory
-pub fn tap(args: &[&str], current_dir: &Path, root_dir: &Path) -> String {
+pub fn tap(
+    fs: &dyn SekaiFs,
+    args: &[&str],
+    current_dir: &Path,
+    root_dir: &Path,
+) -> Result<String, String> {
     let valid_flags = ["-d", "--dir", "-h", "--help"];
     let mut parser = ArgParser::new(&valid_flags);
 
@@ -135,37 +158,34 @@ pub fn tap(args: &[&str], current_dir: &Path, root_dir: &Path) -> String {
                 .unwrap_or(&"") as &str;
 
             if destination.is_empty() {
-                return "tap: No destination specified. Use 'tap --help' for usage.".to_string();
+                return Err(
+                    "tap: No destination specified. Use 'tap --help' for usage.".to_string()
+                );
             }
             if check_dir_info(Path::new(destination)) {
-                return "tap: Cannot create/refer restricted files or directory. Operation Not Allowed."
-                    .to_string();
+                return Err("tap: Cannot create/refer restricted files or directory. Operation Not Allowed."
+                    .to_string());
             }
-            // handle destination path
+            // handle destination path, this is a relative valid path then
             let destination_path = Path::new(destination);
-            if handle_destination(destination_path, current_dir, root_dir).is_err() {
-                // the error string will be printed
-                return handle_destination(destination_path, current_dir, root_dir).unwrap_err();
-            } else {
-                // This is relative valid path
-                destination = destination_path.to_str().unwrap();
-            }
+            handle_destination(fs, destination_path, current_dir, root_dir)?;
+            destination = destination_path.to_str().unwrap();
 
             if args.contains(&"-d") || args.contains(&"--dir") {
                 destination = destination.trim_end_matches('/');
                 // Create a directory
-                create_directory(destination, current_dir, root_dir)
+                create_directory(fs, destination, current_dir, root_dir)
             } else if destination.ends_with('/') {
-                create_directory(destination.trim_end_matches('/'), current_dir, root_dir)
+                create_directory(fs, destination.trim_end_matches('/'), current_dir, root_dir)
             } else {
                 // Create a file
-                create_file(destination, current_dir, root_dir)
+                create_file(fs, destination, current_dir, root_dir)
             }
         }
         Err(e) => match &e[..] {
-            "help" => HELP_TXT.to_string(),
-            "unknown" => "tap: unknown flag\nTry 'help tap' for more information.".to_string(),
-            _ => "Error parsing arguments. Try 'help tap' for more information.".to_string(),
+            "help" => Ok(HELP_TXT.to_string()),
+            "unknown" => Err("tap: unknown flag\nTry 'help tap' for more information.".to_string()),
+            _ => Err("Error parsing arguments. Try 'help tap' for more information.".to_string()),
         },
     }
 }
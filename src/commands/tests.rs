@@ -1,7 +1,16 @@
 #[cfg(test)]
 mod commands_tests {
+    use crate::commands::cmds::{CommandResponse, CommandResult};
     use crate::commands::go::navigate;
-    use crate::utils::test_utils::setup_test_dir;
+    use crate::commands::output::Style;
+    use crate::commands::read::read;
+    use crate::metainfo::info_reader::{InfoTransaction, update_obj_status};
+    use crate::metainfo::mime::is_inline_safe;
+    use crate::sekai_fs::DiskFs;
+    use crate::session::{Sekai, Session};
+    use crate::utils::globals::UserInfo;
+    use crate::utils::test_utils::{ScriptedPrompter, setup_test_dir};
+    use std::sync::Arc;
 
     /// Layout of the temporary directory structure used in this test:
     /// temp_dir/
@@ -22,57 +31,272 @@ mod commands_tests {
         let mut current_dir = root_path.clone();
 
         // Navigate to subdir1
-        let (new_path, msg) = navigate("subdir1", &current_dir, &root_path);
+        let (new_path, msg) = navigate(&DiskFs, "subdir1", &current_dir, &root_path).unwrap();
         println!("Navigating to subdir1: {msg}");
         assert_eq!(new_path, root_path.join("subdir1"));
 
         // Navigate to nested1
         current_dir = new_path;
-        let (new_path, _) = navigate("nested1", &current_dir, &root_path);
+        let (new_path, _) = navigate(&DiskFs, "nested1", &current_dir, &root_path).unwrap();
         assert_eq!(new_path, root_path.join("subdir1/nested1"));
 
         // Navigate back to subdir1
         current_dir = new_path;
-        let (new_path, _) = navigate("..", &current_dir, &root_path);
+        let (new_path, _) = navigate(&DiskFs, "..", &current_dir, &root_path).unwrap();
         assert_eq!(new_path, root_path.join("subdir1"));
 
         // Navigate to subdir2 from subdir1
         current_dir = new_path;
-        let (new_path, _) = navigate("../subdir2", &current_dir, &root_path);
+        let (new_path, _) = navigate(&DiskFs, "../subdir2", &current_dir, &root_path).unwrap();
         assert_eq!(new_path, root_path.join("subdir2"));
 
         // Navigate to HOME
         current_dir = new_path;
-        let (new_path, _) = navigate("HOME", &current_dir, &root_path);
+        let (new_path, _) = navigate(&DiskFs, "HOME", &current_dir, &root_path).unwrap();
         assert_eq!(new_path, root_path);
 
         // Try to go back from root
         current_dir = new_path;
-        let (new_path, message) = navigate("..", &current_dir, &root_path);
-        assert_eq!(new_path, root_path);
+        let message = navigate(&DiskFs, "..", &current_dir, &root_path).unwrap_err();
         assert!(message.contains("You are at the root"));
 
         // Navigate to nonexistent directory
-        let (new_path, message) = navigate("nonexistent", &current_dir, &root_path);
-        assert_eq!(new_path, current_dir);
+        let message = navigate(&DiskFs, "nonexistent", &current_dir, &root_path).unwrap_err();
         assert!(message.contains("No such directory"));
 
         // Navigate to a file
-        let (new_path, message) = navigate("file1.txt", &current_dir, &root_path);
-        println!("{new_path:?}, {current_dir:?}, {message}");
-        assert_eq!(new_path, current_dir);
+        let message = navigate(&DiskFs, "file1.txt", &current_dir, &root_path).unwrap_err();
         assert!(message.contains("Is a file"));
 
         // Navigate to a restricted directory
-        let (new_path, message) = navigate(".dir_info", &current_dir, &root_path);
-        assert_eq!(new_path, current_dir);
+        let message = navigate(&DiskFs, ".dir_info", &current_dir, &root_path).unwrap_err();
         assert!(message.contains("Attempted to go to/refers a restricted directory"));
 
         // Try patterns on destination
         current_dir = root_path.to_path_buf();
-        let (new_path, message) =
-            navigate("subdir1/../subdir2/../.dir_info", &current_dir, &root_path);
-        assert_eq!(new_path, current_dir);
+        let message = navigate(
+            &DiskFs,
+            "subdir1/../subdir2/../.dir_info",
+            &current_dir,
+            &root_path,
+        )
+        .unwrap_err();
         assert!(message.contains("Attempted to go to/refers a restricted directory"));
     }
+
+    fn read_output(args: &[&str], root_path: &std::path::Path) -> String {
+        match read(&DiskFs, args, root_path, root_path) {
+            CommandResult::Output(output) | CommandResult::Error(output) => output.to_string(),
+            _ => panic!("read {args:?} did not give an output"),
+        }
+    }
+
+    #[test]
+    fn test_read_binary_objects() {
+        let (_temp_dir, root_path) = setup_test_dir(true);
+        let png = b"\x89PNG\r\n\x1a\n\x00\x00\x00\x0dIHDR";
+        std::fs::write(root_path.join("map"), png).unwrap();
+        std::fs::write(
+            root_path.join("dump.bin"),
+            b"\x00\x01flag{hidden}\x02ab\xff",
+        )
+        .unwrap();
+
+        // Text is printed as before
+        assert_eq!(
+            read_output(&["file1.txt"], &root_path).trim(),
+            "hello from file1"
+        );
+
+        // Images are typed by their magic bytes, not their name
+        match read(&DiskFs, &["map"], &root_path, &root_path) {
+            CommandResult::ShowAsset(path, mime, _) => {
+                assert_eq!(path, std::path::Path::new("map"));
+                assert_eq!(mime, "image/png");
+                assert!(is_inline_safe(&mime));
+            }
+            _ => panic!("read on an image did not show it"),
+        }
+
+        // Other binary files point to the forensics views
+        assert!(read_output(&["dump.bin"], &root_path).contains("Binary file"));
+        assert_eq!(read_output(&["-s", "dump.bin"], &root_path), "flag{hidden}");
+        let hex = read_output(&["--hex", "dump.bin"], &root_path);
+        assert!(
+            hex.starts_with("00000000: 0001 666c 6167 7b68 6964 6465 6e7d 0261  ..flag{hidden}.a"),
+            "{hex}"
+        );
+        assert!(hex.ends_with("  b."), "{hex}");
+
+        // The author can set the type in info.json
+        update_obj_status(
+            &DiskFs,
+            &root_path.join("dump.bin"),
+            "dump.bin",
+            "mime",
+            serde_json::Value::from("audio/wav"),
+        )
+        .unwrap();
+        assert!(matches!(
+            read(&DiskFs, &["dump.bin"], &root_path, &root_path),
+            CommandResult::ShowAsset(_, mime, _) if mime == "audio/wav"
+        ));
+        // but types which can run scripts are not served to be shown
+        assert!(!is_inline_safe("image/svg+xml"));
+
+        // The checks of read still apply
+        assert!(read_output(&["../outside.png"], &root_path).contains("Access denied"));
+        assert!(read_output(&["missing.png"], &root_path).contains("No such file"));
+    }
+
+    fn output(result: CommandResult) -> String {
+        match result {
+            CommandResult::Output(msg)
+            | CommandResult::Error(msg)
+            | CommandResult::ChangeDirectory(_, msg) => msg.to_string(),
+            _ => String::new(),
+        }
+    }
+
+    /// Test that sessions on the same Sekai keep their own player, directory and history
+    #[test]
+    fn test_sessions_are_independent() {
+        let (_temp_dir, root_path) = setup_test_dir(true);
+        let sekai = Sekai::new(Arc::new(DiskFs), root_path.clone());
+        let mut alice = Session::new(sekai.clone());
+        alice.user = Some(UserInfo::new("alice".into(), String::new(), String::new()));
+        let mut guest = Session::new(sekai);
+
+        assert!(matches!(
+            alice.run("go subdir1"),
+            CommandResult::ChangeDirectory(..)
+        ));
+        assert_eq!(alice.current_dir, root_path.join("subdir1"));
+        assert_eq!(guest.current_dir, root_path);
+
+        assert!(output(alice.run("whoami")).contains("alice"));
+        assert!(output(guest.run("whoami")).contains("Not logged in"));
+        assert!(matches!(guest.run("   "), CommandResult::NotFound));
+        assert_eq!(alice.history, ["go subdir1", "whoami"]);
+        assert_eq!(guest.history, ["whoami"]);
+
+        // Developer commands need Dev Mode
+        assert!(matches!(guest.run("dev"), CommandResult::Error(_)));
+        assert!(output(guest.run("dev")).contains("only available in Dev Mode"));
+        guest.dev_mode = true;
+        assert!(!output(guest.run("dev")).contains("only available in Dev Mode"));
+    }
+
+    /// Test that a Sekai locked with `dev lock` passes `dev check`
+    #[test]
+    fn test_dev_lock_passes_dev_check() {
+        use crate::commands::dev::lock::dev_lock;
+        use crate::metainfo::lint::{LintOptions, lint_sekai};
+        use crate::metainfo::valid_sekai::validate_or_create_sekai;
+        use argon2::password_hash::{SaltString, rand_core::OsRng};
+        let (_temp_dir, root_path) = setup_test_dir(false);
+        assert!(validate_or_create_sekai(&DiskFs, &root_path, false));
+        let salt = SaltString::generate(&mut OsRng);
+        let dev = UserInfo::new("dev".into(), salt.as_str().into(), String::new());
+
+        for (args, answers) in [
+            (&["-t", "-l", "subdir1"][..], &[][..]),
+            (&["-t", "-l", "subdir2"], &[]),
+            (&["-ll", "subdir1", "subdir2"], &["solution", "FLAG{first}"]),
+            (&["-t", "-c", "file1.txt"], &[]),
+            (&["-s", "-l", "file1.txt"], &["key"]),
+        ] {
+            let mut prompter = ScriptedPrompter::new(answers);
+            let result = dev_lock(
+                &DiskFs,
+                args,
+                &root_path,
+                &root_path,
+                Some(&dev),
+                &mut prompter,
+            );
+            assert!(result.is_ok(), "dev lock {args:?}: {result:?}");
+        }
+
+        let report = lint_sekai(&root_path, &LintOptions::default());
+        assert_eq!(report.errors, 0, "{}", report.to_human());
+    }
+
+    /// Test that outputs are made of styled segments and errors come apart from them
+    #[test]
+    fn test_structured_output() {
+        let (_temp_dir, root_path) = setup_test_dir(true);
+        InfoTransaction::new(&DiskFs, &root_path.join("file1.txt"))
+            .set("file1.txt", "obj_salt", "salt".into())
+            .set("file1.txt", "compare_me", "compare".into())
+            .set("file1.txt", "locked", "01".into())
+            .commit()
+            .unwrap();
+        let mut session = Session::new(Sekai::new(Arc::new(DiskFs), root_path.clone()));
+
+        let CommandResult::Output(ls) = session.run("ls") else {
+            panic!("ls did not give an output");
+        };
+        assert!(ls.to_string().contains("   file1.txt (locked)\n"));
+        let styled = |style: Style| -> Vec<&str> {
+            ls.segments()
+                .iter()
+                .filter(|segment| segment.style == style)
+                .map(|segment| segment.text.as_str())
+                .collect()
+        };
+        assert_eq!(styled(Style::Locked), ["file1.txt"]);
+        assert_eq!(styled(Style::Path), ["subdir1", "subdir2"]);
+        assert_eq!(
+            styled(Style::Heading),
+            ["Objects:", "From inside here, you can go to:"]
+        );
+
+        let response = CommandResponse::from(session.run("ls missing"));
+        assert!(response.error);
+        assert!(response.output.contains("cannot access"));
+        let response = CommandResponse::from(session.run("nothing"));
+        assert!(response.error);
+        assert_eq!(response.output, "Command not found. Try `help`.");
+
+        // No more sentinels in the output
+        let response = serde_json::to_value(CommandResponse::from(session.run("clear"))).unwrap();
+        assert_eq!(response["clear"], true);
+        assert_eq!(response["output"], "");
+        let response =
+            serde_json::to_value(CommandResponse::from(session.run("whereami"))).unwrap();
+        assert_eq!(
+            response["segments"],
+            serde_json::json!([{"style": "path", "text": "HOME"}])
+        );
+
+        // Commands which fail are errors, and go stays where it is
+        session.user = Some(UserInfo::new(
+            "alice".to_string(),
+            String::new(),
+            String::new(),
+        ));
+        for failing in [
+            "go missing",
+            "go file1.txt",
+            "del missing",
+            "tap subdir1",
+            "unlock missing",
+            "unlock file1.txt",
+        ] {
+            let response = CommandResponse::from(session.run(failing));
+            assert!(response.error, "{failing}: {}", response.output);
+            assert_eq!(response.new_current_dir, None);
+        }
+        let response = CommandResponse::from(session.run("tap new.txt"));
+        assert!(!response.error, "{}", response.output);
+        let response = CommandResponse::from(session.run("del new.txt"));
+        assert!(!response.error, "{}", response.output);
+        let response = CommandResponse::from(session.run("go subdir1"));
+        assert!(!response.error);
+        assert_eq!(
+            response.new_current_dir,
+            Some(root_path.join("subdir1").display().to_string())
+        );
+    }
 }
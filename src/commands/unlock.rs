@@ -1,10 +1,14 @@
 use super::argparser::ArgParser;
 use super::cmds::normalize_path;
+use super::display_relative_path;
 use crate::metainfo::info_reader::{read_get_obj_info, update_obj_status};
 use crate::metainfo::lock_perm::operation_locked_perm;
 use crate::metainfo::read_lock_perm;
-use crate::rns::security::{argonhash, characterise_enc_key, decrypt, encrypt};
-use crate::utils::{auth::get_current_user, log, prompt::UserPrompter};
+use crate::rns::security::{argonhash, characterise_enc_key, decrypt, encrypt, read_user_flag};
+use crate::sekai_fs::SekaiFs;
+use crate::utils::globals::UserInfo;
+use crate::utils::progress::{self, EventKind};
+use crate::utils::{log, prompt::UserPrompter};
 use argon2::password_hash::SaltString;
 use std::path::Path;
 
@@ -21,11 +25,13 @@ Examples:
 
 "#;
 pub fn unlock(
+    fs: &dyn SekaiFs,
     args: &[&str],
     current_dir: &Path,
     root_dir: &Path,
+    user: Option<&UserInfo>,
     prompter: &mut dyn UserPrompter,
-) -> String {
+) -> Result<String, String> {
     //one argument giving path to the chest/level to be unlocked
     let mut parser = ArgParser::new(&["-l", "--level", "-c", "--chest"]);
     let args_string: Vec<String> = args.iter().map(|s| s.to_string()).collect();
@@ -40,12 +46,12 @@ pub fn unlock(
     );
     match parser.parse(&args_string, "unlock") {
         Ok(_) => {
-            let user_info = match get_current_user() {
+            let user_info = match user {
                 Some(info) => info,
                 None => {
-                    err_msg += "User not authenticated. Please log in.";
+                    err_msg += "User not authenticated. Please log in or play as guest.";
                     log::log_error("unlock", err_msg.as_str());
-                    return err_msg;
+                    return Err(err_msg);
                 }
             };
             let username = &user_info.username;
@@ -55,28 +61,30 @@ pub fn unlock(
             if pos_args.len() != 1 {
                 err_msg += "Exactly one positional argument -giving path to directory/file to be unlocked -is expected.";
                 log::log_info("unlock", err_msg.as_str());
-                return err_msg;
+                return Err(err_msg);
             }
             //now we know only 1 argument is there
             //validate path existence
             let target = normalize_path(&current_dir.join(pos_args[0]));
-            if !target.exists() {
+            if !fs.exists(&target) {
                 err_msg += "Invalid path given";
                 log::log_info("unlock", err_msg.as_str());
-                return err_msg;
+                return Err(err_msg);
             }
             //validated path. now check if it is accessible
             if let Err(msg) = operation_locked_perm(
+                fs,
                 target.parent().unwrap(),
+                root_dir,
                 "unlock",
                 "you cannot try to unlock a chest/level nested inside a locked directory/level",
             ) {
                 err_msg += msg.as_str();
                 log::log_info("unlock", err_msg.as_str());
-                return err_msg;
+                return Err(err_msg);
             }
             //now check if it is a protected thing
-            if let Ok((is_level, is_locked)) = read_lock_perm(&target) {
+            if let Ok((is_level, is_locked)) = read_lock_perm(fs, &target) {
                 if !is_locked {
                     err_msg += format!(
                         "{} is not locked, you can try accessing it directly.",
@@ -84,7 +92,7 @@ pub fn unlock(
                     )
                     .as_str();
                     log::log_info("unlock", err_msg.as_str());
-                    return err_msg;
+                    return Err(err_msg);
                 }
                 //since it protected and open for unlocking read level/chest id
 
@@ -99,11 +107,12 @@ pub fn unlock(
                         locked_obj_name.err().unwrap()
                     );
                     log::log_error("unlock", err_msg.as_str());
-                    return err_msg;
+                    return Err(err_msg);
                 }
                 let locked_obj_name = locked_obj_name.unwrap();
 
                 let locked_obj_info = read_get_obj_info(
+                    fs,
                     &target.parent().unwrap().join("./.dir_info/info.json"),
                     locked_obj_name,
                 );
@@ -113,7 +122,7 @@ pub fn unlock(
                         locked_obj_info.err().unwrap()
                     );
                     log::log_error("unlock", err_msg.as_str());
-                    return err_msg;
+                    return Err(err_msg);
                 }
                 let locked_obj_info = locked_obj_info.unwrap();
 
@@ -127,7 +136,7 @@ pub fn unlock(
                         obj_salt.as_ref().err().unwrap()
                     );
                     log::log_error("unlock", err_msg.as_str());
-                    return err_msg;
+                    return Err(err_msg);
                 }
                 let obj_salt = obj_salt.as_ref().unwrap();
 
@@ -139,13 +148,31 @@ pub fn unlock(
                     err_msg +=
                         &format!("Failed to get compare_me for the level/chest: {locked_obj_name}");
                     log::log_error("unlock", err_msg.as_str());
-                    return err_msg;
+                    return Err(err_msg);
                 }
                 let compare_me = compare_me.as_ref().unwrap();
 
+                // wrong flags are throttled per user and object
+                let object_id = display_relative_path(&target, root_dir);
+                let wait = progress::unlock_wait(username, &object_id);
+                if wait > 0 {
+                    err_msg += &format!("Too many wrong attempts. Try again in {wait} seconds.");
+                    log::log_info("unlock", err_msg.as_str());
+                    return Err(err_msg);
+                }
+
                 // take flag
                 let user_flag =
                     prompter.input(format!("Enter the flag for {locked_obj_name}:").as_str());
+                if user_flag.trim().is_empty() {
+                    err_msg += "No flag provided.";
+                    return Err(err_msg);
+                }
+                if let Err(refused) = progress::start_unlock_attempt(username, &object_id) {
+                    err_msg += &refused;
+                    log::log_info("unlock", err_msg.as_str());
+                    return Err(err_msg);
+                }
 
                 if is_level {
                     let decrypt_me = &locked_obj_info.properties["decrypt_me"]
@@ -156,7 +183,7 @@ pub fn unlock(
                             "Failed to get encrypted flag for the level/chest: {locked_obj_name}"
                         );
                         log::log_error("unlock", err_msg.as_str());
-                        return err_msg;
+                        return Err(err_msg);
                     }
                     let decrypt_me = decrypt_me.as_ref().unwrap();
                     let (result, message) = check_level(
@@ -165,12 +192,13 @@ pub fn unlock(
                         obj_salt,
                         decrypt_me,
                         compare_me,
-                        username,
+                        user_info.flag_name(),
                         user_salt_hex,
                     );
                     if result {
                         //change lock status in info.json
                         let update_attempt = update_obj_status(
+                            fs,
                             &target,
                             locked_obj_name,
                             "locked",
@@ -182,20 +210,31 @@ pub fn unlock(
                                 update_attempt.err().unwrap()
                             );
                             log::log_error("unlock", err_msg.as_str());
-                            return err_msg;
+                            return Err(err_msg);
                         }
                         log::log_info(
                             "unlock",
                             "changes regarding lock status of object made to info.json successfully",
                         );
-                        format!("{locked_obj_name} is unlocked")
+                        progress::clear_unlock_failures(username, &object_id);
+                        progress::record_event(
+                            username,
+                            EventKind::Unlock,
+                            &object_id,
+                            locked_obj_info.get_points(),
+                        );
+                        Ok(format!("{locked_obj_name} is unlocked"))
                     } else {
                         //flag incorrect or faced some error
                         err_msg += message.as_str();
 
                         err_msg += "Invalid flag. Try again.";
+                        let wait = progress::unlock_wait(username, &object_id);
+                        if wait > 0 {
+                            err_msg += &format!(" Next attempt allowed in {wait} seconds.");
+                        }
                         log::log_info("unlock", err_msg.as_str());
-                        err_msg
+                        Err(err_msg)
                     }
                 } else {
                     //if chest
@@ -210,6 +249,7 @@ pub fn unlock(
                     if result {
                         //update obj_info_lock_perm
                         let update_attempt = update_obj_status(
+                            fs,
                             &target,
                             locked_obj_name,
                             "locked",
@@ -221,31 +261,43 @@ pub fn unlock(
                                 update_attempt.err().unwrap()
                             );
                             log::log_error("unlock", err_msg.as_str());
-                            return err_msg;
+                            return Err(err_msg);
                         }
                         log::log_info(
                             "unlock",
                             "changes regarding lock status of object made to info.json successfully",
                         );
-
-                        format!(" Chest {locked_obj_name} is unlocked")
+                        progress::clear_unlock_failures(username, &object_id);
+                        progress::record_event(
+                            username,
+                            EventKind::Unlock,
+                            &object_id,
+                            locked_obj_info.get_points(),
+                        );
+                        Ok(format!(" Chest {locked_obj_name} is unlocked"))
                     } else {
                         //flag incorrect or faced some error
                         err_msg += message.as_str();
                         err_msg += "Invalid flag. Try again.";
+                        let wait = progress::unlock_wait(username, &object_id);
+                        if wait > 0 {
+                            err_msg += &format!(" Next attempt allowed in {wait} seconds.");
+                        }
                         log::log_info("unlock", err_msg.as_str());
-                        err_msg
+                        Err(err_msg)
                     }
                 }
             } else {
                 err_msg += "Unable to read lock status of the given target. Cannot unlock.";
                 log::log_info("unlock", err_msg.as_str());
-                err_msg
+                Err(err_msg)
             }
         }
         Err(e) => match &e[..] {
-            "help" => HELP_TXT.to_string(),
-            _ => "Error parsing arguments. Try 'help unlock' for more information.".to_string(),
+            "help" => Ok(HELP_TXT.to_string()),
+            _ => {
+                Err("Error parsing arguments. Try 'help unlock' for more information.".to_string())
+            }
         },
     }
 }
@@ -256,7 +308,7 @@ fn check_level(
     level_salt: &str,
     encrypted_flag: &str,
     compare_me: &str,
-    username: &str,
+    flag_name: &str,
     user_salt_hex: &str,
 ) -> (bool, String) {
     let obj_salt = SaltString::from_b64(level_salt).expect("Invalid obj_salt format");
@@ -280,13 +332,7 @@ fn check_level(
         );
     }
     let user_salt = user_salt.unwrap();
-    let decrypted_user_flag = decrypt(
-        &characterise_enc_key(
-            &format!("{}_{}", username, username.len()),
-            &format!("{username}_{level_name}"),
-        ),
-        &user_flag,
-    );
+    let decrypted_user_flag = read_user_flag(flag_name, level_name, &user_flag);
     let l1_hashed_user_flag = argonhash(&obj_salt, decrypted_user_flag);
     let hashed_with_usersalt = argonhash(&user_salt, l1_hashed_user_flag);
     let compare_me_decrypted = decrypt(&characterise_enc_key(level_salt, level_name), compare_me);
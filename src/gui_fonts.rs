@@ -0,0 +1,303 @@
+use crate::utils::{assets, config};
+use raylib::ffi::{self, DrawTextEx, LoadFontFromMemory, MeasureTextEx, UnloadFont, Vector2};
+use raylib::prelude::{Color, Font, RaylibHandle, RaylibThread};
+use std::cell::RefCell;
+use std::collections::{BTreeMap, BTreeSet};
+use std::ffi::CString;
+use std::os::raw::{c_char, c_int};
+use std::path::PathBuf;
+use unicode_segmentation::UnicodeSegmentation;
+
+/*
+FONT FALLBACK EXPLANATION:
+
+raylib draws a text with one font, and only with the glyphs it was loaded with, which is ASCII
+unless told otherwise. The shell font has no Devanagari or Kannada, so every other character is
+drawn with the first font in the chain which has it:
+1. The font picked in the settings.
+2. The fonts in `fallback_fonts` of the config, for scripts none of the others have.
+3. The bundled Noto Sans, then common system fonts with Indic scripts, if they are installed.
+
+Bundled fonts are compiled in, see `utils::assets`, and every font is loaded from memory.
+
+The glyphs of a font are loaded when a character it has is first drawn, which reloads the font.
+Characters no font has are drawn as `?`. raylib does not shape text, so conjuncts and vowel signs
+of Indic scripts are drawn one glyph after another.
+*/
+
+/// Bundled font tried after the ones in the config.
+const BUNDLED_FALLBACK_FONT: &str = "NotoSans-VariableFont_wdth,wght.ttf";
+
+/// System fonts tried after the bundled one, the missing ones are skipped.
+const SYSTEM_FONTS: [&str; 10] = [
+    "/usr/share/fonts/truetype/noto/NotoSansDevanagari-Regular.ttf",
+    "/usr/share/fonts/noto/NotoSansDevanagari-Regular.ttf",
+    "/usr/share/fonts/truetype/noto/NotoSansKannada-Regular.ttf",
+    "/usr/share/fonts/noto/NotoSansKannada-Regular.ttf",
+    "/usr/share/fonts/truetype/lohit-devanagari/Lohit-Devanagari.ttf",
+    "/usr/share/fonts/truetype/lohit-kannada/Lohit-Kannada.ttf",
+    "/System/Library/Fonts/Supplemental/Arial Unicode.ttf",
+    "/Library/Fonts/Arial Unicode.ttf",
+    "C:\\Windows\\Fonts\\Nirmala.ttf",
+    "C:\\Windows\\Fonts\\mangal.ttf",
+];
+
+/// Size the shell font is rendered at, it is scaled down when drawn.
+const PRIMARY_BASE_SIZE: c_int = 600;
+/// Fallback fonts can load many glyphs, so they are rendered smaller.
+const FALLBACK_BASE_SIZE: c_int = 128;
+
+/// Printable ASCII, which every font is loaded with.
+fn is_ascii_glyph(c: char) -> bool {
+    (' '..='~').contains(&c)
+}
+
+/// Where a font of the chain is read from.
+#[derive(PartialEq)]
+enum FontSource {
+    /// File name of a bundled font
+    Bundled(String),
+    File(PathBuf),
+}
+
+impl FontSource {
+    fn read(&self) -> Vec<u8> {
+        match self {
+            FontSource::Bundled(name) => assets::font(name)
+                .map(|data| data.into_owned())
+                .unwrap_or_default(),
+            FontSource::File(path) => std::fs::read(path).unwrap_or_default(),
+        }
+    }
+}
+
+struct ChainFont {
+    source: FontSource,
+    base_size: c_int,
+    /// Contents of the font, read when first needed
+    data: Option<Vec<u8>>,
+    /// Loaded when first drawn with
+    font: Option<ffi::Font>,
+    /// Characters beyond ASCII the font is loaded with
+    glyphs: BTreeSet<char>,
+    stale: bool,
+}
+
+impl ChainFont {
+    fn new(source: FontSource, base_size: c_int) -> Self {
+        Self {
+            source,
+            base_size,
+            data: None,
+            font: None,
+            glyphs: BTreeSet::new(),
+            stale: true,
+        }
+    }
+
+    fn data(&mut self) -> &[u8] {
+        self.data.get_or_insert_with(|| self.source.read())
+    }
+
+    fn has_glyph(&mut self, c: char) -> bool {
+        ttf_parser::Face::parse(self.data(), 0).is_ok_and(|face| face.glyph_index(c).is_some())
+    }
+
+    /// The font with all its glyphs, loading it again if glyphs were added.
+    fn font(&mut self) -> ffi::Font {
+        if let (Some(font), false) = (self.font, self.stale) {
+            return font;
+        }
+        if let Some(font) = self.font.take() {
+            unsafe { UnloadFont(font) };
+        }
+        let mut codepoints: Vec<c_int> = (' '..='~')
+            .chain(self.glyphs.iter().copied())
+            .map(|c| c as c_int)
+            .collect();
+        let base_size = self.base_size;
+        let data = self.data();
+        let font = unsafe {
+            LoadFontFromMemory(
+                c".ttf".as_ptr() as *const c_char,
+                data.as_ptr(),
+                data.len() as c_int,
+                base_size,
+                codepoints.as_mut_ptr(),
+                codepoints.len() as c_int,
+            )
+        };
+        self.font = Some(font);
+        self.stale = false;
+        font
+    }
+}
+
+struct ChainState {
+    fonts: Vec<ChainFont>,
+    /// Index of the font each character beyond ASCII is drawn with
+    chosen: BTreeMap<char, usize>,
+}
+
+impl ChainState {
+    /// Font to draw the grapheme with, the first one with its first character. The rest of
+    /// the grapheme, like vowel signs, is loaded into the same font.
+    fn font_for(&mut self, grapheme: &str) -> usize {
+        let mut chars = grapheme.chars();
+        let Some(first) = chars.next() else {
+            return 0;
+        };
+        let index = self.choose(first);
+        for c in chars {
+            if !is_ascii_glyph(c) && !self.fonts[index].glyphs.contains(&c) {
+                let font = &mut self.fonts[index];
+                if font.has_glyph(c) {
+                    font.glyphs.insert(c);
+                    font.stale = true;
+                }
+            }
+        }
+        index
+    }
+
+    fn choose(&mut self, c: char) -> usize {
+        if is_ascii_glyph(c) {
+            return 0;
+        }
+        if let Some(index) = self.chosen.get(&c) {
+            return *index;
+        }
+        // Drawn as '?' with the shell font if no font has it
+        let index = match (0..self.fonts.len()).find(|i| self.fonts[*i].has_glyph(c)) {
+            Some(index) => {
+                let font = &mut self.fonts[index];
+                font.glyphs.insert(c);
+                font.stale = true;
+                index
+            }
+            None => 0,
+        };
+        self.chosen.insert(c, index);
+        index
+    }
+
+    /// Splits the text into runs drawn with the same font.
+    fn runs(&mut self, text: &str) -> Vec<(ffi::Font, CString)> {
+        let mut runs: Vec<(usize, String)> = Vec::new();
+        for grapheme in text.graphemes(true) {
+            let index = self.font_for(grapheme);
+            match runs.last_mut() {
+                Some((last, run)) if *last == index => run.push_str(grapheme),
+                _ => runs.push((index, grapheme.to_string())),
+            }
+        }
+        runs.into_iter()
+            .map(|(index, run)| {
+                let run = CString::new(run.replace('\0', "")).unwrap_or_default();
+                (self.fonts[index].font(), run)
+            })
+            .collect()
+    }
+}
+
+/// The shell font with the fonts drawing the characters it does not have, see the
+/// explanation above.
+pub struct FontChain {
+    state: RefCell<ChainState>,
+}
+
+impl FontChain {
+    /// Chain starting with the bundled font with the file name.
+    pub fn new(primary: &str) -> Self {
+        let mut fonts = vec![ChainFont::new(
+            FontSource::Bundled(primary.to_string()),
+            PRIMARY_BASE_SIZE,
+        )];
+        let configured = config::load_config().fallback_fonts;
+        let sources = configured
+            .iter()
+            .map(PathBuf::from)
+            .filter(|path| path.is_file())
+            .map(FontSource::File)
+            .chain([FontSource::Bundled(BUNDLED_FALLBACK_FONT.to_string())])
+            .chain(
+                SYSTEM_FONTS
+                    .iter()
+                    .map(PathBuf::from)
+                    .filter(|path| path.is_file())
+                    .map(FontSource::File),
+            );
+        for source in sources {
+            if !fonts.iter().any(|f| f.source == source) {
+                fonts.push(ChainFont::new(source, FALLBACK_BASE_SIZE));
+            }
+        }
+        Self {
+            state: RefCell::new(ChainState {
+                fonts,
+                chosen: BTreeMap::new(),
+            }),
+        }
+    }
+
+    /// The shell font. It is loaded again when glyphs are added, so it must not be kept.
+    pub fn primary(&self) -> ffi::Font {
+        self.state.borrow_mut().fonts[0].font()
+    }
+
+    pub fn draw_text(
+        &self,
+        text: &str,
+        position: Vector2,
+        font_size: f32,
+        spacing: f32,
+        color: Color,
+    ) {
+        let mut x = position.x;
+        for (font, run) in self.state.borrow_mut().runs(text) {
+            unsafe {
+                DrawTextEx(
+                    font,
+                    run.as_ptr(),
+                    Vector2 { x, y: position.y },
+                    font_size,
+                    spacing,
+                    color.into(),
+                );
+                x += MeasureTextEx(font, run.as_ptr(), font_size, spacing).x + spacing;
+            }
+        }
+    }
+
+    /// Width of the text as drawn by `draw_text`.
+    pub fn measure_text(&self, text: &str, font_size: f32, spacing: f32) -> f32 {
+        let runs = self.state.borrow_mut().runs(text);
+        let widths: f32 = runs
+            .iter()
+            .map(|(font, run)| unsafe { MeasureTextEx(*font, run.as_ptr(), font_size, spacing).x })
+            .sum();
+        widths + spacing * runs.len().saturating_sub(1) as f32
+    }
+}
+
+/// Loads the bundled font with the file name, for the menus.
+pub fn load_bundled_font(
+    rl: &mut RaylibHandle,
+    thread: &RaylibThread,
+    name: &str,
+    font_size: i32,
+) -> Result<Font, String> {
+    let data = assets::font(name).ok_or_else(|| format!("No bundled font {name}"))?;
+    rl.load_font_from_memory(thread, ".ttf", &data, font_size, None)
+        .map_err(|e| format!("Failed to load font {name}: {e}"))
+}
+
+impl Drop for FontChain {
+    fn drop(&mut self) {
+        for font in self.state.get_mut().fonts.iter_mut() {
+            if let Some(font) = font.font.take() {
+                unsafe { UnloadFont(font) };
+            }
+        }
+    }
+}
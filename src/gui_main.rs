@@ -1,99 +1,13 @@
-use crate::epr_log_error;
 use crate::gui_shell::ShellScreen;
 use crate::menu::{self, menu_options::MenuOption};
-use crate::metainfo::valid_sekai::validate_or_create_sekai;
-use crate::rns::restore_comp;
-use crate::utils::{globals::get_sekai_dir, log};
+use crate::metainfo::valid_sekai::sekai_initialize;
+use crate::sekai_fs::{MemFs, SekaiFs};
+use crate::session::{Sekai, Session};
+use crate::utils::assets;
+use crate::utils::log;
 use raylib::prelude::{RaylibHandle, RaylibThread};
 use std::path::Path;
-
-/// Validates the Sekai directory and sets it as the world directory
-pub fn sekai_initialize(sekai_path: &Path) {
-    log::log_info(
-        "SEKAI",
-        format!("Starting Sekai validation for: {}", sekai_path.display()).as_str(),
-    );
-    // Just check first for HOME directory validity and create if not.
-    // If not valid, create .dir_info for each of them.
-    if !validate_or_create_sekai(sekai_path, false) {
-        epr_log_error!(
-            "SEKAI",
-            "Sekai directory is not valid even after creating default `.dir_info`. Sekai: {sekai_path:?}"
-        );
-        return;
-    } else {
-        // sekai is valid
-        log::log_info("SEKAI", &format!("Sekai is Valid {sekai_path:?}"));
-
-        // Create the restore file if it doesn't exist, since it is required for restoring. The
-        // progress will be saved as `save_me` and will be recreated every run.
-        log::log_info(
-            "SEKAI",
-            &format!(
-                "Creating restore file for Sekai at {:?}",
-                sekai_path.join(".dir_info/restore_me.deemak")
-            ),
-        );
-        // restore_me should be made initially if it doesnt exist, else it will not be created
-        match restore_comp::backup_sekai("restore", sekai_path) {
-            Err(e) => {
-                log::log_error(
-                    "SEKAI",
-                    &format!("Failed to create restore file for: {sekai_path:?} Error: {e}"),
-                );
-                eprintln!(
-                    "Error: Failed to create restore file for: {sekai_path:?} Error: {e}.\nContinuing..."
-                );
-                return;
-            }
-            Ok(msg) => {
-                log::log_info("SEKAI", &msg);
-            }
-        }
-
-        // save_me should be made initially if it doesnt exist, it will be created every run
-        log::log_info(
-            "SEKAI",
-            &format!(
-                "Creating save file for Sekai at {:?}",
-                sekai_path.join(".dir_info/save_me.deemak")
-            ),
-        );
-        // Not copying the restore file to save file, since the password will be different.
-        match restore_comp::backup_sekai("save", sekai_path) {
-            Err(e) => {
-                log::log_error(
-                    "SEKAI",
-                    &format!("Failed to create save file for: {sekai_path:?} Error: {e}"),
-                );
-                eprintln!(
-                    "Error: Failed to create save file for: {sekai_path:?} Error: {e}.\nContinuing..."
-                );
-                return;
-            }
-            Ok(msg) => {
-                log::log_info("SEKAI", &msg);
-            }
-        }
-    }
-
-    // If `save_me` already exists, then the sekai will be restored from it.
-    match restore_comp::restore_sekai("save", sekai_path) {
-        Err(err) => {
-            log::log_error(
-                "SEKAI",
-                &format!("Failed to restore Sekai from save file: {err}"),
-            );
-            eprintln!(
-                "Error: Failed to restore Sekai from save file at {sekai_path:?}. Error: {err}
-Continuing..."
-            );
-        }
-        Ok(_) => {
-            log::log_info("SEKAI", "Sekai restored successfully from save file");
-        }
-    }
-}
+use std::sync::Arc;
 
 #[derive(Debug, Clone, Default)]
 struct InitBools {
@@ -101,17 +15,36 @@ struct InitBools {
     sekai_initialized: bool,
 }
 
-/// Runs the main GUI loop for the Sekai shell
-pub fn run_gui_loop(rl: &mut RaylibHandle, thread: &RaylibThread, font_size: f32) {
+/// Runs the main GUI loop for the Sekai shell, played in the session
+pub fn run_gui_loop(
+    rl: &mut RaylibHandle,
+    thread: &RaylibThread,
+    session: Session,
+    font_size: f32,
+) {
     let mut init_state = InitBools::default();
-    // This will exist, since sekai directory is set in main.rs
-    let sekai_path = &get_sekai_dir();
-    let mut sekai_shell = ShellScreen::new_sekai(rl, thread, sekai_path.to_path_buf(), font_size);
+    let sekai = session.sekai.clone();
+    let sekai_path = &sekai.root;
+    let user = session.user.clone();
+    let mut sekai_shell = ShellScreen::new_sekai(rl, thread, session, font_size);
 
-    // Initialize the Tutorial directory
-    let tutorial_dir = &Path::new(env!("CARGO_MANIFEST_DIR")).join("_tutorial");
-    let mut tutorial_shell =
-        ShellScreen::new_sekai(rl, thread, tutorial_dir.to_path_buf(), font_size);
+    // The Tutorial is compiled in and played from memory
+    let tutorial_dir = &MemFs::root_for(Path::new("tutorial.deemak"));
+    let tutorial_fs: Arc<dyn SekaiFs> = match assets::tutorial_fs(tutorial_dir) {
+        Ok(mem) => Arc::new(mem),
+        Err(e) => {
+            log::log_error("Deemak", &format!("Failed to load the Tutorial: {e}"));
+            Arc::new(MemFs::new(tutorial_dir))
+        }
+    };
+    // The player plays the Tutorial too, without Dev Mode
+    let tutorial = Sekai::new(tutorial_fs.clone(), tutorial_dir.to_path_buf());
+    let tutorial_session = || {
+        let mut session = Session::new(tutorial.clone());
+        session.user = user.clone();
+        session
+    };
+    let mut tutorial_shell = ShellScreen::new_sekai(rl, thread, tutorial_session(), font_size);
 
     loop {
         // Show main menu and get user selection
@@ -124,7 +57,7 @@ pub fn run_gui_loop(rl: &mut RaylibHandle, thread: &RaylibThread, font_size: f32
                     format!("Starting Shell: Sekai: {}", sekai_path.display()).as_str(),
                 );
                 if !init_state.sekai_initialized {
-                    sekai_initialize(sekai_path);
+                    sekai_initialize(sekai.fs.as_ref(), sekai_path);
                     init_state.sekai_initialized = true;
                 }
                 sekai_shell.run(rl, thread);
@@ -139,9 +72,9 @@ pub fn run_gui_loop(rl: &mut RaylibHandle, thread: &RaylibThread, font_size: f32
                 // Tutorial screen
                 log::log_info("Deemak", "Loading Tutorial");
                 if !init_state.tutorial_initialized {
-                    sekai_initialize(tutorial_dir);
+                    sekai_initialize(tutorial_fs.as_ref(), tutorial_dir);
                     tutorial_shell =
-                        ShellScreen::new_sekai(rl, thread, tutorial_dir.to_path_buf(), font_size);
+                        ShellScreen::new_sekai(rl, thread, tutorial_session(), font_size);
                     init_state.tutorial_initialized = true;
                 }
                 tutorial_shell.run(rl, thread);
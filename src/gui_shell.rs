@@ -1,22 +1,26 @@
-use crate::commands::cmds::{CommandResult, cmd_manager};
+use crate::commands::cmds::CommandResult;
 use crate::commands::ls::list_directory_entries;
-use crate::keys::key_to_char;
+use crate::commands::output::{Output, Style};
+use crate::gui_fonts::FontChain;
+use crate::gui_sidebar::Sidebar;
+use crate::keymap::{Action, KeyCombo, KeyContext, Keymap};
 use crate::metainfo::info_reader::read_validate_info;
-use crate::utils::config;
-use crate::utils::globals::FONT_OPTIONS;
+use crate::metainfo::mime::{ObjectKind, image_file_type};
+use crate::sekai_fs::SekaiFs;
+use crate::session::Session;
+use crate::utils::assets::DEFAULT_FONT;
+use crate::utils::globals::{DEEMAK_BANNER, FONT_OPTIONS};
 use crate::utils::prompt::UserPrompter;
 use crate::utils::tab_completion::{TabCompletionResult, process_tab_completion};
-use crate::utils::{shell_history, wrapit::wrapit};
-use raylib::ffi::{
-    ColorFromHSV, DrawLineEx, DrawRectangle, DrawTextEx, LoadFontEx, MeasureTextEx, SetExitKey,
-    Vector2,
-};
+use crate::utils::theme::{self, Theme};
+use crate::utils::{config, log};
+use crate::utils::{text_input, wrapit::wrapit};
+use raylib::ffi::{DrawLineEx, DrawRectangle, SetExitKey, Vector2};
 use raylib::prelude::*;
 use std::cmp::max;
 use std::cmp::min;
-use std::ffi::CString;
-use std::os::raw::c_char;
-use std::{mem::take, os::raw::c_int, path::PathBuf};
+use std::collections::HashMap;
+use std::{mem::take, os::raw::c_int, path::Path};
 use textwrap::wrap;
 
 // This struct is a temporary wrapper to pass to commands that need user input.
@@ -42,17 +46,29 @@ impl<'a> UserPrompter for ShellPrompter<'a> {
     }
 }
 
+/// Image or audio object opened by `read`, shown right of the divider until `clear`.
+struct AssetPanel {
+    title: String,
+    texture: Option<Texture2D>,
+    note: Option<String>,
+}
+
 pub struct ShellScreen {
     input_buffer: String,
     working_buffer: Option<String>,
     output_lines: Vec<String>,
-    current_dir: PathBuf,
-    root_dir: PathBuf,
-    font: ffi::Font,
+    /// Styles of the output lines which are not plain text, by line, as the char each one starts
+    /// at. See `push_output`.
+    output_styles: HashMap<usize, Vec<(usize, Style)>>,
+    session: Session,
+    /// Until the welcome of the Sekai is shown
+    first_run: bool,
+    fonts: FontChain,
     window_width: i32,
     window_height: i32,
     char_width: f32,
     term_split_ratio: f32,
+    font_index: usize,
     font_size: f32,
     scroll_offset: i32,
     active_prompt: Option<String>,
@@ -62,68 +78,72 @@ pub struct ShellScreen {
     selection_end: Option<(usize, usize)>,
     mouse_dragging: bool,
     should_exit: bool,
+    sidebar: Sidebar,
+    asset_panel: Option<AssetPanel>,
+    keymap: Keymap,
 }
 
-pub(crate) static mut FIRST_RUN: bool = true;
-pub const DEEMAK_BANNER: &str = r#"
- _____                            _
-|  __ \                          | |
-| |  | | ___  ___ _ __ ___   __ _| | __
-| |  | |/ _ \/ _ \ '_ ` _ \ / _` | |/ /
-| |__| |  __/  __/ | | | | | (_| |   <
-|_____/ \___|\___|_| |_|_| |\__,_|_|\_|
-
-Developed by Databased Club, Indian Institute of Science, Bangalore.
-Official Github Repo: https://github.com/databasedIISc/deemak
-"#;
-
 pub const INITIAL_MSG: &str = "Type commands and press Enter. Try `help` for more info.";
 
+/// Loads the font of the shell, the first one if the index is not valid.
+fn load_shell_font(font_index: usize) -> FontChain {
+    let font_name = FONT_OPTIONS
+        .get(font_index)
+        .map(|(_, name)| *name)
+        .unwrap_or(DEFAULT_FONT);
+    FontChain::new(font_name)
+}
+
 impl ShellScreen {
+    /// Applies the font, font size, layout and keys of the settings, which may have changed while
+    /// the shell was not shown.
+    fn apply_config(&mut self) {
+        let cfg = config::load_config();
+        if cfg.font_index != self.font_index {
+            self.fonts = load_shell_font(cfg.font_index);
+            self.font_index = cfg.font_index;
+        }
+        self.font_size = cfg.shell_font_size(get_monitor_width(0));
+        self.term_split_ratio = cfg.split_ratio();
+        self.keymap = cfg.keymap;
+        self.char_width = self.fonts.measure_text("W", self.font_size, 1.2);
+    }
+
     pub fn new_sekai(
         rl: &mut RaylibHandle,
         _thread: &RaylibThread,
-        sekai_dir: PathBuf,
+        session: Session,
         font_size: f32,
     ) -> Self {
         // Load font index from config
-        let font_index = config::load_config().font_index;
-        let font_path = FONT_OPTIONS
-            .get(font_index)
-            .map(|(_, path)| *path)
-            .unwrap_or("fontbook/fonts/ttf/JetBrainsMono-Medium.ttf");
-        // Loading Font
-        let font = unsafe {
-            let path = CString::new(font_path).unwrap();
-
-            LoadFontEx(
-                path.as_ptr() as *const c_char,
-                600.0 as c_int,
-                std::ptr::null_mut::<c_int>(),
-                0,
-            )
-        };
+        let cfg = config::load_config();
+        let font_index = cfg.font_index;
+        let fonts = load_shell_font(font_index);
         unsafe {
             SetExitKey(0i32); // No exit key
         }
         let window_width = rl.get_screen_width();
         let window_height = rl.get_screen_height();
-        let char_width = unsafe {
-            let cstr = CString::new("W").unwrap();
-            MeasureTextEx(font, cstr.as_ptr(), font_size, 1.2).x
-        };
+        let char_width = fonts.measure_text("W", font_size, 1.2);
+        let sidebar = Sidebar::new(
+            session.sekai.fs.clone(),
+            session.root(),
+            session.user.clone(),
+        );
         Self {
             input_buffer: String::new(),
             output_lines: Vec::<String>::new(),
+            output_styles: HashMap::new(),
             working_buffer: None,
-            root_dir: sekai_dir.clone(),
-            current_dir: sekai_dir, // Both point to same path initially
-            font,
+            session,
+            first_run: true,
+            fonts,
             font_size,
             window_width,
             window_height,
             char_width,
-            term_split_ratio: 2.0 / 3.0,
+            term_split_ratio: cfg.split_ratio(),
+            font_index,
             scroll_offset: 0,
             active_prompt: None,
             history_index: None,
@@ -132,14 +152,20 @@ impl ShellScreen {
             selection_end: None,
             mouse_dragging: false,
             should_exit: false,
+            sidebar,
+            asset_panel: None,
+            keymap: cfg.keymap,
         }
     }
 
     pub fn run(&mut self, rl: &mut RaylibHandle, thread: &RaylibThread) {
         // Clean up the output lines
         self.output_lines.clear();
+        self.output_styles.clear();
         self.input_buffer.clear();
-        self.current_dir = self.root_dir.clone();
+        self.session.current_dir = self.session.root().to_path_buf();
+        self.apply_config();
+        self.sidebar.refresh(&self.session.current_dir);
         //add to output lines the banner
         let limit: usize = ((self.window_width as f32 * (self.term_split_ratio - 0.12))
             / self.char_width)
@@ -151,16 +177,16 @@ impl ShellScreen {
         self.output_lines
             .extend(wrapped_initial.into_iter().map(|c| c.into_owned()));
 
-        let info_path = self.root_dir.join(".dir_info").join("info.json");
-        let home_about = read_validate_info(&info_path).ok().map(|info| info.about);
-        let home_location = read_validate_info(&info_path)
-            .ok()
-            .map(|info| info.location);
-        let mut home_about = home_about.unwrap_or_else(|| "Welcome User to Deemak!".to_string());
-        let home_location = home_location.unwrap_or_else(|| "HOME".to_string());
+        let info_path = self.session.root().join(".dir_info").join("info.json");
+        let home_info = read_validate_info(self.session.fs(), &info_path).ok();
+        let mut home_about = home_info.as_ref().map_or_else(
+            || "Welcome User to Deemak!".to_string(),
+            |info| info.about.clone(),
+        );
+        let home_location = home_info.map_or_else(|| "HOME".to_string(), |info| info.location);
         let home_about = format!("\nYou are in {home_location}\n\nAbout:\n{home_about}\n");
         let wrapped_home_about = wrap(&home_about, limit);
-        unsafe { FIRST_RUN = false };
+        self.first_run = false;
         self.output_lines
             .extend(wrapped_home_about.into_iter().map(|c| c.into_owned()));
 
@@ -203,32 +229,47 @@ impl ShellScreen {
         // MOUSE END
 
         // Handle keyboard input
-        match rl.get_key_pressed() {
-            Some(KeyboardKey::KEY_ENTER) => {
+        if let Some(combo) = KeyCombo::pressed(rl) {
+            self.handle_key(rl, thread, combo);
+        }
+        self.type_chars(rl);
+
+        // Handle window re-size
+        if rl.is_window_resized() {
+            self.window_width = rl.get_screen_width();
+        }
+
+        // Handle scroll
+        let scroll_y = rl.get_mouse_wheel_move();
+        if scroll_y != 0.0 {
+            self.scroll_offset -= (scroll_y / 2.00) as i32;
+        }
+    }
+
+    /// Runs the action bound to the key, or types it.
+    fn handle_key(&mut self, rl: &mut RaylibHandle, thread: &RaylibThread, combo: KeyCombo) {
+        match self.keymap.action(&combo, KeyContext::Shell) {
+            Some(Action::Submit) => {
                 let input = take(&mut self.input_buffer);
                 if !input.is_empty() {
                     self.process_shell_input(rl, thread, &input);
                     self.scroll_offset = 0;
-                    shell_history::add_to_history(&input);
                     self.history_index = None;
                     self.working_buffer = None; // Clear working buffer after command execution
                 } else {
                     // If input is empty, just add a new line
-                    if !unsafe { FIRST_RUN } {
+                    if !self.first_run {
                         self.output_lines.push("> ".to_string());
                     } else {
-                        unsafe { FIRST_RUN = false };
+                        self.first_run = false;
                     }
                 }
                 self.cursor_pos = 0; // Reset cursor position
             }
-            Some(KeyboardKey::KEY_BACKSPACE) => {
-                if !self.input_buffer.is_empty() && self.cursor_pos > 0 {
-                    self.input_buffer.remove(self.cursor_pos - 1);
-                    self.cursor_pos -= 1;
-                }
+            Some(action @ (Action::DeleteBack | Action::CursorLeft | Action::CursorRight)) => {
+                self.edit_input(action);
             }
-            Some(KeyboardKey::KEY_TAB) => {
+            Some(Action::Complete) => {
                 // Get current command parts
                 let parts: Vec<&str> = self.input_buffer.split_whitespace().collect();
 
@@ -237,7 +278,11 @@ impl ShellScreen {
                     let last_part = parts.last().unwrap();
 
                     // List directory contents
-                    let (files, dirs) = list_directory_entries(&self.current_dir, &self.root_dir);
+                    let (files, dirs) = list_directory_entries(
+                        self.session.fs(),
+                        &self.session.current_dir,
+                        self.session.root(),
+                    );
                     let all_matches = [dirs, files].concat();
 
                     // Find matches
@@ -298,13 +343,13 @@ impl ShellScreen {
                 }
                 self.cursor_pos = self.input_buffer.len(); // Move cursor to end after tab
             }
-            Some(KeyboardKey::KEY_UP) => {
+            Some(Action::HistoryPrev) => {
                 // Save current buffer if we're starting history navigation
                 if self.history_index.is_none() && !self.input_buffer.is_empty() {
                     self.working_buffer = Some(self.input_buffer.clone());
                 }
 
-                let history = shell_history::get_history();
+                let history = &self.session.history;
                 if !history.is_empty() {
                     let new_index = match self.history_index {
                         Some(index) if index > 0 => index - 1,
@@ -316,9 +361,9 @@ impl ShellScreen {
                 }
                 self.cursor_pos = self.input_buffer.len(); //place at the end of the command
             }
-            Some(KeyboardKey::KEY_DOWN) => {
+            Some(Action::HistoryNext) => {
                 if let Some(index) = self.history_index {
-                    let history = shell_history::get_history();
+                    let history = &self.session.history;
                     if index < history.len() - 1 {
                         // Move to next item in history
                         let new_index = index + 1;
@@ -332,94 +377,71 @@ impl ShellScreen {
                     self.cursor_pos = self.input_buffer.len();
                 }
             }
-            Some(KeyboardKey::KEY_LEFT) => {
-                if self.cursor_pos > 0 {
-                    self.cursor_pos -= 1;
-                }
+            Some(Action::ClearScreen) => {
+                // Clear input buffer and reset history index
+                self.output_lines.clear();
+                self.output_styles.clear();
+                self.output_lines.push(INITIAL_MSG.to_string());
+                self.working_buffer = None;
+                self.cursor_pos = 0;
+                // Clear selection
+                self.selection_start = None;
+                self.selection_end = None;
             }
-            Some(KeyboardKey::KEY_RIGHT) => {
-                if self.cursor_pos < self.input_buffer.len() {
-                    self.cursor_pos += 1;
+            Some(Action::Copy) => {
+                if let (Some(start), Some(end)) = (self.selection_start, self.selection_end) {
+                    // Copy selected text
+                    self.copy_selected_text(rl, start, end);
+                } else {
+                    // Nothing to copy, next prompt (original behavior)
+                    self.next_prompt();
                 }
             }
-            Some(key) => {
-                let ctrl_pressed = rl.is_key_down(KeyboardKey::KEY_LEFT_CONTROL)
-                    || rl.is_key_down(KeyboardKey::KEY_RIGHT_CONTROL);
-
-                let shift_pressed = rl.is_key_down(KeyboardKey::KEY_LEFT_SHIFT)
-                    || rl.is_key_down(KeyboardKey::KEY_RIGHT_SHIFT);
-
-                if ctrl_pressed {
-                    match key {
-                        KeyboardKey::KEY_K => {
-                            // Clear input buffer and reset history index
-                            self.output_lines.clear();
-                            self.output_lines.push(INITIAL_MSG.to_string());
-                            self.working_buffer = None;
-                            self.cursor_pos = 0;
-                            // Clear selection
-                            self.selection_start = None;
-                            self.selection_end = None;
-                        }
-                        KeyboardKey::KEY_C => {
-                            if shift_pressed {
-                                // Copy selected text
-                                if let (Some(start), Some(end)) =
-                                    (self.selection_start, self.selection_end)
-                                {
-                                    // Copy selected text
-                                    self.copy_selected_text(rl, start, end);
-                                } else {
-                                    // Next prompt (original behavior)
-                                    self.output_lines.push(format!("> {}", self.input_buffer));
-                                    self.working_buffer = None;
-                                    self.input_buffer.clear();
-                                    self.scroll_offset = 0;
-                                    self.cursor_pos = 0;
-                                }
-                            } else {
-                                // Next prompt
-                                self.output_lines.push(format!("> {}", self.input_buffer));
-                                self.working_buffer = None;
-                                self.input_buffer.clear();
-                                self.scroll_offset = 0;
-                            }
-                        }
-                        KeyboardKey::KEY_V => {
-                            if shift_pressed {
-                                // Paste from clipboard
-                                let clipboard_text = rl.get_clipboard_text().unwrap_or_default();
-                                if !clipboard_text.is_empty() {
-                                    // Remove newlines and carriage returns
-                                    let filtered_text = clipboard_text.replace(['\n', '\r'], "");
-                                    self.input_buffer
-                                        .insert_str(self.cursor_pos, &filtered_text);
-                                    self.cursor_pos += filtered_text.len();
-                                }
-                            }
-                        }
-                        _ => {}
-                    }
-                } else if let Some(c) = key_to_char(key, shift_pressed) {
-                    self.input_buffer.insert(self.cursor_pos, c);
-                    self.cursor_pos += 1;
+            Some(Action::NextPrompt) => self.next_prompt(),
+            Some(Action::Paste) => {
+                // Paste from clipboard
+                let clipboard_text = rl.get_clipboard_text().unwrap_or_default();
+                if !clipboard_text.is_empty() {
+                    // Remove newlines and carriage returns
+                    let filtered_text = clipboard_text.replace(['\n', '\r'], "");
+                    self.cursor_pos =
+                        text_input::insert(&mut self.input_buffer, self.cursor_pos, &filtered_text);
                 }
             }
-            None => {}
+            _ => {}
         }
+    }
 
-        // Handle window re-size
-        if rl.is_window_resized() {
-            self.window_width = rl.get_screen_width();
-        }
+    /// Deletes or moves the cursor by a whole grapheme.
+    fn edit_input(&mut self, action: Action) {
+        self.cursor_pos = match action {
+            Action::DeleteBack => text_input::delete_back(&mut self.input_buffer, self.cursor_pos),
+            Action::CursorLeft => text_input::prev_boundary(&self.input_buffer, self.cursor_pos),
+            Action::CursorRight => text_input::next_boundary(&self.input_buffer, self.cursor_pos),
+            _ => self.cursor_pos,
+        };
+    }
 
-        // Handle scroll
-        let scroll_y = rl.get_mouse_wheel_move();
-        if scroll_y != 0.0 {
-            self.scroll_offset -= (scroll_y / 2.00) as i32;
+    /// Types the characters from the keyboard layout or input method into the input.
+    fn type_chars(&mut self, rl: &mut RaylibHandle) {
+        let mut typed = String::new();
+        while let Some(c) = rl.get_char_pressed() {
+            typed.push(c);
+        }
+        if !typed.is_empty() {
+            self.cursor_pos = text_input::insert(&mut self.input_buffer, self.cursor_pos, &typed);
         }
     }
 
+    /// Leaves the input as it is on a new prompt.
+    fn next_prompt(&mut self) {
+        self.output_lines.push(format!("> {}", self.input_buffer));
+        self.working_buffer = None;
+        self.input_buffer.clear();
+        self.scroll_offset = 0;
+        self.cursor_pos = 0;
+    }
+
     pub fn get_window_lines(&self) -> Vec<String> {
         let char_width = self.char_width; // Assuming char_width is stored in the struct
         let limit = ((self.window_width as f32 * (self.term_split_ratio - 0.12)) / char_width)
@@ -457,21 +479,28 @@ impl ShellScreen {
     }
     pub fn draw(&mut self, rl: &mut RaylibHandle, thread: &RaylibThread) {
         // Draw output lines
-        let char_width = unsafe {
-            let cstr = CString::new("W").unwrap();
-            MeasureTextEx(self.font, cstr.as_ptr(), self.font_size, 1.2).x
-        };
+        let char_width = self.char_width;
         let limit = ((self.window_width as f32 * (self.term_split_ratio - 0.12)) / char_width)
             .floor() as usize;
         let max_lines_on_screen = self.window_height / self.font_size as i32;
 
         let mut visible_lines = Vec::<String>::new();
-        for line in self.output_lines.iter() {
+        let mut visible_styles = Vec::<Vec<(usize, Style)>>::new();
+        for (n, line) in self.output_lines.iter().enumerate() {
             let lines = if line.len() > limit {
                 wrapit(line, limit)
             } else {
                 vec![line.to_string()]
             };
+            let styles = self.output_styles.get(&n);
+            let mut start = 0;
+            for wrapped in &lines {
+                let end = start + wrapped.chars().count();
+                visible_styles.push(
+                    styles.map_or_else(Vec::new, |styles| wrapped_styles(styles, start, end)),
+                );
+                start = end;
+            }
             visible_lines.extend(lines);
         }
 
@@ -480,8 +509,9 @@ impl ShellScreen {
         self.scroll_offset = max(self.scroll_offset, min_scroll_offset);
         self.scroll_offset = min(self.scroll_offset, 0); // Never go below bottom
 
+        let theme = theme::current();
         let mut d = rl.begin_drawing(thread);
-        d.clear_background(Color::BLACK);
+        d.clear_background(theme.background.color());
 
         // Input
         let input_line = if let Some(ref prompt) = self.active_prompt {
@@ -498,6 +528,7 @@ impl ShellScreen {
         };
 
         let length_input: usize = input_lines.len();
+        visible_styles.resize(visible_styles.len() + length_input, Vec::new());
         visible_lines.extend(input_lines);
 
         let mut index: usize;
@@ -510,6 +541,7 @@ impl ShellScreen {
             visible_lines.len() as i32 - 1,
         ) as usize;
         let display_lines = &visible_lines[index..];
+        let display_styles = &visible_styles[index..];
 
         // MOUSE TEXT SELECTION START
         // This below is the same as get_window_lines function, but i wasnt able to use it directly
@@ -591,7 +623,7 @@ impl ShellScreen {
                             y as c_int,
                             (end_x - start_x) as c_int,
                             self.font_size as c_int,
-                            ColorFromHSV(210.0, 0.5, 0.5), // Blue selection color
+                            theme.selection.color().into(),
                         );
                     }
                 }
@@ -601,41 +633,21 @@ impl ShellScreen {
 
         // When drawing text, we need to ensure it appears above the selection
         for (i, line) in display_lines.iter().enumerate() {
-            unsafe {
-                let pos: Vector2 = Vector2 {
-                    x: 10.0,
-                    y: 10.0 + (i as f32 * self.font_size),
-                };
-                let content = CString::new(line.to_string()).unwrap();
-                DrawTextEx(
-                    self.font,
-                    content.as_ptr() as *const c_char,
-                    pos,
-                    self.font_size,
-                    1.2,
-                    ColorFromHSV(0.0, 0.0, 1.0),
-                );
-            }
+            let pos: Vector2 = Vector2 {
+                x: 10.0,
+                y: 10.0 + (i as f32 * self.font_size),
+            };
+            self.draw_output_line(line, &display_styles[i], pos, &theme);
         }
         //promt
 
         // '>' at the beginning of every line
-        unsafe {
-            let pos_cursr: Vector2 = Vector2 {
-                x: 10.0,
-                y: 10.0 + ((display_lines.len() - length_input) as f32 * self.font_size),
-            };
-            let content = CString::new(">").unwrap();
-
-            DrawTextEx(
-                self.font,
-                content.as_ptr() as *const c_char,
-                pos_cursr,
-                self.font_size,
-                1.2,
-                ColorFromHSV(0.0, 0.0, 1.0),
-            );
-        }
+        let pos_cursr: Vector2 = Vector2 {
+            x: 10.0,
+            y: 10.0 + ((display_lines.len() - length_input) as f32 * self.font_size),
+        };
+        self.fonts
+            .draw_text(">", pos_cursr, self.font_size, 1.2, theme.text.color());
 
         // CURSOR
         let cursor_prefix = if let Some(ref prompt) = self.active_prompt {
@@ -648,21 +660,20 @@ impl ShellScreen {
         } else {
             format!("{}{}", cursor_prefix, &self.input_buffer)
         };
-        let cursor_line =
-            display_lines.len() - length_input + wrapit(&cursor_text, limit).len() - 1;
-        let cursor_x_offset = unsafe {
-            let c_string = CString::new(cursor_text).unwrap();
-            (MeasureTextEx(self.font, c_string.as_ptr(), self.font_size, 1.2).x)
-                % ((limit as f32 + 6.0) * char_width)
-        };
+        let cursor_wrapped = wrapit(&cursor_text, limit);
+        let cursor_line = display_lines.len() - length_input + cursor_wrapped.len() - 1;
+        // The cursor is after the text on its wrapped line
+        let cursor_x_offset = cursor_wrapped.last().map_or(0.0, |line| {
+            self.fonts.measure_text(line, self.font_size, 1.2)
+        });
         // Draw cursor
         unsafe {
             DrawRectangle(
                 (10.6 + cursor_x_offset) as c_int,
                 (10.0 + (cursor_line as f32 * self.font_size)) as c_int,
-                (char_width as f32 * 1.2) as c_int,
+                (char_width * 1.2) as c_int,
                 self.font_size as c_int,
-                ColorFromHSV(0.0, 0.0, 1.0),
+                theme.text.color().into(),
             );
         }
 
@@ -679,11 +690,67 @@ impl ShellScreen {
                     y: self.window_height as f32,
                 },
                 2.0,
-                ColorFromHSV(0.0, 0.0, 0.3),
+                theme.divider.color().into(),
+            );
+        }
+        self.draw_side_panel(&mut d, &theme);
+    }
+
+    /// Draws the line in the colours of its styles, see `push_output`.
+    fn draw_output_line(&self, line: &str, styles: &[(usize, Style)], pos: Vector2, theme: &Theme) {
+        if styles.is_empty() {
+            self.fonts
+                .draw_text(line, pos, self.font_size, 1.2, theme.text.color());
+            return;
+        }
+        let chars: Vec<char> = line.chars().collect();
+        for (i, &(from, style)) in styles.iter().enumerate() {
+            let to = styles
+                .get(i + 1)
+                .map_or(chars.len(), |next| next.0)
+                .min(chars.len());
+            if from >= to {
+                continue;
+            }
+            let before: String = chars[..from].iter().collect();
+            let run: String = chars[from..to].iter().collect();
+            // draw_text leaves the spacing between runs as well
+            let x = if before.is_empty() {
+                pos.x
+            } else {
+                pos.x + self.fonts.measure_text(&before, self.font_size, 1.2) + 1.2
+            };
+            self.fonts.draw_text(
+                &run,
+                Vector2 { x, y: pos.y },
+                self.font_size,
+                1.2,
+                theme.style(style).color(),
             );
         }
     }
 
+    /// Adds the output of a command, keeping the styles of its lines for `draw`. The text of
+    /// errors is drawn as an error.
+    fn push_output(&mut self, output: &Output, error: bool) {
+        for line in output.lines() {
+            let mut text = String::new();
+            let mut styles = Vec::new();
+            for segment in line {
+                let style = match segment.style {
+                    Style::Text if error => Style::Error,
+                    style => style,
+                };
+                styles.push((text.chars().count(), style));
+                text.push_str(&segment.text);
+            }
+            if styles.iter().any(|&(_, style)| style != Style::Text) {
+                self.output_styles.insert(self.output_lines.len(), styles);
+            }
+            self.output_lines.push(text);
+        }
+    }
+
     pub fn process_input(&mut self, input: &str, prefix: Option<&str>) -> Vec<String> {
         if input.is_empty() {
             return self.output_lines.clone();
@@ -708,36 +775,156 @@ impl ShellScreen {
         }
         self.output_lines = self.process_input(input, Some(">"));
 
-        // Parse and execute command
-        let current_dir = self.current_dir.clone();
-        let root_dir = self.root_dir.clone();
-        let parts: Vec<&str> = input.split_whitespace().collect();
+        // Prompts of commands draw the shell, so the session is lent to the command meanwhile
+        let fs = self.session.sekai.fs.clone();
+        let lent = Session::new(self.session.sekai.clone());
+        let mut session = std::mem::replace(&mut self.session, lent);
         let mut prompter = ShellPrompter {
             shell: self,
             rl,
             thread,
         };
-        match cmd_manager(&parts, &current_dir, &root_dir, &mut prompter) {
-            CommandResult::ChangeDirectory(new_dir, message) => {
-                self.current_dir = new_dir;
-                self.output_lines
-                    .extend(message.split('\n').map(|s| s.to_string()));
+        let result = session.run_with(input, &mut prompter);
+        self.session = session;
+        match result {
+            CommandResult::ChangeDirectory(_, output) | CommandResult::Output(output) => {
+                self.push_output(&output, false);
             }
-            CommandResult::Output(output) => {
-                self.output_lines
-                    .extend(output.split('\n').map(|s| s.to_string()));
+            CommandResult::Error(output) => {
+                self.push_output(&output, true);
+            }
+            CommandResult::ShowAsset(path, mime, message) => {
+                self.push_output(&message, false);
+                let path = self.session.root().join(path);
+                self.asset_panel = Some(Self::open_asset(
+                    rl,
+                    thread,
+                    fs.as_ref(),
+                    &path,
+                    &mime,
+                    message.to_string(),
+                ));
             }
             CommandResult::Clear => {
                 self.output_lines.clear();
+                self.output_styles.clear();
                 self.output_lines.push(INITIAL_MSG.to_string());
+                self.asset_panel = None;
             }
             CommandResult::Exit => {
                 self.should_exit = true;
             }
             CommandResult::NotFound => {
-                self.output_lines
-                    .push("Command not found. Try `help`.".to_string());
+                self.push_output(&CommandResult::not_found_output(), true);
+            }
+        }
+        // Places and flags change with going somewhere, unlocking and solving
+        self.sidebar.refresh(&self.session.current_dir);
+    }
+
+    /// Loads the image of the object into a texture, audio is only played by the web client.
+    fn open_asset(
+        rl: &mut RaylibHandle,
+        thread: &RaylibThread,
+        fs: &dyn SekaiFs,
+        path: &Path,
+        mime: &str,
+        title: String,
+    ) -> AssetPanel {
+        let mut panel = AssetPanel {
+            title,
+            texture: None,
+            note: None,
+        };
+        match ObjectKind::from_mime(mime) {
+            ObjectKind::Image => {
+                let texture = image_file_type(mime)
+                    .ok_or_else(|| format!("{mime} images cannot be shown here"))
+                    .and_then(|file_type| {
+                        let bytes = fs.read(path).map_err(|e| e.to_string())?;
+                        let image = Image::load_image_from_mem(file_type, &bytes)
+                            .map_err(|e| e.to_string())?;
+                        rl.load_texture_from_image(thread, &image)
+                            .map_err(|e| e.to_string())
+                    });
+                match texture {
+                    Ok(texture) => panel.texture = Some(texture),
+                    Err(e) => {
+                        log::log_warning("read", &format!("Failed to open image: {e}"));
+                        panel.note = Some("Could not open the image.".to_string());
+                    }
+                }
             }
+            _ => panel.note = Some("Sounds can only be played in the web client.".to_string()),
+        }
+        panel
+    }
+
+    /// Draws the sidebar right of the divider, with the opened image below it.
+    fn draw_side_panel(&self, d: &mut RaylibDrawHandle, theme: &Theme) {
+        let left = self.window_width as f32 * self.term_split_ratio + 10.0;
+        let width = self.window_width as f32 * (1.0 - self.term_split_ratio) - 20.0;
+        let height = self.window_height as f32 - 20.0;
+        let sidebar_height = if self.asset_panel.is_some() {
+            height / 2.0
+        } else {
+            height
+        };
+        self.sidebar.draw(
+            theme,
+            &self.fonts,
+            self.font_size,
+            self.char_width,
+            left,
+            10.0,
+            width,
+            sidebar_height,
+        );
+        self.draw_asset_panel(d, theme, left, 10.0 + sidebar_height, width);
+    }
+
+    /// Draws the opened image from the top down to the bottom of the window, scaled to fit.
+    fn draw_asset_panel(
+        &self,
+        d: &mut RaylibDrawHandle,
+        theme: &Theme,
+        left: f32,
+        top: f32,
+        width: f32,
+    ) {
+        let Some(panel) = &self.asset_panel else {
+            return;
+        };
+        let limit = ((width / self.char_width).floor() as usize).max(1);
+        let lines: Vec<String> = [Some(&panel.title), panel.note.as_ref()]
+            .into_iter()
+            .flatten()
+            .flat_map(|line| wrapit(line, limit))
+            .collect();
+        for (i, line) in lines.iter().enumerate() {
+            self.fonts.draw_text(
+                line,
+                Vector2 {
+                    x: left,
+                    y: top + i as f32 * self.font_size,
+                },
+                self.font_size,
+                1.2,
+                theme.muted.color(),
+            );
+        }
+
+        if let Some(texture) = &panel.texture {
+            let top = top + (lines.len() + 1) as f32 * self.font_size;
+            let height = self.window_height as f32 - top - 10.0;
+            let scale = (width / texture.width() as f32).min(height / texture.height() as f32);
+            d.draw_texture_ex(
+                texture,
+                Vector2 { x: left, y: top },
+                0.0,
+                scale,
+                Color::WHITE,
+            );
         }
     }
 
@@ -765,7 +952,7 @@ impl ShellScreen {
                 return true;
             }
             if rl.is_key_pressed(raylib::consts::KeyboardKey::KEY_N)
-                || rl.is_key_pressed(raylib::consts::KeyboardKey::KEY_ENTER)
+                || self.keymap.is_pressed(rl, Action::Submit)
             {
                 self.active_prompt = None;
                 self.output_lines.push(format!("{message} [y/N] no"));
@@ -789,44 +976,22 @@ impl ShellScreen {
                 self.should_exit = true;
                 return String::new();
             }
-            self.update(rl, thread);
             self.draw(rl, thread);
 
-            match rl.get_key_pressed() {
-                Some(KeyboardKey::KEY_ENTER) => {
-                    let input = take(&mut self.input_buffer);
-                    self.active_prompt = None;
-                    self.output_lines.push(format!("{message}: {input}"));
-                    self.cursor_pos = 0;
-                    return input;
-                }
-                Some(KeyboardKey::KEY_BACKSPACE) => {
-                    if !self.input_buffer.is_empty() && self.cursor_pos > 0 {
-                        self.input_buffer.remove(self.cursor_pos - 1);
-                        self.cursor_pos -= 1;
-                    }
-                }
-                Some(KeyboardKey::KEY_LEFT) => {
-                    if self.cursor_pos > 0 {
-                        self.cursor_pos -= 1;
-                    }
-                }
-                Some(KeyboardKey::KEY_RIGHT) => {
-                    if self.cursor_pos < self.input_buffer.len() {
-                        self.cursor_pos += 1;
-                    }
-                }
-                Some(key) => {
-                    let shift = rl.is_key_down(KeyboardKey::KEY_LEFT_SHIFT)
-                        || rl.is_key_down(KeyboardKey::KEY_RIGHT_SHIFT);
-
-                    if let Some(c) = key_to_char(key, shift) {
-                        self.input_buffer.insert(self.cursor_pos, c);
-                        self.cursor_pos += 1;
+            if let Some(combo) = KeyCombo::pressed(rl) {
+                match self.keymap.action(&combo, KeyContext::Shell) {
+                    Some(Action::Submit) => {
+                        let input = take(&mut self.input_buffer);
+                        self.active_prompt = None;
+                        self.output_lines.push(format!("{message}: {input}"));
+                        self.cursor_pos = 0;
+                        return input;
                     }
+                    Some(action) => self.edit_input(action),
+                    None => {}
                 }
-                None => {}
             }
+            self.type_chars(rl);
         }
     }
 
@@ -851,7 +1016,7 @@ impl ShellScreen {
         // Calculate which character in the line
         let line = &all_lines[line_index];
         let char_index = ((pos.x - 10.0) / self.char_width).floor() as usize;
-        let char_index = char_index.min(line.len());
+        let char_index = char_index.min(line.chars().count());
 
         Some((line_index, char_index))
     }
@@ -879,11 +1044,14 @@ impl ShellScreen {
             }
 
             let line = &all_lines[line_idx];
+            // Selections are in characters, lines are sliced in bytes
+            let line_chars = line.chars().count();
             let start_char = if line_idx == start.0 { start.1 } else { 0 };
-            let end_char = if line_idx == end.0 { end.1 } else { line.len() };
+            let end_char = if line_idx == end.0 { end.1 } else { line_chars };
 
-            if start_char < line.len() {
-                let slice = &line[start_char..end_char.min(line.len())];
+            if start_char < line_chars {
+                let slice = &line[text_input::char_to_byte(line, start_char)
+                    ..text_input::char_to_byte(line, end_char.max(start_char))];
                 selected_text.push_str(slice);
 
                 if line_idx != end.0 {
@@ -897,3 +1065,16 @@ impl ShellScreen {
         }
     }
 }
+
+/// Styles of the part of an output line from the char `start` to `end`, as the char each one
+/// starts at within the part.
+fn wrapped_styles(styles: &[(usize, Style)], start: usize, end: usize) -> Vec<(usize, Style)> {
+    let mut wrapped = Vec::new();
+    for (i, &(from, style)) in styles.iter().enumerate() {
+        let to = styles.get(i + 1).map_or(usize::MAX, |next| next.0);
+        if from < end && to > start {
+            wrapped.push((from.saturating_sub(start), style));
+        }
+    }
+    wrapped
+}
@@ -0,0 +1,265 @@
+use crate::commands::display_relative_path;
+use crate::gui_fonts::FontChain;
+use crate::metainfo::info_reader::read_validate_info;
+use crate::metainfo::lock_perm::read_lock_perm;
+use crate::sekai_fs::SekaiFs;
+use crate::utils::globals::UserInfo;
+use crate::utils::progress::{self, EventKind};
+use crate::utils::theme::Theme;
+use crate::utils::wrapit::wrapit;
+use raylib::ffi::Vector2;
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// A line of the sidebar, headings are drawn brighter.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SidebarLine {
+    pub text: String,
+    pub heading: bool,
+}
+
+impl SidebarLine {
+    fn heading(text: &str) -> Self {
+        Self {
+            text: text.to_string(),
+            heading: true,
+        }
+    }
+
+    fn text(text: String) -> Self {
+        Self {
+            text,
+            heading: false,
+        }
+    }
+}
+
+/// Status of the player, shown right of the terminal in the GUI shell.
+/// It is refreshed after every command, except the session time which is read when drawn.
+pub struct Sidebar {
+    fs: Arc<dyn SekaiFs>,
+    root_dir: PathBuf,
+    current_dir: PathBuf,
+    /// The player, None if nobody logged in
+    user: Option<UserInfo>,
+    location: String,
+    about: String,
+    /// Places the player has been to, relative to HOME
+    visited: BTreeSet<PathBuf>,
+    /// Tree of the visited places, read again on refresh since it reads every info.json
+    map: Vec<String>,
+    /// Solved and unlocked objects of the player
+    progress: Vec<String>,
+}
+
+impl Sidebar {
+    pub fn new(fs: Arc<dyn SekaiFs>, root_dir: &Path, user: Option<UserInfo>) -> Self {
+        let mut sidebar = Self {
+            fs,
+            root_dir: root_dir.to_path_buf(),
+            current_dir: root_dir.to_path_buf(),
+            user,
+            location: String::new(),
+            about: String::new(),
+            visited: BTreeSet::new(),
+            map: Vec::new(),
+            progress: Vec::new(),
+        };
+        sidebar.refresh(root_dir);
+        sidebar
+    }
+
+    /// Reads the place the player is in and their progress again.
+    pub fn refresh(&mut self, current_dir: &Path) {
+        self.current_dir = current_dir.to_path_buf();
+        if let Ok(rel_path) = current_dir.strip_prefix(&self.root_dir) {
+            // The parents were passed through to get here
+            for ancestor in rel_path.ancestors() {
+                self.visited.insert(ancestor.to_path_buf());
+            }
+        }
+
+        let info =
+            read_validate_info(self.fs.as_ref(), &current_dir.join(".dir_info/info.json")).ok();
+        self.location = info
+            .as_ref()
+            .map(|info| info.location.clone())
+            .unwrap_or_else(|| display_relative_path(current_dir, &self.root_dir));
+        self.about = info.map(|info| info.about).unwrap_or_default();
+        self.map = self.visited_tree();
+
+        self.progress = match self.user.as_ref().map(UserInfo::get_username) {
+            Some(username) => progress::load_progress()
+                .user_events(username)
+                .into_iter()
+                .map(|event| {
+                    let kind = match event.kind {
+                        EventKind::Solve => "solved",
+                        EventKind::Unlock => "unlocked",
+                    };
+                    format!("{} {kind} (+{})", event.object, event.points)
+                })
+                .collect(),
+            None => Vec::new(),
+        };
+    }
+
+    /// Visited places as an indented tree. Locked places inside them are shown too, since the
+    /// player has seen them but cannot go in yet.
+    pub fn visited_tree(&self) -> Vec<String> {
+        let mut lines = Vec::new();
+        for rel_path in &self.visited {
+            let depth = rel_path.components().count();
+            let name = rel_path
+                .file_name()
+                .map(|n| n.to_string_lossy().into_owned())
+                .unwrap_or_else(|| "HOME".to_string());
+            let marker = if self.root_dir.join(rel_path) == self.current_dir {
+                "> "
+            } else {
+                "  "
+            };
+            lines.push(format!("{marker}{}{name}", "  ".repeat(depth)));
+
+            for locked in self.locked_places(rel_path) {
+                lines.push(format!("  {}{locked} (locked)", "  ".repeat(depth + 1)));
+            }
+        }
+        lines
+    }
+
+    fn locked_places(&self, rel_path: &Path) -> Vec<String> {
+        let fs = self.fs.as_ref();
+        let Ok(entries) = fs.read_dir(&self.root_dir.join(rel_path)) else {
+            return Vec::new();
+        };
+        entries
+            .into_iter()
+            .filter(|path| fs.is_dir(path))
+            .filter(|path| read_lock_perm(fs, path).is_ok_and(|(_, locked)| locked))
+            .filter_map(|path| path.file_name().map(|n| n.to_string_lossy().into_owned()))
+            .collect()
+    }
+
+    /// All lines of the sidebar, wrapped to the width in characters.
+    pub fn lines(&self, width: usize) -> Vec<SidebarLine> {
+        let width = width.max(1);
+        let mut lines = vec![SidebarLine::heading(&self.location)];
+        lines.extend(
+            self.about
+                .lines()
+                .flat_map(|line| wrapit(line, width))
+                .map(SidebarLine::text),
+        );
+
+        lines.push(SidebarLine::text(String::new()));
+        lines.push(SidebarLine::heading("Map"));
+        lines.extend(self.map.iter().cloned().map(SidebarLine::text));
+
+        lines.push(SidebarLine::text(String::new()));
+        lines.push(SidebarLine::heading("Player"));
+        lines.push(SidebarLine::text(
+            self.user
+                .as_ref()
+                .map_or("Not logged in", UserInfo::get_username)
+                .to_string(),
+        ));
+        if let Some(duration) = self.user.as_ref().and_then(UserInfo::get_login_duration) {
+            let secs = duration.as_secs();
+            lines.push(SidebarLine::text(format!(
+                "Session: {:02}:{:02}:{:02}",
+                secs / 3600,
+                secs % 3600 / 60,
+                secs % 60
+            )));
+        }
+
+        lines.push(SidebarLine::text(String::new()));
+        lines.push(SidebarLine::heading("Flags"));
+        if self.progress.is_empty() {
+            lines.push(SidebarLine::text("(none yet)".to_string()));
+        }
+        lines.extend(
+            self.progress
+                .iter()
+                .flat_map(|line| wrapit(line, width))
+                .map(SidebarLine::text),
+        );
+        lines
+    }
+
+    /// Draws the sidebar into the area, cutting off the lines which do not fit.
+    #[allow(clippy::too_many_arguments)]
+    pub fn draw(
+        &self,
+        theme: &Theme,
+        fonts: &FontChain,
+        font_size: f32,
+        char_width: f32,
+        x: f32,
+        y: f32,
+        width: f32,
+        height: f32,
+    ) {
+        let max_lines = (height / font_size).floor() as usize;
+        let limit = (width / char_width).floor() as usize;
+        for (i, line) in self.lines(limit).iter().take(max_lines).enumerate() {
+            let color = if line.heading {
+                theme.text
+            } else {
+                theme.muted
+            };
+            fonts.draw_text(
+                &line.text,
+                Vector2 {
+                    x,
+                    y: y + i as f32 * font_size,
+                },
+                font_size,
+                1.2,
+                color.color(),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metainfo::info_reader::update_obj_status;
+    use crate::sekai_fs::DiskFs;
+    use crate::utils::test_utils::setup_test_dir;
+    use serde_json::Value;
+
+    #[test]
+    fn test_visited_tree() {
+        let (_temp_dir, root_path) = setup_test_dir(true);
+        // A locked object needs its lock properties to be valid
+        for (key, value) in [("obj_salt", "s"), ("compare_me", "c"), ("locked", "11")] {
+            update_obj_status(
+                &DiskFs,
+                &root_path.join("subdir2"),
+                "subdir2",
+                key,
+                Value::from(value),
+            )
+            .unwrap();
+        }
+
+        let mut sidebar = Sidebar::new(Arc::new(DiskFs), &root_path, None);
+        assert_eq!(sidebar.visited_tree(), ["> HOME", "    subdir2 (locked)"]);
+
+        // Going somewhere also visits its parents
+        sidebar.refresh(&root_path.join("subdir1/nested1"));
+        assert_eq!(
+            sidebar.visited_tree(),
+            [
+                "  HOME",
+                "    subdir2 (locked)",
+                "    subdir1",
+                ">     nested1"
+            ]
+        );
+    }
+}
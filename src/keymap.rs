@@ -0,0 +1,604 @@
+use crate::keys::key_to_char;
+use crate::utils::log;
+use raylib::prelude::{KeyboardKey, RaylibHandle};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::BTreeMap;
+use std::fmt;
+
+/*
+KEYMAP EXPLANATION:
+
+Every key the GUI reacts to, other than typing, is an `Action` bound to a `KeyCombo` in the
+keymap, which is saved in the config as `"action": "Ctrl+Shift+C"`. Actions left out of the
+config keep their default key, so new actions do not need the config to be reset.
+
+An action is used in one or more screens (its contexts). Two actions sharing a context cannot
+have the same key, and in the screens where the player types (the shell and the login fields) a
+key which types a character needs Ctrl or Alt, otherwise it could not be typed anymore.
+*/
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyContext {
+    Shell,
+    Menu,
+    Login,
+}
+
+impl KeyContext {
+    /// Screens where the keys which are not bound type characters.
+    pub fn is_typing(&self) -> bool {
+        matches!(self, KeyContext::Shell | KeyContext::Login)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Action {
+    Submit,
+    DeleteBack,
+    Complete,
+    HistoryPrev,
+    HistoryNext,
+    CursorLeft,
+    CursorRight,
+    ClearScreen,
+    NextPrompt,
+    Copy,
+    Paste,
+    MenuUp,
+    MenuDown,
+    MenuLeft,
+    MenuRight,
+    MenuSelect,
+    SwitchTab,
+}
+
+impl Action {
+    pub const ALL: [Action; 17] = [
+        Action::Submit,
+        Action::DeleteBack,
+        Action::Complete,
+        Action::HistoryPrev,
+        Action::HistoryNext,
+        Action::CursorLeft,
+        Action::CursorRight,
+        Action::ClearScreen,
+        Action::NextPrompt,
+        Action::Copy,
+        Action::Paste,
+        Action::MenuUp,
+        Action::MenuDown,
+        Action::MenuLeft,
+        Action::MenuRight,
+        Action::MenuSelect,
+        Action::SwitchTab,
+    ];
+
+    /// Name of the action in the config.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Action::Submit => "submit",
+            Action::DeleteBack => "delete_back",
+            Action::Complete => "complete",
+            Action::HistoryPrev => "history_prev",
+            Action::HistoryNext => "history_next",
+            Action::CursorLeft => "cursor_left",
+            Action::CursorRight => "cursor_right",
+            Action::ClearScreen => "clear_screen",
+            Action::NextPrompt => "next_prompt",
+            Action::Copy => "copy",
+            Action::Paste => "paste",
+            Action::MenuUp => "menu_up",
+            Action::MenuDown => "menu_down",
+            Action::MenuLeft => "menu_left",
+            Action::MenuRight => "menu_right",
+            Action::MenuSelect => "menu_select",
+            Action::SwitchTab => "switch_tab",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|action| action.name() == name)
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            Action::Submit => "Run the command",
+            Action::DeleteBack => "Delete the character before the cursor",
+            Action::Complete => "File completion only till Current Working Directory",
+            Action::HistoryPrev => "Previous command in history",
+            Action::HistoryNext => "Next command in history",
+            Action::CursorLeft => "Move the cursor left",
+            Action::CursorRight => "Move the cursor right",
+            Action::ClearScreen => "Clear the screen",
+            Action::NextPrompt => "Next prompt",
+            Action::Copy => "Copy the selected text",
+            Action::Paste => "Paste",
+            Action::MenuUp => "Previous option",
+            Action::MenuDown => "Next option",
+            Action::MenuLeft => "Decrease the value of a setting",
+            Action::MenuRight => "Increase the value of a setting",
+            Action::MenuSelect => "Choose the option",
+            Action::SwitchTab => "Switch between Login, Register and Guest",
+        }
+    }
+
+    /// Screens the action is used in. Menu actions also move between the login fields, and
+    /// deleting works in them too.
+    pub fn contexts(&self) -> &'static [KeyContext] {
+        match self {
+            Action::MenuUp
+            | Action::MenuDown
+            | Action::MenuLeft
+            | Action::MenuRight
+            | Action::MenuSelect => &[KeyContext::Menu, KeyContext::Login],
+            Action::SwitchTab => &[KeyContext::Login],
+            Action::DeleteBack => &[KeyContext::Shell, KeyContext::Login],
+            _ => &[KeyContext::Shell],
+        }
+    }
+
+    pub fn default_combo(&self) -> KeyCombo {
+        let key = |key| KeyCombo::new(key, false, false, false);
+        let ctrl = |key| KeyCombo::new(key, true, false, false);
+        let ctrl_shift = |key| KeyCombo::new(key, true, true, false);
+        match self {
+            Action::Submit | Action::MenuSelect => key(KeyboardKey::KEY_ENTER),
+            Action::DeleteBack => key(KeyboardKey::KEY_BACKSPACE),
+            Action::Complete | Action::SwitchTab => key(KeyboardKey::KEY_TAB),
+            Action::HistoryPrev | Action::MenuUp => key(KeyboardKey::KEY_UP),
+            Action::HistoryNext | Action::MenuDown => key(KeyboardKey::KEY_DOWN),
+            Action::CursorLeft | Action::MenuLeft => key(KeyboardKey::KEY_LEFT),
+            Action::CursorRight | Action::MenuRight => key(KeyboardKey::KEY_RIGHT),
+            Action::ClearScreen => ctrl(KeyboardKey::KEY_K),
+            Action::NextPrompt => ctrl(KeyboardKey::KEY_C),
+            Action::Copy => ctrl_shift(KeyboardKey::KEY_C),
+            Action::Paste => ctrl_shift(KeyboardKey::KEY_V),
+        }
+    }
+
+    fn shares_context(&self, other: &Action) -> bool {
+        self.contexts().iter().any(|c| other.contexts().contains(c))
+    }
+}
+
+/// Names of the keys which can be bound, as written in the config.
+const KEY_NAMES: [(&str, KeyboardKey); 34] = [
+    ("Enter", KeyboardKey::KEY_ENTER),
+    ("Tab", KeyboardKey::KEY_TAB),
+    ("Backspace", KeyboardKey::KEY_BACKSPACE),
+    ("Delete", KeyboardKey::KEY_DELETE),
+    ("Insert", KeyboardKey::KEY_INSERT),
+    ("Escape", KeyboardKey::KEY_ESCAPE),
+    ("Space", KeyboardKey::KEY_SPACE),
+    ("Up", KeyboardKey::KEY_UP),
+    ("Down", KeyboardKey::KEY_DOWN),
+    ("Left", KeyboardKey::KEY_LEFT),
+    ("Right", KeyboardKey::KEY_RIGHT),
+    ("Home", KeyboardKey::KEY_HOME),
+    ("End", KeyboardKey::KEY_END),
+    ("PageUp", KeyboardKey::KEY_PAGE_UP),
+    ("PageDown", KeyboardKey::KEY_PAGE_DOWN),
+    ("F1", KeyboardKey::KEY_F1),
+    ("F2", KeyboardKey::KEY_F2),
+    ("F3", KeyboardKey::KEY_F3),
+    ("F4", KeyboardKey::KEY_F4),
+    ("F5", KeyboardKey::KEY_F5),
+    ("F6", KeyboardKey::KEY_F6),
+    ("F7", KeyboardKey::KEY_F7),
+    ("F8", KeyboardKey::KEY_F8),
+    ("F9", KeyboardKey::KEY_F9),
+    ("F10", KeyboardKey::KEY_F10),
+    ("F11", KeyboardKey::KEY_F11),
+    ("F12", KeyboardKey::KEY_F12),
+    ("Minus", KeyboardKey::KEY_MINUS),
+    ("Equal", KeyboardKey::KEY_EQUAL),
+    ("Comma", KeyboardKey::KEY_COMMA),
+    ("Period", KeyboardKey::KEY_PERIOD),
+    ("Slash", KeyboardKey::KEY_SLASH),
+    ("Semicolon", KeyboardKey::KEY_SEMICOLON),
+    ("Grave", KeyboardKey::KEY_GRAVE),
+];
+
+const LETTERS: [KeyboardKey; 26] = [
+    KeyboardKey::KEY_A,
+    KeyboardKey::KEY_B,
+    KeyboardKey::KEY_C,
+    KeyboardKey::KEY_D,
+    KeyboardKey::KEY_E,
+    KeyboardKey::KEY_F,
+    KeyboardKey::KEY_G,
+    KeyboardKey::KEY_H,
+    KeyboardKey::KEY_I,
+    KeyboardKey::KEY_J,
+    KeyboardKey::KEY_K,
+    KeyboardKey::KEY_L,
+    KeyboardKey::KEY_M,
+    KeyboardKey::KEY_N,
+    KeyboardKey::KEY_O,
+    KeyboardKey::KEY_P,
+    KeyboardKey::KEY_Q,
+    KeyboardKey::KEY_R,
+    KeyboardKey::KEY_S,
+    KeyboardKey::KEY_T,
+    KeyboardKey::KEY_U,
+    KeyboardKey::KEY_V,
+    KeyboardKey::KEY_W,
+    KeyboardKey::KEY_X,
+    KeyboardKey::KEY_Y,
+    KeyboardKey::KEY_Z,
+];
+
+const DIGITS: [KeyboardKey; 10] = [
+    KeyboardKey::KEY_ZERO,
+    KeyboardKey::KEY_ONE,
+    KeyboardKey::KEY_TWO,
+    KeyboardKey::KEY_THREE,
+    KeyboardKey::KEY_FOUR,
+    KeyboardKey::KEY_FIVE,
+    KeyboardKey::KEY_SIX,
+    KeyboardKey::KEY_SEVEN,
+    KeyboardKey::KEY_EIGHT,
+    KeyboardKey::KEY_NINE,
+];
+
+const MODIFIERS: [KeyboardKey; 8] = [
+    KeyboardKey::KEY_LEFT_SHIFT,
+    KeyboardKey::KEY_RIGHT_SHIFT,
+    KeyboardKey::KEY_LEFT_CONTROL,
+    KeyboardKey::KEY_RIGHT_CONTROL,
+    KeyboardKey::KEY_LEFT_ALT,
+    KeyboardKey::KEY_RIGHT_ALT,
+    KeyboardKey::KEY_LEFT_SUPER,
+    KeyboardKey::KEY_RIGHT_SUPER,
+];
+
+fn key_name(key: KeyboardKey) -> Option<String> {
+    if let Some(i) = LETTERS.iter().position(|&k| k == key) {
+        return Some(((b'A' + i as u8) as char).to_string());
+    }
+    if let Some(i) = DIGITS.iter().position(|&k| k == key) {
+        return Some(i.to_string());
+    }
+    KEY_NAMES
+        .iter()
+        .find(|(_, k)| *k == key)
+        .map(|(name, _)| name.to_string())
+}
+
+fn key_from_name(name: &str) -> Option<KeyboardKey> {
+    let mut chars = name.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        let c = c.to_ascii_uppercase();
+        if c.is_ascii_uppercase() {
+            return Some(LETTERS[(c as u8 - b'A') as usize]);
+        }
+        if c.is_ascii_digit() {
+            return Some(DIGITS[(c as u8 - b'0') as usize]);
+        }
+    }
+    KEY_NAMES
+        .iter()
+        .find(|(n, _)| n.eq_ignore_ascii_case(name))
+        .map(|(_, key)| *key)
+}
+
+/// A key with the modifiers held down with it, written like `Ctrl+Shift+C`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyCombo {
+    pub key: KeyboardKey,
+    pub ctrl: bool,
+    pub shift: bool,
+    pub alt: bool,
+}
+
+impl KeyCombo {
+    pub fn new(key: KeyboardKey, ctrl: bool, shift: bool, alt: bool) -> Self {
+        Self {
+            key,
+            ctrl,
+            shift,
+            alt,
+        }
+    }
+
+    /// The key pressed this frame with the modifiers held down, skipping the modifiers
+    /// themselves. Keys without a name cannot be bound and are still returned for typing.
+    pub fn pressed(rl: &mut RaylibHandle) -> Option<Self> {
+        let key = rl.get_key_pressed()?;
+        if MODIFIERS.contains(&key) {
+            return None;
+        }
+        Some(Self::new(
+            key,
+            rl.is_key_down(KeyboardKey::KEY_LEFT_CONTROL)
+                || rl.is_key_down(KeyboardKey::KEY_RIGHT_CONTROL),
+            rl.is_key_down(KeyboardKey::KEY_LEFT_SHIFT)
+                || rl.is_key_down(KeyboardKey::KEY_RIGHT_SHIFT),
+            rl.is_key_down(KeyboardKey::KEY_LEFT_ALT) || rl.is_key_down(KeyboardKey::KEY_RIGHT_ALT),
+        ))
+    }
+
+    /// Character the combo types on a US layout. Only for combos which were not pressed, like
+    /// the ones in the config, pressed combos tell by the character raylib delivers with them.
+    fn us_layout_char(&self) -> Option<char> {
+        if self.ctrl || self.alt {
+            return None;
+        }
+        key_to_char(self.key, self.shift)
+    }
+}
+
+impl fmt::Display for KeyCombo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.ctrl {
+            write!(f, "Ctrl+")?;
+        }
+        if self.alt {
+            write!(f, "Alt+")?;
+        }
+        if self.shift {
+            write!(f, "Shift+")?;
+        }
+        match key_name(self.key) {
+            Some(name) => write!(f, "{name}"),
+            None => write!(f, "{:?}", self.key),
+        }
+    }
+}
+
+impl std::str::FromStr for KeyCombo {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut combo = KeyCombo::new(KeyboardKey::KEY_NULL, false, false, false);
+        let mut parts: Vec<&str> = s.split('+').map(str::trim).collect();
+        // "Ctrl++" would split the last key away, it is written "Ctrl+Equal" instead
+        let key = parts.pop().filter(|k| !k.is_empty());
+        for modifier in parts {
+            match modifier.to_ascii_lowercase().as_str() {
+                "ctrl" | "control" => combo.ctrl = true,
+                "shift" => combo.shift = true,
+                "alt" => combo.alt = true,
+                _ => return Err(format!("Unknown modifier '{modifier}' in '{s}'")),
+            }
+        }
+        combo.key = key
+            .and_then(key_from_name)
+            .ok_or_else(|| format!("Unknown key in '{s}'"))?;
+        Ok(combo)
+    }
+}
+
+/// Bindings of the actions, the actions which are not in it use their default key.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Keymap {
+    bindings: BTreeMap<Action, KeyCombo>,
+}
+
+impl Keymap {
+    pub fn combo(&self, action: Action) -> KeyCombo {
+        self.bindings
+            .get(&action)
+            .copied()
+            .unwrap_or_else(|| action.default_combo())
+    }
+
+    /// Action bound to the combo in the screen, None if it is not bound there.
+    pub fn action(&self, combo: &KeyCombo, context: KeyContext) -> Option<Action> {
+        Action::ALL
+            .into_iter()
+            .find(|action| action.contexts().contains(&context) && self.combo(*action) == *combo)
+    }
+
+    /// Whether the key of the action was pressed this frame, with exactly its modifiers.
+    pub fn is_pressed(&self, rl: &RaylibHandle, action: Action) -> bool {
+        let combo = self.combo(action);
+        let ctrl = rl.is_key_down(KeyboardKey::KEY_LEFT_CONTROL)
+            || rl.is_key_down(KeyboardKey::KEY_RIGHT_CONTROL);
+        let shift = rl.is_key_down(KeyboardKey::KEY_LEFT_SHIFT)
+            || rl.is_key_down(KeyboardKey::KEY_RIGHT_SHIFT);
+        let alt =
+            rl.is_key_down(KeyboardKey::KEY_LEFT_ALT) || rl.is_key_down(KeyboardKey::KEY_RIGHT_ALT);
+        rl.is_key_pressed(combo.key)
+            && combo.ctrl == ctrl
+            && combo.shift == shift
+            && combo.alt == alt
+    }
+
+    /// Another action the combo is bound to in a screen the action is used in.
+    pub fn conflict(&self, action: Action, combo: &KeyCombo) -> Option<Action> {
+        Action::ALL.into_iter().find(|other| {
+            *other != action && other.shares_context(&action) && self.combo(*other) == *combo
+        })
+    }
+
+    /// Reason the action cannot be bound to the combo, other than a conflict. `typed` is the
+    /// character the combo types, if any.
+    fn check(action: Action, combo: &KeyCombo, typed: Option<char>) -> Result<(), String> {
+        if key_name(combo.key).is_none() {
+            return Err(format!("{combo} cannot be bound"));
+        }
+        if typed.is_some() && action.contexts().iter().any(|c| c.is_typing()) {
+            return Err(format!(
+                "{combo} types a character, add Ctrl or Alt to bind it"
+            ));
+        }
+        Ok(())
+    }
+
+    /// Binds the action to the combo, unless it conflicts with another action or would stop a
+    /// character from being typed. Returns the reason otherwise.
+    ///
+    /// `typed` is the character raylib delivered in the frame the combo was pressed. Which keys
+    /// type depends on the layout of the player, like AltGr combos which are Ctrl+Alt to raylib,
+    /// so it is taken from what was typed rather than from the key.
+    pub fn bind(
+        &mut self,
+        action: Action,
+        combo: KeyCombo,
+        typed: Option<char>,
+    ) -> Result<(), String> {
+        Self::check(action, &combo, typed)?;
+        if let Some(other) = self.conflict(action, &combo) {
+            return Err(format!(
+                "{combo} is already bound to \"{}\"",
+                other.description()
+            ));
+        }
+        self.set(action, combo);
+        Ok(())
+    }
+
+    fn set(&mut self, action: Action, combo: KeyCombo) {
+        if combo == action.default_combo() {
+            self.bindings.remove(&action);
+        } else {
+            self.bindings.insert(action, combo);
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.bindings.clear();
+    }
+}
+
+impl Serialize for Keymap {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let names: BTreeMap<&str, String> = self
+            .bindings
+            .iter()
+            .map(|(action, combo)| (action.name(), combo.to_string()))
+            .collect();
+        names.serialize(serializer)
+    }
+}
+
+/// Bindings which cannot be read, or which conflict, are dropped so the rest of the config
+/// still loads.
+impl<'de> Deserialize<'de> for Keymap {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let names = BTreeMap::<String, String>::deserialize(deserializer)?;
+        let mut keymap = Keymap::default();
+        for (name, combo) in names {
+            let result = Action::from_name(&name)
+                .ok_or_else(|| format!("Unknown action '{name}'"))
+                .and_then(|action| Ok((action, combo.parse::<KeyCombo>()?)))
+                .and_then(|(action, combo)| {
+                    Keymap::check(action, &combo, combo.us_layout_char())?;
+                    keymap.set(action, combo);
+                    Ok(())
+                });
+            if let Err(e) = result {
+                log::log_warning("Keymap", &e);
+            }
+        }
+        // Conflicts are only known once every binding is read, since two keys may be swapped.
+        // The defaults do not conflict, so dropping bindings always ends.
+        while let Some(action) = keymap
+            .bindings
+            .keys()
+            .copied()
+            .find(|action| keymap.conflict(*action, &keymap.combo(*action)).is_some())
+        {
+            log::log_warning(
+                "Keymap",
+                &format!("{} conflicts, using its default key", action.name()),
+            );
+            keymap.bindings.remove(&action);
+        }
+        Ok(keymap)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keymap_bindings() {
+        let combo: KeyCombo = "ctrl+shift+c".parse().unwrap();
+        assert_eq!(combo, Action::Copy.default_combo());
+        assert_eq!(combo.to_string(), "Ctrl+Shift+C");
+        assert_eq!("F5".parse::<KeyCombo>().unwrap().key, KeyboardKey::KEY_F5);
+        assert!("Hyper+C".parse::<KeyCombo>().is_err());
+        assert!("Ctrl+".parse::<KeyCombo>().is_err());
+
+        let mut keymap = Keymap::default();
+        // Already used in the shell, but Up in the menus does not matter
+        assert!(
+            keymap
+                .bind(Action::ClearScreen, "Ctrl+C".parse().unwrap(), None)
+                .is_err()
+        );
+        assert!(
+            keymap
+                .bind(Action::ClearScreen, "Up".parse().unwrap(), None)
+                .is_err()
+        );
+        assert!(
+            keymap
+                .bind(Action::MenuUp, "Ctrl+K".parse().unwrap(), None)
+                .is_ok()
+        );
+        // Would stop the letter from being typed in the shell
+        assert!(
+            keymap
+                .bind(Action::ClearScreen, "L".parse().unwrap(), Some('l'))
+                .is_err()
+        );
+        // AltGr+Q types @ on a German layout, raylib sees Ctrl+Alt+Q
+        assert!(
+            keymap
+                .bind(
+                    Action::ClearScreen,
+                    "Ctrl+Alt+Q".parse().unwrap(),
+                    Some('@')
+                )
+                .is_err()
+        );
+        assert!(
+            keymap
+                .bind(Action::ClearScreen, "Ctrl+L".parse().unwrap(), None)
+                .is_ok()
+        );
+
+        let combo = keymap.combo(Action::ClearScreen);
+        assert_eq!(
+            keymap.action(&combo, KeyContext::Shell),
+            Some(Action::ClearScreen)
+        );
+        assert_eq!(keymap.action(&combo, KeyContext::Menu), None);
+
+        keymap.reset();
+        assert_eq!(keymap, Keymap::default());
+    }
+
+    #[test]
+    fn test_keymap_config() {
+        // Swapped keys load, the unknown action, bad key and conflict are dropped
+        let keymap: Keymap = serde_json::from_str(
+            r#"{
+                "clear_screen": "Ctrl+C",
+                "next_prompt": "Ctrl+K",
+                "fly": "F1",
+                "paste": "Ctrl+Nope",
+                "copy": "Ctrl+L",
+                "complete": "Ctrl+L",
+                "history_next": "J"
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(keymap.combo(Action::ClearScreen).to_string(), "Ctrl+C");
+        assert_eq!(keymap.combo(Action::NextPrompt).to_string(), "Ctrl+K");
+        assert_eq!(keymap.combo(Action::Paste), Action::Paste.default_combo());
+        assert_ne!(keymap.combo(Action::Copy), keymap.combo(Action::Complete));
+        // A key which types a letter is not read from the config either
+        assert_eq!(
+            keymap.combo(Action::HistoryNext),
+            Action::HistoryNext.default_combo()
+        );
+
+        let saved = serde_json::to_string(&keymap).unwrap();
+        assert_eq!(serde_json::from_str::<Keymap>(&saved).unwrap(), keymap);
+    }
+}
@@ -1,5 +1,7 @@
 use raylib::prelude::KeyboardKey;
 
+/// Character the key types on a US layout. Text is typed from the characters raylib queues
+/// instead, this only tells which keys type text for the bindings read from the config.
 pub fn key_to_char(key: KeyboardKey, shift: bool) -> Option<char> {
     let c = match key {
         KeyboardKey::KEY_LEFT_SHIFT => return None,
@@ -1,19 +1,31 @@
 #![allow(unused_variables, unused_mut, dead_code)]
 pub mod commands;
+#[cfg(feature = "gui")]
+pub mod gui_fonts;
+#[cfg(feature = "gui")]
 pub mod gui_main;
+#[cfg(feature = "gui")]
 pub mod gui_shell;
+#[cfg(feature = "gui")]
+pub mod gui_sidebar;
+#[cfg(feature = "gui")]
+pub mod keymap;
+#[cfg(feature = "gui")]
 pub mod keys;
+#[cfg(feature = "gui")]
 pub mod login;
+#[cfg(feature = "gui")]
 pub mod menu;
 pub mod metainfo;
 pub mod rns;
+pub mod sekai_fs;
+#[cfg(feature = "web")]
 pub mod server;
+pub mod session;
+pub use session::{Sekai, Session};
 pub mod utils;
-
-use std::sync::OnceLock;
-
-pub static DEV_MODE: OnceLock<bool> = OnceLock::new();
-pub static SEKAI_DIR: OnceLock<String> = OnceLock::new();
+#[cfg(feature = "wasm")]
+pub mod wasm;
 
 // DEEMAK Macros!
 #[macro_export]
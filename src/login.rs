@@ -1,11 +1,16 @@
-use crate::keys::key_to_char;
+use crate::gui_fonts::FontChain;
+use crate::keymap::{Action, KeyCombo, KeyContext};
+use crate::session::Sekai;
+use crate::utils::assets::DEFAULT_FONT;
 use crate::utils::auth;
-use crate::utils::globals::{UserInfo, set_user_info};
-use raylib::ffi::{DrawTextEx, LoadFontEx, MeasureTextEx, SetExitKey, Vector2};
+use crate::utils::config::load_config;
+use crate::utils::globals::UserInfo;
+use crate::utils::{text_input, theme, user_mgr};
+use raylib::ffi::{DrawTextEx, MeasureTextEx, SetExitKey, Vector2};
 use raylib::prelude::*;
 use std::ffi::CString;
-use std::os::raw::{c_char, c_int};
 use std::time::{Duration, Instant};
+use unicode_segmentation::UnicodeSegmentation;
 
 // Login/Register UI configuration
 struct LoginConfig {
@@ -101,6 +106,7 @@ impl LoginAnimation {
 enum TabType {
     Login = 0,
     Register = 1,
+    Guest = 2,
 }
 
 struct TabManager {
@@ -135,6 +141,7 @@ impl TabManager {
                     self.register_fields.username.entering = true;
                     self.register_fields.password.entering = false;
                 }
+                TabType::Guest => {}
             }
         }
     }
@@ -146,7 +153,7 @@ impl TabManager {
 
     fn get_current_fields(&mut self) -> &mut FieldPair {
         match self.active_tab {
-            TabType::Login => &mut self.login_fields,
+            TabType::Login | TabType::Guest => &mut self.login_fields,
             TabType::Register => &mut self.register_fields,
         }
     }
@@ -155,6 +162,7 @@ impl TabManager {
         match self.active_tab {
             TabType::Login => LOGIN_FOOTER,
             TabType::Register => REGISTER_FOOTER,
+            TabType::Guest => GUEST_FOOTER,
         }
     }
 }
@@ -163,6 +171,8 @@ impl TabManager {
 struct FieldPair {
     pub username: InputField,
     pub password: InputField,
+    /// User who logged in and the password they were given, while choosing a new one
+    pub given_password: Option<(String, String)>,
 }
 
 impl FieldPair {
@@ -170,10 +180,13 @@ impl FieldPair {
         Self {
             username: InputField::new("Username :", true),
             password: InputField::new("Password :", false),
+            given_password: None,
         }
     }
 
     fn clear(&mut self) {
+        self.given_password = None;
+        self.password.label = "Password :";
         self.username.value.clear();
         self.password.value.clear();
         self.username.warning = false;
@@ -203,16 +216,17 @@ impl FieldPair {
     fn draw_fields(
         &self,
         d: &mut RaylibDrawHandle,
-        font: raylib::ffi::Font,
+        fonts: &FontChain,
         config: &LoginConfig,
         highlight_color: Color,
     ) {
+        let theme = theme::current();
         let user_y = config.top_y + config.field_spacing;
         let pass_y = user_y + 100.0;
 
         self.username.draw(
             d,
-            font,
+            fonts,
             (config.base_x, user_y),
             (config.box_width, config.box_height),
             highlight_color,
@@ -220,7 +234,7 @@ impl FieldPair {
         );
         self.password.draw(
             d,
-            font,
+            fonts,
             (config.base_x, pass_y),
             (config.box_width, config.box_height),
             highlight_color,
@@ -233,7 +247,7 @@ impl FieldPair {
                 config.base_x as i32,
                 (pass_y + 90.0) as i32,
                 20,
-                Color::RED,
+                theme.error.color(),
             );
         }
     }
@@ -251,7 +265,9 @@ impl MouseHandler {
                 return;
             }
 
-            Self::handle_field_clicks(mouse_pos, tab_manager, config);
+            if tab_manager.active_tab != TabType::Guest {
+                Self::handle_field_clicks(mouse_pos, tab_manager, config);
+            }
         }
     }
 
@@ -281,6 +297,18 @@ impl MouseHandler {
             return true;
         }
 
+        // Guest tab
+        if Self::point_in_rect(
+            mouse_pos,
+            tab_x + 2.0 * config.tab_width,
+            tab_y,
+            config.tab_width,
+            config.tab_height,
+        ) {
+            tab_manager.switch_tab(TabType::Guest);
+            return true;
+        }
+
         false
     }
 
@@ -350,45 +378,39 @@ impl InputField {
         }
     }
 
-    // Handle keyboard input for this field
-    fn handle_input(&mut self, key: KeyboardKey, shift: bool) {
-        match key {
-            KeyboardKey::KEY_BACKSPACE => {
-                self.value.pop();
-                self.warning_text.clear();
-                self.warning = false;
-            }
-            KeyboardKey::KEY_SPACE => {
-                self.value.push(' ');
-                self.warning_text.clear();
-                self.warning = false;
-            }
-            _ => {
-                if let Some(c) = key_to_char(key, shift) {
-                    self.value.push(c);
-                    self.warning_text.clear();
-                    self.warning = false;
-                }
-            }
-        }
+    // Type text from the keyboard layout or input method at the end of this field
+    fn type_text(&mut self, text: &str) {
+        let end = self.value.len();
+        text_input::insert(&mut self.value, end, text);
+        self.warning_text.clear();
+        self.warning = false;
+    }
+
+    // Delete the last grapheme of this field
+    fn delete_back(&mut self) {
+        let end = self.value.len();
+        text_input::delete_back(&mut self.value, end);
+        self.warning_text.clear();
+        self.warning = false;
     }
 
     // Draw the input field UI, with optional masking for password fields
     fn draw(
         &self,
         d: &mut RaylibDrawHandle,
-        font: raylib::ffi::Font,
+        fonts: &FontChain,
         (base_x, base_y): (f32, f32),
         (box_width, box_height): (f32, f32),
         highlight_color: Color,
         mask: bool, // true for password fields
     ) {
+        let theme = theme::current();
         d.draw_text(
             self.label,
             base_x as i32,
             (base_y + 5.0) as i32,
             30,
-            Color::alpha(&Color::WHITE, 0.9),
+            theme.text.fade(0.9),
         );
         d.draw_rectangle_lines(
             base_x as i32,
@@ -398,12 +420,12 @@ impl InputField {
             if self.entering {
                 highlight_color
             } else {
-                Color::GRAY
+                theme.dim.color()
             },
         );
         // Show masked or plain text
         let text = if mask {
-            "*".repeat(self.value.len())
+            "*".repeat(self.value.graphemes(true).count())
         } else {
             self.value.clone()
         };
@@ -411,11 +433,10 @@ impl InputField {
         // Find the starting byte index for the substring that fits in the box.
         let mut start_byte_index = 0;
         let mut current_width = 0.0;
-        for (i, c) in text.char_indices().rev() {
-            let s = CString::new(c.to_string()).unwrap();
-            let char_width = unsafe { MeasureTextEx(font, s.as_ptr(), 30.0, 1.0).x };
+        for (i, grapheme) in text.grapheme_indices(true).rev() {
+            let char_width = fonts.measure_text(grapheme, 30.0, 1.0);
             if current_width + char_width + 10.0 > box_width {
-                start_byte_index = i + c.len_utf8();
+                start_byte_index = i + grapheme.len();
                 break;
             }
             current_width += char_width;
@@ -429,37 +450,34 @@ impl InputField {
         } else {
             visible.to_string()
         };
-        let display_c = CString::new(display).unwrap();
         let text_color = if self.entering {
             highlight_color
         } else {
-            Color::alpha(&Color::WHITE, 0.7)
+            theme.text.fade(0.7)
         };
-        unsafe {
-            DrawTextEx(
-                font,
-                display_c.as_ptr(),
-                Vector2 {
-                    x: base_x + 5.0,
-                    y: base_y + 45.0,
-                },
-                30.0,
-                0.1,
-                text_color.into(),
-            );
-        }
+        fonts.draw_text(
+            &display,
+            Vector2 {
+                x: base_x + 5.0,
+                y: base_y + 45.0,
+            },
+            30.0,
+            0.1,
+            text_color,
+        );
     }
 }
 
 // Footer notes for login and register
 const LOGIN_FOOTER: &str = "Welcome to Deemak by DBD! Use up/down keys to switch focus. Press Enter to continue. Not registered yet? Press Tab to switch from login to register.";
 const REGISTER_FOOTER: &str = "Welcome to Deemak by DBD! Use up/down keys to switch focus. Press Enter to submit. Already registered? Press Tab to switch from register to login.";
+const GUEST_FOOTER: &str = "Welcome to Deemak by DBD! Play offline without an account. Your progress is kept, and the guest can be upgraded to a real account later with `deemak auth upgrade`.";
 
 // Authentication handler
 struct AuthHandler;
 
 impl AuthHandler {
-    fn handle_login(fields: &mut FieldPair, users: &[auth::User]) -> Option<bool> {
+    fn handle_login(fields: &mut FieldPair, users: &mut [auth::User]) -> Option<UserInfo> {
         if fields.username.entering {
             if !fields.username.value.is_empty() {
                 fields.username.entering = false;
@@ -467,21 +485,30 @@ impl AuthHandler {
                 fields.username.warning = false;
             }
         } else if fields.password.entering && !fields.password.value.is_empty() {
-            let username = fields.username.value.trim();
-            let password = fields.password.value.trim();
+            let username = fields.username.value.trim().to_string();
+            let password = fields.password.value.trim().to_string();
+            if let Some((given_user, given_password)) = fields.given_password.clone() {
+                if given_user == username {
+                    return Self::change_password(fields, users, &username, given_password);
+                }
+                // The username was edited, log in again
+                fields.given_password = None;
+                fields.password.label = "Password :";
+                fields.password.value.clear();
+                return None;
+            }
             if let Some(user) = users.iter().find(|u| u.username == username) {
-                if auth::verify_password(&password.to_string(), &user.salt, &user.password_hash) {
-                    // Create and authenticate UserInfo
-                    let mut user_info = UserInfo::new(
-                        username.to_string(),
-                        user.salt.clone(),
-                        user.password_hash.clone(),
-                    );
-                    user_info.authenticate(); // Mark as authenticated with timestamp
-
-                    // Set global user info
-                    set_user_info(user_info).ok();
-                    return Some(true);
+                if auth::verify_password(&password, &user.salt, &user.password_hash) {
+                    if user.must_change_password {
+                        // Passwords handed out by the admin must be changed before playing
+                        fields.given_password = Some((username, password));
+                        fields.password.value.clear();
+                        fields.password.label = "New password :";
+                        fields.password.warning = true;
+                        fields.password.warning_text = "Choose a new password to play".to_string();
+                        return None;
+                    }
+                    return Some(auth::authenticate_user(user));
                 } else {
                     fields.password.warning = true;
                     fields.password.warning_text = "Invalid password!".to_string();
@@ -494,7 +521,34 @@ impl AuthHandler {
         None
     }
 
-    fn handle_register(fields: &mut FieldPair, users: &mut Vec<auth::User>) -> Option<bool> {
+    /// Sets the new password typed by a user who must change it, and logs them in.
+    fn change_password(
+        fields: &mut FieldPair,
+        users: &mut [auth::User],
+        username: &str,
+        given_password: String,
+    ) -> Option<UserInfo> {
+        let new_password = fields.password.value.trim();
+        let changed = if new_password == given_password {
+            Err("Choose another password than the given one!".to_string())
+        } else {
+            user_mgr::set_user_password(users, username, new_password)
+        };
+        if let Err(e) = changed {
+            fields.password.warning = true;
+            fields.password.warning_text = e;
+            return None;
+        }
+        if std::panic::catch_unwind(|| auth::save_users(users)).is_err() {
+            fields.password.warning = true;
+            fields.password.warning_text = "Failed to save user!".to_string();
+            return None;
+        }
+        let user = users.iter().find(|u| u.username == username)?;
+        Some(auth::authenticate_user(user))
+    }
+
+    fn handle_register(fields: &mut FieldPair, users: &mut Vec<auth::User>) -> Option<UserInfo> {
         if fields.username.entering {
             if !fields.username.value.is_empty() {
                 fields.username.entering = false;
@@ -514,6 +568,10 @@ impl AuthHandler {
                             username: username.to_string(),
                             salt: salt.clone(),
                             password_hash: hash.clone(),
+                            guest: false,
+                            flag_name: None,
+                            created: None,
+                            must_change_password: false,
                         });
                         match std::panic::catch_unwind(|| auth::save_users(users)) {
                             Err(_) => {
@@ -525,10 +583,7 @@ impl AuthHandler {
                                 let mut user_info =
                                     UserInfo::new(username.to_string(), salt.clone(), hash.clone());
                                 user_info.authenticate(); // Mark as authenticated with timestamp
-
-                                // Set global user info
-                                set_user_info(user_info).ok();
-                                return Some(true);
+                                return Some(user_info);
                             }
                         }
                     }
@@ -541,20 +596,27 @@ impl AuthHandler {
         }
         None
     }
+
+    fn handle_guest(sekai: &Sekai, warning: &mut String) -> Option<UserInfo> {
+        match auth::login_as_guest(sekai) {
+            Ok(user_info) => Some(user_info),
+            Err(e) => {
+                *warning = e;
+                None
+            }
+        }
+    }
 }
 
-// Main login/register UI loop
-pub fn show_login(rl: &mut RaylibHandle, thread: &RaylibThread, _font_size: f32) -> bool {
+// Main login/register UI loop, returns the user who logged in, None if the window was closed
+pub fn show_login(
+    rl: &mut RaylibHandle,
+    thread: &RaylibThread,
+    sekai: &Sekai,
+    _font_size: f32,
+) -> Option<UserInfo> {
     // Load custom font
-    let font = unsafe {
-        let path = CString::new("fontbook/fonts/ttf/JetBrainsMono-Medium.ttf").unwrap();
-        LoadFontEx(
-            path.as_ptr() as *const c_char,
-            600 as c_int,
-            std::ptr::null_mut::<c_int>(),
-            0,
-        )
-    };
+    let fonts = FontChain::new(DEFAULT_FONT);
     let font_d = rl.get_font_default();
 
     // Load users and initialize components
@@ -570,6 +632,8 @@ pub fn show_login(rl: &mut RaylibHandle, thread: &RaylibThread, _font_size: f32)
     let config = LoginConfig::default();
     let mut animation = LoginAnimation::new(&config);
     let mut tab_manager = TabManager::new(users.is_empty());
+    let mut guest_warning = String::new();
+    let keymap = load_config().keymap;
 
     unsafe {
         SetExitKey(0i32); // Disable exit key (ESC) to prevent accidental exit during login
@@ -577,7 +641,7 @@ pub fn show_login(rl: &mut RaylibHandle, thread: &RaylibThread, _font_size: f32)
 
     // Main event loop
     while !rl.window_should_close() {
-        let input = rl.get_key_pressed();
+        let input = KeyCombo::pressed(rl);
         animation.update();
 
         // Handle input for login/register fields
@@ -586,50 +650,65 @@ pub fn show_login(rl: &mut RaylibHandle, thread: &RaylibThread, _font_size: f32)
             MouseHandler::handle_clicks(rl, &mut tab_manager, &config);
 
             // Handle keyboard input
-            if let Some(key) = input {
-                let shift = rl.is_key_down(KeyboardKey::KEY_LEFT_SHIFT)
-                    || rl.is_key_down(KeyboardKey::KEY_RIGHT_SHIFT);
-                match key {
-                    KeyboardKey::KEY_TAB => {
+            if let Some(combo) = input {
+                match keymap.action(&combo, KeyContext::Login) {
+                    Some(Action::SwitchTab) => {
                         tab_manager.switch_tab(match tab_manager.active_tab {
                             TabType::Login => TabType::Register,
-                            TabType::Register => TabType::Login,
+                            TabType::Register => TabType::Guest,
+                            TabType::Guest => TabType::Login,
                         });
                     }
-                    KeyboardKey::KEY_DOWN | KeyboardKey::KEY_UP => {
+                    Some(Action::MenuDown | Action::MenuUp) => {
                         tab_manager.get_current_fields().toggle_focus();
                     }
-                    KeyboardKey::KEY_ENTER => {
+                    Some(Action::DeleteBack) => {
+                        let fields = tab_manager.get_current_fields();
+                        if fields.username.entering {
+                            fields.username.delete_back();
+                        } else {
+                            fields.password.delete_back();
+                        }
+                    }
+                    Some(Action::MenuSelect) => {
                         let result = match tab_manager.active_tab {
                             TabType::Login => {
-                                AuthHandler::handle_login(&mut tab_manager.login_fields, &users)
+                                AuthHandler::handle_login(&mut tab_manager.login_fields, &mut users)
                             }
                             TabType::Register => AuthHandler::handle_register(
                                 &mut tab_manager.register_fields,
                                 &mut users,
                             ),
+                            TabType::Guest => AuthHandler::handle_guest(sekai, &mut guest_warning),
                         };
-                        if let Some(success) = result {
-                            return success;
-                        }
-                    }
-                    _ => {
-                        // Handle character input
-                        let fields = tab_manager.get_current_fields();
-                        if fields.username.entering {
-                            fields.username.handle_input(key, shift);
-                        } else {
-                            fields.password.handle_input(key, shift);
+                        if result.is_some() {
+                            return result;
                         }
                     }
+                    _ => {}
+                }
+            }
+
+            // Handle character input
+            let mut typed = String::new();
+            while let Some(c) = rl.get_char_pressed() {
+                typed.push(c);
+            }
+            if !typed.is_empty() && tab_manager.active_tab != TabType::Guest {
+                let fields = tab_manager.get_current_fields();
+                if fields.username.entering {
+                    fields.username.type_text(&typed);
+                } else {
+                    fields.password.type_text(&typed);
                 }
             }
         }
 
         // Begin drawing UI
+        let theme = theme::current();
         let mut d = rl.begin_drawing(thread);
-        d.clear_background(Color::BLACK);
-        let highlight_color = Color::GOLD;
+        d.clear_background(theme.background.color());
+        let highlight_color = theme.accent.color();
 
         // Draw welcome text
         if animation.stream_index > 0 {
@@ -638,7 +717,7 @@ pub fn show_login(rl: &mut RaylibHandle, thread: &RaylibThread, _font_size: f32)
                 200,
                 config.top_y as i32,
                 20,
-                Color::alpha(&Color::GRAY, 1.0),
+                theme.dim.fade(1.0),
             );
         }
 
@@ -653,7 +732,7 @@ pub fn show_login(rl: &mut RaylibHandle, thread: &RaylibThread, _font_size: f32)
                 },
                 60.0,
                 2.0,
-                Color::WHITE,
+                theme.text.color(),
             );
         }
 
@@ -673,7 +752,7 @@ pub fn show_login(rl: &mut RaylibHandle, thread: &RaylibThread, _font_size: f32)
                 if tab_manager.active_tab == TabType::Login {
                     highlight_color
                 } else {
-                    Color::alpha(&Color::GRAY, 0.3)
+                    theme.dim.fade(0.3)
                 },
             );
             d.draw_rectangle(
@@ -684,7 +763,7 @@ pub fn show_login(rl: &mut RaylibHandle, thread: &RaylibThread, _font_size: f32)
                 if tab_manager.active_tab == TabType::Register {
                     highlight_color
                 } else {
-                    Color::alpha(&Color::GRAY, 0.3)
+                    theme.dim.fade(0.3)
                 },
             );
 
@@ -698,9 +777,9 @@ pub fn show_login(rl: &mut RaylibHandle, thread: &RaylibThread, _font_size: f32)
                 24.0,
                 1.0,
                 if tab_manager.active_tab == TabType::Login {
-                    Color::BLACK
+                    theme.background.color()
                 } else {
-                    Color::WHITE
+                    theme.text.color()
                 },
             );
             d.draw_text_ex(
@@ -713,30 +792,84 @@ pub fn show_login(rl: &mut RaylibHandle, thread: &RaylibThread, _font_size: f32)
                 24.0,
                 1.0,
                 if tab_manager.active_tab == TabType::Register {
-                    Color::BLACK
+                    theme.background.color()
                 } else {
-                    Color::WHITE
+                    theme.text.color()
+                },
+            );
+
+            d.draw_rectangle(
+                (tab_x + 2.0 * config.tab_width) as i32,
+                tab_y as i32,
+                config.tab_width as i32,
+                config.tab_height as i32,
+                if tab_manager.active_tab == TabType::Guest {
+                    highlight_color
+                } else {
+                    theme.dim.fade(0.3)
+                },
+            );
+            d.draw_text_ex(
+                &font_d,
+                "Guest",
+                raylib::prelude::Vector2 {
+                    x: tab_x + 2.0 * config.tab_width + 40.0,
+                    y: tab_y + 8.0,
+                },
+                24.0,
+                1.0,
+                if tab_manager.active_tab == TabType::Guest {
+                    theme.background.color()
+                } else {
+                    theme.text.color()
                 },
             );
 
             // Draw current tab's fields
-            let fields = tab_manager.get_current_fields();
-            fields.draw_fields(&mut d, font, &config, highlight_color);
+            if tab_manager.active_tab == TabType::Guest {
+                let text_y = (config.top_y + config.field_spacing) as i32;
+                d.draw_text(
+                    "Play offline as a guest.",
+                    config.base_x as i32,
+                    text_y,
+                    30,
+                    theme.text.fade(0.9),
+                );
+                d.draw_text(
+                    "Press Enter to continue.",
+                    config.base_x as i32,
+                    text_y + 50,
+                    24,
+                    theme.text.fade(0.7),
+                );
+                if !guest_warning.is_empty() {
+                    d.draw_text(
+                        &guest_warning,
+                        config.base_x as i32,
+                        text_y + 100,
+                        20,
+                        theme.error.color(),
+                    );
+                }
+            } else {
+                let fields = tab_manager.get_current_fields();
+                fields.draw_fields(&mut d, &fonts, &config, highlight_color);
+            }
 
             // Draw divider and footer
             let footer_text = tab_manager.get_footer_text();
-            let footer_height = calculate_footer_height(font, footer_text, screen_width);
+            let footer_height = calculate_footer_height(fonts.primary(), footer_text, screen_width);
             let divider_y = screen_height as f32 - footer_height - config.divider_margin;
             d.draw_line(
                 30,
                 divider_y as i32,
                 screen_width - 30,
                 divider_y as i32,
-                Color::alpha(&Color::GRAY, 0.5),
+                theme.dim.fade(0.5),
             );
             draw_footer_legacy(
                 &mut d,
-                font,
+                fonts.primary(),
                 footer_text,
                 screen_width,
                 screen_height as f32,
@@ -749,12 +882,12 @@ pub fn show_login(rl: &mut RaylibHandle, thread: &RaylibThread, _font_size: f32)
                 10,
                 d.get_screen_height() - 30,
                 16,
-                Color::alpha(&Color::GRAY, 0.4),
+                theme.dim.fade(0.4),
             );
         }
     }
 
-    false // Login failed or window closed
+    None // Window closed
 }
 
 // Helper function to calculate the total height needed for the footer
@@ -799,6 +932,7 @@ fn draw_footer_legacy(
     screen_width: i32,
     screen_height: f32,
 ) {
+    let theme = theme::current();
     let max_width = screen_width as f32 - 80.0;
     let font_size = 20.0;
     let spacing = 0.1;
@@ -843,7 +977,7 @@ fn draw_footer_legacy(
                 Vector2 { x, y },
                 font_size,
                 spacing,
-                Color::alpha(&Color::GRAY, 0.5).into(),
+                theme.dim.fade(0.5).into(),
             );
         }
     }
@@ -1,15 +1,20 @@
 #![allow(unused_variables, unused_mut, dead_code)]
-use crate::gui_main::{run_gui_loop, sekai_initialize};
-use crate::gui_shell::DEEMAK_BANNER;
-use crate::rns::create_dmk_sekai::{self, original_from_encrypted_sekai};
+use crate::metainfo::scaffold::{self, WorldTemplate};
+use crate::metainfo::valid_sekai::validate_or_create_sekai;
+use crate::metainfo::{lint, lock_graph};
+use crate::rns::create_dmk_sekai;
+use crate::rns::pack;
+use crate::rns::passlock::NonceMode;
+use crate::sekai_fs::{DiskFs, MemFs};
+use crate::utils::cleanup::{self, exit_deemak};
 use crate::utils::file_mgr::DeemakSekaiMgr;
-use crate::utils::{cleanup::exit_deemak, debug_mode, log};
-use clap::{Parser, Subcommand};
+use crate::utils::globals::DEEMAK_BANNER;
+use crate::utils::{auth, config, log, progress, user_mgr};
+use clap::{Parser, Subcommand, ValueEnum};
 use deemak::utils::file_mgr::{SekaiOperation, input_file_password};
 use deemak::*;
-use raylib::ffi::{SetConfigFlags, SetTargetFPS};
-use raylib::prelude::get_monitor_width;
 use std::path::PathBuf;
+use std::sync::Arc;
 
 #[derive(Parser, Debug)]
 #[command(version, about, long_about = None)]
@@ -27,6 +32,10 @@ struct DeemakArgs {
     #[arg(long, default_value_t = false)]
     web: bool,
 
+    /// Skip the login screen and play offline with the local guest profile
+    #[arg(long, default_value_t = false)]
+    guest: bool,
+
     /// Development subcommands
     #[command(subcommand)]
     command: Option<DeemakCommands>,
@@ -41,7 +50,61 @@ enum DeemakCommands {
     },
 
     /// Authorization of user login and registration
-    Auth,
+    Auth {
+        #[command(subcommand)]
+        subcommand: DeemakAuth,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum DeemakAuth {
+    /// Add a new user, the password is prompted for, or read from stdin if it is not a terminal
+    Add {
+        /// Username of the new user
+        username: String,
+    },
+
+    /// Remove an existing user
+    Remove {
+        /// Username of the user to remove
+        username: String,
+    },
+
+    /// Change the password of an existing user, prompted for like `add`
+    Passwd {
+        /// Username of the user
+        username: String,
+    },
+
+    /// List all registered users
+    List,
+
+    /// Upgrade a guest to a real account, keeping its progress
+    Upgrade {
+        /// Username of the guest
+        guest: String,
+
+        /// New username for the account (defaults to the guest's username)
+        #[arg(short, long)]
+        username: Option<String>,
+    },
+
+    /// Import users from a CSV file of `username[,password]` lines.
+    /// Passwords are generated and printed for users without a password. Users have to change
+    /// these passwords when they first log in.
+    /// A file written by `export` is imported with its password hashes.
+    Import {
+        /// Path to the CSV file
+        #[arg(value_name = "CSV_FILE")]
+        csv: PathBuf,
+    },
+
+    /// Export users (without passwords) as CSV
+    Export {
+        /// Output file (defaults to stdout)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
 }
 
 #[derive(Subcommand, Debug)]
@@ -62,6 +125,66 @@ enum DeemakDev {
         /// Force overwrite existing Deemak file(if it exists)
         #[arg(short, long, default_value_t = false)]
         force: bool,
+
+        /// Build the same file from the same Sekai: sorted entries, and no times, owners or
+        /// permissions of this machine in the archive
+        #[arg(long, default_value_t = false)]
+        reproducible: bool,
+
+        /// Derive the encryption key and nonce from the contents instead of picking random
+        /// ones, so that the same Sekai and password give a byte-identical file
+        #[arg(long, default_value_t = false, requires = "reproducible")]
+        content_nonce: bool,
+    },
+
+    /// Create a new Sekai directory from a template
+    New {
+        /// Template to start from
+        #[arg(short, long, value_enum, default_value_t = WorldTemplate::Tutorial)]
+        template: WorldTemplate,
+    },
+
+    /// Check the Sekai directory for problems without changing it.
+    /// Exits with 0 if there are no problems, 1 for warnings only and 2 for errors.
+    Check {
+        /// Print the report as JSON
+        #[arg(long, default_value_t = false)]
+        json: bool,
+
+        /// Report files bigger than this many bytes
+        #[arg(long, value_name = "BYTES", default_value_t = lint::DEFAULT_MAX_FILE_SIZE)]
+        max_size: u64,
+    },
+
+    /// Show how the levels and chests of the Sekai directory depend on each other.
+    /// Objects which can never be reached are flagged as unsolvable.
+    Graph {
+        /// Output format
+        #[arg(short, long, value_enum, default_value_t = GraphFormat::Dot)]
+        format: GraphFormat,
+
+        /// Output file (defaults to stdout)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+
+    /// Pack a Sekai directory for shipping, without the files created while playing.
+    /// The authoring files are encrypted into a `.authoring` sidecar next to the Deemak file.
+    Pack {
+        /// Password for encryption
+        #[arg(short, long)]
+        password: Option<String>,
+
+        /// Output directory or `.deemak` file (defaults to current directory)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+
+    /// Unpack a Deemak Encrypted file for editing, with the authoring files from its sidecar
+    Unpack {
+        /// Output directory (defaults to the name of the Deemak file in current directory)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
     },
 
     /// Restore Sekai from a Deemak Encrypted file
@@ -75,11 +198,205 @@ enum DeemakDev {
         password: Option<String>,
     },
 }
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum GraphFormat {
+    /// Graphviz DOT, render with `dot -Tsvg`
+    Dot,
+    Json,
+}
+
+/// Runs the `auth` subcommands for managing users of the database.
+fn run_auth_command(cmd: DeemakAuth) {
+    let mut users = auth::load_users();
+    match cmd {
+        DeemakAuth::Add { username } => {
+            let password = input_file_password(true);
+            if let Err(e) = user_mgr::add_user(&mut users, &username, &password) {
+                fatal_error!("AUTH", "Failed to add user: {}", e);
+            }
+            auth::save_users(&users);
+            println!("User '{username}' added successfully");
+        }
+        DeemakAuth::Remove { username } => {
+            if let Err(e) = user_mgr::remove_user(&mut users, &username) {
+                fatal_error!("AUTH", "Failed to remove user: {}", e);
+            }
+            auth::save_users(&users);
+            println!("User '{username}' removed successfully");
+        }
+        DeemakAuth::Passwd { username } => {
+            if !users.iter().any(|u| u.username == username) {
+                fatal_error!("AUTH", "User '{}' does not exist", username);
+            }
+            let password = input_file_password(true);
+            if let Err(e) = user_mgr::set_user_password(&mut users, &username, &password) {
+                fatal_error!("AUTH", "Failed to change password: {}", e);
+            }
+            auth::save_users(&users);
+            println!("Password changed for user '{username}'");
+        }
+        DeemakAuth::List => {
+            if users.is_empty() {
+                println!("No users registered.");
+            }
+            for user in &users {
+                if user.guest {
+                    println!("{} (guest)", user.username);
+                } else if user.must_change_password {
+                    println!("{} (must change password)", user.username);
+                } else {
+                    println!("{}", user.username);
+                }
+            }
+        }
+        DeemakAuth::Upgrade { guest, username } => {
+            let password = input_file_password(true);
+            let user = auth::upgrade_guest_user(&mut users, &guest, username.as_deref(), &password)
+                .unwrap_or_else(|e| {
+                    fatal_error!("AUTH", "Failed to upgrade guest: {}", e);
+                });
+            auth::save_users(&users);
+            if let Err(e) = progress::rename_user_progress(&guest, &user.username) {
+                epr_log_error!("AUTH", "Failed to move the progress of '{}': {}", guest, e);
+            }
+            // The local guest profile is now a real account
+            let mut cfg = config::load_config();
+            if cfg.guest_user.as_deref() == Some(guest.as_str()) {
+                cfg.guest_user = None;
+                config::save_config(&cfg);
+            }
+            println!("Guest '{guest}' upgraded to user '{}'", user.username);
+        }
+        DeemakAuth::Import { csv } => {
+            let content = std::fs::read_to_string(&csv).unwrap_or_else(|e| {
+                fatal_error!("AUTH", "Failed to read {}: {}", csv.display(), e);
+            });
+            let entries = user_mgr::parse_users_csv(&content).unwrap_or_else(|e| {
+                fatal_error!("AUTH", "Invalid CSV file {}: {}", csv.display(), e);
+            });
+            let count = entries.len();
+            let generated = user_mgr::import_users(&mut users, entries).unwrap_or_else(|e| {
+                fatal_error!("AUTH", "Failed to import users: {}", e);
+            });
+            auth::save_users(&users);
+            println!("Imported {} user(s) from {}", count, csv.display());
+            if !generated.is_empty() {
+                println!("\nGenerated passwords (shown only once, changed at the first login):");
+                println!("{}", user_mgr::CSV_HEADER);
+                for (username, password) in generated {
+                    println!("{username},{password}");
+                }
+            }
+        }
+        DeemakAuth::Export { output } => {
+            let csv = user_mgr::export_users_csv(&users);
+            match output {
+                Some(path) => {
+                    std::fs::write(&path, csv).unwrap_or_else(|e| {
+                        fatal_error!("AUTH", "Failed to write {}: {}", path.display(), e);
+                    });
+                    println!("Exported {} user(s) to {}", users.len(), path.display());
+                }
+                None => print!("{csv}"),
+            }
+        }
+    }
+}
+
+/// Runs `dev new`, creating a Sekai directory from the template.
+fn run_new_command(sekai_path: &std::path::Path, template: WorldTemplate) {
+    if let Err(e) = scaffold::scaffold_sekai(sekai_path, template) {
+        fatal_error!("SEKAI", "Failed to create new Sekai: {}", e);
+    }
+    println!(
+        "Created a new {} Sekai at {}",
+        template.name(),
+        sekai_path.display()
+    );
+    println!(
+        "The walkthrough with all the solutions is in {}",
+        sekai_path.join(scaffold::WALKTHROUGH_FILE).display()
+    );
+    println!("Play it with `dev play`, and check it with `dev check` as you build it.");
+}
+
+/// Runs `dev check` on a Sekai directory, and exits with the code of the report.
+fn run_check_command(sekai_path: &std::path::Path, json: bool, max_size: u64) -> ! {
+    if !sekai_path.is_dir() {
+        fatal_error!(
+            "SEKAI",
+            "dev check works on a Sekai directory. Restore the Deemak file first with `dev restore`."
+        );
+    }
+    let options = lint::LintOptions {
+        max_file_size: max_size,
+    };
+    let report = lint::lint_sekai(sekai_path, &options);
+    if json {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&report).expect("Failed to serialize lint report")
+        );
+    } else {
+        println!("{}", report.to_human());
+    }
+    std::process::exit(report.exit_code());
+}
+
+/// Runs `dev graph` on a Sekai directory.
+fn run_graph_command(sekai_path: &std::path::Path, format: GraphFormat, output: Option<PathBuf>) {
+    if !sekai_path.is_dir() {
+        fatal_error!(
+            "SEKAI",
+            "dev graph works on a Sekai directory. Restore the Deemak file first with `dev restore`."
+        );
+    }
+    let graph = lock_graph::LockGraph::build(sekai_path);
+    let rendered = match format {
+        GraphFormat::Dot => graph.to_dot(),
+        GraphFormat::Json => graph.to_json(),
+    };
+    let unsolvable = graph.unsolvable(&graph.reachable());
+    match output {
+        Some(path) => {
+            std::fs::write(&path, rendered).unwrap_or_else(|e| {
+                fatal_error!("SEKAI", "Failed to write {}: {}", path.display(), e);
+            });
+            println!("Lock graph written to {}", path.display());
+        }
+        None => print!("{rendered}"),
+    }
+    if !unsolvable.is_empty() {
+        eprintln!("{} object(s) can never be reached:", unsolvable.len());
+        for path in unsolvable {
+            eprintln!("  {path}");
+        }
+    }
+}
+
 fn main() {
-    println!("{DEEMAK_BANNER}");
+    let args = DeemakArgs::parse();
+    let _instance = cleanup::InstanceGuard::install();
+    // Keep the reports on stdout clean for other tools
+    let machine_output = matches!(
+        args.command,
+        Some(DeemakCommands::Dev {
+            subcommand: DeemakDev::Check { json: true, .. } | DeemakDev::Graph { output: None, .. }
+        })
+    );
+    if !machine_output {
+        println!("{DEEMAK_BANNER}");
+    }
     log::log_info("Application", "Starting DEEMAK Shell");
 
-    let args = DeemakArgs::parse();
+    // A new Sekai is created where nothing exists yet.
+    if let Some(DeemakCommands::Dev {
+        subcommand: DeemakDev::New { template },
+    }) = args.command
+    {
+        run_new_command(&args.sekai_directory, template);
+        return;
+    }
     // Check existance.
     if !args.sekai_directory.exists() {
         fatal_error!(
@@ -88,10 +405,29 @@ fn main() {
             args.sekai_directory.clone().display()
         );
     }
-    // Set DEV MODE true if Dev commands are present
-    if let Some(DeemakCommands::Dev { .. }) = args.command {
-        DEV_MODE.set(true).unwrap_or_default();
+    // Auth commands only work on the user database, no need to touch the Sekai.
+    if let Some(DeemakCommands::Auth { subcommand }) = args.command {
+        run_auth_command(subcommand);
+        return;
+    }
+    // Checking must not create or fix the .dir_info's, so it runs before the Sekai is validated.
+    if let Some(DeemakCommands::Dev {
+        subcommand: DeemakDev::Check { json, max_size },
+    }) = args.command
+    {
+        run_check_command(&args.sekai_directory, json, max_size);
     }
+    if let Some(DeemakCommands::Dev {
+        subcommand: DeemakDev::Graph { format, output },
+    }) = args.command
+    {
+        run_graph_command(&args.sekai_directory, format, output);
+        return;
+    }
+
+    // Dev Mode is on if Dev commands are present, it makes logging verbose too
+    let dev_mode = matches!(args.command, Some(DeemakCommands::Dev { .. }));
+    log::set_debug_mode(dev_mode);
 
     // This object will manage the whole Sekai operations for us.
     let mut sekai_obj = DeemakSekaiMgr::new(args.sekai_directory, None);
@@ -111,7 +447,7 @@ fn main() {
     let sekai_path = sekai_obj.abs_path.clone();
     log::log_info("SEKAI", &format!("Sekai path provided: {sekai_path:?}"));
 
-    let (possible_sekai_opers, criteria) = sekai_obj.oper_allowed();
+    let (possible_sekai_opers, criteria) = sekai_obj.oper_allowed(dev_mode);
     if SekaiOperation::Invalid.is_present(possible_sekai_opers.clone()) {
         fatal_error!(
             "SEKAI",
@@ -126,11 +462,13 @@ fn main() {
                 if !sekai_obj.is_directory {
                     println!("Please confirm the password of deemak file before proceeding ahead.");
                     let _pass_check_user = input_file_password(false);
-                    if sekai_obj.get_password() != _pass_check_user {
-                        fatal_error!(
+                    match sekai_obj.check_password(&_pass_check_user) {
+                        Ok(true) => {}
+                        Ok(false) => fatal_error!(
                             "SEKAI",
                             "Oops! Password didn't match with the deemak sekai file."
-                        )
+                        ),
+                        Err(e) => fatal_error!("SEKAI", "Failed to check password: {}", e),
                     }
                 }
                 match subcommand {
@@ -138,6 +476,8 @@ fn main() {
                         password,
                         output,
                         force,
+                        reproducible,
+                        content_nonce,
                     } => {
                         // Make sure Create Criteria is met, as mentioned in file_mgr
                         if !SekaiOperation::Create.is_present(possible_sekai_opers) {
@@ -147,13 +487,7 @@ fn main() {
 
                         log::log_info("DEEMAK", "Creating Deemak Encrypted Sekai file");
                         // Get input password securely
-                        sekai_obj.set_password(true).unwrap_or_else(|e| {
-                            fatal_error!(
-                                "SEKAI",
-                                "Failed to set password for Sekai: {}",
-                                e.to_string()
-                            );
-                        });
+                        sekai_obj.set_password(true);
 
                         let mut output_path = if let Some(out) = output {
                             out
@@ -176,6 +510,15 @@ fn main() {
                             &output_path,
                             output_obj.password.clone().unwrap().as_str(),
                             force,
+                            &pack::is_unshipped_file,
+                            create_dmk_sekai::BuildOptions {
+                                reproducible,
+                                nonce_mode: if content_nonce {
+                                    NonceMode::ContentDerived
+                                } else {
+                                    NonceMode::Random
+                                },
+                            },
                         ) {
                             Ok(_) => {
                                 let absolute_created_path = std::fs::canonicalize(&output_path)
@@ -234,6 +577,76 @@ fn main() {
                         }
                         return;
                     }
+                    DeemakDev::Pack { password, output } => {
+                        if !SekaiOperation::Create.is_present(possible_sekai_opers) {
+                            SekaiOperation::Create.log_err(Some(criteria));
+                            exit_deemak(1);
+                        }
+                        log::log_info("DEEMAK", "Packing Sekai directory");
+                        let password = password.unwrap_or_else(|| input_file_password(true));
+                        let output_path = output.unwrap_or_else(|| {
+                            std::env::current_dir().expect("Failed to get current directory")
+                        });
+                        match pack::pack_sekai(&sekai_obj.abs_path, &output_path, &password) {
+                            Ok(report) => {
+                                if report.changed {
+                                    pr_info!(
+                                        "SEKAI",
+                                        "Successfully packed Sekai to: {}",
+                                        report.pack.display()
+                                    );
+                                } else {
+                                    pr_info!(
+                                        "SEKAI",
+                                        "Sekai is unchanged, kept: {}",
+                                        report.pack.display()
+                                    );
+                                }
+                                if let Some(sidecar) = report.sidecar {
+                                    pr_info!(
+                                        "SEKAI",
+                                        "Authoring files are in: {}. Do not ship it!",
+                                        sidecar.display()
+                                    );
+                                }
+                            }
+                            Err(e) => fatal_error!("SEKAI", "Failed to pack Sekai: {}", e),
+                        }
+                        return;
+                    }
+                    DeemakDev::Unpack { output } => {
+                        if !SekaiOperation::Restore.is_present(possible_sekai_opers) {
+                            SekaiOperation::Restore.log_err(Some(criteria));
+                            exit_deemak(1);
+                        }
+                        log::log_info("SEKAI", "Unpacking Deemak Encrypted file");
+                        let output_path = output.unwrap_or_else(|| {
+                            std::env::current_dir().expect("Failed to get current directory")
+                        });
+                        // Checked against the file before the subcommand
+                        let password = sekai_obj.password.clone().unwrap_or_default();
+                        match pack::unpack_sekai(&sekai_obj.abs_path, &output_path, &password) {
+                            Ok((unpacked_path, has_authoring)) => {
+                                pr_info!(
+                                    "SEKAI",
+                                    "Successfully unpacked Sekai to: {}",
+                                    unpacked_path.display()
+                                );
+                                if !has_authoring {
+                                    pr_info!(
+                                        "SEKAI",
+                                        "No authoring sidecar found at {}, solutions are not included.",
+                                        pack::sidecar_path(&sekai_obj.abs_path).display()
+                                    );
+                                }
+                            }
+                            Err(e) => fatal_error!("SEKAI", "Failed to unpack Sekai: {}", e),
+                        }
+                        return;
+                    }
+                    DeemakDev::New { .. } | DeemakDev::Check { .. } | DeemakDev::Graph { .. } => {
+                        unreachable!("New, check and graph are handled earlier")
+                    }
                     DeemakDev::Play => {
                         // Make sure Play Criteria is met, as mentioned in file_mgr
                         if !SekaiOperation::Play.is_present(possible_sekai_opers) {
@@ -241,102 +654,144 @@ fn main() {
                             exit_deemak(1);
                         }
                         /*
-                        Here, DEV_MODE is set, so logging will be verbose.
-                        Also the temporary directory is set as the working directory.
+                        Here, Dev Mode is on, so logging will be verbose.
+                        The Sekai directory is played on the disk.
                         See [`DeemakFileMgr::oper_allowed`] for more details.
                         */
                         log::log_info("DEEMAK", "Playing Sekai in Developer Mode");
                     }
                 }
             }
-            DeemakCommands::Auth => {
-                // TODO: Implement authentication commands
-                log::log_info("DEEMAK", "Authentication commands are not implemented yet.");
-                exit_deemak(1);
-            }
+            DeemakCommands::Auth { .. } => unreachable!("Auth commands are handled earlier"),
         }
     }
 
     /*
-    If DEV_MODE is on, sekai_path can either be a file or a directory.
-    In either case, _tmp_sekai_wd will be the current working directory since we want to easily see changes.
-    If DEV_MODE is off, sekai_path must be a file, and it will be restored to a temporary directory.
+    If Dev Mode is on, sekai_path can either be a file or a directory.
+    A directory is played on the disk, since we want to easily see changes.
+    A file is decrypted into memory, so no plaintext of the Sekai is ever written to the disk.
+    See `sekai_fs` for more details.
     */
-
-    // Deemak working should go inside a temporary directory.
-    let _tmp_sekai_wd = sekai_obj.temp_location;
-    let _sekai_from_enc_path = original_from_encrypted_sekai(&sekai_path, &_tmp_sekai_wd, None)
-        .map_err(|e| {
-            fatal_error!(
+    let sekai = if sekai_obj.is_directory {
+        Sekai::new(Arc::new(DiskFs), sekai_path.clone())
+    } else {
+        let root = MemFs::root_for(&sekai_path);
+        match MemFs::open_deemak(&sekai_path, &root) {
+            Ok(mem) => Sekai::new(Arc::new(mem), root),
+            Err(e) => fatal_error!(
                 "SEKAI",
-                "Failed to restore Sekai: {}. Please check the Sekai file or directory.",
+                "Failed to open Sekai: {}. Please check the Sekai file or directory.",
                 e
-            );
-        })
-        .unwrap();
-    let sekai_enc_obj = DeemakSekaiMgr::new(_sekai_from_enc_path, None);
+            ),
+        }
+    };
 
-    // Automatically creates the Sekai dirinfo's if they do not exist and set the global SEKAI_DIR
-    if !sekai_enc_obj.valid_sekai {
+    // Automatically creates the Sekai dirinfo's if they do not exist
+    if !validate_or_create_sekai(sekai.fs.as_ref(), &sekai.root, false) {
         fatal_error!(
             "SEKAI",
-            "Restored Sekai path is not valid: {}. Please provide a valid Sekai file or directory.",
-            sekai_enc_obj.abs_path.display()
+            "Sekai is not valid: {}. Please provide a valid Sekai file or directory.",
+            sekai_path.display()
         );
     }
 
     // NOTE: All Directory operations and variables settings should be done before this point.
     //
     // We have 2 modes, the web and the raylib gui. The web argument runs it on the web, else
-    // raylib gui is set by default.
+    // raylib gui is set by default. Each is only there if deemak was built with its feature.
     //
     // NOTE: #############    WEB USAGE    #############
     //
     // Initialize the server if --web argument is provided
     if args.web {
-        // TODO: Remove the extra sekai_no_hajimari call, it will be shifted to the server module
-        // later on.
-        sekai_initialize(&sekai_path);
-        log::log_info("Application", "Running in web mode");
-        // server::launch_web(sekai_dir.clone().unwrap());
-        let _ = deemak::server::server();
-        return;
+        #[cfg(feature = "web")]
+        {
+            // TODO: Remove the extra sekai_no_hajimari call, it will be shifted to the server module
+            // later on.
+            metainfo::valid_sekai::sekai_initialize(sekai.fs.as_ref(), &sekai.root);
+            log::log_info("Application", "Running in web mode");
+            let _ = deemak::server::server(sekai, dev_mode);
+            return;
+        }
+        #[cfg(not(feature = "web"))]
+        fatal_error!(
+            "Application",
+            "This deemak is built without the web server, rebuild it with the `web` feature."
+        );
     }
 
     // NOTE: #############    RAYLIB GUI USAGE    #############
-    //
-    // Initialize Raylib window
     if args.gui {
-        unsafe {
-            SetConfigFlags(4);
-            SetTargetFPS(60);
-        }
-        let loglevel = if !debug_mode() {
-            raylib::consts::TraceLogLevel::LOG_ERROR
-        } else {
-            raylib::consts::TraceLogLevel::LOG_ALL
-        };
-
-        let (mut rl, thread) = raylib::init()
-            .log_level(loglevel)
-            .size(800, 600)
-            .title("DEEMAK Shell")
-            .build();
-        let font_size = get_monitor_width(0) as f32 / 73.5;
-        rl.set_trace_log(loglevel);
-        // Disable escape key exit to prevent accidental application closure
-        unsafe {
-            raylib::ffi::SetExitKey(0i32);
-        }
-        log::log_info("Application", "DEEMAK initialized successfully");
+        #[cfg(feature = "gui")]
+        run_gui(sekai, args.guest, dev_mode);
+        #[cfg(not(feature = "gui"))]
+        fatal_error!(
+            "Application",
+            "This deemak is built without the GUI, rebuild it with the `gui` feature or use --web."
+        );
+    }
+}
 
-        // Show login screen before menu
-        if !deemak::login::show_login(&mut rl, &thread, font_size) {
-            log::log_info("Application", "Login aborted by user.");
-            return; // Exit if window closed during login
+/// Logs in and plays the Sekai in the raylib window.
+#[cfg(feature = "gui")]
+fn run_gui(sekai: Sekai, guest: bool, dev_mode: bool) {
+    use crate::gui_main::run_gui_loop;
+    use crate::utils::debug_mode;
+    use raylib::ffi::{SetConfigFlags, SetTargetFPS};
+    use raylib::prelude::get_monitor_width;
+
+    // Initialize Raylib window
+    unsafe {
+        SetConfigFlags(4);
+        SetTargetFPS(60);
+    }
+    let loglevel = if !debug_mode() {
+        raylib::consts::TraceLogLevel::LOG_ERROR
+    } else {
+        raylib::consts::TraceLogLevel::LOG_ALL
+    };
+
+    let (mut rl, thread) = raylib::init()
+        .log_level(loglevel)
+        .size(800, 600)
+        .title("DEEMAK Shell")
+        .build();
+    let font_size = config::load_config().shell_font_size(get_monitor_width(0));
+    rl.set_trace_log(loglevel);
+    // Disable escape key exit to prevent accidental application closure
+    unsafe {
+        raylib::ffi::SetExitKey(0i32);
+    }
+    log::log_info("Application", "DEEMAK initialized successfully");
+
+    // Show login screen before menu, unless playing as guest
+    let user = if guest {
+        match auth::login_as_guest(&sekai) {
+            Ok(guest) => {
+                log::log_info("Application", &format!("Playing as {}", guest.username));
+                guest
+            }
+            Err(e) => fatal_error!("AUTH", "Failed to log in as guest: {}", e),
         }
+    } else {
+        match deemak::login::show_login(&mut rl, &thread, &sekai, font_size) {
+            Some(user) => user,
+            None => {
+                log::log_info("Application", "Login aborted by user.");
+                return; // Exit if window closed during login
+            }
+        }
+    };
+    let mut session = Session::new(sekai);
+    session.user = Some(user);
+    session.dev_mode = dev_mode;
 
-        // Run the GUI loop
-        run_gui_loop(&mut rl, &thread, font_size);
+    // Wait for the event to start, if the Sekai is a timed event
+    if !deemak::menu::countdown::show_event_gate(&mut rl, &thread, &session) {
+        log::log_info("Application", "Sekai is not playable by the user.");
+        return;
     }
+
+    // Run the GUI loop
+    run_gui_loop(&mut rl, &thread, session, font_size);
 }
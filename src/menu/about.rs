@@ -1,5 +1,7 @@
+use crate::utils::assets::{self, DEFAULT_FONT};
+use crate::utils::theme;
 use raylib::ffi::{
-    ColorFromHSV, DrawTextEx, GetScreenHeight, GetScreenWidth, LoadFontEx, MeasureTextEx, Vector2,
+    DrawTextEx, GetScreenHeight, GetScreenWidth, LoadFontFromMemory, MeasureTextEx, Vector2,
 };
 use raylib::prelude::*;
 use std::ffi::CString;
@@ -30,10 +32,12 @@ pub fn show_about(rl: &mut RaylibHandle, thread: &RaylibThread) {
     let mut input_ready = false;
 
     // Load font
+    let data = assets::font(DEFAULT_FONT).unwrap_or_default();
     let font = unsafe {
-        let path = CString::new("fontbook/fonts/ttf/JetBrainsMono-Medium.ttf").unwrap();
-        LoadFontEx(
-            path.as_ptr() as *const c_char,
+        LoadFontFromMemory(
+            c".ttf".as_ptr() as *const c_char,
+            data.as_ptr(),
+            data.len() as c_int,
             600.0 as c_int,
             std::ptr::null_mut::<c_int>(),
             0,
@@ -74,8 +78,9 @@ pub fn show_about(rl: &mut RaylibHandle, thread: &RaylibThread) {
         }
 
         // Draw
+        let theme = theme::current();
         let mut d = rl.begin_drawing(thread);
-        d.clear_background(Color::BLACK);
+        d.clear_background(theme.background.color());
 
         // Draw animated text
         let mut y_offset = 0.0;
@@ -94,7 +99,7 @@ pub fn show_about(rl: &mut RaylibHandle, thread: &RaylibThread) {
                     pos,
                     24.0,
                     1.0,
-                    ColorFromHSV(0.0, 0.0, 1.0),
+                    theme.text.color().into(),
                 );
             }
             y_offset += line_height;
@@ -118,7 +123,7 @@ pub fn show_about(rl: &mut RaylibHandle, thread: &RaylibThread) {
                     pos,
                     20.0,
                     1.0,
-                    ColorFromHSV(0.0, 0.0, 0.51),
+                    theme.dim.color().into(),
                 );
             }
         } else {
@@ -137,7 +142,7 @@ pub fn show_about(rl: &mut RaylibHandle, thread: &RaylibThread) {
                     pos,
                     20.0,
                     1.0,
-                    ColorFromHSV(0.0, 0.0, 0.51),
+                    theme.dim.color().into(),
                 );
             }
         }
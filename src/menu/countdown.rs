@@ -0,0 +1,79 @@
+use crate::metainfo::event_policy::{
+    EventPolicy, EventStatus, check_user_access, format_countdown,
+};
+use crate::session::Session;
+use crate::utils::{log, theme};
+use raylib::prelude::*;
+
+/// Gate before the menu for timed events.
+/// Shows a countdown until the event starts, or the reason if the user cannot play.
+/// Returns false if the user cannot play or the window was closed.
+pub fn show_event_gate(rl: &mut RaylibHandle, thread: &RaylibThread, session: &Session) -> bool {
+    let (policy, denied) = match session.sekai.event_policy() {
+        Ok(Some(policy)) => {
+            let username = session.username().unwrap_or_default().to_string();
+            let denied = check_user_access(&policy, &username).err();
+            (policy, denied)
+        }
+        Ok(None) => return true,
+        Err(e) => (EventPolicy::default(), Some(e)),
+    };
+    if let Some(reason) = &denied {
+        log::log_warning("EVENT", reason);
+    }
+    let font = rl.get_font_default();
+
+    while !rl.window_should_close() {
+        let status = policy.status();
+        if denied.is_none() {
+            match status {
+                EventStatus::NotStarted(_) => {}
+                EventStatus::Running => return true,
+                EventStatus::Ended => {
+                    log::log_info("EVENT", "Event has ended, Sekai is read-only");
+                    return true;
+                }
+            }
+        }
+        if denied.is_some()
+            && (rl.is_key_pressed(KeyboardKey::KEY_ENTER)
+                || rl.is_key_pressed(KeyboardKey::KEY_ESCAPE))
+        {
+            return false;
+        }
+
+        let theme = theme::current();
+        let mut d = rl.begin_drawing(thread);
+        d.clear_background(theme.background.color());
+        d.draw_text_ex(
+            &font,
+            "DEEMAK SHELL",
+            Vector2::new(200.0, 180.0),
+            60.0,
+            2.0,
+            theme.text.color(),
+        );
+
+        match (&denied, status) {
+            (Some(reason), _) => {
+                d.draw_text(reason, 200, 300, 24, theme.error.color());
+                d.draw_text("Press Enter to exit.", 200, 350, 20, theme.text.fade(0.7));
+            }
+            (None, EventStatus::NotStarted(secs)) => {
+                d.draw_text("The event starts in", 200, 290, 24, theme.dim.color());
+                d.draw_text(&format_countdown(secs), 200, 330, 60, theme.accent.color());
+                if let Some(start) = &policy.start {
+                    d.draw_text(
+                        &format!("Start: {start}"),
+                        200,
+                        420,
+                        20,
+                        theme.text.fade(0.7),
+                    );
+                }
+            }
+            _ => {}
+        }
+    }
+    false
+}
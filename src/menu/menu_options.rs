@@ -1,3 +1,5 @@
+use crate::keymap::{Action, KeyCombo, KeyContext, Keymap};
+use crate::utils::{config, theme};
 use raylib::prelude::*;
 use std::time::{Duration, Instant};
 
@@ -93,6 +95,7 @@ impl MenuAnimation {
 struct MenuInput {
     pub selected: usize,
     pub last_change: Instant,
+    keymap: Keymap,
 }
 
 impl MenuInput {
@@ -100,24 +103,25 @@ impl MenuInput {
         Self {
             selected: 0,
             last_change: Instant::now(),
+            keymap: config::load_config().keymap,
         }
     }
 
     fn handle_input(&mut self, rl: &mut RaylibHandle, config: &MenuConfig) -> Option<MenuOption> {
         if self.last_change.elapsed() > config.input_delay
-            && let Some(key) = rl.get_key_pressed()
+            && let Some(combo) = KeyCombo::pressed(rl)
         {
-            match key {
-                KeyboardKey::KEY_UP => {
+            match self.keymap.action(&combo, KeyContext::Menu) {
+                Some(Action::MenuUp) => {
                     self.selected =
                         (self.selected + MenuOption::opts().len() - 1) % MenuOption::opts().len();
                     self.last_change = Instant::now();
                 }
-                KeyboardKey::KEY_DOWN => {
+                Some(Action::MenuDown) => {
                     self.selected = (self.selected + 1) % MenuOption::opts().len();
                     self.last_change = Instant::now();
                 }
-                KeyboardKey::KEY_ENTER => {
+                Some(Action::MenuSelect) => {
                     return Some(MenuOption::opts()[self.selected]);
                 }
                 _ => {}
@@ -143,8 +147,9 @@ pub fn show_menu(rl: &mut RaylibHandle, thread: &RaylibThread) -> Option<MenuOpt
         animation.update(&config);
 
         // Draw everything
+        let theme = theme::current();
         let mut d = rl.begin_drawing(thread);
-        d.clear_background(Color::BLACK);
+        d.clear_background(theme.background.color());
 
         // Draw title
         d.draw_text_ex(
@@ -153,15 +158,15 @@ pub fn show_menu(rl: &mut RaylibHandle, thread: &RaylibThread) -> Option<MenuOpt
             Vector2::new(config.title_x, animation.y_offset),
             config.title_font_size,
             2.0,
-            Color::new(255, 255, 255, 255),
+            theme.text.color(),
         );
 
         // Draw menu options
         for (i, option) in MenuOption::opts().iter().enumerate() {
             let color = if i == input.selected {
-                Color::GOLD
+                theme.accent.color()
             } else {
-                Color::new(200, 200, 200, (animation.alpha * 200.0) as u8)
+                theme.muted.fade(animation.alpha * 200.0 / 255.0)
             };
 
             d.draw_text_ex(
@@ -183,12 +188,7 @@ pub fn show_menu(rl: &mut RaylibHandle, thread: &RaylibThread) -> Option<MenuOpt
             config.cursor_x as i32,
             (config.menu_start_y + input.selected as f32 * config.menu_item_height) as i32,
             config.cursor_font_size,
-            Color::new(
-                255,
-                255,
-                255,
-                ((animation.alpha * 0.5).sin().abs() * 255.0) as u8,
-            ),
+            theme.text.fade((animation.alpha * 0.5).sin().abs()),
         );
 
         // Draw footer
@@ -199,7 +199,7 @@ pub fn show_menu(rl: &mut RaylibHandle, thread: &RaylibThread) -> Option<MenuOpt
             d.get_screen_width() - size - 10,
             d.get_screen_height() - 30,
             config.footer_font_size,
-            Color::alpha(&Color::GRAY, 0.4),
+            theme.dim.fade(0.4),
         );
 
         let version = "Version 1.0";
@@ -208,7 +208,7 @@ pub fn show_menu(rl: &mut RaylibHandle, thread: &RaylibThread) -> Option<MenuOpt
             10,
             d.get_screen_height() - 30,
             config.footer_font_size,
-            Color::alpha(&Color::GRAY, 0.4),
+            theme.dim.fade(0.4),
         );
     }
 
@@ -1,4 +1,5 @@
 pub mod menu_options;
 pub use menu_options::show_menu;
 pub mod about;
+pub mod countdown;
 pub mod settings;
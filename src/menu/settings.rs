@@ -1,4 +1,6 @@
-use crate::utils::{config, globals::FONT_OPTIONS};
+use crate::gui_fonts::load_bundled_font;
+use crate::keymap::{Action, KeyCombo, Keymap};
+use crate::utils::{assets::DEFAULT_FONT, config, globals::FONT_OPTIONS, theme};
 
 use raylib::prelude::*;
 use std::time::{Duration, Instant};
@@ -12,22 +14,74 @@ trait Screen {
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum SettingsOption {
     Font,
+    Theme,
+    FontSize,
+    Layout,
     Keybindings,
     Back,
 }
 
 impl SettingsOption {
     pub fn opts() -> &'static [Self] {
-        &[Self::Font, Self::Keybindings, Self::Back]
+        &[
+            Self::Font,
+            Self::Theme,
+            Self::FontSize,
+            Self::Layout,
+            Self::Keybindings,
+            Self::Back,
+        ]
     }
 
     pub fn as_str(&self) -> &'static str {
         match self {
             Self::Font => "Font",
+            Self::Theme => "Theme",
+            Self::FontSize => "Font Size",
+            Self::Layout => "Terminal Width",
             Self::Keybindings => "Keybindings",
             Self::Back => "Back",
         }
     }
+
+    /// Current value of the option, for the options changed with LEFT and RIGHT.
+    pub fn value(&self, cfg: &config::DeemakConfig, monitor_width: i32) -> Option<String> {
+        match self {
+            Self::Theme => Some(theme::current().name),
+            Self::FontSize => Some(format!("{:.0}", cfg.shell_font_size(monitor_width))),
+            Self::Layout => Some(format!("{:.0}%", cfg.split_ratio() * 100.0)),
+            _ => None,
+        }
+    }
+
+    /// Steps the value of the option forwards or backwards, and applies it at once.
+    /// Returns false if the option has no value.
+    pub fn adjust(&self, cfg: &mut config::DeemakConfig, step: i32, monitor_width: i32) -> bool {
+        match self {
+            Self::Theme => {
+                let themes = theme::available_themes();
+                let current = themes
+                    .iter()
+                    .position(|t| t.name == theme::current().name)
+                    .unwrap_or(0) as i32;
+                let next = &themes[(current + step).rem_euclid(themes.len() as i32) as usize];
+                cfg.theme = next.name.clone();
+                theme::set_current(next.clone());
+            }
+            Self::FontSize => {
+                let size = cfg.shell_font_size(monitor_width).round() + 2.0 * step as f32;
+                cfg.font_size =
+                    Some(size.clamp(config::FONT_SIZE_RANGE.0, config::FONT_SIZE_RANGE.1));
+            }
+            Self::Layout => {
+                let ratio = cfg.split_ratio() + 0.05 * step as f32;
+                cfg.term_split_ratio =
+                    ratio.clamp(config::SPLIT_RATIO_RANGE.0, config::SPLIT_RATIO_RANGE.1);
+            }
+            _ => return false,
+        }
+        true
+    }
 }
 
 pub fn show_font_selection(
@@ -38,10 +92,7 @@ pub fn show_font_selection(
     // Load all fonts to be displayed for the preview.
     let loaded_fonts: Vec<Font> = FONT_OPTIONS
         .iter()
-        .map(|(_, path)| {
-            rl.load_font_ex(thread, path, 30, None)
-                .unwrap_or_else(|e| panic!("Failed to load font: {path}. Error: {e}"))
-        })
+        .map(|(_, name)| load_bundled_font(rl, thread, name, 30).unwrap_or_else(|e| panic!("{e}")))
         .collect();
 
     let default_font = rl.get_font_default();
@@ -54,34 +105,29 @@ pub fn show_font_selection(
 
     // Store the currently configured font to display the '*' marker.
     let mut saved_font_index = config::load_config().font_index;
+    let keymap = config::load_config().keymap;
 
     // Load the custom font for the footnote.
-    let custom_font = rl
-        .load_font_ex(
-            thread,
-            "fontbook/fonts/ttf/JetBrainsMono-Medium.ttf",
-            18,
-            None,
-        )
-        .expect("Failed to load custom font");
+    let custom_font =
+        load_bundled_font(rl, thread, DEFAULT_FONT, 18).expect("Failed to load custom font");
 
     while !rl.window_should_close() {
         if last_change.elapsed() > Duration::from_millis(100) {
-            if rl.is_key_pressed(KeyboardKey::KEY_UP) {
+            if keymap.is_pressed(rl, Action::MenuUp) {
                 if current_selection > 0 {
                     current_selection -= 1;
                 } else {
                     current_selection = options_count - 1; // Wrap around
                 }
                 last_change = Instant::now();
-            } else if rl.is_key_pressed(KeyboardKey::KEY_DOWN) {
+            } else if keymap.is_pressed(rl, Action::MenuDown) {
                 if current_selection < options_count - 1 {
                     current_selection += 1;
                 } else {
                     current_selection = 0; // Wrap around
                 }
                 last_change = Instant::now();
-            } else if rl.is_key_pressed(KeyboardKey::KEY_ENTER) {
+            } else if keymap.is_pressed(rl, Action::MenuSelect) {
                 // If the selection index is within the font list, it's a font change.
                 if current_selection < FONT_OPTIONS.len() {
                     *selected_font = current_selection;
@@ -96,22 +142,23 @@ pub fn show_font_selection(
             }
         }
 
+        let theme = theme::current();
         let mut d = rl.begin_drawing(thread);
-        d.clear_background(Color::BLACK);
+        d.clear_background(theme.background.color());
         d.draw_text_ex(
             &default_font,
             "Select Font",
             Vector2::new(200.0, 100.0),
             40.0,
             2.0,
-            Color::WHITE,
+            theme.text.color(),
         );
 
         for (i, (name, _)) in FONT_OPTIONS.iter().enumerate() {
             let color = if i == current_selection {
-                Color::GOLD
+                theme.accent.color()
             } else {
-                Color::GRAY
+                theme.dim.color()
             };
 
             // Create the display text with the "(default)" label for the first option.
@@ -129,7 +176,7 @@ pub fn show_font_selection(
                     Vector2::new(170.0, 180.0 + (i as f32 * 30.0)),
                     25.0,
                     1.0,
-                    Color::GREEN,
+                    theme.success.color(),
                 );
             }
             // Draw a cursor for the currently hovered selection.
@@ -140,7 +187,7 @@ pub fn show_font_selection(
                     Vector2::new(170.0, 180.0 + (i as f32 * 30.0)),
                     25.0,
                     1.0,
-                    Color::GOLD,
+                    theme.accent.color(),
                 );
             }
 
@@ -159,9 +206,9 @@ pub fn show_font_selection(
         // Determine if the "Back" option is currently selected.
         let is_back_selected = current_selection == FONT_OPTIONS.len();
         let back_color = if is_back_selected {
-            Color::GOLD
+            theme.accent.color()
         } else {
-            Color::GRAY
+            theme.dim.color()
         };
         d.draw_text_ex(
             &default_font,
@@ -181,7 +228,7 @@ pub fn show_font_selection(
                 Vector2::new(170.0, cursor_y),
                 25.0,
                 1.0,
-                Color::GOLD,
+                theme.accent.color(),
             );
         }
 
@@ -192,15 +239,28 @@ pub fn show_font_selection(
             (d.get_screen_width() as f32 - footnote_width as f32) / 2.0,
             d.get_screen_height() as f32 - 50.0,
         );
-        d.draw_text_ex(&custom_font, footnote, footnote_pos, 18.0, 1.0, Color::GRAY);
+        d.draw_text_ex(
+            &custom_font,
+            footnote,
+            footnote_pos,
+            18.0,
+            1.0,
+            theme.dim.color(),
+        );
     }
     // Fonts are automatically unloaded when `loaded_fonts` and `custom_font` go out of scope.
 }
 
-/// A screen to display the application's keybindings.
+/// A screen to show the keybindings and rebind them by pressing the new keys.
 struct KeybindingsScreen {
     font: Font,
-    keybindings: Vec<(String, String)>,
+    keymap: Keymap,
+    /// Index in the actions, followed by "Reset to defaults" and "Back"
+    selected: usize,
+    /// Waiting for the new keys of the selected action
+    capturing: bool,
+    /// Result of the last change, and whether it failed
+    message: Option<(String, bool)>,
     last_change: Instant,
     alpha: f32,
     y_offset: f32,
@@ -210,31 +270,15 @@ struct KeybindingsScreen {
 impl KeybindingsScreen {
     /// Creates a new `KeybindingsScreen`.
     fn new(rl: &mut RaylibHandle, thread: &RaylibThread) -> Self {
-        let font = rl
-            .load_font_ex(
-                thread,
-                "fontbook/fonts/ttf/JetBrainsMono-Medium.ttf",
-                20,
-                None,
-            )
+        let font = load_bundled_font(rl, thread, DEFAULT_FONT, 20)
             .expect("Failed to load keybindings font");
 
-        let keybindings = [
-            ("Keyboard characters", "Keyboard chars"),
-            ("Ctrl+Shift+C ", "Copy (Linux/MacOS)"),
-            ("Ctrl+Shift+V ", "Paste (Linux/MacOS)"),
-            ("Ctrl+K", "Clear Line"),
-            ("Ctrl+C", "Next prompt"),
-            ("Tab", "File completion only till Current Working Directory"),
-            ("Arrow keys", "Navigate through history"),
-        ]
-        .iter()
-        .map(|(key, desc)| (key.to_string(), desc.to_string()))
-        .collect();
-
         Self {
             font,
-            keybindings,
+            keymap: config::load_config().keymap,
+            selected: 0,
+            capturing: false,
+            message: None,
             last_change: Instant::now(),
             alpha: 0.0,
             y_offset: 200.0,
@@ -242,12 +286,67 @@ impl KeybindingsScreen {
         }
     }
 
+    fn save(&self) {
+        let mut cfg = config::load_config();
+        cfg.keymap = self.keymap.clone();
+        config::save_config(&cfg);
+    }
+
     /// Handles user input for the keybindings screen. Returns true if the screen should be closed.
     fn handle_input(&mut self, rl: &mut RaylibHandle) -> bool {
-        if self.last_change.elapsed() > Duration::from_millis(150)
-            && rl.is_key_pressed(KeyboardKey::KEY_ENTER)
-        {
-            return true; // Exit screen
+        if self.last_change.elapsed() <= Duration::from_millis(150) {
+            return false;
+        }
+        let options_count = Action::ALL.len() + 2;
+
+        if self.capturing {
+            let Some(combo) = KeyCombo::pressed(rl) else {
+                return false;
+            };
+            // Queued in the same frame as the key, if the key types on the layout of the player
+            let typed = rl.get_char_pressed();
+            let action = Action::ALL[self.selected];
+            self.message = Some(
+                if combo == KeyCombo::new(KeyboardKey::KEY_ESCAPE, false, false, false) {
+                    ("Cancelled".to_string(), false)
+                } else {
+                    match self.keymap.bind(action, combo, typed) {
+                        Ok(()) => {
+                            self.save();
+                            (
+                                format!("\"{}\" is now {combo}", action.description()),
+                                false,
+                            )
+                        }
+                        Err(e) => (e, true),
+                    }
+                },
+            );
+            self.capturing = false;
+            self.last_change = Instant::now();
+            return false;
+        }
+
+        if self.keymap.is_pressed(rl, Action::MenuUp) {
+            self.selected = (self.selected + options_count - 1) % options_count;
+            self.last_change = Instant::now();
+        } else if self.keymap.is_pressed(rl, Action::MenuDown) {
+            self.selected = (self.selected + 1) % options_count;
+            self.last_change = Instant::now();
+        } else if self.keymap.is_pressed(rl, Action::MenuSelect) {
+            match self.selected {
+                i if i < Action::ALL.len() => {
+                    self.capturing = true;
+                    self.message = None;
+                }
+                i if i == Action::ALL.len() => {
+                    self.keymap.reset();
+                    self.save();
+                    self.message = Some(("Keybindings reset to defaults".to_string(), false));
+                }
+                _ => return true, // Exit screen
+            }
+            self.last_change = Instant::now();
         }
         false
     }
@@ -260,8 +359,9 @@ impl KeybindingsScreen {
 
     /// Draws the keybindings screen.
     fn draw(&self, d: &mut RaylibDrawHandle) {
+        let theme = theme::current();
         let font_heading = d.get_font_default();
-        d.clear_background(Color::BLACK);
+        d.clear_background(theme.background.color());
 
         // Draw heading with animation
         d.draw_text_ex(
@@ -270,7 +370,7 @@ impl KeybindingsScreen {
             Vector2::new(200.0, self.y_offset),
             50.0,
             2.0,
-            Color::new(255, 255, 255, (self.alpha * 255.0) as u8),
+            theme.text.fade(self.alpha),
         );
 
         // Draw column headers
@@ -281,7 +381,7 @@ impl KeybindingsScreen {
             Vector2::new(100.0, header_y),
             20.0,
             1.0,
-            Color::LIGHTGRAY,
+            theme.muted.color(),
         );
         d.draw_text_ex(
             &self.font,
@@ -289,7 +389,7 @@ impl KeybindingsScreen {
             Vector2::new(400.0, header_y),
             20.0,
             1.0,
-            Color::LIGHTGRAY,
+            theme.muted.color(),
         );
 
         // Draw separator line
@@ -299,24 +399,31 @@ impl KeybindingsScreen {
             separator_y as i32,
             650,
             separator_y as i32,
-            Color::GRAY,
+            theme.dim.color(),
         );
 
         // Draw keybindings with wrapping for descriptions
         let mut y = separator_y + 20.0;
         let max_desc_width = d.get_screen_width() as f32 - 400.0 - 20.0; // 20px padding
 
-        for (key, desc) in &self.keybindings {
-            d.draw_text_ex(
-                &self.font,
-                key,
-                Vector2::new(100.0, y),
-                20.0,
-                1.0,
-                Color::WHITE,
-            );
+        for (i, action) in Action::ALL.iter().enumerate() {
+            let color = if i == self.selected {
+                theme.accent.color()
+            } else {
+                theme.text.color()
+            };
+            let key = if i == self.selected && self.capturing {
+                "Press keys...".to_string()
+            } else {
+                self.keymap.combo(*action).to_string()
+            };
+            if i == self.selected {
+                d.draw_text_ex(&self.font, ">", Vector2::new(80.0, y), 20.0, 1.0, color);
+            }
+            d.draw_text_ex(&self.font, &key, Vector2::new(100.0, y), 20.0, 1.0, color);
 
-            let wrapped_lines = wrap_text(&self.font, desc, 20.0, 1.0, max_desc_width);
+            let wrapped_lines =
+                wrap_text(&self.font, action.description(), 20.0, 1.0, max_desc_width);
             let mut line_y = y;
             for line in &wrapped_lines {
                 d.draw_text_ex(
@@ -325,7 +432,7 @@ impl KeybindingsScreen {
                     Vector2::new(400.0, line_y),
                     20.0,
                     1.0,
-                    Color::WHITE,
+                    color,
                 );
                 line_y += 25.0; // Line height
             }
@@ -333,19 +440,57 @@ impl KeybindingsScreen {
             y += (wrapped_lines.len() as f32 * 25.0).max(30.0); // Move to the next entry
         }
 
-        // Draw "Press Enter to go back" message
-        let back_msg = "Press Enter to go back";
-        let back_msg_width = d.measure_text(back_msg, 20);
+        y += 10.0;
+        for (i, label) in ["Reset to defaults", "Back"].iter().enumerate() {
+            let is_selected = self.selected == Action::ALL.len() + i;
+            let color = if is_selected {
+                theme.accent.color()
+            } else {
+                theme.muted.color()
+            };
+            if is_selected {
+                d.draw_text_ex(&self.font, ">", Vector2::new(80.0, y), 20.0, 1.0, color);
+            }
+            d.draw_text_ex(&self.font, label, Vector2::new(100.0, y), 20.0, 1.0, color);
+            y += 30.0;
+        }
+
+        if let Some((message, failed)) = &self.message {
+            let color = if *failed {
+                theme.error.color()
+            } else {
+                theme.success.color()
+            };
+            d.draw_text_ex(
+                &self.font,
+                message,
+                Vector2::new(100.0, y + 10.0),
+                20.0,
+                1.0,
+                color,
+            );
+        }
+
+        // Draw the hint for rebinding
+        let back_msg = if self.capturing {
+            "Press the new keys, or Escape to cancel".to_string()
+        } else {
+            format!(
+                "Press {} on a keybinding to change it",
+                self.keymap.combo(Action::MenuSelect)
+            )
+        };
+        let back_msg_width = d.measure_text(&back_msg, 20);
         d.draw_text_ex(
             &font_heading,
-            back_msg,
+            &back_msg,
             Vector2::new(
                 (d.get_screen_width() as f32 - back_msg_width as f32) / 2.0,
                 d.get_screen_height() as f32 - 50.0,
             ),
             20.0,
             1.0,
-            Color::GRAY,
+            theme.dim.color(),
         );
     }
 }
@@ -415,20 +560,45 @@ pub fn show_settings(rl: &mut RaylibHandle, thread: &RaylibThread) {
     let target_y = 200.0;
     let font = rl.get_font_default();
     let mut selected_font: usize = config::load_config().font_index;
+    let mut cfg = config::load_config();
+    let monitor_width = get_monitor_width(0);
 
     while !rl.window_should_close() {
         if last_change.elapsed() > Duration::from_millis(150) {
-            if rl.is_key_pressed(KeyboardKey::KEY_UP) {
+            let step = if cfg.keymap.is_pressed(rl, Action::MenuRight) {
+                1
+            } else if cfg.keymap.is_pressed(rl, Action::MenuLeft) {
+                -1
+            } else {
+                0
+            };
+            if step != 0 {
+                // Saved at once, so that the shell picks it up when it is shown again
+                if SettingsOption::opts()[selected].adjust(&mut cfg, step, monitor_width) {
+                    config::save_config(&cfg);
+                }
+                last_change = Instant::now();
+            } else if cfg.keymap.is_pressed(rl, Action::MenuUp) {
                 selected = selected.saturating_sub(1);
                 last_change = Instant::now();
-            } else if rl.is_key_pressed(KeyboardKey::KEY_DOWN) {
+            } else if cfg.keymap.is_pressed(rl, Action::MenuDown) {
                 selected = (selected + 1).min(SettingsOption::opts().len() - 1);
                 last_change = Instant::now();
-            } else if rl.is_key_pressed(KeyboardKey::KEY_ENTER) {
+            } else if cfg.keymap.is_pressed(rl, Action::MenuSelect) {
                 match SettingsOption::opts()[selected] {
-                    SettingsOption::Font => show_font_selection(rl, thread, &mut selected_font),
-                    SettingsOption::Keybindings => show_keybindings(rl, thread),
+                    SettingsOption::Font => {
+                        show_font_selection(rl, thread, &mut selected_font);
+                        cfg = config::load_config();
+                    }
+                    SettingsOption::Keybindings => {
+                        show_keybindings(rl, thread);
+                        cfg = config::load_config();
+                    }
                     SettingsOption::Back => return,
+                    option => {
+                        option.adjust(&mut cfg, 1, monitor_width);
+                        config::save_config(&cfg);
+                    }
                 }
                 last_change = Instant::now();
             }
@@ -437,8 +607,9 @@ pub fn show_settings(rl: &mut RaylibHandle, thread: &RaylibThread) {
         alpha = (alpha + 0.02).min(1.0);
         y_offset += (target_y - y_offset) * 0.1;
 
+        let theme = theme::current();
         let mut d = rl.begin_drawing(thread);
-        d.clear_background(Color::BLACK);
+        d.clear_background(theme.background.color());
 
         d.draw_text_ex(
             &font,
@@ -446,19 +617,23 @@ pub fn show_settings(rl: &mut RaylibHandle, thread: &RaylibThread) {
             Vector2::new(200.0, y_offset),
             50.0,
             2.0,
-            Color::new(255, 255, 255, (alpha * 255.0) as u8),
+            theme.text.fade(alpha),
         );
 
         for (i, option) in SettingsOption::opts().iter().enumerate() {
             let color = if i == selected {
-                Color::GOLD
+                theme.accent.color()
             } else {
-                Color::new(200, 200, 200, (alpha * 200.0) as u8)
+                theme.muted.fade(alpha * 200.0 / 255.0)
             };
 
+            let label = match option.value(&cfg, monitor_width) {
+                Some(value) => format!("{}:  < {value} >", option.as_str()),
+                None => option.as_str().to_string(),
+            };
             d.draw_text_ex(
                 &font,
-                option.as_str(),
+                &label,
                 Vector2::new(200.0, 300.0 + (i as f32 * 50.0)),
                 30.0,
                 1.0,
@@ -471,7 +646,24 @@ pub fn show_settings(rl: &mut RaylibHandle, thread: &RaylibThread) {
             175,
             300 + selected as i32 * 50,
             30,
-            Color::new(255, 255, 255, ((alpha * 0.5).sin().abs() * 255.0) as u8),
+            theme.text.fade((alpha * 0.5).sin().abs()),
+        );
+
+        let hint = format!(
+            "{}/{} to choose, {}/{} to change, {} to open",
+            cfg.keymap.combo(Action::MenuUp),
+            cfg.keymap.combo(Action::MenuDown),
+            cfg.keymap.combo(Action::MenuLeft),
+            cfg.keymap.combo(Action::MenuRight),
+            cfg.keymap.combo(Action::MenuSelect),
+        );
+        d.draw_text_ex(
+            &font,
+            &hint,
+            Vector2::new(200.0, 320.0 + (SettingsOption::opts().len() as f32 * 50.0)),
+            20.0,
+            1.0,
+            theme.dim.fade(alpha),
         );
     }
 }
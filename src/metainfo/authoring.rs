@@ -0,0 +1,110 @@
+use crate::sekai_fs::SekaiFs;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/*
+AUTHORING FILE EXPLANATION:
+
+The Sekai only keeps hashed and encrypted forms of the solutions, so the author keeps the
+plaintext solutions and flags in `HOME/.dir_info/authoring.json`. `dev lock` records them there,
+and `dev new` writes the ones of the template.
+
+The authoring files are never packed into a world. `dev pack` encrypts them with the developer
+password into a sidecar next to the `.deemak` file, and `dev unpack` puts them back.
+*/
+
+/// Where the solutions of the Sekai are kept while authoring, relative to HOME.
+pub const AUTHORING_FILE: &str = ".dir_info/authoring.json";
+
+/// Level whose `decrypt_me` gives the flag when solved.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct LevelSolution {
+    /// Path of the level, relative to HOME
+    pub path: String,
+    pub solution: String,
+    pub flag: String,
+    /// Level which is opened by the flag
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub unlocks: Option<String>,
+    #[serde(default)]
+    pub points: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ChestSolution {
+    /// Path of the chest, relative to HOME
+    pub path: String,
+    pub solution: String,
+    #[serde(default)]
+    pub points: u64,
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, PartialEq)]
+pub struct Authoring {
+    #[serde(default)]
+    pub levels: Vec<LevelSolution>,
+    #[serde(default)]
+    pub chests: Vec<ChestSolution>,
+}
+
+impl Authoring {
+    /// Reads the authoring file of the Sekai, empty if it does not exist yet.
+    pub fn load(fs: &dyn SekaiFs, root: &Path) -> Result<Self, String> {
+        let path = root.join(AUTHORING_FILE);
+        if !fs.exists(&path) {
+            return Ok(Self::default());
+        }
+        let contents = fs
+            .read_to_string(&path)
+            .map_err(|e| format!("Failed to read {}: {e}", path.display()))?;
+        serde_json::from_str(&contents).map_err(|e| format!("Invalid {AUTHORING_FILE}: {e}"))
+    }
+
+    pub fn save(&self, fs: &dyn SekaiFs, root: &Path) -> Result<(), String> {
+        let path = root.join(AUTHORING_FILE);
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| format!("Failed to serialize {AUTHORING_FILE}: {e}"))?;
+        fs.write(&path, json.as_bytes())
+            .map_err(|e| format!("Failed to write {}: {e}", path.display()))
+    }
+
+    /// Records the solution of a level, replacing the previous one but keeping its points.
+    pub fn set_level(&mut self, mut level: LevelSolution) {
+        if let Some(old) = self.levels.iter().position(|l| l.path == level.path) {
+            let old = self.levels.remove(old);
+            if level.points == 0 {
+                level.points = old.points;
+            }
+        }
+        self.levels.push(level);
+    }
+
+    /// Records the solution of a chest, replacing the previous one but keeping its points.
+    pub fn set_chest(&mut self, mut chest: ChestSolution) {
+        if let Some(old) = self.chests.iter().position(|c| c.path == chest.path) {
+            let old = self.chests.remove(old);
+            if chest.points == 0 {
+                chest.points = old.points;
+            }
+        }
+        self.chests.push(chest);
+    }
+
+    /// Forgets the solutions of the object, when its lock is removed.
+    pub fn remove(&mut self, path: &str) {
+        self.levels.retain(|l| l.path != path);
+        self.chests.retain(|c| c.path != path);
+    }
+}
+
+/// Updates the authoring file of the Sekai. Failing to record a solution only loses the
+/// plaintext copy, so callers log the error and carry on.
+pub fn update_authoring(
+    fs: &dyn SekaiFs,
+    root: &Path,
+    update: impl FnOnce(&mut Authoring),
+) -> Result<(), String> {
+    let mut authoring = Authoring::load(fs, root)?;
+    update(&mut authoring);
+    authoring.save(fs, root)
+}
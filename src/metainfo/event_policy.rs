@@ -0,0 +1,246 @@
+use super::info_reader::{InfoError, read_validate_info};
+use crate::sekai_fs::SekaiFs;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+
+/*
+EVENT POLICY EXPLANATION:
+
+A Sekai can be run as a timed event by adding an `event` section to the root (HOME)
+`.dir_info/info.json`. Since it lives inside the Sekai, it is also part of the `.deemak` file.
+
+    "event": {
+        "start": "2026-01-31T10:00:00+05:30",
+        "end": "2026-01-31T18:00:00+05:30",
+        "allowed_users": ["alice"],
+        "allowed_groups": { "team_rocket": ["jessie", "james"] }
+    }
+
+All fields are optional.
+- Before `start`, only a countdown is shown and no commands can be run.
+- After `end`, the Sekai is read-only. Players can look around, but not change or unlock anything.
+- If `allowed_users` or `allowed_groups` are given, only those users can play the Sekai.
+
+A broken `event` section does not make the HOME info.json unreadable. The Sekai is played without
+a policy, the error is logged and reported by `dev check`.
+*/
+
+/// Commands that change the Sekai or the progress, refused once the event has ended.
+pub const WRITE_COMMANDS: [&str; 8] = [
+    "tap", "del", "copy", "save", "restore", "solve", "unlock", "dev",
+];
+/// Commands that can be run before the event starts.
+pub const PRE_START_COMMANDS: [&str; 4] = ["help", "whoami", "clear", "exit"];
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct EventPolicy {
+    /// Start time in RFC 3339 format
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub start: Option<String>,
+    /// End time in RFC 3339 format
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub end: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub allowed_users: Vec<String>,
+    /// Group name to the usernames in the group
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub allowed_groups: HashMap<String, Vec<String>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventStatus {
+    /// Seconds left for the event to start
+    NotStarted(i64),
+    Running,
+    Ended,
+}
+
+fn parse_time(field: &str, value: &Option<String>) -> Result<Option<i64>, String> {
+    match value {
+        None => Ok(None),
+        Some(v) => DateTime::parse_from_rfc3339(v.trim())
+            .map(|t| Some(t.timestamp()))
+            .map_err(|e| format!("Invalid event {field} '{v}', expected RFC 3339: {e}")),
+    }
+}
+
+impl EventPolicy {
+    pub fn start_time(&self) -> Result<Option<i64>, String> {
+        parse_time("start", &self.start)
+    }
+
+    pub fn end_time(&self) -> Result<Option<i64>, String> {
+        parse_time("end", &self.end)
+    }
+
+    /// Checks that the times are valid and the event ends after it starts.
+    pub fn validate(&self) -> Result<(), String> {
+        if let (Some(start), Some(end)) = (self.start_time()?, self.end_time()?)
+            && end <= start
+        {
+            return Err("Event end must be after its start".to_string());
+        }
+        Ok(())
+    }
+
+    /// Status of the event at the given unix time. Invalid times are ignored.
+    pub fn status_at(&self, now: i64) -> EventStatus {
+        if let Ok(Some(start)) = self.start_time()
+            && now < start
+        {
+            return EventStatus::NotStarted(start - now);
+        }
+        if let Ok(Some(end)) = self.end_time()
+            && now >= end
+        {
+            return EventStatus::Ended;
+        }
+        EventStatus::Running
+    }
+
+    pub fn status(&self) -> EventStatus {
+        self.status_at(Utc::now().timestamp())
+    }
+
+    /// Whether the policy limits who can play.
+    pub fn is_restricted(&self) -> bool {
+        !self.allowed_users.is_empty() || !self.allowed_groups.is_empty()
+    }
+
+    pub fn is_user_allowed(&self, username: &str) -> bool {
+        !self.is_restricted()
+            || self.allowed_users.iter().any(|u| u == username)
+            || self
+                .allowed_groups
+                .values()
+                .any(|members| members.iter().any(|u| u == username))
+    }
+
+    /// Checks if the command can be run now, returning the reason if not.
+    pub fn check_command(&self, cmd: &str) -> Result<(), String> {
+        match self.status() {
+            EventStatus::NotStarted(secs) if !PRE_START_COMMANDS.contains(&cmd) => Err(format!(
+                "The event has not started yet. It starts in {}.",
+                format_countdown(secs)
+            )),
+            EventStatus::Ended if WRITE_COMMANDS.contains(&cmd) => {
+                Err("The event has ended. The Sekai is read-only now.".to_string())
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Parses and validates the `event` section of the HOME info.json.
+pub fn parse_event_policy(event: &serde_json::Value) -> Result<EventPolicy, String> {
+    let policy =
+        EventPolicy::deserialize(event).map_err(|e| format!("Invalid event policy: {e}"))?;
+    policy.validate()?;
+    Ok(policy)
+}
+
+/// Reads the event policy of the Sekai, if it has one.
+pub fn read_event_policy(
+    fs: &dyn SekaiFs,
+    root_dir: &Path,
+) -> Result<Option<EventPolicy>, InfoError> {
+    let info = read_validate_info(fs, &root_dir.join(".dir_info/info.json"))?;
+    info.event
+        .as_ref()
+        .map(|event| parse_event_policy(event).map_err(InfoError::ValidationError))
+        .transpose()
+}
+
+/// Checks if the user can play the Sekai, and returns the status of the event.
+pub fn check_user_access(policy: &EventPolicy, username: &str) -> Result<EventStatus, String> {
+    if !policy.is_user_allowed(username) {
+        return Err(format!(
+            "User '{username}' is not allowed to play this Sekai."
+        ));
+    }
+    Ok(policy.status())
+}
+
+/// Formats seconds as `[Nd ]HH:MM:SS`.
+pub fn format_countdown(secs: i64) -> String {
+    let secs = secs.max(0);
+    let (days, rem) = (secs / 86400, secs % 86400);
+    let clock = format!("{:02}:{:02}:{:02}", rem / 3600, (rem % 3600) / 60, rem % 60);
+    if days > 0 {
+        format!("{days}d {clock}")
+    } else {
+        clock
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::cmds::CommandResult;
+    use crate::sekai_fs::DiskFs;
+    use crate::session::{Sekai, Session};
+    use crate::utils::globals::UserInfo;
+    use crate::utils::test_utils::setup_test_dir;
+    use std::fs;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_event_policy() {
+        let (_temp_dir, root_path) = setup_test_dir(true);
+        assert_eq!(read_event_policy(&DiskFs, &root_path).unwrap(), None);
+
+        // Add an event to the HOME info.json
+        let info_path = root_path.join(".dir_info/info.json");
+        let mut info: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&info_path).unwrap()).unwrap();
+        info["event"] = serde_json::json!({
+            "start": "2030-01-01T10:00:00+00:00",
+            "end": "2030-01-01T18:00:00+00:00",
+            "allowed_users": ["alice"],
+            "allowed_groups": { "team": ["bob"] }
+        });
+        fs::write(&info_path, info.to_string()).unwrap();
+
+        let policy = read_event_policy(&DiskFs, &root_path).unwrap().unwrap();
+        let start = policy.start_time().unwrap().unwrap();
+        let end = policy.end_time().unwrap().unwrap();
+        assert_eq!(policy.status_at(start - 90), EventStatus::NotStarted(90));
+        assert_eq!(policy.status_at(start), EventStatus::Running);
+        assert_eq!(policy.status_at(end), EventStatus::Ended);
+
+        assert!(policy.is_user_allowed("alice"));
+        assert!(policy.is_user_allowed("bob"));
+        assert!(!policy.is_user_allowed("eve"));
+
+        // Not started yet, so only a few commands work
+        assert!(policy.check_command("help").is_ok());
+        assert!(policy.check_command("ls").is_err());
+
+        // End before start is rejected
+        info["event"]["end"] = serde_json::json!("2029-01-01T10:00:00+00:00");
+        fs::write(&info_path, info.to_string()).unwrap();
+        assert!(read_event_policy(&DiskFs, &root_path).is_err());
+
+        // A broken policy leaves HOME readable, but the Sekai cannot be played without it
+        info["event"] = serde_json::json!({ "strat": "2030-01-01T10:00:00+00:00" });
+        fs::write(&info_path, info.to_string()).unwrap();
+        assert!(read_validate_info(&DiskFs, &info_path).is_ok());
+        assert!(read_event_policy(&DiskFs, &root_path).is_err());
+        let sekai = Sekai::new(Arc::new(DiskFs), root_path.clone());
+        assert!(sekai.event_policy().is_err());
+        let mut session = Session::new(sekai);
+        session.user = Some(UserInfo::new(
+            "alice".to_string(),
+            String::new(),
+            String::new(),
+        ));
+        assert!(matches!(session.run("ls"), CommandResult::Error(_)));
+        assert!(matches!(session.run("help"), CommandResult::Error(_)));
+
+        assert_eq!(format_countdown(3661), "01:01:01");
+        assert_eq!(format_countdown(90061), "1d 01:01:01");
+    }
+}
@@ -0,0 +1,188 @@
+use super::info_reader::{Info, read_validate_info};
+use crate::commands::cmds::normalize_path;
+use crate::sekai_fs::{EntryKind, FileVersion, SekaiFs};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+
+/*
+INFO INDEX EXPLANATION:
+
+Lock checks read the info.json of every ancestor, `ls` reads it once per entry, so parsing them on
+every call is slow on deep Sekai's, more so on a network filesystem. Each `SekaiFs` keeps an
+`InfoIndex` of the parsed info.json files instead:
+- `index_sekai` fills it when the Sekai is loaded, and `read_validate_info` is served from it.
+- An entry is used only while the `FileVersion` of the file is the one it was parsed at. For the
+  disk that is the modification time, size, and on Unix the inode change time and number, so
+  edits of the author are seen without reloading. For a `MemFs` it changes on every write.
+- Writes made through `info_reader::write_info` drop the entry, the next read parses it again.
+*/
+
+/// Parsed info.json files of a filesystem, see the explanation above.
+#[derive(Default)]
+pub struct InfoIndex {
+    entries: RwLock<HashMap<PathBuf, (FileVersion, Arc<Info>)>>,
+}
+
+impl InfoIndex {
+    /// The parsed info.json, if it did not change since it was indexed.
+    pub fn get(&self, fs: &dyn SekaiFs, info_path: &Path) -> Option<Arc<Info>> {
+        let key = normalize_path(info_path);
+        let entries = self.entries.read().unwrap();
+        let (version, info) = entries.get(&key)?;
+        (fs.version(&key) == Some(*version)).then(|| Arc::clone(info))
+    }
+
+    /// Keeps the info parsed from the file at the version.
+    pub fn insert(&self, info_path: &Path, version: FileVersion, info: Arc<Info>) {
+        self.entries
+            .write()
+            .unwrap()
+            .insert(normalize_path(info_path), (version, info));
+    }
+
+    /// Drops the entries of the path and everything below it.
+    pub fn forget(&self, path: &Path) {
+        let path = normalize_path(path);
+        self.entries
+            .write()
+            .unwrap()
+            .retain(|key, _| !key.starts_with(&path));
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.read().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Parses the info.json of every directory of the Sekai into the index of the filesystem.
+/// Returns the number of info.json files indexed.
+pub fn index_sekai(fs: &dyn SekaiFs, root: &Path) -> usize {
+    let mut indexed = 0;
+    let mut dirs = vec![root.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        if read_validate_info(fs, &dir.join(".dir_info/info.json")).is_ok() {
+            indexed += 1;
+        }
+        for entry in fs.read_dir(&dir).unwrap_or_default() {
+            if fs.kind(&entry) == Some(EntryKind::Dir)
+                && entry.file_name().and_then(|n| n.to_str()) != Some(".dir_info")
+            {
+                dirs.push(entry);
+            }
+        }
+    }
+    indexed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metainfo::info_reader::{read_about, update_obj_status, write_about};
+    use crate::metainfo::valid_sekai::validate_or_create_sekai;
+    use crate::sekai_fs::{DiskFs, MemFs};
+    use crate::utils::test_utils::setup_test_dir;
+    use serde_json::Value;
+    use std::fs;
+
+    /// Test that the index is filled on load and follows the writes of a MemFs
+    #[test]
+    fn test_memfs_index() {
+        let (_temp_dir, root_path) = setup_test_dir(false);
+        assert!(validate_or_create_sekai(&DiskFs, &root_path, false));
+        let root = MemFs::root_for(Path::new("world.deemak"));
+        let mem = MemFs::load_dir(&root_path, &root).unwrap();
+        let index = mem.info_index();
+        let home_info = root.join(".dir_info/info.json");
+        let nested_info = root.join("subdir1/nested1/.dir_info/info.json");
+
+        assert_eq!(index_sekai(&mem, &root), 5);
+        assert_eq!(index.len(), 5);
+        assert!(index.get(&mem, &nested_info).is_some());
+
+        // Writes through the info reader drop the entry, it is parsed again when read
+        write_about(&mem, &home_info, "Changed about".to_string()).unwrap();
+        assert!(index.get(&mem, &home_info).is_none());
+        assert_eq!(read_about(&mem, &home_info).unwrap(), "Changed about");
+        assert_eq!(index.get(&mem, &home_info).unwrap().about, "Changed about");
+        update_obj_status(
+            &mem,
+            &root.join("subdir1/nested1/file4.txt"),
+            "file4.txt",
+            "points",
+            Value::from(3),
+        )
+        .unwrap();
+        let nested = read_validate_info(&mem, &nested_info).unwrap();
+        assert_eq!(nested.objects["file4.txt"].get_points(), 3);
+        assert!(index.get(&mem, &nested_info).is_some());
+
+        // Other writes make it stale, even with the same length
+        let json = serde_json::to_string_pretty(&nested)
+            .unwrap()
+            .replace("\"points\": 3", "\"points\": 4");
+        mem.write(&nested_info, json.as_bytes()).unwrap();
+        assert!(index.get(&mem, &nested_info).is_none());
+        let info = read_validate_info(&mem, &nested_info).unwrap();
+        assert_eq!(info.objects["file4.txt"].get_points(), 4);
+
+        // Moved files are new at their path
+        let moved_info = root.join("subdir2/nested2/.dir_info/info.json");
+        let subdir1 = read_validate_info(&mem, &root.join("subdir1/.dir_info/info.json")).unwrap();
+        assert!(index.get(&mem, &moved_info).is_some());
+        mem.rename(&root.join("subdir1"), &root.join("subdir2/moved"))
+            .unwrap();
+        mem.rename(&root.join("subdir2/moved/.dir_info/info.json"), &moved_info)
+            .unwrap();
+        assert!(index.get(&mem, &moved_info).is_none());
+        assert_eq!(
+            read_validate_info(&mem, &moved_info).unwrap().location,
+            subdir1.location
+        );
+    }
+
+    /// Test that edits made on the disk by other programs are seen
+    #[test]
+    fn test_disk_index() {
+        let (_temp_dir, root_path) = setup_test_dir(true);
+        let info_path = root_path.join(".dir_info/info.json");
+
+        let about = read_about(&DiskFs, &info_path).unwrap();
+        assert_eq!(
+            DiskFs.info_index().get(&DiskFs, &info_path).unwrap().about,
+            about
+        );
+
+        let mut info = read_validate_info(&DiskFs, &info_path).unwrap();
+        info.about = "Edited by the author".to_string();
+        fs::write(&info_path, serde_json::to_string(&info).unwrap()).unwrap();
+        assert!(DiskFs.info_index().get(&DiskFs, &info_path).is_none());
+        assert_eq!(
+            read_about(&DiskFs, &info_path).unwrap(),
+            "Edited by the author"
+        );
+
+        // A rewrite of the same size which sets the modification time back is seen as well
+        let modified = fs::metadata(&info_path).unwrap().modified().unwrap();
+        info.about = "Edited by the editor".to_string();
+        fs::write(&info_path, serde_json::to_string(&info).unwrap()).unwrap();
+        fs::File::options()
+            .write(true)
+            .open(&info_path)
+            .unwrap()
+            .set_modified(modified)
+            .unwrap();
+        assert_eq!(
+            read_about(&DiskFs, &info_path).unwrap(),
+            "Edited by the editor"
+        );
+
+        DiskFs.remove_dir_all(&root_path.join(".dir_info")).unwrap();
+        assert!(DiskFs.info_index().get(&DiskFs, &info_path).is_none());
+        assert!(read_validate_info(&DiskFs, &info_path).is_err());
+    }
+}
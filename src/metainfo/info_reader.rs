@@ -1,10 +1,11 @@
 use crate::commands::cmds::normalize_path;
+use crate::sekai_fs::SekaiFs;
 use crate::utils::relative_deemak_path;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::collections::HashMap;
-use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use thiserror::Error;
 
 #[derive(Debug, Clone, Deserialize, Serialize, Default)]
@@ -46,6 +47,25 @@ impl ObjectInfo {
         self
     }
 
+    pub fn with_points(mut self, points: u64) -> Self {
+        self.properties
+            .insert("points".to_string(), Value::from(points));
+        self
+    }
+
+    /// Points awarded for solving/unlocking this object, 0 if not set.
+    pub fn get_points(&self) -> u64 {
+        self.properties
+            .get("points")
+            .and_then(|v| v.as_u64())
+            .unwrap_or(0)
+    }
+
+    /// MIME type set by the author, overriding the sniffed one.
+    pub fn get_mime(&self) -> Option<&str> {
+        self.properties.get("mime").and_then(|v| v.as_str())
+    }
+
     pub fn without_decrypt_me(mut self) -> Self {
         self.properties.remove("decrypt_me");
         self
@@ -56,7 +76,7 @@ impl ObjectInfo {
     }
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(deny_unknown_fields)]
 pub struct Info {
     #[serde(rename = "location")]
@@ -65,6 +85,10 @@ pub struct Info {
     pub about: String,
     #[serde(rename = "objects")]
     pub objects: HashMap<String, ObjectInfo>,
+    /// Event policy of the Sekai, only read from the HOME info.json. It is parsed on its own by
+    /// `event_policy::parse_event_policy`, so a broken policy leaves the info.json readable.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub event: Option<Value>,
 }
 
 #[derive(Debug, Error)]
@@ -80,41 +104,38 @@ pub enum InfoError {
 }
 
 impl Info {
-    /// Creates default Info values for a path
-    pub fn default_for_path(path: &Path, home_dir: bool) -> Self {
+    /// Creates default Info values for a path in the Sekai with the HOME
+    pub fn default_for_path(fs: &dyn SekaiFs, path: &Path, home: &Path) -> Self {
         let norm_path = normalize_path(path);
         // NOTE: Since deafult Permission is "00", decrypt_me and obj_salt are not going to be set.
         Info {
-            location: Self::default_location(&norm_path, home_dir),
-            about: Self::default_about(&norm_path, home_dir),
-            objects: Self::default_objects(&norm_path),
+            location: Self::default_location(&norm_path, home),
+            about: Self::default_about(&norm_path, home),
+            objects: Self::default_objects(fs, &norm_path),
+            event: None,
         }
     }
 
-    pub fn default_about(path: &Path, home_dir: bool) -> String {
+    pub fn default_about(path: &Path, home: &Path) -> String {
         format!(
             "You are in '{}'. Look around and explore!",
-            Self::default_location(path, home_dir)
+            Self::default_location(path, home)
         )
     }
-    pub fn default_location(path: &Path, home_dir: bool) -> String {
-        if home_dir {
-            "HOME".to_string()
-        } else {
-            relative_deemak_path(path, None).display().to_string()
-        }
+    pub fn default_location(path: &Path, home: &Path) -> String {
+        relative_deemak_path(path, Some(&normalize_path(home)))
+            .display()
+            .to_string()
     }
 
-    pub fn default_objects(path: &Path) -> HashMap<String, ObjectInfo> {
+    pub fn default_objects(fs: &dyn SekaiFs, path: &Path) -> HashMap<String, ObjectInfo> {
         // Create default empty objects map
         let mut objects = HashMap::new();
 
         // If the path is a directory, read its files/dir and add to objects
-        if path.is_dir()
-            && let Ok(entries) = std::fs::read_dir(path)
-        {
-            for entry in entries.filter_map(|e| e.ok()) {
-                if let Some(name) = entry.file_name().to_str()
+        if let Ok(entries) = fs.read_dir(path) {
+            for entry in entries {
+                if let Some(name) = entry.file_name().and_then(|n| n.to_str())
                     && name != ".dir_info"
                 {
                     objects.insert(
@@ -143,15 +164,148 @@ impl Info {
     }
 }
 
-/// Reads, validates and returns Info
-pub fn read_validate_info(info_path: &Path) -> Result<Info, InfoError> {
-    if !info_path.exists() {
+/// Reads, validates and returns Info. Served from the index of the filesystem while the file is
+/// unchanged, see `info_index`.
+pub fn read_validate_info(fs: &dyn SekaiFs, info_path: &Path) -> Result<Info, InfoError> {
+    let index = fs.info_index();
+    if let Some(info) = index.get(fs, info_path) {
+        return Ok(info.as_ref().clone());
+    }
+    if !fs.exists(info_path) {
+        return Err(InfoError::NotFound(info_path.display().to_string()));
+    }
+
+    // Taken before reading, so a write in between makes the entry stale instead of wrong
+    let version = fs.version(info_path);
+    let contents = fs.read_to_string(info_path)?;
+    let info = validated(serde_json::from_str(&contents)?)?;
+    if let Some(version) = version {
+        index.insert(info_path, version, Arc::new(info.clone()));
+    }
+    Ok(info)
+}
+
+/// Writes the info to info.json and drops it from the index. It is written next to info.json
+/// first, flushed to the disk and then renamed over it, so a crash never leaves a truncated
+/// info.json behind. The caller holds the lock of the directory until it returns, see
+/// `update_info`.
+fn write_info(fs: &dyn SekaiFs, info_path: &Path, info: &Info) -> Result<(), InfoError> {
+    let json = serde_json::to_string_pretty(info)?;
+    // Unique, as the lock only keeps other programs out where directories can be locked
+    let temp_path = info_path.with_extension(format!("json.{:016x}.tmp", rand::random::<u64>()));
+    if let Err(e) = fs
+        .write(&temp_path, json.as_bytes())
+        .and_then(|_| fs.sync_file(&temp_path))
+        .and_then(|_| fs.rename(&temp_path, info_path))
+    {
+        let _ = fs.remove_file(&temp_path);
+        return Err(e.into());
+    }
+    // Not cached with the version read now, another writer may have renamed its info.json over
+    // this one in between. The next read parses it again, taking the version before reading.
+    fs.info_index().forget(info_path);
+    Ok(())
+}
+
+/// Reads the info.json, changes it and writes it back, holding the lock of its directory so that
+/// concurrent changes are not lost. Nothing is written if the change fails or leaves the info
+/// invalid.
+pub fn update_info<T>(
+    fs: &dyn SekaiFs,
+    info_path: &Path,
+    change: impl FnOnce(&mut Info) -> Result<T, InfoError>,
+) -> Result<T, InfoError> {
+    if !fs.exists(info_path) {
         return Err(InfoError::NotFound(info_path.display().to_string()));
     }
+    let _lock = fs.lock_dir(info_path.parent().unwrap_or(info_path))?;
+    // Another program may have written it within the resolution of the modification time
+    fs.info_index().forget(info_path);
+
+    let mut info = read_validate_info(fs, info_path)?;
+    let result = change(&mut info)?;
+    write_info(fs, info_path, &validated(info)?)?;
+    Ok(result)
+}
+
+/// Property changes of objects in the same info.json, applied together by `commit`. Either all
+/// of them are written or none.
+///
+/// Example:
+///     InfoTransaction::new(fs, &obj_path)
+///         .set("chest", "compare_me", Value::String(compare_me))
+///         .set("chest", "locked", Value::String("01".to_string()))
+///         .commit()?;
+pub struct InfoTransaction<'a> {
+    fs: &'a dyn SekaiFs,
+    info_path: PathBuf,
+    /// (object, property, new value), None removes the property
+    changes: Vec<(String, String, Option<Value>)>,
+}
+
+impl<'a> InfoTransaction<'a> {
+    /// Changes to the info.json of the directory the object is in.
+    pub fn new(fs: &'a dyn SekaiFs, obj_path: &Path) -> Self {
+        Self {
+            fs,
+            info_path: obj_info_path(obj_path),
+            changes: Vec::new(),
+        }
+    }
+
+    /// Sets the property, adding the object if it is not in info.json yet.
+    pub fn set(mut self, obj_name: &str, key: &str, value: Value) -> Self {
+        self.changes
+            .push((obj_name.to_string(), key.to_string(), Some(value)));
+        self
+    }
+
+    /// Removes the property, if the object has it.
+    pub fn remove(mut self, obj_name: &str, key: &str) -> Self {
+        self.changes
+            .push((obj_name.to_string(), key.to_string(), None));
+        self
+    }
+
+    pub fn commit(self) -> Result<(), InfoError> {
+        let Self {
+            fs,
+            info_path,
+            changes,
+        } = self;
+        update_info(fs, &info_path, |info| {
+            for (obj_name, key, value) in changes {
+                match value {
+                    Some(value) => {
+                        info.objects
+                            .entry(obj_name)
+                            .or_default()
+                            .properties
+                            .insert(key, value);
+                    }
+                    None => {
+                        if let Some(obj_info) = info.objects.get_mut(&obj_name) {
+                            obj_info.properties.remove(&key);
+                        }
+                    }
+                }
+            }
+            Ok(())
+        })
+    }
+}
 
-    let contents = fs::read_to_string(info_path)?;
-    let mut info: Info = serde_json::from_str(&contents)?;
+/// The info.json holding the object, in the directory it is in.
+fn obj_info_path(obj_path: &Path) -> PathBuf {
+    obj_path
+        .parent()
+        .unwrap()
+        .join(".dir_info")
+        .join("info.json")
+}
 
+/// Trims and validates the Info parsed from info.json.
+fn validated(mut info: Info) -> Result<Info, InfoError> {
     // Trim string fields
     info.about = info.about.trim().to_string();
     info.location = info.location.trim().to_string();
@@ -201,178 +355,113 @@ pub fn read_validate_info(info_path: &Path) -> Result<Info, InfoError> {
     info.validate()?;
     Ok(info)
 }
-pub fn read_about(info_path: &Path) -> Result<String, String> {
-    let info = read_validate_info(info_path);
+pub fn read_about(fs: &dyn SekaiFs, info_path: &Path) -> Result<String, String> {
+    let info = read_validate_info(fs, info_path);
     if info.is_err() {
         return Err(format!("Error reading info file: {}", info.err().unwrap()));
     }
     Ok(info.unwrap().about)
 }
-pub fn read_location(info_path: &Path) -> Result<String, String> {
-    let info = read_validate_info(info_path);
+pub fn read_location(fs: &dyn SekaiFs, info_path: &Path) -> Result<String, String> {
+    let info = read_validate_info(fs, info_path);
     if info.is_err() {
         return Err(format!("Error reading info file: {}", info.err().unwrap()));
     }
     Ok(info.unwrap().location)
 }
-pub fn write_about(info_path: &Path, new_value: String) -> Result<String, String> {
-    let mut info = read_validate_info(info_path).map_err(|e| e.to_string())?;
-    info.about = new_value;
-    let json = serde_json::to_string_pretty(&info).map_err(|e| e.to_string())?;
-    fs::write(info_path, json).map_err(|e| e.to_string())?;
+pub fn write_about(
+    fs: &dyn SekaiFs,
+    info_path: &Path,
+    new_value: String,
+) -> Result<String, String> {
+    update_info(fs, info_path, |info| {
+        info.about = new_value;
+        Ok(())
+    })
+    .map_err(|e| e.to_string())?;
     Ok("write successful".to_string())
 }
 
 /// Add an object to info.json with optional initial properties
 /// Example:
-///     add_obj_to_info(&info_path, "file.txt", None);
+///     add_obj_to_info(fs, &info_path, "file.txt", None);
 pub fn add_obj_to_info(
+    fs: &dyn SekaiFs,
     obj_path: &Path,
     obj_name: &str,
     initial_props: Option<HashMap<String, Value>>,
 ) -> Result<(), InfoError> {
-    let info_path = &obj_path
-        .parent()
-        .unwrap()
-        .join(".dir_info")
-        .join("info.json");
-    let mut info = read_validate_info(info_path)?;
-
-    if !info.objects.contains_key(obj_name) {
-        let obj_info = if let Some(props) = initial_props {
-            ObjectInfo { properties: props }
-        } else {
-            ObjectInfo::with_locked(DEFAULT_PERMISSIONS.to_string()) // Default to locked=false if no props provided
-        };
-
-        info.objects.insert(obj_name.to_string(), obj_info);
-
-        let json = serde_json::to_string_pretty(&info)?;
-        std::fs::write(info_path, json)?;
-    }
-
-    Ok(())
+    update_info(fs, &obj_info_path(obj_path), |info| {
+        if !info.objects.contains_key(obj_name) {
+            let obj_info = if let Some(props) = initial_props {
+                ObjectInfo { properties: props }
+            } else {
+                ObjectInfo::with_locked(DEFAULT_PERMISSIONS.to_string()) // Default to locked=false if no props provided
+            };
+            info.objects.insert(obj_name.to_string(), obj_info);
+        }
+        Ok(())
+    })
 }
 
 /// Delete an object from info.json
-pub fn del_obj_from_info(obj_path: &Path, obj_name: &str) -> Result<(), InfoError> {
-    let info_path = &obj_path
-        .parent()
-        .unwrap()
-        .join(".dir_info")
-        .join("info.json");
-    let mut info = read_validate_info(info_path)?;
-
-    if info.objects.remove(obj_name).is_some() {
-        let json = serde_json::to_string_pretty(&info)?;
-        std::fs::write(info_path, json)?;
-    }
-    Ok(())
-}
-pub fn set_as_default_obj(obj_path: &Path, obj_name: &str) -> Result<(), InfoError> {
-    let info_path = &obj_path
-        .parent()
-        .unwrap()
-        .join(".dir_info")
-        .join("info.json");
-    let mut info = read_validate_info(info_path)?;
-
-    // Get or create the object entry
-    let obj_info = info
-        .objects
-        .entry(obj_name.to_string())
-        .or_insert_with(ObjectInfo::default);
-
-    // Set the locked property to "00" (unlocked)
-    obj_info
-        .properties
-        .insert("locked".to_string(), Value::String("00".to_string()));
-    obj_info.properties.remove("decrypt_me");
-    obj_info.properties.remove("compare_me");
-    // Write back the updated info
-    let json = serde_json::to_string_pretty(&info)?;
-    std::fs::write(info_path, json)?;
-
-    Ok(())
+pub fn del_obj_from_info(
+    fs: &dyn SekaiFs,
+    obj_path: &Path,
+    obj_name: &str,
+) -> Result<(), InfoError> {
+    update_info(fs, &obj_info_path(obj_path), |info| {
+        info.objects.remove(obj_name);
+        Ok(())
+    })
 }
-pub fn del_decrypt_me_from_info(obj_path: &Path, obj_name: &str) -> Result<(), InfoError> {
-    let info_path = &obj_path
-        .parent()
-        .unwrap()
-        .join(".dir_info")
-        .join("info.json");
-    let mut info = read_validate_info(info_path)?;
 
-    if let Some(obj_info) = info.objects.get_mut(obj_name) {
-        obj_info.properties.remove("decrypt_me");
-        let json = serde_json::to_string_pretty(&info)?;
-        std::fs::write(info_path, json)?;
-    }
-    Ok(())
+/// Makes the object an unlocked chest, without any lock properties left.
+pub fn set_as_default_obj(
+    fs: &dyn SekaiFs,
+    obj_path: &Path,
+    obj_name: &str,
+) -> Result<(), InfoError> {
+    InfoTransaction::new(fs, obj_path)
+        .set(obj_name, "locked", Value::String("00".to_string()))
+        .remove(obj_name, "decrypt_me")
+        .remove(obj_name, "compare_me")
+        .remove(obj_name, "unlocked_by")
+        .commit()
 }
-pub fn del_compare_me_from_info(obj_path: &Path, obj_name: &str) -> Result<(), InfoError> {
-    let info_path = &obj_path
-        .parent()
-        .unwrap()
-        .join(".dir_info")
-        .join("info.json");
-    let mut obj_info = read_get_obj_info(info_path, obj_name)?;
 
-    obj_info = obj_info.without_compare_me();
-    let json = serde_json::to_string_pretty(&obj_info)?;
-    std::fs::write(info_path, json)?;
-    Ok(())
+pub fn del_decrypt_me_from_info(
+    fs: &dyn SekaiFs,
+    obj_path: &Path,
+    obj_name: &str,
+) -> Result<(), InfoError> {
+    InfoTransaction::new(fs, obj_path)
+        .remove(obj_name, "decrypt_me")
+        .commit()
 }
 
-pub fn del_compare_met_from_info(obj_path: &Path, obj_name: &str) -> Result<(), InfoError> {
-    let info_path = &obj_path
-        .parent()
-        .unwrap()
-        .join(".dir_info")
-        .join("info.json");
-    if !info_path.exists() {
-        return Err(InfoError::NotFound(info_path.display().to_string()));
-    }
-    let obj_info = read_get_obj_info(info_path, obj_name);
-    if obj_info.is_err() {
-        return Err(InfoError::ValidationError(
-            "Object not found in info.json".to_string(),
-        ));
-    }
-    let mut obj_info = obj_info.unwrap();
-
-    obj_info = obj_info.without_compare_me();
-    let json = serde_json::to_string_pretty(&obj_info)?;
-    std::fs::write(info_path, json)?;
-    Ok(())
+pub fn del_compare_me_from_info(
+    fs: &dyn SekaiFs,
+    obj_path: &Path,
+    obj_name: &str,
+) -> Result<(), InfoError> {
+    InfoTransaction::new(fs, obj_path)
+        .remove(obj_name, "compare_me")
+        .commit()
 }
-pub fn set_to_unlocked_chest(obj_path: &Path, obj_name: &str) -> Result<(), InfoError> {
-    let info_path = &obj_path
-        .parent()
-        .unwrap()
-        .join(".dir_info")
-        .join("info.json");
-    let mut info = read_validate_info(info_path)?;
-
-    // Get or create the object entry
-    let obj_info = info
-        .objects
-        .entry(obj_name.to_string())
-        .or_insert_with(ObjectInfo::default);
-
-    // Set the locked property to "00" (unlocked)
-    obj_info
-        .properties
-        .insert("locked".to_string(), Value::String("00".to_string()));
 
-    // Write back the updated info
-    let json = serde_json::to_string_pretty(&info)?;
-    std::fs::write(info_path, json)?;
-
-    Ok(())
+pub fn set_to_unlocked_chest(
+    fs: &dyn SekaiFs,
+    obj_path: &Path,
+    obj_name: &str,
+) -> Result<(), InfoError> {
+    InfoTransaction::new(fs, obj_path)
+        .set(obj_name, "locked", Value::String("00".to_string()))
+        .commit()
 }
 
-/// Update or add a status property for an object
+/// Update or add a status property for an object. Use `InfoTransaction` to change several
+/// properties together.
 ///
 /// # Arguments
 /// * `obj_path` - Path to the object whose status is to be updated
@@ -380,38 +469,25 @@ pub fn set_to_unlocked_chest(obj_path: &Path, obj_name: &str) -> Result<(), Info
 /// * `status` - Status key to update (e.g., "locked", "hidden")
 /// * `st_value` - Value to set (must be serializable to JSON)
 pub fn update_obj_status(
+    fs: &dyn SekaiFs,
     obj_path: &Path,
     obj_name: &str,
     status: &str,
     st_value: Value,
 ) -> Result<(), InfoError> {
-    let info_path = &obj_path
-        .parent()
-        .unwrap()
-        .join(".dir_info")
-        .join("info.json");
-    let mut info = read_validate_info(info_path)?;
-
-    // Get or create the object entry
-    let obj_info = info
-        .objects
-        .entry(obj_name.to_string())
-        .or_insert_with(ObjectInfo::default);
-
-    // Update or add the property
-    obj_info.properties.insert(status.to_string(), st_value);
-
-    // Write back the updated info
-    let json = serde_json::to_string_pretty(&info)?;
-    std::fs::write(info_path, json)?;
-
-    Ok(())
+    InfoTransaction::new(fs, obj_path)
+        .set(obj_name, status, st_value)
+        .commit()
 }
 
 /// Gets object info from a directory's info.json, returning the existing info or a default
 /// Returns Error if the info.json is invalid or can't be read
-pub fn read_get_obj_info(info_path: &Path, obj_name: &str) -> Result<ObjectInfo, InfoError> {
-    let info = read_validate_info(info_path)?;
+pub fn read_get_obj_info(
+    fs: &dyn SekaiFs,
+    info_path: &Path,
+    obj_name: &str,
+) -> Result<ObjectInfo, InfoError> {
+    let info = read_validate_info(fs, info_path)?;
     Ok(info
         .objects
         .get(obj_name)
@@ -419,9 +495,14 @@ pub fn read_get_obj_info(info_path: &Path, obj_name: &str) -> Result<ObjectInfo,
         .unwrap_or_default())
 }
 
-pub fn get_encrypted_flag(path: &Path, level_name: &str) -> Result<String, String> {
+pub fn get_encrypted_flag(
+    fs: &dyn SekaiFs,
+    path: &Path,
+    level_name: &str,
+) -> Result<String, String> {
     //the flag is stored in ./dir_info/info.json of parent directory
     match read_get_obj_info(
+        fs,
         &path.parent().unwrap().join(".dir_info/info.json"),
         level_name,
     ) {
@@ -443,7 +524,17 @@ pub fn get_encrypted_flag(path: &Path, level_name: &str) -> Result<String, Strin
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::sekai_fs::{DiskFs, MemFs};
     use crate::utils::test_utils::{create_file, setup_test_dir};
+    use std::fs;
+    use std::thread;
+
+    /// Properties of the object in the info.json of the directory, as on the disk
+    fn props(root_path: &Path, obj_name: &str) -> Option<HashMap<String, Value>> {
+        let data = fs::read_to_string(root_path.join(".dir_info/info.json")).unwrap();
+        let info: Info = serde_json::from_str(&data).unwrap();
+        info.objects.get(obj_name).map(|obj| obj.properties.clone())
+    }
 
     #[test]
     fn test_update_obj_status() {
@@ -457,10 +548,11 @@ mod tests {
 
         // Update the object's status
         update_obj_status(
+            &DiskFs,
             &obj_path,
             "file.txt",
             "locked",
-            serde_json::Value::String("01".to_string()),
+            serde_json::Value::String("10".to_string()),
         )
         .unwrap();
 
@@ -474,7 +566,141 @@ mod tests {
                 .properties
                 .get("locked")
                 .unwrap(),
-            &serde_json::Value::String("01".to_string())
+            &serde_json::Value::String("10".to_string())
         );
     }
+
+    #[test]
+    fn test_write_about() {
+        let (_temp_dir, root_path) = setup_test_dir(true);
+        let info_path = &root_path.join(".dir_info/info.json");
+
+        write_about(&DiskFs, info_path, "New about".to_string()).unwrap();
+        assert_eq!(read_about(&DiskFs, info_path).unwrap(), "New about");
+        // Written next to info.json first, nothing is left behind
+        assert!(!root_path.join(".dir_info/info.json.tmp").exists());
+        assert!(write_about(&DiskFs, info_path, " ".to_string()).is_err());
+        assert_eq!(read_about(&DiskFs, info_path).unwrap(), "New about");
+    }
+
+    #[test]
+    fn test_add_and_del_obj() {
+        let (_temp_dir, root_path) = setup_test_dir(true);
+        let obj_path = root_path.join("new.txt");
+        create_file(&obj_path, "new");
+
+        add_obj_to_info(&DiskFs, &obj_path, "new.txt", None).unwrap();
+        assert_eq!(props(&root_path, "new.txt").unwrap()["locked"], "00");
+        // Existing objects are kept as they are
+        let custom = HashMap::from([("points".to_string(), Value::from(5))]);
+        add_obj_to_info(&DiskFs, &obj_path, "new.txt", Some(custom)).unwrap();
+        assert!(!props(&root_path, "new.txt").unwrap().contains_key("points"));
+
+        del_obj_from_info(&DiskFs, &obj_path, "new.txt").unwrap();
+        assert!(props(&root_path, "new.txt").is_none());
+        assert!(props(&root_path, "file1.txt").is_some());
+        del_obj_from_info(&DiskFs, &obj_path, "new.txt").unwrap();
+    }
+
+    #[test]
+    fn test_lock_property_mutators() {
+        let (_temp_dir, root_path) = setup_test_dir(true);
+        let obj_path = root_path.join("file1.txt");
+        InfoTransaction::new(&DiskFs, &obj_path)
+            .set("file1.txt", "obj_salt", Value::from("salt"))
+            .set("file1.txt", "compare_me", Value::from("compare"))
+            .set("file1.txt", "decrypt_me", Value::from("decrypt"))
+            .set("file1.txt", "unlocked_by", Value::from("subdir1"))
+            .set("file1.txt", "locked", Value::from("11"))
+            .commit()
+            .unwrap();
+
+        // Removing compare_me alone would leave a locked object without it
+        assert!(del_compare_me_from_info(&DiskFs, &obj_path, "file1.txt").is_err());
+        del_decrypt_me_from_info(&DiskFs, &obj_path, "file1.txt").unwrap();
+        let file1 = props(&root_path, "file1.txt").unwrap();
+        assert!(!file1.contains_key("decrypt_me"));
+        assert_eq!(file1["compare_me"], "compare");
+
+        set_to_unlocked_chest(&DiskFs, &obj_path, "file1.txt").unwrap();
+        assert_eq!(props(&root_path, "file1.txt").unwrap()["locked"], "00");
+        del_compare_me_from_info(&DiskFs, &obj_path, "file1.txt").unwrap();
+        let file1 = props(&root_path, "file1.txt").unwrap();
+        assert!(!file1.contains_key("compare_me"));
+        // Only the property goes, not the rest of info.json
+        assert!(props(&root_path, "subdir1").is_some());
+
+        update_obj_status(&DiskFs, &obj_path, "file1.txt", "locked", Value::from("10")).unwrap();
+        set_as_default_obj(&DiskFs, &obj_path, "file1.txt").unwrap();
+        let file1 = props(&root_path, "file1.txt").unwrap();
+        assert_eq!(file1["locked"], "00");
+        assert!(!file1.contains_key("unlocked_by"));
+        assert_eq!(file1["obj_salt"], "salt");
+    }
+
+    /// Test that the changes of a transaction are written together or not at all
+    #[test]
+    fn test_info_transaction() {
+        let (_temp_dir, root_path) = setup_test_dir(true);
+        let obj_path = root_path.join("file1.txt");
+        let before = fs::read_to_string(root_path.join(".dir_info/info.json")).unwrap();
+
+        let locked_without_salt = InfoTransaction::new(&DiskFs, &obj_path)
+            .set("file1.txt", "compare_me", Value::from("compare"))
+            .set("file1.txt", "locked", Value::from("01"))
+            .commit();
+        assert!(matches!(
+            locked_without_salt,
+            Err(InfoError::ValidationError(_))
+        ));
+        assert_eq!(
+            fs::read_to_string(root_path.join(".dir_info/info.json")).unwrap(),
+            before
+        );
+
+        let missing = root_path.join("missing/file.txt");
+        assert!(matches!(
+            update_obj_status(&DiskFs, &missing, "file.txt", "points", Value::from(1)),
+            Err(InfoError::NotFound(_))
+        ));
+    }
+
+    /// Test that concurrent changes of the same info.json are not lost
+    #[test]
+    fn test_concurrent_updates() {
+        let (_temp_dir, root_path) = setup_test_dir(true);
+        let root = MemFs::root_for(Path::new("world.deemak"));
+        let mem = MemFs::load_dir(&root_path, &root).unwrap();
+
+        thread::scope(|scope| {
+            for i in 0..8 {
+                let (mem, root_path) = (&mem, &root_path);
+                let root = &root;
+                scope.spawn(move || {
+                    let name = format!("obj{i}");
+                    update_obj_status(
+                        &DiskFs,
+                        &root_path.join(&name),
+                        &name,
+                        "points",
+                        Value::from(i),
+                    )
+                    .unwrap();
+                    update_obj_status(mem, &root.join(&name), &name, "points", Value::from(i))
+                        .unwrap();
+                });
+            }
+        });
+
+        let info = read_validate_info(&mem, &root.join(".dir_info/info.json")).unwrap();
+        for i in 0..8 {
+            assert_eq!(props(&root_path, &format!("obj{i}")).unwrap()["points"], i);
+            assert_eq!(info.objects[&format!("obj{i}")].get_points(), i);
+        }
+        // Every write renamed its own temporary file over info.json
+        for (fs, dir) in [(&DiskFs as &dyn SekaiFs, &root_path), (&mem, &root)] {
+            let entries = fs.read_dir(&dir.join(".dir_info")).unwrap();
+            assert_eq!(entries, [dir.join(".dir_info/info.json")]);
+        }
+    }
 }
@@ -0,0 +1,494 @@
+use super::event_policy::parse_event_policy;
+use super::info_reader::Info;
+use super::lock_graph::{LockGraph, LockNode};
+use crate::commands::cmds::RESTRICTED_FILES;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/*
+SEKAI LINTER:
+
+`deemak <SEKAI_DIR> dev check` reports problems in a Sekai directory without changing it,
+unlike `validate_or_create_sekai` which silently creates or merges the `.dir_info`s.
+
+Locked objects which can never be opened are reported as unreachable, or as a cycle if they
+wait on each other. See `lock_graph` for how the locks depend on each other.
+*/
+
+/// Files bigger than this are reported, since the shell reads them whole.
+pub const DEFAULT_MAX_FILE_SIZE: u64 = 1024 * 1024;
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct LintIssue {
+    pub severity: Severity,
+    /// Short kebab-case name of the check, like `missing-object`
+    pub code: &'static str,
+    /// Path relative to the Sekai, like `HOME/subdir1/file2.txt`
+    pub path: String,
+    pub message: String,
+}
+
+#[derive(Serialize, Debug, Default)]
+pub struct LintReport {
+    pub errors: usize,
+    pub warnings: usize,
+    pub issues: Vec<LintIssue>,
+}
+
+pub struct LintOptions {
+    pub max_file_size: u64,
+}
+
+impl Default for LintOptions {
+    fn default() -> Self {
+        Self {
+            max_file_size: DEFAULT_MAX_FILE_SIZE,
+        }
+    }
+}
+
+impl LintReport {
+    fn push(&mut self, severity: Severity, code: &'static str, path: &str, message: String) {
+        match severity {
+            Severity::Error => self.errors += 1,
+            Severity::Warning => self.warnings += 1,
+        }
+        self.issues.push(LintIssue {
+            severity,
+            code,
+            path: path.to_string(),
+            message,
+        });
+    }
+
+    fn error(&mut self, code: &'static str, path: &str, message: String) {
+        self.push(Severity::Error, code, path, message);
+    }
+
+    fn warning(&mut self, code: &'static str, path: &str, message: String) {
+        self.push(Severity::Warning, code, path, message);
+    }
+
+    /// 0 if there are no problems, 1 if there are only warnings, 2 if there are errors.
+    pub fn exit_code(&self) -> i32 {
+        if self.errors > 0 {
+            2
+        } else if self.warnings > 0 {
+            1
+        } else {
+            0
+        }
+    }
+
+    pub fn to_human(&self) -> String {
+        if self.issues.is_empty() {
+            return "No problems found.".to_string();
+        }
+        let mut output = String::new();
+        for issue in &self.issues {
+            let severity = match issue.severity {
+                Severity::Error => "error",
+                Severity::Warning => "warning",
+            };
+            output += &format!(
+                "{severity}[{}] {}: {}\n",
+                issue.code, issue.path, issue.message
+            );
+        }
+        output += &format!("\n{} error(s), {} warning(s)", self.errors, self.warnings);
+        output
+    }
+}
+
+fn sekai_path(rel: &Path) -> String {
+    if rel.components().count() == 0 {
+        "HOME".to_string()
+    } else {
+        format!("HOME/{}", rel.display())
+    }
+}
+
+/// Entries of the directory except `.dir_info`, sorted by name.
+fn dir_entries(dir: &Path) -> Vec<(String, PathBuf)> {
+    let mut entries: Vec<(String, PathBuf)> = fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .filter_map(|e| e.ok())
+                .filter(|e| e.file_name() != ".dir_info")
+                .map(|e| (e.file_name().to_string_lossy().to_string(), e.path()))
+                .collect()
+        })
+        .unwrap_or_default();
+    entries.sort();
+    entries
+}
+
+/// Reads the info.json as written, without the validation and trimming of `read_validate_info`.
+fn read_raw_info(info_path: &Path) -> Result<Info, String> {
+    let contents = fs::read_to_string(info_path).map_err(|e| e.to_string())?;
+    serde_json::from_str(&contents).map_err(|e| e.to_string())
+}
+
+/// Checks the objects of a single info.json, collecting their locks.
+fn check_objects(
+    info: &Info,
+    dir_path: &str,
+    report: &mut LintReport,
+    nodes: &mut BTreeMap<String, LockNode>,
+) {
+    let mut names: Vec<&String> = info.objects.keys().collect();
+    names.sort();
+    for name in names {
+        let obj = &info.objects[name];
+        let path = format!("{dir_path}/{name}");
+        let prop = |key: &str| obj.properties.get(key).and_then(|v| v.as_str());
+
+        let node = match LockNode::from_object(obj) {
+            Ok(Some(node)) => node,
+            Ok(None) => {
+                report.warning(
+                    "missing-lock",
+                    &path,
+                    "No 'locked' property, the object is treated as unlocked".to_string(),
+                );
+                continue;
+            }
+            Err(e) => {
+                report.error("invalid-lock", &path, e);
+                continue;
+            }
+        };
+
+        if node.is_locked {
+            for key in ["compare_me", "obj_salt"] {
+                if prop(key).is_none_or(|v| v.trim().is_empty()) {
+                    report.error(
+                        "missing-lock-data",
+                        &path,
+                        format!("Locked object has no '{key}', it can never be unlocked"),
+                    );
+                }
+            }
+        }
+        if node.is_level && !node.has_decrypt_me {
+            report.warning(
+                "missing-decrypt-me",
+                &path,
+                "Level has no 'decrypt_me', solving it gives no flag".to_string(),
+            );
+        }
+        nodes.insert(path, node);
+    }
+}
+
+fn check_directory(
+    root: &Path,
+    dir: &Path,
+    options: &LintOptions,
+    report: &mut LintReport,
+    nodes: &mut BTreeMap<String, LockNode>,
+) {
+    let rel = dir.strip_prefix(root).unwrap_or(dir);
+    let dir_path = sekai_path(rel);
+    let is_home = rel.components().count() == 0;
+    let entries = dir_entries(dir);
+
+    let info_path = dir.join(".dir_info/info.json");
+    let info = if !info_path.exists() {
+        report.error(
+            "missing-info",
+            &dir_path,
+            "No .dir_info/info.json in the directory".to_string(),
+        );
+        None
+    } else {
+        match read_raw_info(&info_path) {
+            Ok(info) => Some(info),
+            Err(e) => {
+                report.error("invalid-info", &dir_path, format!("Invalid info.json: {e}"));
+                None
+            }
+        }
+    };
+
+    if let Some(info) = &info {
+        if is_home && info.location.trim() != "HOME" {
+            report.error(
+                "home-location",
+                &dir_path,
+                format!(
+                    "The root info.json must have location 'HOME', found '{}'",
+                    info.location
+                ),
+            );
+        } else if info.location.trim().is_empty() {
+            report.warning("empty-location", &dir_path, "Location is empty".to_string());
+        }
+        if info.about.trim().is_empty() {
+            report.warning("empty-about", &dir_path, "About is empty".to_string());
+        }
+        if let Some(event) = &info.event {
+            if !is_home {
+                report.warning(
+                    "ignored-event",
+                    &dir_path,
+                    "Event policy is only read from the root info.json".to_string(),
+                );
+            } else if let Err(e) = parse_event_policy(event) {
+                report.error("invalid-event", &dir_path, e);
+            }
+        }
+
+        let on_disk: HashSet<&str> = entries.iter().map(|(name, _)| name.as_str()).collect();
+        let mut listed: Vec<&String> = info.objects.keys().collect();
+        listed.sort();
+        for name in listed {
+            if !on_disk.contains(name.as_str()) {
+                report.error(
+                    "missing-object",
+                    &format!("{dir_path}/{name}"),
+                    "Listed in info.json but does not exist".to_string(),
+                );
+            }
+        }
+        for (name, _) in &entries {
+            if !info.objects.contains_key(name) {
+                report.warning(
+                    "unlisted-object",
+                    &format!("{dir_path}/{name}"),
+                    "Exists but is not listed in info.json".to_string(),
+                );
+            }
+        }
+        check_objects(info, &dir_path, report, nodes);
+    }
+
+    for (name, path) in &entries {
+        let obj_path = format!("{dir_path}/{name}");
+        // Same rule as the shell, which hides and refuses these names
+        if RESTRICTED_FILES.iter().any(|&file| name.contains(file)) {
+            report.error(
+                "restricted-name",
+                &obj_path,
+                "Name is reserved by Deemak, players cannot see or use it".to_string(),
+            );
+        }
+        if path.is_dir() {
+            check_directory(root, path, options, report, nodes);
+        } else if let Ok(meta) = fs::metadata(path)
+            && meta.len() > options.max_file_size
+        {
+            report.warning(
+                "oversized-file",
+                &obj_path,
+                format!(
+                    "File is {} bytes, more than the limit of {} bytes",
+                    meta.len(),
+                    options.max_file_size
+                ),
+            );
+        }
+    }
+}
+
+/// Checks that every locked object can be opened, reporting unreachable ones and cycles.
+fn check_locks(graph: &LockGraph, report: &mut LintReport) {
+    let nodes = &graph.nodes;
+    for (path, node) in nodes {
+        let Some(source) = &node.unlocked_by else {
+            if node.is_level && node.is_locked {
+                report.warning(
+                    "unknown-lock-source",
+                    path,
+                    "Locked level has no 'unlocked_by', the level giving its flag is unknown"
+                        .to_string(),
+                );
+            }
+            continue;
+        };
+        match nodes.get(source) {
+            None => report.error(
+                "invalid-lock-source",
+                path,
+                format!("'unlocked_by' refers to '{source}', which is not an object"),
+            ),
+            Some(src) if !src.is_level => report.error(
+                "invalid-lock-source",
+                path,
+                format!("'unlocked_by' refers to '{source}', which is not a level"),
+            ),
+            _ => {}
+        }
+    }
+
+    let reachable = graph.reachable();
+    let blocked: Vec<&String> = nodes
+        .iter()
+        .filter(|(p, n)| n.is_locked && !reachable.contains(p.as_str()))
+        .map(|(p, _)| p)
+        .collect();
+    if blocked.is_empty() {
+        return;
+    }
+
+    let cycles = graph.cycles(&reachable);
+    let in_cycle: HashSet<&str> = cycles.iter().flatten().map(|s| s.as_str()).collect();
+    for cycle in &cycles {
+        let mut chain = cycle.clone();
+        chain.push(cycle[0].clone());
+        report.error(
+            "lock-cycle",
+            &cycle[0],
+            format!("Locks wait on each other: {}", chain.join(" -> ")),
+        );
+    }
+    for path in blocked {
+        if in_cycle.contains(path.as_str()) {
+            continue;
+        }
+        let node = &nodes[path];
+        let waiting: Vec<String> = graph
+            .dependencies(path)
+            .into_iter()
+            .filter(|d| !reachable.contains(d.as_str()))
+            .collect();
+        let reason = if waiting.is_empty() {
+            let source = node.unlocked_by.as_deref().unwrap_or_default();
+            format!("'{source}' gives no flag for it")
+        } else {
+            format!("it waits on {}", waiting.join(", "))
+        };
+        report.error(
+            "unreachable",
+            path,
+            format!("Can never be unlocked, {reason}"),
+        );
+    }
+}
+
+/// Checks the Sekai directory and reports all the problems found. Nothing is changed on disk.
+pub fn lint_sekai(root: &Path, options: &LintOptions) -> LintReport {
+    let mut report = LintReport::default();
+    let mut nodes = BTreeMap::new();
+    check_directory(root, root, options, &mut report, &mut nodes);
+    check_locks(&LockGraph::new(nodes), &mut report);
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metainfo::valid_sekai::validate_or_create_sekai;
+    use crate::sekai_fs::DiskFs;
+    use crate::utils::test_utils::{create_file, setup_test_dir};
+    use serde_json::json;
+
+    fn set_object(dir: &Path, name: &str, props: serde_json::Value) {
+        let info_path = dir.join(".dir_info/info.json");
+        let mut info: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&info_path).unwrap()).unwrap();
+        info["objects"][name] = props;
+        fs::write(&info_path, info.to_string()).unwrap();
+    }
+
+    fn codes(report: &LintReport) -> Vec<&'static str> {
+        report.issues.iter().map(|i| i.code).collect()
+    }
+
+    #[test]
+    fn test_lint_sekai() {
+        let (_temp_dir, root_path) = setup_test_dir(false);
+        assert!(validate_or_create_sekai(&DiskFs, &root_path, false));
+        let report = lint_sekai(&root_path, &LintOptions::default());
+        assert_eq!(report.exit_code(), 0, "{}", report.to_human());
+
+        // Disk and info.json disagree
+        fs::remove_file(root_path.join("file1.txt")).unwrap();
+        create_file(root_path.join("subdir1/new.txt"), "new");
+        // Level without a decrypt_me, locking a level that waits on itself
+        set_object(&root_path, "subdir1", json!({"locked": "10"}));
+        set_object(
+            &root_path,
+            "subdir2",
+            json!({"locked": "11", "obj_salt": "s", "compare_me": "c", "decrypt_me": "d",
+                   "unlocked_by": "HOME/subdir2/nested2"}),
+        );
+        set_object(
+            &root_path.join("subdir2"),
+            "nested2",
+            json!({"locked": "11", "obj_salt": "s", "compare_me": "c", "decrypt_me": "d",
+                   "unlocked_by": "HOME/subdir2"}),
+        );
+        // Locked chest without its lock data
+        set_object(
+            &root_path.join("subdir1"),
+            "file2.txt",
+            json!({"locked": "01"}),
+        );
+        // Misspelled field of the event policy
+        let home_info = root_path.join(".dir_info/info.json");
+        let mut info: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&home_info).unwrap()).unwrap();
+        info["event"] = json!({"strat": "2030-01-01T10:00:00+00:00"});
+        fs::write(&home_info, info.to_string()).unwrap();
+
+        let report = lint_sekai(&root_path, &LintOptions { max_file_size: 10 });
+        let found = codes(&report);
+        for code in [
+            "missing-object",
+            "unlisted-object",
+            "missing-decrypt-me",
+            "missing-lock-data",
+            "lock-cycle",
+            "oversized-file",
+            "invalid-event",
+        ] {
+            assert!(found.contains(&code), "{code} not in {found:?}");
+        }
+        assert_eq!(report.exit_code(), 2);
+        // Nothing was fixed on disk
+        let info = read_raw_info(&root_path.join("subdir1/.dir_info/info.json")).unwrap();
+        assert!(!info.objects.contains_key("new.txt"));
+    }
+
+    #[test]
+    fn test_lint_unreachable_level() {
+        let (_temp_dir, root_path) = setup_test_dir(false);
+        assert!(validate_or_create_sekai(&DiskFs, &root_path, false));
+        // subdir1 gives no flag, so subdir2 can never be opened
+        set_object(&root_path, "subdir1", json!({"locked": "10"}));
+        set_object(
+            &root_path,
+            "subdir2",
+            json!({"locked": "11", "obj_salt": "s", "compare_me": "c", "decrypt_me": "d",
+                   "unlocked_by": "HOME/subdir1"}),
+        );
+        let report = lint_sekai(&root_path, &LintOptions::default());
+        let unreachable: Vec<&LintIssue> = report
+            .issues
+            .iter()
+            .filter(|i| i.code == "unreachable")
+            .collect();
+        assert_eq!(unreachable.len(), 1);
+        assert_eq!(unreachable[0].path, "HOME/subdir2");
+
+        // Once subdir1 gives a flag, everything is reachable
+        set_object(
+            &root_path,
+            "subdir1",
+            json!({"locked": "10", "decrypt_me": "d"}),
+        );
+        let report = lint_sekai(&root_path, &LintOptions::default());
+        assert!(!codes(&report).contains(&"unreachable"));
+        assert_eq!(report.exit_code(), 0, "{}", report.to_human());
+    }
+}
@@ -0,0 +1,387 @@
+use super::info_reader::{Info, ObjectInfo};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::path::Path;
+
+/*
+LOCK GRAPH EXPLANATION:
+
+Every object of the Sekai with a `locked` property is a node, named by its path like
+`HOME/opb/level_1`. There are 2 kinds of edges:
+- Nesting: an object can only be reached once all the locked objects it is inside are opened.
+- Unlock: `dev lock -ll <from> <to>` encrypts the flag of `<to>` in the `decrypt_me` of `<from>`,
+  and records `"unlocked_by": "<from>"` on `<to>`. So `<to>` needs `<from>` to be solved first.
+
+A player starts at HOME. Objects which can never be reached are unsolvable, this includes
+everything nested inside a lock which can never be opened.
+*/
+
+/// A lockable object of the Sekai.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LockNode {
+    pub is_level: bool,
+    pub is_locked: bool,
+    pub has_decrypt_me: bool,
+    /// Level whose flag opens this level
+    pub unlocked_by: Option<String>,
+}
+
+impl LockNode {
+    /// Reads the lock of an object. Returns None if it has no `locked` property,
+    /// and an error if it is not a 2-bit string.
+    pub fn from_object(obj: &ObjectInfo) -> Result<Option<Self>, String> {
+        let prop = |key: &str| obj.properties.get(key).and_then(|v| v.as_str());
+        let Some(locked) = prop("locked") else {
+            return Ok(None);
+        };
+        if locked.len() != 2 || !locked.chars().all(|c| c == '0' || c == '1') {
+            return Err(format!(
+                "Invalid 'locked' value '{locked}', must be a 2-bit string"
+            ));
+        }
+        let bits: Vec<bool> = locked.chars().map(|c| c == '1').collect();
+        Ok(Some(Self {
+            is_level: bits[0],
+            is_locked: bits[1],
+            has_decrypt_me: prop("decrypt_me").is_some_and(|v| !v.trim().is_empty()),
+            unlocked_by: prop("unlocked_by").map(|s| s.trim().to_string()),
+        }))
+    }
+
+    /// Levels and locked chests are shown in the graph, other objects are plain content.
+    pub fn is_shown(&self) -> bool {
+        self.is_level || self.is_locked
+    }
+
+    fn kind(&self) -> &'static str {
+        if self.is_level { "level" } else { "chest" }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct LockGraph {
+    pub nodes: BTreeMap<String, LockNode>,
+}
+
+#[derive(Serialize)]
+pub struct GraphNode {
+    pub id: String,
+    pub kind: &'static str,
+    pub locked: bool,
+    pub solvable: bool,
+}
+
+#[derive(Serialize)]
+pub struct GraphEdge {
+    pub from: String,
+    pub to: String,
+    /// `nesting` or `unlock`
+    pub kind: &'static str,
+}
+
+#[derive(Serialize)]
+pub struct GraphExport {
+    pub nodes: Vec<GraphNode>,
+    pub edges: Vec<GraphEdge>,
+    /// Objects which can never be reached, including plain content inside them
+    pub unsolvable: Vec<String>,
+    pub cycles: Vec<Vec<String>>,
+}
+
+fn sekai_path(rel: &Path) -> String {
+    if rel.components().count() == 0 {
+        "HOME".to_string()
+    } else {
+        format!("HOME/{}", rel.display())
+    }
+}
+
+fn collect_nodes(root: &Path, dir: &Path, nodes: &mut BTreeMap<String, LockNode>) {
+    let dir_path = sekai_path(dir.strip_prefix(root).unwrap_or(dir));
+    if let Ok(contents) = fs::read_to_string(dir.join(".dir_info/info.json"))
+        && let Ok(info) = serde_json::from_str::<Info>(&contents)
+    {
+        for (name, obj) in &info.objects {
+            if let Ok(Some(node)) = LockNode::from_object(obj) {
+                nodes.insert(format!("{dir_path}/{name}"), node);
+            }
+        }
+    }
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.filter_map(|e| e.ok()) {
+        if entry.file_name() != ".dir_info" && entry.path().is_dir() {
+            collect_nodes(root, &entry.path(), nodes);
+        }
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+fn quote(id: &str) -> String {
+    format!("\"{}\"", escape(id))
+}
+
+impl LockGraph {
+    pub fn new(nodes: BTreeMap<String, LockNode>) -> Self {
+        Self { nodes }
+    }
+
+    /// Reads the locks of all the objects of the Sekai directory. Invalid info.json's are
+    /// skipped, `dev check` reports them.
+    pub fn build(root: &Path) -> Self {
+        let mut nodes = BTreeMap::new();
+        collect_nodes(root, root, &mut nodes);
+        Self { nodes }
+    }
+
+    /// Objects that must be opened or solved before the given object can be reached.
+    pub fn dependencies(&self, path: &str) -> Vec<String> {
+        let mut deps: Vec<String> = self
+            .nodes
+            .iter()
+            .filter(|(p, n)| n.is_locked && path.starts_with(&format!("{p}/")))
+            .map(|(p, _)| p.clone())
+            .collect();
+        if let Some(node) = self.nodes.get(path)
+            && node.is_level
+            && node.is_locked
+            && let Some(source) = &node.unlocked_by
+        {
+            deps.push(source.clone());
+        }
+        deps
+    }
+
+    /// Objects that a player starting at HOME can reach, and open if locked.
+    pub fn reachable(&self) -> HashSet<String> {
+        let mut reachable: HashSet<String> = HashSet::new();
+        loop {
+            let mut changed = false;
+            for (path, node) in &self.nodes {
+                if reachable.contains(path)
+                    || !self
+                        .dependencies(path)
+                        .iter()
+                        .all(|d| reachable.contains(d))
+                {
+                    continue;
+                }
+                // The flag must actually come out of the source level
+                let flag_ok = match (&node.unlocked_by, node.is_level && node.is_locked) {
+                    (Some(source), true) => {
+                        self.nodes.get(source).is_some_and(|s| s.has_decrypt_me)
+                    }
+                    _ => true,
+                };
+                if flag_ok {
+                    reachable.insert(path.clone());
+                    changed = true;
+                }
+            }
+            if !changed {
+                return reachable;
+            }
+        }
+    }
+
+    /// Objects which can never be reached, sorted.
+    pub fn unsolvable(&self, reachable: &HashSet<String>) -> Vec<String> {
+        self.nodes
+            .keys()
+            .filter(|p| !reachable.contains(*p))
+            .cloned()
+            .collect()
+    }
+
+    /// Cycles among the locks which cannot be reached, each listed once.
+    pub fn cycles(&self, reachable: &HashSet<String>) -> Vec<Vec<String>> {
+        let mut cycles: Vec<Vec<String>> = Vec::new();
+        let mut seen: HashSet<String> = HashSet::new();
+        let locked = self
+            .nodes
+            .iter()
+            .filter(|(p, n)| n.is_locked && !reachable.contains(*p))
+            .map(|(p, _)| p);
+        for start in locked {
+            // Follow dependencies, a path coming back to the start is a cycle
+            let mut stack: Vec<Vec<String>> = vec![vec![start.clone()]];
+            while let Some(chain) = stack.pop() {
+                let current = chain.last().unwrap();
+                for dep in self.dependencies(current) {
+                    if reachable.contains(&dep) || !self.nodes.contains_key(&dep) {
+                        continue;
+                    }
+                    if dep == *start {
+                        if chain.iter().all(|p| !seen.contains(p)) {
+                            seen.extend(chain.iter().cloned());
+                            cycles.push(chain.clone());
+                        }
+                    } else if !chain.contains(&dep) {
+                        let mut next = chain.clone();
+                        next.push(dep);
+                        stack.push(next);
+                    }
+                }
+            }
+        }
+        cycles
+    }
+
+    /// Closest shown object containing the given one, HOME if there is none.
+    fn shown_parent(&self, path: &str) -> String {
+        let mut current = path;
+        while let Some((parent, _)) = current.rsplit_once('/') {
+            if self.nodes.get(parent).is_some_and(|n| n.is_shown()) {
+                return parent.to_string();
+            }
+            current = parent;
+        }
+        "HOME".to_string()
+    }
+
+    /// Levels, locked chests and the edges between them, with unsolvable objects flagged.
+    pub fn export(&self) -> GraphExport {
+        let reachable = self.reachable();
+        let mut nodes = Vec::new();
+        let mut edges = Vec::new();
+        for (path, node) in self.nodes.iter().filter(|(_, n)| n.is_shown()) {
+            nodes.push(GraphNode {
+                id: path.clone(),
+                kind: node.kind(),
+                locked: node.is_locked,
+                solvable: reachable.contains(path),
+            });
+            edges.push(GraphEdge {
+                from: self.shown_parent(path),
+                to: path.clone(),
+                kind: "nesting",
+            });
+            if node.is_level
+                && let Some(source) = &node.unlocked_by
+            {
+                edges.push(GraphEdge {
+                    from: source.clone(),
+                    to: path.clone(),
+                    kind: "unlock",
+                });
+            }
+        }
+        GraphExport {
+            nodes,
+            edges,
+            unsolvable: self.unsolvable(&reachable),
+            cycles: self.cycles(&reachable),
+        }
+    }
+
+    /// Graphviz DOT of the graph. Unsolvable objects are filled red, unlock edges are bold.
+    pub fn to_dot(&self) -> String {
+        let export = self.export();
+        let mut dot =
+            String::from("digraph sekai {\n    rankdir=LR;\n    node [fontname=\"monospace\"];\n");
+        dot += "    \"HOME\" [shape=house];\n";
+        for node in &export.nodes {
+            let name = node.id.rsplit('/').next().unwrap_or(&node.id);
+            let shape = if node.kind == "level" {
+                "box"
+            } else {
+                "ellipse"
+            };
+            let state = if node.locked { "locked " } else { "" };
+            let mut attrs = format!(
+                "shape={shape}, label=\"{}\\n{state}{}\"",
+                escape(name),
+                node.kind
+            );
+            if !node.solvable {
+                attrs += ", style=filled, fillcolor=\"#f4cccc\", color=red";
+            }
+            dot += &format!("    {} [{attrs}];\n", quote(&node.id));
+        }
+        for edge in &export.edges {
+            let attrs = match edge.kind {
+                "unlock" => "style=bold, color=blue, label=\"flag\"",
+                _ => "style=dashed",
+            };
+            dot += &format!(
+                "    {} -> {} [{attrs}];\n",
+                quote(&edge.from),
+                quote(&edge.to)
+            );
+        }
+        dot += "}\n";
+        dot
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(&self.export()).expect("Failed to serialize lock graph")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(locked: &str, decrypt_me: bool, unlocked_by: Option<&str>) -> LockNode {
+        let bits: Vec<bool> = locked.chars().map(|c| c == '1').collect();
+        LockNode {
+            is_level: bits[0],
+            is_locked: bits[1],
+            has_decrypt_me: decrypt_me,
+            unlocked_by: unlocked_by.map(|s| s.to_string()),
+        }
+    }
+
+    #[test]
+    fn test_lock_graph() {
+        let mut nodes = BTreeMap::new();
+        nodes.insert("HOME/l1".to_string(), node("10", true, None));
+        nodes.insert("HOME/l2".to_string(), node("11", true, Some("HOME/l1")));
+        // l3 needs l4 which is inside l3
+        nodes.insert("HOME/l3".to_string(), node("11", true, Some("HOME/l3/l4")));
+        nodes.insert("HOME/l3/l4".to_string(), node("10", true, None));
+        nodes.insert("HOME/l3/l4/chest".to_string(), node("01", false, None));
+        nodes.insert("HOME/l3/notes.txt".to_string(), node("00", false, None));
+        let graph = LockGraph::new(nodes);
+
+        let reachable = graph.reachable();
+        assert!(reachable.contains("HOME/l2"));
+        assert_eq!(
+            graph.unsolvable(&reachable),
+            vec![
+                "HOME/l3",
+                "HOME/l3/l4",
+                "HOME/l3/l4/chest",
+                "HOME/l3/notes.txt"
+            ]
+        );
+        assert_eq!(
+            graph.cycles(&reachable),
+            vec![vec!["HOME/l3".to_string(), "HOME/l3/l4".to_string()]]
+        );
+
+        let export = graph.export();
+        // Plain content is not shown, but still counts as unsolvable
+        assert!(!export.nodes.iter().any(|n| n.id == "HOME/l3/notes.txt"));
+        assert!(
+            export
+                .edges
+                .iter()
+                .any(|e| e.kind == "unlock" && e.from == "HOME/l1" && e.to == "HOME/l2")
+        );
+        assert!(
+            export.edges.iter().any(|e| e.kind == "nesting"
+                && e.from == "HOME/l3/l4"
+                && e.to == "HOME/l3/l4/chest")
+        );
+
+        let dot = graph.to_dot();
+        assert!(dot.starts_with("digraph sekai {"));
+        assert!(dot.contains("\"HOME/l1\" -> \"HOME/l2\" [style=bold"));
+    }
+}
@@ -1,4 +1,5 @@
 use super::info_reader::read_validate_info;
+use crate::sekai_fs::SekaiFs;
 use crate::utils::log;
 use crate::utils::relative_deemak_path;
 use std::path::Path;
@@ -10,14 +11,14 @@ use std::path::Path;
 ///     1st bit: Locked/Unlocked bit.
 ///     2nd bit: Type of lock. 1 => Level locking, 0 => Chest locking.
 /// The bit correspondence: "1" => True, "0" => False
-pub fn read_lock_perm(obj_path: &Path) -> Result<(bool, bool), String> {
+pub fn read_lock_perm(fs: &dyn SekaiFs, obj_path: &Path) -> Result<(bool, bool), String> {
     let info_path = obj_path
         .parent()
         .ok_or("Object has no parent directory")?
         .join(".dir_info/info.json");
 
     let info =
-        read_validate_info(&info_path).map_err(|e| format!("Failed to read info.json: {e}"))?;
+        read_validate_info(fs, &info_path).map_err(|e| format!("Failed to read info.json: {e}"))?;
 
     let obj_name = obj_path
         .file_name()
@@ -54,17 +55,19 @@ pub fn read_lock_perm(obj_path: &Path) -> Result<(bool, bool), String> {
 /// Returns: Ok if operation can proceed, Err with message if locked. OR Err if lock status cannot
 /// be determined.
 pub fn operation_locked_perm(
+    fs: &dyn SekaiFs,
     obj_path: &Path,
+    root_dir: &Path,
     operation: &str,
     message: &str,
 ) -> Result<(), String> {
     // Check all parents up to root
     let mut current = obj_path;
     while let Some(parent) = current.parent() {
-        if let Ok((_, locked)) = read_lock_perm(current)
+        if let Ok((_, locked)) = read_lock_perm(fs, current)
             && locked
         {
-            let rel_path = relative_deemak_path(current, None);
+            let rel_path = relative_deemak_path(current, Some(root_dir));
             log::log_warning(
                 operation,
                 &format!("Locked path: {} - {}", rel_path.display(), message),
@@ -80,9 +83,9 @@ pub fn operation_locked_perm(
     }
 
     // Check the object itself
-    match read_lock_perm(obj_path) {
+    match read_lock_perm(fs, obj_path) {
         Ok((_, true)) => {
-            let rel_path = relative_deemak_path(obj_path, None);
+            let rel_path = relative_deemak_path(obj_path, Some(root_dir));
             log::log_warning(
                 operation,
                 &format!("Locked: {} - {}", rel_path.display(), message),
@@ -100,7 +103,7 @@ pub fn operation_locked_perm(
                 operation,
                 &format!(
                     "Lock check failed for {}: {}",
-                    relative_deemak_path(obj_path, None).display(),
+                    relative_deemak_path(obj_path, Some(root_dir)).display(),
                     e
                 ),
             );
@@ -0,0 +1,164 @@
+use super::info_reader::read_get_obj_info;
+use crate::sekai_fs::SekaiFs;
+use std::path::Path;
+
+/*
+OBJECT TYPES EXPLANATION:
+
+Every file object has a MIME type, which decides how `read` shows it:
+- Text is printed as it is.
+- Images open in the right panel of the GUI, and inline in the web client.
+- Audio plays inline in the web client.
+- Anything else is binary, and can only be looked at with `read --hex` or `read --strings`.
+
+The type is taken from the `mime` property of the object in its info.json if the author set one,
+otherwise it is sniffed: readable UTF-8 is text, else the magic bytes of the file decide, then
+its extension.
+*/
+
+/// How many bytes are looked at to sniff the type of a file.
+const SNIFF_LEN: usize = 512;
+
+pub const OCTET_STREAM: &str = "application/octet-stream";
+
+/// Magic bytes at the start of a file, and its MIME type.
+const MAGIC: [(&[u8], &str); 10] = [
+    (b"\x89PNG\r\n\x1a\n", "image/png"),
+    (b"\xff\xd8\xff", "image/jpeg"),
+    (b"GIF87a", "image/gif"),
+    (b"GIF89a", "image/gif"),
+    (b"BM", "image/bmp"),
+    (b"OggS", "audio/ogg"),
+    (b"fLaC", "audio/flac"),
+    (b"ID3", "audio/mpeg"),
+    (b"%PDF-", "application/pdf"),
+    (b"PK\x03\x04", "application/zip"),
+];
+
+const EXTENSIONS: [(&str, &str); 11] = [
+    ("png", "image/png"),
+    ("jpg", "image/jpeg"),
+    ("jpeg", "image/jpeg"),
+    ("gif", "image/gif"),
+    ("bmp", "image/bmp"),
+    ("wav", "audio/wav"),
+    ("ogg", "audio/ogg"),
+    ("mp3", "audio/mpeg"),
+    ("flac", "audio/flac"),
+    ("pdf", "application/pdf"),
+    ("zip", "application/zip"),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ObjectKind {
+    Text,
+    Image,
+    Audio,
+    Binary,
+}
+
+impl ObjectKind {
+    pub fn from_mime(mime: &str) -> Self {
+        match mime.split('/').next() {
+            Some("text") => ObjectKind::Text,
+            Some("image") => ObjectKind::Image,
+            Some("audio") => ObjectKind::Audio,
+            _ => ObjectKind::Binary,
+        }
+    }
+}
+
+/// Sniffs the MIME type from the first bytes of a file, falling back to the extension of its
+/// name. Valid UTF-8 without control characters is always text.
+pub fn sniff_mime(head: &[u8], file_name: &str) -> &'static str {
+    if is_text(head) {
+        return "text/plain";
+    }
+    if let Some((_, mime)) = MAGIC.iter().find(|(magic, _)| head.starts_with(magic)) {
+        return mime;
+    }
+    // RIFF is a container, the format is after the size
+    if head.starts_with(b"RIFF") && head.len() >= 12 {
+        match &head[8..12] {
+            b"WAVE" => return "audio/wav",
+            b"WEBP" => return "image/webp",
+            _ => {}
+        }
+    }
+    if head.len() >= 2 && head[0] == 0xff && head[1] & 0xe0 == 0xe0 {
+        // MP3 frame without an ID3 tag
+        return "audio/mpeg";
+    }
+
+    let extension = Path::new(file_name)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| ext.to_ascii_lowercase());
+    extension
+        .as_deref()
+        .and_then(|ext| EXTENSIONS.iter().find(|(e, _)| *e == ext))
+        .map_or(OCTET_STREAM, |(_, mime)| mime)
+}
+
+/// The head may cut a multi-byte character at its end, which is still text.
+fn is_text(head: &[u8]) -> bool {
+    let valid = match std::str::from_utf8(head) {
+        Ok(text) => text,
+        Err(e) if e.error_len().is_none() => {
+            // Everything before valid_up_to is valid UTF-8
+            std::str::from_utf8(&head[..e.valid_up_to()]).unwrap_or_default()
+        }
+        Err(_) => return false,
+    };
+    !valid
+        .chars()
+        .any(|c| c.is_control() && !matches!(c, '\n' | '\r' | '\t' | '\x0c'))
+}
+
+/// MIME type of a file object, see the explanation above.
+pub fn object_mime(fs: &dyn SekaiFs, obj_path: &Path) -> String {
+    let obj_name = obj_path
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or_default();
+    if let Some(parent) = obj_path.parent()
+        && let Ok(obj_info) = read_get_obj_info(fs, &parent.join(".dir_info/info.json"), obj_name)
+        && let Some(mime) = obj_info.get_mime()
+    {
+        return mime.to_string();
+    }
+
+    let mut head = fs.read(obj_path).unwrap_or_default();
+    head.truncate(SNIFF_LEN);
+    sniff_mime(&head, obj_name).to_string()
+}
+
+/// Types the web server sends as they are, browsers only show or play them. Other types, like
+/// an `image/svg+xml` or `text/html` set by an author, could run scripts on the page of the
+/// server, so they are sent as downloads.
+const INLINE_SAFE: [&str; 9] = [
+    "image/png",
+    "image/jpeg",
+    "image/gif",
+    "image/bmp",
+    "image/webp",
+    "audio/wav",
+    "audio/ogg",
+    "audio/mpeg",
+    "audio/flac",
+];
+
+pub fn is_inline_safe(mime: &str) -> bool {
+    INLINE_SAFE.contains(&mime)
+}
+
+/// Extension raylib uses to pick the decoder of an image.
+pub fn image_file_type(mime: &str) -> Option<&'static str> {
+    match mime {
+        "image/png" => Some(".png"),
+        "image/jpeg" => Some(".jpg"),
+        "image/gif" => Some(".gif"),
+        "image/bmp" => Some(".bmp"),
+        _ => None,
+    }
+}
@@ -1,7 +1,14 @@
+pub mod authoring;
+pub mod event_policy;
+pub mod info_index;
 pub mod info_reader;
+pub mod lint;
+pub mod mime;
 mod tests;
 
 pub mod valid_sekai;
 
+pub mod lock_graph;
 pub mod lock_perm;
+pub mod scaffold;
 pub use lock_perm::read_lock_perm;
@@ -0,0 +1,221 @@
+use super::authoring::Authoring;
+use super::info_reader::update_obj_status;
+use super::valid_sekai::validate_or_create_sekai;
+use crate::rns::security::{chest_compare_me, level_decrypt_me};
+use crate::sekai_fs::DiskFs;
+use crate::utils::assets::TUTORIAL_DIR;
+use argon2::password_hash::{SaltString, rand_core::OsRng};
+use clap::ValueEnum;
+use include_dir::{Dir, include_dir};
+use serde_json::Value;
+use std::fs;
+use std::path::Path;
+
+/*
+WORLD TEMPLATES:
+
+`deemak <SEKAI_DIR> dev new --template <TEMPLATE>` creates a starter Sekai from a template.
+Templates are embedded in the binary from `templates/<name>/`:
+- `world/`: the Sekai itself, with the `.dir_info/info.json` of every directory filled in.
+  The tutorial template starts from the compiled `_tutorial`, with `world/` copied over it.
+- `template.json`: the example level and chest, with their solutions. The locks are created
+  when the Sekai is made, since every Sekai gets its own salts. It is kept as the authoring
+  file of the new Sekai.
+- `walkthrough.txt`: commands which finish the Sekai. It is written to the HOME `.dir_info`,
+  which players cannot read.
+*/
+
+static TEMPLATES: Dir = include_dir!("$CARGO_MANIFEST_DIR/templates");
+
+/// Where the walkthrough of the template is written, relative to HOME.
+pub const WALKTHROUGH_FILE: &str = ".dir_info/walkthrough.txt";
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WorldTemplate {
+    /// The Deemak tutorial, teaching all the commands
+    Tutorial,
+    /// Challenges with flags and points, for a Capture The Flag event
+    Ctf,
+    /// A short adventure with places to explore
+    Story,
+}
+
+impl WorldTemplate {
+    pub fn name(&self) -> &'static str {
+        match self {
+            WorldTemplate::Tutorial => "tutorial",
+            WorldTemplate::Ctf => "ctf",
+            WorldTemplate::Story => "story",
+        }
+    }
+
+    fn file(&self, name: &str) -> Option<&'static str> {
+        TEMPLATES
+            .get_file(format!("{}/{name}", self.name()))
+            .and_then(|f| f.contents_utf8())
+    }
+
+    fn locks(&self) -> Result<Authoring, String> {
+        match self.file("template.json") {
+            Some(json) => serde_json::from_str(json)
+                .map_err(|e| format!("Invalid template.json of {}: {e}", self.name())),
+            None => Ok(Authoring::default()),
+        }
+    }
+
+    pub fn walkthrough(&self) -> &'static str {
+        self.file("walkthrough.txt").unwrap_or_default()
+    }
+}
+
+/// Writes the files of an embedded directory under the target, without the prefix of `dir`.
+fn extract_dir(dir: &Dir, prefix: &Path, target: &Path) -> Result<(), String> {
+    for sub_dir in dir.dirs() {
+        let path = target.join(
+            sub_dir
+                .path()
+                .strip_prefix(prefix)
+                .unwrap_or(sub_dir.path()),
+        );
+        fs::create_dir_all(&path)
+            .map_err(|e| format!("Failed to create {}: {e}", path.display()))?;
+        extract_dir(sub_dir, prefix, target)?;
+    }
+    for file in dir.files() {
+        let path = target.join(file.path().strip_prefix(prefix).unwrap_or(file.path()));
+        fs::write(&path, file.contents())
+            .map_err(|e| format!("Failed to write {}: {e}", path.display()))?;
+    }
+    Ok(())
+}
+
+fn set_props(root: &Path, rel_path: &str, props: Vec<(&str, Value)>) -> Result<(), String> {
+    let obj_path = root.join(rel_path);
+    if !obj_path.exists() {
+        return Err(format!("Template object does not exist: {rel_path}"));
+    }
+    let obj_name = obj_path
+        .file_name()
+        .and_then(|n| n.to_str())
+        .ok_or_else(|| format!("Invalid template object: {rel_path}"))?;
+    for (key, value) in props {
+        update_obj_status(&DiskFs, &obj_path, obj_name, key, value)
+            .map_err(|e| format!("Failed to lock {rel_path}: {e}"))?;
+    }
+    Ok(())
+}
+
+/// Creates the example levels and chests of the template.
+fn apply_locks(root: &Path, locks: &Authoring) -> Result<(), String> {
+    for level in &locks.levels {
+        let name = Path::new(&level.path)
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or_default();
+        set_props(
+            root,
+            &level.path,
+            vec![
+                (
+                    "obj_salt",
+                    Value::from(SaltString::generate(&mut OsRng).as_str()),
+                ),
+                (
+                    "decrypt_me",
+                    Value::from(level_decrypt_me(name, &level.solution, &level.flag)),
+                ),
+                ("points", Value::from(level.points)),
+                ("locked", Value::from("10")),
+            ],
+        )?;
+    }
+    for chest in &locks.chests {
+        let name = Path::new(&chest.path)
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or_default();
+        let obj_salt = SaltString::generate(&mut OsRng);
+        set_props(
+            root,
+            &chest.path,
+            vec![
+                ("obj_salt", Value::from(obj_salt.as_str())),
+                (
+                    "compare_me",
+                    Value::from(chest_compare_me(name, &obj_salt, &chest.solution)),
+                ),
+                ("points", Value::from(chest.points)),
+                // Last, since a locked object is only valid with its salt and compare_me
+                ("locked", Value::from("01")),
+            ],
+        )?;
+    }
+    Ok(())
+}
+
+/// Creates a new Sekai from the template. The target must not exist, or be an empty directory.
+pub fn scaffold_sekai(target: &Path, template: WorldTemplate) -> Result<(), String> {
+    if target.exists() {
+        let mut entries =
+            fs::read_dir(target).map_err(|e| format!("Cannot use {}: {e}", target.display()))?;
+        if entries.next().is_some() {
+            return Err(format!(
+                "{} already exists and is not empty",
+                target.display()
+            ));
+        }
+    }
+    fs::create_dir_all(target)
+        .map_err(|e| format!("Failed to create {}: {e}", target.display()))?;
+
+    if template == WorldTemplate::Tutorial {
+        extract_dir(&TUTORIAL_DIR, Path::new(""), target)?;
+    }
+    let world_prefix = format!("{}/world", template.name());
+    if let Some(world) = TEMPLATES.get_dir(&world_prefix) {
+        extract_dir(world, Path::new(&world_prefix), target)?;
+    }
+
+    // Sync the info.json's with the files, so that every object is listed
+    if !validate_or_create_sekai(&DiskFs, target, false) {
+        return Err("Failed to create the .dir_info's of the new Sekai".to_string());
+    }
+    let locks = template.locks()?;
+    apply_locks(target, &locks)?;
+    locks.save(&DiskFs, target)?;
+
+    let walkthrough = target.join(WALKTHROUGH_FILE);
+    fs::write(&walkthrough, template.walkthrough())
+        .map_err(|e| format!("Failed to write {}: {e}", walkthrough.display()))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metainfo::lint::{LintOptions, lint_sekai};
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_scaffold_templates() {
+        let temp_dir = TempDir::new().unwrap();
+        for template in [
+            WorldTemplate::Tutorial,
+            WorldTemplate::Ctf,
+            WorldTemplate::Story,
+        ] {
+            let target = temp_dir.path().join(template.name());
+            scaffold_sekai(&target, template).unwrap();
+            assert!(target.join("README").exists());
+            assert!(target.join(WALKTHROUGH_FILE).exists());
+            assert!(!Authoring::load(&DiskFs, &target).unwrap().levels.is_empty());
+
+            // A fresh Sekai has nothing to complain about
+            let report = lint_sekai(&target, &LintOptions::default());
+            assert_eq!(report.errors, 0, "{}", report.to_human());
+
+            // Never overwrite an existing Sekai
+            assert!(scaffold_sekai(&target, template).is_err());
+        }
+    }
+}
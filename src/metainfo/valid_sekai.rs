@@ -1,4 +1,8 @@
+use super::info_index::index_sekai;
 use super::info_reader::read_validate_info;
+use crate::epr_log_error;
+use crate::rns::restore_comp;
+use crate::sekai_fs::SekaiFs;
 use crate::utils::log;
 use std::path::Path;
 
@@ -7,7 +11,7 @@ use std::path::Path;
 /// else use default values.
 ///
 /// If the files present in info.json do not exist anymore, they will be removed
-pub fn create_dir_info(dir: &Path, home_dir: bool) -> bool {
+pub fn create_dir_info(fs: &dyn SekaiFs, dir: &Path, home: &Path) -> bool {
     // Skip if this is a .dir_info directory
     if dir.file_name().and_then(|n| n.to_str()) == Some(".dir_info") {
         return true;
@@ -17,14 +21,14 @@ pub fn create_dir_info(dir: &Path, home_dir: bool) -> bool {
     let info_path = dir_info.join("info.json");
 
     // Try to read existing info if present
-    let mut existing_info = if info_path.exists() {
-        read_validate_info(&info_path).ok()
+    let mut existing_info = if fs.exists(&info_path) {
+        read_validate_info(fs, &info_path).ok()
     } else {
         None
     };
 
     // Create directory if needed
-    if let Err(e) = std::fs::create_dir_all(&dir_info) {
+    if let Err(e) = fs.create_dir_all(&dir_info) {
         log::log_error(
             "SEKAI",
             &format!("Failed to create .dir_info in {}: {}", dir.display(), e),
@@ -33,14 +37,15 @@ pub fn create_dir_info(dir: &Path, home_dir: bool) -> bool {
     }
 
     // Get default values
-    let default_info = super::info_reader::Info::default_for_path(dir, home_dir);
+    let default_info = super::info_reader::Info::default_for_path(fs, dir, home);
 
     // Get current directory entries (excluding .dir_info)
-    let current_entries: std::collections::HashSet<String> = match std::fs::read_dir(dir) {
+    let current_entries: std::collections::HashSet<String> = match fs.read_dir(dir) {
         Ok(entries) => entries
-            .filter_map(|e| e.ok())
-            .filter(|e| e.file_name() != ".dir_info")
-            .filter_map(|e| e.file_name().into_string().ok())
+            .iter()
+            .filter_map(|e| e.file_name().and_then(|n| n.to_str()))
+            .filter(|name| *name != ".dir_info")
+            .map(str::to_string)
             .collect(),
         Err(_) => std::collections::HashSet::new(),
     };
@@ -71,7 +76,7 @@ pub fn create_dir_info(dir: &Path, home_dir: bool) -> bool {
         }
         None => {
             // No existing info - create new with only existing objects
-            let mut new_info = super::info_reader::Info::default_for_path(dir, home_dir);
+            let mut new_info = super::info_reader::Info::default_for_path(fs, dir, home);
             new_info.objects.retain(|k, _| current_entries.contains(k));
             // Store it in existing_info so it lives long enough
             existing_info = Some(new_info);
@@ -80,10 +85,10 @@ pub fn create_dir_info(dir: &Path, home_dir: bool) -> bool {
     };
 
     // Write the merged info
-    match std::fs::write(
+    match fs.write(
         &info_path,
-        match serde_json::to_string_pretty(&info_to_write) {
-            Ok(json) => json,
+        &match serde_json::to_string_pretty(&info_to_write) {
+            Ok(json) => json.into_bytes(),
             Err(e) => {
                 log::log_error(
                     "SEKAI",
@@ -105,16 +110,16 @@ pub fn create_dir_info(dir: &Path, home_dir: bool) -> bool {
 }
 
 /// Main validation function with auto-creation
-pub fn validate_or_create_sekai(sekai_path: &Path, home_check: bool) -> bool {
+pub fn validate_or_create_sekai(fs: &dyn SekaiFs, sekai_path: &Path, home_check: bool) -> bool {
     // Initial path checks
-    if !sekai_path.exists() {
+    if !fs.exists(sekai_path) {
         log::log_error(
             "SEKAI",
             &format!("Directory does not exist: {}", sekai_path.display()),
         );
         return false;
     }
-    if !sekai_path.is_dir() {
+    if !fs.is_dir(sekai_path) {
         log::log_error(
             "SEKAI",
             &format!("Path is not a directory: {}", sekai_path.display()),
@@ -130,7 +135,7 @@ pub fn validate_or_create_sekai(sekai_path: &Path, home_check: bool) -> bool {
     // Check for just home directory validation
     if home_check {
         // Check if the home directory is valid and create if not
-        if !create_dir_info(sekai_path, true) {
+        if !create_dir_info(fs, sekai_path, sekai_path) {
             log::log_error(
                 "SEKAI",
                 &format!(
@@ -144,7 +149,7 @@ pub fn validate_or_create_sekai(sekai_path: &Path, home_check: bool) -> bool {
     }
 
     // Process directories recursively with single-pass validation/creation
-    let all_valid = process_directory_recursive(sekai_path, true);
+    let all_valid = process_directory_recursive(fs, sekai_path, sekai_path);
 
     if all_valid {
         log::log_info("SEKAI", "Directory structure is valid");
@@ -159,7 +164,7 @@ pub fn validate_or_create_sekai(sekai_path: &Path, home_check: bool) -> bool {
 }
 
 /// Recursively processes directories to validate or create valid .dir_info
-fn process_directory_recursive(dir: &Path, is_home: bool) -> bool {
+fn process_directory_recursive(fs: &dyn SekaiFs, dir: &Path, home: &Path) -> bool {
     let mut all_valid = true;
 
     // Skip .dir_info directories
@@ -169,16 +174,16 @@ fn process_directory_recursive(dir: &Path, is_home: bool) -> bool {
 
     // Check/create info for current directory
     let info_path = dir.join(".dir_info/info.json");
-    if !info_path.exists() {
+    if !fs.exists(&info_path) {
         log::log_info(
             "SEKAI",
             &format!("Creating valid .dir_info for: {}", dir.display()),
         );
-        if !create_dir_info(dir, is_home) {
+        if !create_dir_info(fs, dir, home) {
             all_valid = false;
         }
     // Else if not valid, try to create it
-    } else if !create_dir_info(dir, is_home) {
+    } else if !create_dir_info(fs, dir, home) {
         log::log_error(
             "SEKAI",
             &format!("Failed to create valid .dir_info for: {}", dir.display()),
@@ -186,14 +191,103 @@ fn process_directory_recursive(dir: &Path, is_home: bool) -> bool {
         all_valid = false;
     }
     // Process subdirectories if current directory is valid
-    if all_valid && let Ok(entries) = std::fs::read_dir(dir) {
-        for entry in entries.filter_map(|e| e.ok()) {
-            let path = entry.path();
-            if path.is_dir() {
-                all_valid &= process_directory_recursive(&path, false);
+    if all_valid && let Ok(entries) = fs.read_dir(dir) {
+        for path in entries {
+            if fs.is_dir(&path) {
+                all_valid &= process_directory_recursive(fs, &path, home);
             }
         }
     }
 
     all_valid
 }
+
+/// Validates the Sekai directory and sets it as the world directory
+pub fn sekai_initialize(fs: &dyn SekaiFs, sekai_path: &Path) {
+    log::log_info(
+        "SEKAI",
+        format!("Starting Sekai validation for: {}", sekai_path.display()).as_str(),
+    );
+    // Just check first for HOME directory validity and create if not.
+    // If not valid, create .dir_info for each of them.
+    if !validate_or_create_sekai(fs, sekai_path, false) {
+        epr_log_error!(
+            "SEKAI",
+            "Sekai directory is not valid even after creating default `.dir_info`. Sekai: {sekai_path:?}"
+        );
+        return;
+    } else {
+        // sekai is valid
+        log::log_info("SEKAI", &format!("Sekai is Valid {sekai_path:?}"));
+        let indexed = index_sekai(fs, sekai_path);
+        log::log_info("SEKAI", &format!("Indexed {indexed} info.json files"));
+
+        // Create the restore file if it doesn't exist, since it is required for restoring. The
+        // progress will be saved as `save_me` and will be recreated every run.
+        log::log_info(
+            "SEKAI",
+            &format!(
+                "Creating restore file for Sekai at {:?}",
+                sekai_path.join(".dir_info/restore_me.deemak")
+            ),
+        );
+        // restore_me should be made initially if it doesnt exist, else it will not be created
+        match restore_comp::backup_sekai(fs, "restore", sekai_path) {
+            Err(e) => {
+                log::log_error(
+                    "SEKAI",
+                    &format!("Failed to create restore file for: {sekai_path:?} Error: {e}"),
+                );
+                eprintln!(
+                    "Error: Failed to create restore file for: {sekai_path:?} Error: {e}.\nContinuing..."
+                );
+                return;
+            }
+            Ok(msg) => {
+                log::log_info("SEKAI", &msg);
+            }
+        }
+
+        // save_me should be made initially if it doesnt exist, it will be created every run
+        log::log_info(
+            "SEKAI",
+            &format!(
+                "Creating save file for Sekai at {:?}",
+                sekai_path.join(".dir_info/save_me.deemak")
+            ),
+        );
+        // Not copying the restore file to save file, since the password will be different.
+        match restore_comp::backup_sekai(fs, "save", sekai_path) {
+            Err(e) => {
+                log::log_error(
+                    "SEKAI",
+                    &format!("Failed to create save file for: {sekai_path:?} Error: {e}"),
+                );
+                eprintln!(
+                    "Error: Failed to create save file for: {sekai_path:?} Error: {e}.\nContinuing..."
+                );
+                return;
+            }
+            Ok(msg) => {
+                log::log_info("SEKAI", &msg);
+            }
+        }
+    }
+
+    // If `save_me` already exists, then the sekai will be restored from it.
+    match restore_comp::restore_sekai(fs, "save", sekai_path) {
+        Err(err) => {
+            log::log_error(
+                "SEKAI",
+                &format!("Failed to restore Sekai from save file: {err}"),
+            );
+            eprintln!(
+                "Error: Failed to restore Sekai from save file at {sekai_path:?}. Error: {err}
+Continuing..."
+            );
+        }
+        Ok(_) => {
+            log::log_info("SEKAI", "Sekai restored successfully from save file");
+        }
+    }
+}
@@ -1,15 +1,26 @@
-use super::passlock::{check_dmk_magic, decrypt_file, encrypt_file};
+use super::passlock::{NonceMode, check_dmk_magic, decrypt_file, encrypt_file};
 use super::passlock::{zlib_compress, zlib_decompress};
 use super::restore_comp::generate_temp_path;
 use crate::utils::log;
 use std::path::{Path, PathBuf};
 
-/// Encrypts a Sekai folder into a Deemak encrypted file
+/// How a Deemak file is built, see the reproducible builds in [`super::passlock`].
+#[derive(Clone, Copy, Debug, Default)]
+pub struct BuildOptions {
+    /// Sorted entries and normalized tar headers
+    pub reproducible: bool,
+    pub nonce_mode: NonceMode,
+}
+
+/// Encrypts a Sekai folder into a Deemak encrypted file.
+/// Files for which `skip` is true are left out, it is given the path relative to the Sekai.
 pub fn deemak_encrypt_sekai(
     sekai_path: &Path,
     output_path: &Path,
     password: &str,
     force: bool,
+    skip: &dyn Fn(&Path) -> bool,
+    options: BuildOptions,
 ) -> Result<(), String> {
     log::log_debug(
         "Deemak Encryption",
@@ -33,12 +44,15 @@ pub fn deemak_encrypt_sekai(
         return Ok(());
     }
 
-    // Create temp zlib file in same directory
-    let temp_zlib = generate_temp_path("zlib_compress").with_extension("tmp.zlib");
+    // Create temp zlib file in the temporary directory of this instance
+    let temp_dir = generate_temp_path("zlib_compress")
+        .map_err(|e| format!("Failed to create temporary directory: {e}"))?;
+    let temp_zlib = temp_dir.path().join("sekai.tmp.zlib");
 
     // Compress first
     if sekai_path.is_dir() {
-        zlib_compress(sekai_path, &temp_zlib).map_err(|e| format!("Compression failed: {e}"))?;
+        zlib_compress(sekai_path, &temp_zlib, skip, options.reproducible)
+            .map_err(|e| format!("Compression failed: {e}"))?;
         log::log_info(
             "Deemak Encryption",
             format!("Successfully compressed Sekai: {}", temp_zlib.display()).as_str(),
@@ -47,11 +61,9 @@ pub fn deemak_encrypt_sekai(
         return Err("Input must be a directory".to_string());
     }
     // Then encrypt
-    encrypt_file(&temp_zlib, encryption_path, password)
+    encrypt_file(&temp_zlib, encryption_path, password, options.nonce_mode)
         .map_err(|e| format!("Encryption failed: {e}"))?;
 
-    // Clean up
-    std::fs::remove_file(&temp_zlib).map_err(|e| format!("Failed to clean up temp file: {e}"))?;
     log::log_info(
         "DEEMAK",
         &format!("Successfully encrypted to {}", encryption_path.display()),
@@ -78,7 +90,9 @@ pub fn original_from_encrypted_sekai(
         output_path.to_path_buf()
     };
 
-    let temp_zlib = generate_temp_path("zlib_compress").with_extension("tmp.zlib");
+    let temp_dir = generate_temp_path("zlib_compress")
+        .map_err(|e| format!("Failed to create temporary directory: {e}"))?;
+    let temp_zlib = temp_dir.path().join("sekai.tmp.zlib");
 
     // Ensure clean state
     if output_dir.exists() {
@@ -101,12 +115,8 @@ pub fn original_from_encrypted_sekai(
         Ok(&output_dir)
     })();
 
-    // Clean up temp file in all cases
-    if temp_zlib.exists()
-        && let Err(e) = std::fs::remove_file(&temp_zlib)
-    {
-        log::log_error("DEEMAK", &format!("Failed to clean up temp file: {e}"));
-    }
+    // The temp file is deleted with its directory in all cases
+    drop(temp_dir);
 
     match result {
         Ok(dir) => {
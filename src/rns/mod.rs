@@ -1,4 +1,5 @@
 pub mod create_dmk_sekai;
+pub mod pack;
 pub mod passlock;
 pub mod restore_comp;
 pub mod security;
@@ -0,0 +1,265 @@
+use super::passlock::{
+    NonceMode, decrypt_bytes, encrypt_bytes, encrypt_locked_bytes, has_current_layout,
+    normalized_tarball, tree_entries, zlib_bytes,
+};
+use super::restore_comp::{RESTORE_FILE, SAVE_FILE};
+use crate::metainfo::authoring::AUTHORING_FILE;
+use crate::metainfo::scaffold::WALKTHROUGH_FILE;
+use crate::utils::log;
+use flate2::read::ZlibDecoder;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tar::Archive;
+
+/*
+PACK AND UNPACK EXPLANATION:
+
+`dev restore` gives back a Sekai exactly as it was encrypted, including the `restore_me.deemak`
+and `save_me.deemak` runtime files which playing drops into HOME/.dir_info. For editing a
+shipped world instead:
+
+- `dev pack` packs a Sekai directory into `<name>.deemak` without the runtime files.
+  The authoring files (solutions and walkthrough) are never packed into the world, they go into
+  the sidecar `<name>.authoring`, encrypted with the same developer password.
+  Only ship the `.deemak` file, the sidecar is for the author. The `.deemak` file carries a
+  random key which has nothing to do with the password, so anyone can open the world but not
+  the sidecar, whose key is derived from the password with Argon2 and a salt.
+- `dev unpack` extracts the `.deemak` file into a directory without the runtime files, and puts
+  the authoring files back from the sidecar if it is next to the `.deemak` file.
+
+Packing an unchanged Sekai gives the same bytes. The tarball is built with sorted entries and
+normalized headers, and every info.json is written with sorted keys, since the objects of an
+info.json are kept in a HashMap. Encryption uses a new nonce every time, so a pack whose
+contents did not change is left untouched instead of being encrypted again.
+*/
+
+/// Extension of the authoring sidecar of a `.deemak` file.
+pub const SIDECAR_EXTENSION: &str = "authoring";
+
+/// Files of HOME which are only for the author, relative to HOME.
+const AUTHORING_FILES: [&str; 2] = [AUTHORING_FILE, WALKTHROUGH_FILE];
+
+/// Compressed tarballs of a Sekai directory, before encryption.
+#[derive(Debug, PartialEq)]
+pub struct PackContents {
+    pub world: Vec<u8>,
+    /// None if the Sekai has no authoring files
+    pub authoring: Option<Vec<u8>>,
+}
+
+#[derive(Debug)]
+pub struct PackReport {
+    pub pack: PathBuf,
+    pub sidecar: Option<PathBuf>,
+    /// False if the existing pack already had the same contents
+    pub changed: bool,
+}
+
+/// Sidecar with the authoring files of the `.deemak` file.
+pub fn sidecar_path(pack_path: &Path) -> PathBuf {
+    pack_path.with_extension(SIDECAR_EXTENSION)
+}
+
+/// `restore_me.deemak` and `save_me.deemak` of HOME, given relative to HOME.
+fn is_runtime_file(rel_path: &Path) -> bool {
+    rel_path.parent() == Some(Path::new(".dir_info"))
+        && rel_path
+            .file_name()
+            .is_some_and(|name| name == RESTORE_FILE || name == SAVE_FILE)
+}
+
+fn is_authoring_file(rel_path: &Path) -> bool {
+    AUTHORING_FILES.iter().any(|f| rel_path == Path::new(f))
+}
+
+/// Files which must not be shipped to players, given relative to HOME.
+pub fn is_unshipped_file(rel_path: &Path) -> bool {
+    is_runtime_file(rel_path) || is_authoring_file(rel_path)
+}
+
+/// Rewrites an info.json with sorted keys, so that its bytes only depend on its contents.
+fn canonical_info(contents: Vec<u8>) -> Vec<u8> {
+    match serde_json::from_slice::<serde_json::Value>(&contents) {
+        Ok(value) => serde_json::to_vec_pretty(&value).unwrap_or(contents),
+        // `dev check` reports invalid info.json's, pack them as they are
+        Err(_) => contents,
+    }
+}
+
+/// Builds the contents of the pack and the sidecar of a Sekai directory.
+pub fn pack_contents(sekai_dir: &Path) -> Result<PackContents, String> {
+    let entries = tree_entries(sekai_dir, &is_runtime_file)
+        .map_err(|e| format!("Failed to read Sekai: {e}"))?;
+    let (authoring, mut world): (Vec<_>, Vec<_>) = entries
+        .into_iter()
+        .partition(|(rel_path, _)| is_authoring_file(rel_path));
+    for (rel_path, contents) in &mut world {
+        if rel_path.ends_with(".dir_info/info.json")
+            && let Some(data) = contents.take()
+        {
+            *contents = Some(canonical_info(data));
+        }
+    }
+
+    let compress = |entries: &[(PathBuf, Option<Vec<u8>>)]| {
+        normalized_tarball(entries)
+            .and_then(|tarball| zlib_bytes(&tarball))
+            .map_err(|e| format!("Compression failed: {e}"))
+    };
+    Ok(PackContents {
+        world: compress(&world)?,
+        authoring: if authoring.is_empty() {
+            None
+        } else {
+            Some(compress(&authoring)?)
+        },
+    })
+}
+
+/// Encrypts the contents to the path, unless the file there already has the same contents in
+/// the current layout. The key is only stored in the file if `carry_key` is set.
+/// Returns whether the file was written.
+fn write_if_changed(
+    path: &Path,
+    contents: &[u8],
+    password: &str,
+    carry_key: bool,
+) -> Result<bool, String> {
+    if let Ok(existing) = fs::read(path)
+        && has_current_layout(&existing, carry_key)
+        && decrypt_bytes(&existing, password).is_ok_and(|old| old == contents)
+    {
+        return Ok(false);
+    }
+    let data = if carry_key {
+        encrypt_bytes(contents, password, NonceMode::Random)?
+    } else {
+        encrypt_locked_bytes(contents, password, NonceMode::Random)?
+    };
+    fs::write(path, data).map_err(|e| format!("Failed to write {}: {e}", path.display()))?;
+    Ok(true)
+}
+
+/// Packs a Sekai directory for shipping, see the explanation above.
+pub fn pack_sekai(
+    sekai_dir: &Path,
+    output_path: &Path,
+    password: &str,
+) -> Result<PackReport, String> {
+    if !sekai_dir.is_dir() {
+        return Err("Input must be a directory".to_string());
+    }
+    let pack_path = if output_path.extension().is_some_and(|ext| ext == "deemak") {
+        output_path.to_path_buf()
+    } else {
+        let filename = sekai_dir
+            .file_name()
+            .ok_or("Invalid Sekai directory name")?;
+        output_path.join(filename).with_extension("deemak")
+    };
+    log::log_debug(
+        "Deemak Pack",
+        &format!(
+            "Input Sekai Path: {}, Output Path: {}",
+            sekai_dir.display(),
+            pack_path.display(),
+        ),
+    );
+
+    let contents = pack_contents(sekai_dir)?;
+    let mut changed = write_if_changed(&pack_path, &contents.world, password, true)?;
+
+    let sidecar = match &contents.authoring {
+        Some(authoring) => {
+            let sidecar = sidecar_path(&pack_path);
+            changed |= write_if_changed(&sidecar, authoring, password, false)?;
+            Some(sidecar)
+        }
+        None => None,
+    };
+    log::log_info(
+        "Deemak Pack",
+        &format!(
+            "Packed Sekai to {}, changed: {changed}",
+            pack_path.display()
+        ),
+    );
+    Ok(PackReport {
+        pack: pack_path,
+        sidecar,
+        changed,
+    })
+}
+
+/// Extracts decrypted contents into the directory, skipping the runtime files.
+fn extract_contents(contents: &[u8], output_dir: &Path) -> Result<(), String> {
+    let mut archive = Archive::new(ZlibDecoder::new(contents));
+    let entries = archive
+        .entries()
+        .map_err(|e| format!("Decompression failed: {e}"))?;
+    for entry in entries {
+        let mut entry = entry.map_err(|e| format!("Decompression failed: {e}"))?;
+        let rel_path = entry
+            .path()
+            .map_err(|e| format!("Invalid path in pack: {e}"))?
+            .into_owned();
+        if is_runtime_file(&rel_path) {
+            continue;
+        }
+        entry
+            .unpack_in(output_dir)
+            .map_err(|e| format!("Failed to extract {}: {e}", rel_path.display()))?;
+    }
+    Ok(())
+}
+
+/// Unpacks a `.deemak` file for editing, see the explanation above.
+/// Returns the Sekai directory, and whether the authoring files were restored from the sidecar.
+pub fn unpack_sekai(
+    pack_path: &Path,
+    output_path: &Path,
+    password: &str,
+) -> Result<(PathBuf, bool), String> {
+    let output_dir = if output_path.is_dir() {
+        output_path.join(pack_path.file_stem().ok_or("Invalid encrypted filename")?)
+    } else {
+        output_path.to_path_buf()
+    };
+    if output_dir.exists() {
+        return Err(format!(
+            "Output path already exists: {}",
+            output_dir.display()
+        ));
+    }
+
+    let sidecar = sidecar_path(pack_path);
+    let result = (|| {
+        let data = fs::read(pack_path)
+            .map_err(|e| format!("Failed to read {}: {e}", pack_path.display()))?;
+        let world = decrypt_bytes(&data, password)?;
+        fs::create_dir_all(&output_dir)
+            .map_err(|e| format!("Failed to create {}: {e}", output_dir.display()))?;
+        extract_contents(&world, &output_dir)?;
+
+        if !sidecar.exists() {
+            return Ok(false);
+        }
+        let data =
+            fs::read(&sidecar).map_err(|e| format!("Failed to read {}: {e}", sidecar.display()))?;
+        let authoring = decrypt_bytes(&data, password)
+            .map_err(|e| format!("Failed to decrypt {}: {e}", sidecar.display()))?;
+        extract_contents(&authoring, &output_dir)?;
+        Ok(true)
+    })();
+
+    match result {
+        Ok(has_authoring) => Ok((output_dir, has_authoring)),
+        Err(e) => {
+            // Clean up partial output if unpacking failed
+            if output_dir.exists() {
+                let _ = fs::remove_dir_all(&output_dir);
+            }
+            Err(e)
+        }
+    }
+}
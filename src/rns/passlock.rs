@@ -1,19 +1,28 @@
 use aes_gcm::aead::{Aead, rand_core::OsRng, rand_core::RngCore};
 use aes_gcm::{Aes256Gcm, Key, KeyInit, Nonce}; // AES-GCM
+use argon2::Argon2;
 use flate2::Compression;
 use flate2::read::ZlibDecoder;
 use flate2::write::ZlibEncoder;
 use sha2::{Digest, Sha256};
 use std::fs::{self, File, read, write};
 use std::io::{self, Write};
-use std::path::Path;
-use tar::{Archive, Builder};
+use std::path::{Path, PathBuf};
+use tar::{Archive, Builder, EntryType, Header};
 use walkdir::WalkDir;
 
 use crate::utils::log;
 
 const NONCE_SIZE: usize = 12;
-const MAGIC_HEADER: &[u8; 8] = b"dbdeemak";
+const KEY_SIZE: usize = 32;
+const SALT_SIZE: usize = 16;
+/// Header of Deemak files which carry their key
+const MAGIC_HEADER: &[u8; 8] = b"dbdeemk3";
+/// Header of Deemak files which only open with the password
+const LOCKED_MAGIC_HEADER: &[u8; 8] = b"dbdeemlk";
+/// Header of Deemak files whose password is in the `pass.deemak` xattr, or not stored at all
+const LEGACY_MAGIC_HEADER: &[u8; 8] = b"dbdeemak";
+const XATTR_PASSWORD: &str = "pass.deemak";
 
 /*
 ORDER OF OPERATIONS FOR DEEMAK ENCRYPTION:
@@ -29,7 +38,7 @@ Requirements: - Sekai file/directory which is already not encrypted
 Process:
 1. Check for requirements and proceed if valid.
 2. Compress the Sekai directory to a zlib tarball.
-3. Encrypt the zlib tarball using AES-GCM with the provided password.
+3. Encrypt the zlib tarball using AES-GCM with a new key, and derive a verifier from the provided password.
 4. Write the encrypted data to a file with a .deemak extension and add headers.
 5. Clean up temporary files if necessary.
 
@@ -37,19 +46,76 @@ II. Restoring original Sekai from Deemak Encrypted file:
 Requirements: - Deemak encrypted file
 
 Process:
-1. Check if the file is a valid Deemak file by checking the magic header and xattr metadata.
-2. Read password from xattr metadata.
-3. Decrypt the file using AES-GCM with the password to obtain the zlib tarball.
+1. Check if the file is a valid Deemak file by checking the magic header.
+2. Read the key from the header.
+3. Decrypt the file using AES-GCM with the key to obtain the zlib tarball.
 4. Decompress the zlib tarball to restore the original Sekai directory.
 5. Clean up temporary files if necessary.
 
+FILE LAYOUT:
+A Deemak file is `dbdeemk3`, the 32 byte key, a 16 byte salt, the 32 byte Argon2 of the developer
+password with the salt, the nonce and the ciphertext. Everything needed to open the world is
+inside the file, so it survives email, downloads, zips and FAT/exFAT drives, which all drop
+extended attributes. Players never type the password, the key only keeps the contents from
+being read as plain files. The key is random, so it tells nothing about the password, which is
+only checked against its Argon2.
+
+Files which only open with the password, like the authoring sidecar of `dev pack`, are
+`dbdeemlk`, a 16 byte salt, the nonce and the ciphertext. Their key is the Argon2 of the password
+with the salt, so the key of a `.deemak` file never opens them.
+
+Older `dbdeemak` files are followed by the nonce and the ciphertext, and keep the password in the
+`pass.deemak` xattr. They are still opened as long as the xattr is there.
+
 USE CASES OF THIS MODULE:
 1. Deemak will read a Sekai file which is Deemak Encrypted.
 2. restore_me, save_me, etc. files will all be Deemak Encrypted files.
+
+REPRODUCIBLE BUILDS:
+By default the tarball follows the filesystem order and keeps the mtimes, owners and permissions
+of the files, and the nonce is random. So building the same Sekai twice gives different files.
+In reproducible mode the tarball is built by `normalized_tarball`, and with
+`NonceMode::ContentDerived` the nonce is derived from the key and the contents, so the same Sekai
+and password always give the same `.deemak` file. The key and the salts are then derived from the
+contents too, never from the password. This changes nothing about what the file protects: the key
+of a `.deemak` file is in the file in either mode, so its contents are only obfuscated, never
+confidential, and the password only gates the dev operations through its Argon2. `dbdeemlk` files
+stay keyed by the password.
 */
 
-/// Compresses a file/directory to a zlib tarball
-pub fn zlib_compress(source_path: &Path, output_file: &Path) -> io::Result<()> {
+/// How the AES-GCM nonce of a Deemak file is chosen.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum NonceMode {
+    /// A new random nonce for every encryption
+    #[default]
+    Random,
+    /// The nonce is the first 12 bytes of SHA-256 over a domain tag, the key and the plaintext.
+    ///
+    /// AES-GCM breaks when the same key and nonce encrypt two *different* plaintexts. Here the
+    /// nonce is a function of the key and the plaintext, so a nonce only repeats for the same
+    /// plaintext, which gives the same ciphertext. The cost is that anyone can tell two files
+    /// hold the same Sekai, which is the point of a reproducible build.
+    ///
+    /// The key of a `.deemak` file is then derived from the contents and stored in the file, as
+    /// it always is, so the password plays no part in its encryption. Its contents are obfuscated,
+    /// not confidential, and the password only gates the dev operations. Files locked with the
+    /// password, like the `dev pack` sidecar, are still keyed by it.
+    ContentDerived,
+}
+
+/// Compresses a file/directory to a zlib tarball, leaving out the files for which `skip` is true.
+/// `skip` is given the path relative to the source. If `reproducible` is set, the tarball is
+/// built by [`normalized_tarball`].
+pub fn zlib_compress(
+    source_path: &Path,
+    output_file: &Path,
+    skip: &dyn Fn(&Path) -> bool,
+    reproducible: bool,
+) -> io::Result<()> {
+    if reproducible {
+        let tarball = normalized_tarball(&tree_entries(source_path, skip)?)?;
+        return fs::write(output_file, zlib_bytes(&tarball)?);
+    }
     let file = File::create(output_file)?;
     let mut encoder = ZlibEncoder::new(file, Compression::best());
 
@@ -61,6 +127,9 @@ pub fn zlib_compress(source_path: &Path, output_file: &Path) -> io::Result<()> {
             let relative_path = path.strip_prefix(source_path).unwrap();
 
             if path.is_file() {
+                if skip(relative_path) {
+                    continue;
+                }
                 tar_builder.append_file(relative_path, &mut File::open(path)?)?;
             } else if path.is_dir() {
                 tar_builder.append_dir(relative_path, path)?;
@@ -73,29 +142,77 @@ pub fn zlib_compress(source_path: &Path, output_file: &Path) -> io::Result<()> {
     Ok(())
 }
 
-/// Set Xattr password to a file
-pub fn set_xattr_password(file_path: &Path, password: &str) -> Result<(), String> {
-    log::log_debug(
-        "Xattr Set",
-        &format!("Setting xattr password for file: {}", file_path.display()),
-    );
-    xattr::set(file_path, "pass.deemak", password.as_bytes())
-        .map_err(|e| format!("Failed to set xattr metadata: {e}"))?;
-    Ok(())
+/// Reads the directories and files under the source, for [`normalized_tarball`].
+/// Files for which `skip` is true are left out, it is given the path relative to the source.
+pub fn tree_entries(
+    source_path: &Path,
+    skip: &dyn Fn(&Path) -> bool,
+) -> io::Result<Vec<(PathBuf, Option<Vec<u8>>)>> {
+    let mut entries = Vec::new();
+    for entry in WalkDir::new(source_path).min_depth(1) {
+        let entry = entry?;
+        let relative_path = entry.path().strip_prefix(source_path).unwrap();
+        if entry.file_type().is_dir() {
+            entries.push((relative_path.to_path_buf(), None));
+        } else if entry.file_type().is_file() && !skip(relative_path) {
+            entries.push((relative_path.to_path_buf(), Some(read(entry.path())?)));
+        }
+    }
+    Ok(entries)
 }
 
-/// Get Xattr password from a file
-pub fn get_xattr_password(file_path: &Path) -> String {
-    let password_bytes =
-        xattr::get(file_path, "pass.deemak").expect("Failed to read xattr metadata");
-    match password_bytes {
-        Some(bytes) => String::from_utf8(bytes).expect("Password metadata is not valid UTF-8"),
-        _ => {
-            let new_pass = format!("sekai_{}", rand::random::<u64>());
-            let _ = set_xattr_password(file_path, &new_pass);
-            new_pass
+/// Builds a tarball which only depends on the given paths and contents, so that the same
+/// Sekai always gives the same bytes. Entries are sorted by path, and the headers carry no
+/// times, owners or permissions of the machine. Directories are given with `None` contents.
+pub fn normalized_tarball(entries: &[(PathBuf, Option<Vec<u8>>)]) -> io::Result<Vec<u8>> {
+    let mut sorted: Vec<&(PathBuf, Option<Vec<u8>>)> = entries.iter().collect();
+    sorted.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let mut tar_builder = Builder::new(Vec::new());
+    for (path, contents) in sorted {
+        let mut header = Header::new_gnu();
+        header.set_mtime(0);
+        header.set_uid(0);
+        header.set_gid(0);
+        match contents {
+            Some(data) => {
+                header.set_entry_type(EntryType::Regular);
+                header.set_mode(0o644);
+                header.set_size(data.len() as u64);
+                tar_builder.append_data(&mut header, path, data.as_slice())?;
+            }
+            None => {
+                header.set_entry_type(EntryType::Directory);
+                header.set_mode(0o755);
+                header.set_size(0);
+                tar_builder.append_data(&mut header, path, io::empty())?;
+            }
         }
     }
+    tar_builder.into_inner()
+}
+
+/// Compresses the data with zlib, the same data always gives the same bytes.
+pub fn zlib_bytes(data: &[u8]) -> io::Result<Vec<u8>> {
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::best());
+    encoder.write_all(data)?;
+    encoder.finish()
+}
+
+/// Password of a legacy Deemak file, kept in its xattr.
+#[cfg(not(target_arch = "wasm32"))]
+fn xattr_password(file_path: &Path) -> Result<String, String> {
+    let password_bytes = xattr::get(file_path, XATTR_PASSWORD)
+        .map_err(|e| format!("Failed to read metadata: {e}"))?
+        .ok_or_else(|| "Missing password metadata".to_string())?;
+    String::from_utf8(password_bytes)
+        .map_err(|_| "Password metadata is not valid UTF-8".to_string())
+}
+
+/// Files carry no xattrs in the browser, so legacy Deemak files cannot be opened there.
+#[cfg(target_arch = "wasm32")]
+fn xattr_password(_file_path: &Path) -> Result<String, String> {
+    Err("Legacy Deemak files are not supported in the browser".to_string())
 }
 
 /// Decompresses a zlib tarball to a file/directory
@@ -107,13 +224,19 @@ pub fn zlib_decompress(archive_path: &Path, output_path: &Path) -> io::Result<()
     Ok(())
 }
 
+/// Key of the legacy files, the unsalted SHA-256 of the password.
 fn derive_key_from_password(password: &str) -> Key<Aes256Gcm> {
     let mut hasher = Sha256::new_with_prefix(password.as_bytes());
     let hash = hasher.finalize();
     *Key::<Aes256Gcm>::from_slice(&hash[..32])
 }
 
-pub fn encrypt_file(input_path: &Path, output_path: &Path, password: &str) -> Result<(), String> {
+pub fn encrypt_file(
+    input_path: &Path,
+    output_path: &Path,
+    password: &str,
+    nonce_mode: NonceMode,
+) -> Result<(), String> {
     log::log_debug(
         "Encryption",
         &format!(
@@ -128,47 +251,243 @@ pub fn encrypt_file(input_path: &Path, output_path: &Path, password: &str) -> Re
         fs::remove_file(output_path)
             .map_err(|e| format!("Failed to remove existing output file: {e}"))?;
     }
-    let key = derive_key_from_password(password);
-    let cipher = Aes256Gcm::new(&key);
-
     let plaintext = read(input_path).map_err(|e| format!("Failed to read input file: {e}"))?;
+    let data = encrypt_bytes(&plaintext, password, nonce_mode)?;
+
+    let mut file =
+        File::create(output_path).map_err(|e| format!("Failed to create output file: {e}"))?;
+    file.write_all(&data)
+        .map_err(|e| format!("Failed to write encrypted file: {e}"))?;
+
+    Ok(())
+}
+
+/// Encrypts the data with the password, returning the contents of a Deemak file which
+/// carries its key, see the file layout above.
+pub fn encrypt_bytes(
+    plaintext: &[u8],
+    password: &str,
+    nonce_mode: NonceMode,
+) -> Result<Vec<u8>, String> {
+    let key: [u8; KEY_SIZE] = fresh_bytes(nonce_mode, b"deemak-content-key-v1", plaintext);
+    let salt: [u8; SALT_SIZE] = fresh_bytes(nonce_mode, b"deemak-verifier-salt-v1", plaintext);
+    let verifier = argon2_key(password, &salt)?;
+    let sealed = seal(Key::<Aes256Gcm>::from_slice(&key), plaintext, nonce_mode)?;
+    let mut data =
+        Vec::with_capacity(MAGIC_HEADER.len() + KEY_SIZE + SALT_SIZE + KEY_SIZE + sealed.len());
+    data.extend_from_slice(MAGIC_HEADER);
+    data.extend_from_slice(&key);
+    data.extend_from_slice(&salt);
+    data.extend_from_slice(&verifier);
+    data.extend_from_slice(&sealed);
+    Ok(data)
+}
+
+/// Encrypts the data with a key derived from the password, without storing the key, so that it
+/// only opens with the password.
+pub fn encrypt_locked_bytes(
+    plaintext: &[u8],
+    password: &str,
+    nonce_mode: NonceMode,
+) -> Result<Vec<u8>, String> {
+    let salt: [u8; SALT_SIZE] = fresh_bytes(nonce_mode, b"deemak-locked-salt-v1", plaintext);
+    let key = argon2_key(password, &salt)?;
+    let sealed = seal(Key::<Aes256Gcm>::from_slice(&key), plaintext, nonce_mode)?;
+    let mut data = Vec::with_capacity(LOCKED_MAGIC_HEADER.len() + SALT_SIZE + sealed.len());
+    data.extend_from_slice(LOCKED_MAGIC_HEADER);
+    data.extend_from_slice(&salt);
+    data.extend_from_slice(&sealed);
+    Ok(data)
+}
+
+/// Random bytes, or with `NonceMode::ContentDerived` bytes derived from the tag and the
+/// plaintext, so that the same Sekai gives the same file. They never depend on the password,
+/// since they are stored in the file.
+fn fresh_bytes<const N: usize>(nonce_mode: NonceMode, tag: &[u8], plaintext: &[u8]) -> [u8; N] {
+    let mut bytes = [0u8; N];
+    match nonce_mode {
+        NonceMode::Random => OsRng.fill_bytes(&mut bytes),
+        NonceMode::ContentDerived => {
+            let mut hasher = Sha256::new_with_prefix(tag);
+            hasher.update(plaintext);
+            bytes.copy_from_slice(&hasher.finalize()[..N]);
+        }
+    }
+    bytes
+}
+
+/// Argon2 of the password with the salt, as a key or a verifier.
+fn argon2_key(password: &str, salt: &[u8]) -> Result<[u8; KEY_SIZE], String> {
+    let mut key = [0u8; KEY_SIZE];
+    Argon2::default()
+        .hash_password_into(password.as_bytes(), salt, &mut key)
+        .map_err(|e| format!("Failed to derive the key: {e}"))?;
+    Ok(key)
+}
+
+/// The nonce followed by the ciphertext.
+fn seal(key: &Key<Aes256Gcm>, plaintext: &[u8], nonce_mode: NonceMode) -> Result<Vec<u8>, String> {
+    let cipher = Aes256Gcm::new(key);
 
     let mut nonce_bytes = [0u8; NONCE_SIZE];
-    OsRng.fill_bytes(&mut nonce_bytes);
+    match nonce_mode {
+        NonceMode::Random => OsRng.fill_bytes(&mut nonce_bytes),
+        NonceMode::ContentDerived => {
+            let mut hasher = Sha256::new_with_prefix(b"deemak-nonce-v1");
+            hasher.update(key);
+            hasher.update(plaintext);
+            nonce_bytes.copy_from_slice(&hasher.finalize()[..NONCE_SIZE]);
+        }
+    }
     let nonce = Nonce::from_slice(&nonce_bytes);
 
     let ciphertext = cipher
-        .encrypt(nonce, plaintext.as_ref())
+        .encrypt(nonce, plaintext)
         .map_err(|_| "Encryption failed".to_string())?;
 
-    let mut file =
-        File::create(output_path).map_err(|e| format!("Failed to create output file: {e}"))?;
+    let mut data = Vec::with_capacity(NONCE_SIZE + ciphertext.len());
+    data.extend_from_slice(&nonce_bytes);
+    data.extend_from_slice(&ciphertext);
+    Ok(data)
+}
+
+/// How the key of a Deemak file is found, by its header.
+enum Lock<'a> {
+    /// `dbdeemk3`: the content key, and the salt and verifier of the password
+    Carried {
+        key: &'a [u8],
+        salt: &'a [u8],
+        verifier: &'a [u8],
+    },
+    /// `dbdeemlk`: the key is the Argon2 of the password with the salt
+    Locked { salt: &'a [u8] },
+    /// `dbdeemak`: the key is the SHA-256 of the password in the xattr
+    Legacy,
+}
 
-    file.write_all(MAGIC_HEADER)
-        .map_err(|e| format!("Failed to write header: {e}"))?;
-    file.write_all(&nonce_bytes)
-        .map_err(|e| format!("Failed to write nonce: {e}"))?;
-    file.write_all(&ciphertext)
-        .map_err(|e| format!("Failed to write ciphertext: {e}"))?;
+/// Contents of a Deemak file, split by its layout.
+struct DeemakData<'a> {
+    lock: Lock<'a>,
+    nonce: &'a [u8],
+    ciphertext: &'a [u8],
+}
 
-    xattr::set(output_path, "pass.deemak", password.as_bytes())
-        .map_err(|e| format!("Failed to set xattr metadata: {e}"))?;
+/// Splits the first `len` bytes off the data.
+fn take(data: &[u8], len: usize) -> Result<(&[u8], &[u8]), String> {
+    data.split_at_checked(len)
+        .ok_or_else(|| "Invalid encrypted file".to_string())
+}
 
-    Ok(())
+impl<'a> DeemakData<'a> {
+    fn parse(data: &'a [u8]) -> Result<Self, String> {
+        let (lock, rest) = if let Some(rest) = data.strip_prefix(MAGIC_HEADER) {
+            let (key, rest) = take(rest, KEY_SIZE)?;
+            let (salt, rest) = take(rest, SALT_SIZE)?;
+            let (verifier, rest) = take(rest, KEY_SIZE)?;
+            (
+                Lock::Carried {
+                    key,
+                    salt,
+                    verifier,
+                },
+                rest,
+            )
+        } else if let Some(rest) = data.strip_prefix(LOCKED_MAGIC_HEADER) {
+            let (salt, rest) = take(rest, SALT_SIZE)?;
+            (Lock::Locked { salt }, rest)
+        } else if let Some(rest) = data.strip_prefix(LEGACY_MAGIC_HEADER) {
+            (Lock::Legacy, rest)
+        } else if data.len() < MAGIC_HEADER.len() {
+            return Err("Invalid encrypted file".to_string());
+        } else {
+            return Err("Invalid magic header, not a valid .deemak file".to_string());
+        };
+        let (nonce, ciphertext) = take(rest, NONCE_SIZE)?;
+        Ok(Self {
+            lock,
+            nonce,
+            ciphertext,
+        })
+    }
+
+    /// The key of the file, if it is the password. None if the password is wrong.
+    fn password_key(&self, password: &str) -> Result<Option<Key<Aes256Gcm>>, String> {
+        let key = match self.lock {
+            Lock::Carried {
+                key,
+                salt,
+                verifier,
+            } => (argon2_key(password, salt)? == verifier).then_some(key.to_vec()),
+            Lock::Locked { salt } => Some(argon2_key(password, salt)?.to_vec()),
+            Lock::Legacy => Some(derive_key_from_password(password).to_vec()),
+        };
+        Ok(key.map(|key| *Key::<Aes256Gcm>::from_slice(&key)))
+    }
+
+    fn decrypt(&self, key: &Key<Aes256Gcm>) -> Result<Vec<u8>, String> {
+        Aes256Gcm::new(key)
+            .decrypt(Nonce::from_slice(self.nonce), self.ciphertext)
+            .map_err(|_| "Decryption failed — wrong password or corrupted file".to_string())
+    }
+}
+
+/// Whether the contents are of a Deemak file in the current layout, which carries its key if
+/// `carry_key` is set, or else only opens with the password.
+pub fn has_current_layout(data: &[u8], carry_key: bool) -> bool {
+    match DeemakData::parse(data).map(|data| data.lock) {
+        Ok(Lock::Carried { .. }) => carry_key,
+        Ok(Lock::Locked { .. }) => !carry_key,
+        _ => false,
+    }
+}
+
+/// Decrypts the contents of a Deemak file with the password.
+pub fn decrypt_bytes(data: &[u8], password: &str) -> Result<Vec<u8>, String> {
+    let data = DeemakData::parse(data)?;
+    let key = data
+        .password_key(password)?
+        .ok_or_else(|| "Decryption failed — wrong password".to_string())?;
+    data.decrypt(&key)
+}
+
+/// Decrypts a Deemak file without the password, with the key it carries, or the password in
+/// the xattr of a legacy file.
+pub fn open_bytes(data: &[u8], file_path: &Path) -> Result<Vec<u8>, String> {
+    let data = DeemakData::parse(data)?;
+    let key = match data.lock {
+        Lock::Carried { key, .. } => *Key::<Aes256Gcm>::from_slice(key),
+        Lock::Locked { .. } => return Err("This file only opens with the password".to_string()),
+        Lock::Legacy => derive_key_from_password(&xattr_password(file_path)?),
+    };
+    data.decrypt(&key)
 }
 
+/// Whether the password is the one the Deemak file was encrypted with.
+pub fn check_password(file_path: &Path, password: &str) -> Result<bool, String> {
+    let data = read(file_path).map_err(|e| format!("Failed to read input file: {e}"))?;
+    let data = DeemakData::parse(&data)?;
+    match data.lock {
+        Lock::Legacy => Ok(xattr_password(file_path)? == password),
+        // Only decrypting tells whether the password is right
+        Lock::Locked { .. } => Ok(data
+            .password_key(password)?
+            .is_some_and(|key| data.decrypt(&key).is_ok())),
+        Lock::Carried { .. } => Ok(data.password_key(password)?.is_some()),
+    }
+}
+
+/// Whether the file is a Deemak file which can be opened: it carries its key, or it is a
+/// legacy file with the password in its xattr.
 pub fn check_dmk_magic(sekai_path: &Path) -> Result<bool, String> {
     if !sekai_path.is_file() {
         return Err("Provided path is not a file".to_string());
     }
     let data = fs::read(sekai_path).map_err(|e| format!("Failed to read input file: {e}"))?;
-
-    // Check magic header exists and matches
-    let magic_ok = data.len() >= MAGIC_HEADER.len() + NONCE_SIZE && data.starts_with(MAGIC_HEADER);
-
-    // Check xattr exists
-    let xattr_ok = xattr::get(sekai_path, "pass.deemak").is_ok();
-    Ok(magic_ok && xattr_ok)
+    Ok(match DeemakData::parse(&data).map(|data| data.lock) {
+        Ok(Lock::Carried { .. }) => true,
+        Ok(Lock::Legacy) => xattr_password(sekai_path).is_ok(),
+        Ok(Lock::Locked { .. }) | Err(_) => false,
+    })
 }
 
 pub fn decrypt_file(
@@ -182,38 +501,14 @@ pub fn decrypt_file(
     }
     let data = read(input_path).map_err(|e| format!("Failed to read input file: {e}"))?;
 
-    if data.len() < MAGIC_HEADER.len() + NONCE_SIZE {
-        return Err("Invalid encrypted file".to_string());
-    }
-
-    let (magic, rest) = data.split_at(MAGIC_HEADER.len());
-    if magic != MAGIC_HEADER {
-        return Err("Invalid magic header, not a valid .deemak file".to_string());
-    }
-
-    let (nonce_bytes, ciphertext) = rest.split_at(NONCE_SIZE);
-    let nonce = Nonce::from_slice(nonce_bytes);
-
-    let password_bytes = xattr::get(input_path, "pass.deemak")
-        .map_err(|e| format!("Failed to read metadata: {e}"))?
-        .ok_or_else(|| "Missing password metadata".to_string())?;
-
-    let password = String::from_utf8(password_bytes)
-        .map_err(|_| "Password metadata is not valid UTF-8".to_string())?;
-
     // We don't actually need user password, but if something is given, we check it.
     if let Some(input_pass) = input_pass
-        && input_pass != password
+        && !check_password(input_path, input_pass)?
     {
         return Err("Provided password does not match the file's password".to_string());
     }
 
-    let key = derive_key_from_password(&password);
-    let cipher = Aes256Gcm::new(&key);
-
-    let plaintext = cipher
-        .decrypt(nonce, ciphertext)
-        .map_err(|_| "Decryption failed — wrong password or corrupted file".to_string())?;
+    let plaintext = open_bytes(&data, input_path)?;
 
     write(output_path, plaintext).map_err(|e| format!("Failed to write decrypted file: {e}"))?;
 
@@ -90,3 +90,34 @@ pub fn decrypt(enc_key: &[Vec<char>], text: &str) -> String {
         })
         .collect()
 }
+
+/// `decrypt_me` of a level, which gives the flag when the level is solved.
+pub fn level_decrypt_me(level_name: &str, solution: &str, flag: &str) -> String {
+    encrypt(&characterise_enc_key(level_name, solution), flag)
+}
+
+/// `compare_me` of a chest, which `unlock` checks the key against.
+pub fn chest_compare_me(chest_name: &str, obj_salt: &SaltString, solution: &str) -> String {
+    let hashed_solution = argonhash(obj_salt, solution.to_string());
+    encrypt(
+        &characterise_enc_key(chest_name, &hashed_solution),
+        &hashed_solution,
+    )
+}
+
+/// The flag of a level as given to the player, only valid for the `flag_name` of that player.
+pub fn user_flag(flag_name: &str, level_name: &str, flag: &str) -> String {
+    encrypt(&user_flag_key(flag_name, level_name), flag)
+}
+
+/// The flag of a level from the flag given to the player by `user_flag`.
+pub fn read_user_flag(flag_name: &str, level_name: &str, user_flag: &str) -> String {
+    decrypt(&user_flag_key(flag_name, level_name), user_flag)
+}
+
+fn user_flag_key(flag_name: &str, level_name: &str) -> Vec<Vec<char>> {
+    characterise_enc_key(
+        &format!("{}_{}", flag_name, flag_name.len()),
+        &format!("{flag_name}_{level_name}"),
+    )
+}
@@ -1,6 +1,14 @@
 #[cfg(test)]
 mod rns_test {
+    use crate::metainfo::authoring::AUTHORING_FILE;
+    use crate::metainfo::scaffold::WALKTHROUGH_FILE;
+    use crate::rns::pack::{pack_contents, sidecar_path, unpack_sekai};
+    use crate::rns::passlock::{
+        NonceMode, check_dmk_magic, check_password, decrypt_bytes, decrypt_file, encrypt_bytes,
+        encrypt_locked_bytes, open_bytes, zlib_compress,
+    };
     use crate::rns::restore_comp::{backup_sekai, can_restore, can_save, restore_sekai};
+    use crate::sekai_fs::DiskFs;
     use crate::utils::test_utils::{create_file, get_dir_contents, remove_file, setup_test_dir};
     use std::fs;
     use std::fs::File;
@@ -10,17 +18,17 @@ mod rns_test {
     fn test_can_restore_and_can_save_functionality() {
         let (_temp_dir, root_path) = setup_test_dir(false);
 
-        assert!(!can_restore(&root_path));
-        assert!(!can_save(&root_path));
+        assert!(!can_restore(&DiskFs, &root_path));
+        assert!(!can_save(&DiskFs, &root_path));
 
         fs::create_dir_all(root_path.join(".dir_info")).unwrap();
         File::create(root_path.join(".dir_info").join("restore_me.deemak")).unwrap();
-        assert!(can_restore(&root_path));
-        assert!(!can_save(&root_path));
+        assert!(can_restore(&DiskFs, &root_path));
+        assert!(!can_save(&DiskFs, &root_path));
 
         File::create(root_path.join(".dir_info").join("save_me.deemak")).unwrap();
-        assert!(can_restore(&root_path));
-        assert!(can_save(&root_path));
+        assert!(can_restore(&DiskFs, &root_path));
+        assert!(can_save(&DiskFs, &root_path));
     }
 
     /// Test to ensure that backup and restore functionality handles errors correctly
@@ -28,14 +36,14 @@ mod rns_test {
     fn test_invalid_usage_errors() {
         let (_temp_dir, root_path) = setup_test_dir(false);
 
-        let backup_result = backup_sekai("invalid_usage", &root_path);
+        let backup_result = backup_sekai(&DiskFs, "invalid_usage", &root_path);
         assert!(backup_result.is_err());
         assert_eq!(
             backup_result.err().unwrap().kind(),
             std::io::ErrorKind::InvalidInput
         );
 
-        let restore_result = restore_sekai("invalid_usage", &root_path);
+        let restore_result = restore_sekai(&DiskFs, "invalid_usage", &root_path);
         assert!(restore_result.is_err());
         assert_eq!(
             restore_result.err().unwrap().kind(),
@@ -49,11 +57,11 @@ mod rns_test {
     fn test_backup_restore_idempotency() {
         let (_temp_dir, root_path) = setup_test_dir(true);
 
-        let first_backup_result = backup_sekai("restore", &root_path).unwrap();
+        let first_backup_result = backup_sekai(&DiskFs, "restore", &root_path).unwrap();
         assert!(first_backup_result.contains("created"));
 
         // Calling backup again for "restore" should skip creation
-        let second_backup_result = backup_sekai("restore", &root_path).unwrap();
+        let second_backup_result = backup_sekai(&DiskFs, "restore", &root_path).unwrap();
         assert!(second_backup_result.contains("skipping creation"));
     }
 
@@ -64,8 +72,8 @@ mod rns_test {
         let initial_contents = get_dir_contents(&root_path, false);
 
         // Create a restore point
-        assert!(backup_sekai("restore", &root_path).is_ok());
-        assert!(can_restore(&root_path));
+        assert!(backup_sekai(&DiskFs, "restore", &root_path).is_ok());
+        assert!(can_restore(&DiskFs, &root_path));
         assert!(
             root_path
                 .join(".dir_info")
@@ -78,7 +86,7 @@ mod rns_test {
         create_file(root_path.join("subdir1/file2.txt"), "new content");
 
         // Restore from the starting point
-        assert!(restore_sekai("restore", &root_path).is_ok());
+        assert!(restore_sekai(&DiskFs, "restore", &root_path).is_ok());
 
         // Check if the directory is restored to its original state
         let restored_contents = get_dir_contents(&root_path, false);
@@ -92,16 +100,16 @@ mod rns_test {
 
         // 1. Create initial state and a restore point
         let initial_contents = get_dir_contents(&root_path, false);
-        assert!(backup_sekai("restore", &root_path).is_ok());
-        assert!(backup_sekai("save", &root_path).is_ok());
-        assert!(can_save(&root_path));
+        assert!(backup_sekai(&DiskFs, "restore", &root_path).is_ok());
+        assert!(backup_sekai(&DiskFs, "save", &root_path).is_ok());
+        assert!(can_save(&DiskFs, &root_path));
 
         // 2. Modify the directory and create a save point
         remove_file(root_path.join("file1.txt"));
         create_file(root_path.join("new_file.txt"), "new content");
         create_file(root_path.join("subdir1/new_file.txt"), "new content");
         let modified_contents_1 = get_dir_contents(&root_path, false);
-        assert!(backup_sekai("save", &root_path).is_ok());
+        assert!(backup_sekai(&DiskFs, "save", &root_path).is_ok());
 
         // 3. Modify the directory again
         remove_file(root_path.join("subdir1/file2.txt"));
@@ -111,11 +119,219 @@ mod rns_test {
         );
         let modified_contents_2 = get_dir_contents(&root_path, false);
         // restore to the first save point and check
-        assert!(restore_sekai("save", &root_path).is_ok());
+        assert!(restore_sekai(&DiskFs, "save", &root_path).is_ok());
         assert_eq!(get_dir_contents(&root_path, false), modified_contents_1);
 
         // 4. Restore to the initial state
-        assert!(restore_sekai("restore", &root_path).is_ok());
+        assert!(restore_sekai(&DiskFs, "restore", &root_path).is_ok());
         assert_eq!(get_dir_contents(&root_path, false), initial_contents);
     }
+
+    /// Test that backups leave out the authoring files, and restoring keeps the ones on disk
+    #[test]
+    fn test_backup_skips_authoring_files() {
+        let (_temp_dir, root_path) = setup_test_dir(true);
+        create_file(root_path.join(AUTHORING_FILE), "{}");
+        create_file(root_path.join(WALKTHROUGH_FILE), "solutions");
+        assert!(backup_sekai(&DiskFs, "restore", &root_path).is_ok());
+
+        // A backup holding the walkthrough would bring it back
+        remove_file(root_path.join(WALKTHROUGH_FILE));
+        assert!(restore_sekai(&DiskFs, "restore", &root_path).is_ok());
+        assert!(!root_path.join(WALKTHROUGH_FILE).exists());
+        assert!(root_path.join(AUTHORING_FILE).exists());
+    }
+
+    /// Test that unpacking and packing again gives the same pack, without the runtime files
+    /// and with the authoring files kept in the sidecar
+    #[test]
+    fn test_pack_unpack_round_trip() {
+        let (temp_dir, root_path) = setup_test_dir(true);
+        let password = "dev_password";
+        fs::write(root_path.join(".dir_info/restore_me.deemak"), "runtime").unwrap();
+        fs::write(
+            root_path.join(AUTHORING_FILE),
+            r#"{"levels": [], "chests": []}"#,
+        )
+        .unwrap();
+
+        let contents = pack_contents(&root_path).unwrap();
+        assert_eq!(contents, pack_contents(&root_path).unwrap());
+        assert!(contents.authoring.is_some());
+
+        // Write the pack as `dev pack` does, the xattr password is not needed to unpack
+        let pack_path = temp_dir.path().join("world.deemak");
+        fs::write(
+            &pack_path,
+            encrypt_bytes(&contents.world, password, NonceMode::Random).unwrap(),
+        )
+        .unwrap();
+        fs::write(
+            sidecar_path(&pack_path),
+            encrypt_bytes(
+                contents.authoring.as_ref().unwrap(),
+                password,
+                NonceMode::Random,
+            )
+            .unwrap(),
+        )
+        .unwrap();
+
+        let unpacked = temp_dir.path().join("unpacked");
+        let (unpacked, has_authoring) = unpack_sekai(&pack_path, &unpacked, password).unwrap();
+        assert!(has_authoring);
+        assert!(unpacked.join(AUTHORING_FILE).exists());
+        assert!(!unpacked.join(".dir_info/restore_me.deemak").exists());
+        assert_eq!(
+            get_dir_contents(&root_path.join("subdir1"), false),
+            get_dir_contents(&unpacked.join("subdir1"), false)
+        );
+
+        // The order of the keys of an info.json does not change the pack
+        let info_path = unpacked.join(".dir_info/info.json");
+        let info: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&info_path).unwrap()).unwrap();
+        fs::write(&info_path, serde_json::to_string(&info).unwrap()).unwrap();
+        assert_eq!(contents, pack_contents(&unpacked).unwrap());
+
+        // A wrong password cannot unpack
+        let other = temp_dir.path().join("other");
+        assert!(unpack_sekai(&pack_path, &other, "wrong").is_err());
+        assert!(!other.exists());
+    }
+
+    /// Test that two reproducible builds of the same Sekai are identical, even when the files
+    /// were written in another order and at another time
+    #[test]
+    fn test_reproducible_build() {
+        let (temp_dir, root_path) = setup_test_dir(true);
+        let copy_path = temp_dir.path().join("copy");
+        let mut files: Vec<_> = walkdir::WalkDir::new(&root_path)
+            .min_depth(1)
+            .into_iter()
+            .map(|e| {
+                e.unwrap()
+                    .path()
+                    .strip_prefix(&root_path)
+                    .unwrap()
+                    .to_path_buf()
+            })
+            .collect();
+        files.reverse();
+        for rel_path in &files {
+            let target = copy_path.join(rel_path);
+            fs::create_dir_all(target.parent().unwrap()).unwrap();
+            if root_path.join(rel_path).is_dir() {
+                fs::create_dir_all(&target).unwrap();
+            } else {
+                fs::copy(root_path.join(rel_path), &target).unwrap();
+            }
+        }
+
+        let build = |source: &std::path::Path, name: &str, nonce_mode: NonceMode| {
+            let zlib_path = temp_dir.path().join(name);
+            zlib_compress(source, &zlib_path, &|_| false, true).unwrap();
+            encrypt_bytes(&fs::read(zlib_path).unwrap(), "password", nonce_mode).unwrap()
+        };
+        let first = build(&root_path, "first.zlib", NonceMode::ContentDerived);
+        let second = build(&copy_path, "second.zlib", NonceMode::ContentDerived);
+        assert_eq!(first, second);
+        assert!(decrypt_bytes(&first, "password").is_ok());
+
+        // A random nonce still differs between builds, and other contents get another nonce
+        let random = build(&root_path, "random.zlib", NonceMode::Random);
+        assert_ne!(first, random);
+        create_file(copy_path.join("file1.txt"), "changed");
+        let changed = build(&copy_path, "changed.zlib", NonceMode::ContentDerived);
+        // The nonce follows the 8 bytes of the magic header, the 32 bytes of the key, the 16 of
+        // the salt and the 32 of the verifier
+        assert_ne!(first[88..100], changed[88..100]);
+    }
+
+    /// Test that the key a `.deemak` file carries tells nothing about the password, and does
+    /// not open the sidecar encrypted with the same password
+    #[test]
+    fn test_carried_key_does_not_open_sidecar() {
+        use sha2::{Digest, Sha256};
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let password = "dev_password";
+        let world = encrypt_bytes(b"world", password, NonceMode::Random).unwrap();
+        let sidecar = encrypt_locked_bytes(b"solutions", password, NonceMode::Random).unwrap();
+        let password_hash = Sha256::digest(password.as_bytes());
+        assert!(!world.windows(32).any(|w| w == password_hash.as_slice()));
+
+        // Put the key of the world in front of the sidecar, as a file carrying its key
+        let header = &world[..8 + 32 + 16 + 32];
+        let sidecar_sealed = &sidecar[8 + 16..];
+        let path = temp_dir.path().join("world.authoring");
+        let grafted = [header, sidecar_sealed].concat();
+        assert!(open_bytes(&grafted, &path).is_err());
+        let mut hashed = grafted.clone();
+        hashed[8..40].copy_from_slice(&password_hash);
+        assert!(open_bytes(&hashed, &path).is_err());
+        assert!(open_bytes(&sidecar, &path).is_err());
+        assert_eq!(decrypt_bytes(&sidecar, password).unwrap(), b"solutions");
+    }
+
+    /// Test that a Deemak file opens without the xattr, and that legacy files still do with it
+    #[test]
+    fn test_deemak_file_without_xattr() {
+        use aes_gcm::aead::Aead;
+        use aes_gcm::{Aes256Gcm, KeyInit, Nonce};
+        use sha2::{Digest, Sha256};
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let plaintext = b"zlib tarball".to_vec();
+
+        let path = temp_dir.path().join("world.deemak");
+        fs::write(
+            &path,
+            encrypt_bytes(&plaintext, "password", NonceMode::Random).unwrap(),
+        )
+        .unwrap();
+        assert!(check_dmk_magic(&path).unwrap());
+        assert!(check_password(&path, "password").unwrap());
+        assert!(!check_password(&path, "wrong").unwrap());
+        assert_eq!(
+            open_bytes(&fs::read(&path).unwrap(), &path).unwrap(),
+            plaintext
+        );
+        let output = temp_dir.path().join("world.zlib");
+        assert!(decrypt_file(&path, &output, Some("wrong")).is_err());
+        decrypt_file(&path, &output, None).unwrap();
+        assert_eq!(fs::read(&output).unwrap(), plaintext);
+
+        // Locked files need the password
+        let locked = encrypt_locked_bytes(&plaintext, "password", NonceMode::Random).unwrap();
+        assert_eq!(decrypt_bytes(&locked, "password").unwrap(), plaintext);
+        assert!(decrypt_bytes(&locked, "wrong").is_err());
+        let locked_path = temp_dir.path().join("world.authoring");
+        fs::write(&locked_path, &locked).unwrap();
+        assert!(!check_dmk_magic(&locked_path).unwrap());
+        assert!(check_password(&locked_path, "password").unwrap());
+        assert!(!check_password(&locked_path, "wrong").unwrap());
+
+        // Legacy files are keyed by the SHA-256 of the password, which is in their xattr,
+        // so a legacy file without the xattr is not valid
+        let key = Sha256::digest(b"password");
+        let nonce = [7u8; 12];
+        let sealed = Aes256Gcm::new_from_slice(&key)
+            .unwrap()
+            .encrypt(Nonce::from_slice(&nonce), plaintext.as_slice())
+            .unwrap();
+        let legacy_data = [b"dbdeemak".as_slice(), &nonce, &sealed].concat();
+        let legacy = temp_dir.path().join("legacy.deemak");
+        fs::write(&legacy, &legacy_data).unwrap();
+        assert!(!check_dmk_magic(&legacy).unwrap());
+        assert!(open_bytes(&legacy_data, &legacy).is_err());
+
+        // Filesystems like tmpfs may not support user xattrs
+        if xattr::set(&legacy, "pass.deemak", b"password").is_ok() {
+            assert!(check_dmk_magic(&legacy).unwrap());
+            assert!(check_password(&legacy, "password").unwrap());
+            decrypt_file(&legacy, &output, Some("password")).unwrap();
+            assert_eq!(fs::read(&output).unwrap(), plaintext);
+        }
+    }
 }
@@ -0,0 +1,129 @@
+use super::{DirLock, EntryKind, FileVersion, SekaiFs};
+use crate::metainfo::info_index::InfoIndex;
+use once_cell::sync::Lazy;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// The disk is shared by every `DiskFs`, and so are the info.json files parsed from it.
+static DISK_INDEX: Lazy<InfoIndex> = Lazy::new(InfoIndex::default);
+
+/// Held by `lock_dir`, for the threads of this process. Other programs wait on the directory.
+static DISK_DIR_LOCK: Mutex<()> = Mutex::new(());
+
+/// The Sekai directory on the disk, for dev mode.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DiskFs;
+
+impl SekaiFs for DiskFs {
+    fn kind(&self, path: &Path) -> Option<EntryKind> {
+        let metadata = fs::metadata(path).ok()?;
+        Some(if metadata.is_dir() {
+            EntryKind::Dir
+        } else {
+            EntryKind::File
+        })
+    }
+
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        fs::read(path)
+    }
+
+    fn write(&self, path: &Path, contents: &[u8]) -> io::Result<()> {
+        // The modification time may not change between two quick writes
+        DISK_INDEX.forget(path);
+        fs::write(path, contents)
+    }
+
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<PathBuf>> {
+        let mut paths = fs::read_dir(path)?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect::<io::Result<Vec<_>>>()?;
+        paths.sort();
+        Ok(paths)
+    }
+
+    fn create_dir(&self, path: &Path) -> io::Result<()> {
+        fs::create_dir(path)
+    }
+
+    fn remove_file(&self, path: &Path) -> io::Result<()> {
+        fs::remove_file(path)
+    }
+
+    fn remove_dir(&self, path: &Path) -> io::Result<()> {
+        fs::remove_dir(path)
+    }
+
+    fn remove_dir_all(&self, path: &Path) -> io::Result<()> {
+        DISK_INDEX.forget(path);
+        fs::remove_dir_all(path)
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> io::Result<()> {
+        DISK_INDEX.forget(from);
+        DISK_INDEX.forget(to);
+        fs::rename(from, to)
+    }
+
+    /// The modification time and size, so changes made by other programs are seen as well.
+    /// On Unix also the inode change time and number, since the modification time can be set
+    /// back, like `cp -p` and `rsync -t` do, and a rewrite may keep the size.
+    fn version(&self, path: &Path) -> Option<FileVersion> {
+        let metadata = fs::metadata(path).ok()?;
+        let modified = metadata.modified().ok()?;
+        #[cfg(unix)]
+        let (changed, inode) = {
+            use std::os::unix::fs::MetadataExt;
+            let changed = u128::try_from(metadata.ctime()).unwrap_or(0) * 1_000_000_000
+                + u128::try_from(metadata.ctime_nsec()).unwrap_or(0);
+            (changed, metadata.ino())
+        };
+        #[cfg(not(unix))]
+        let (changed, inode) = (0, 0);
+        Some(FileVersion {
+            stamp: modified
+                .duration_since(std::time::UNIX_EPOCH)
+                .map_or(0, |since| since.as_nanos()),
+            changed,
+            inode,
+            len: metadata.len(),
+        })
+    }
+
+    fn info_index(&self) -> &InfoIndex {
+        &DISK_INDEX
+    }
+
+    fn sync_file(&self, path: &Path) -> io::Result<()> {
+        // Opened for writing, as some systems only flush files open for writing
+        fs::OpenOptions::new().write(true).open(path)?.sync_all()
+    }
+
+    fn lock_dir(&self, dir: &Path) -> io::Result<DirLock<'_>> {
+        let guard = DISK_DIR_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        // Directories can only be opened like this on Unix
+        #[cfg(unix)]
+        let file = {
+            let file = fs::File::open(dir)?;
+            file.lock()?;
+            Some(file)
+        };
+        #[cfg(not(unix))]
+        let file = None;
+        Ok(DirLock {
+            _guard: guard,
+            _file: file,
+        })
+    }
+
+    fn create_dir_all(&self, path: &Path) -> io::Result<()> {
+        fs::create_dir_all(path)
+    }
+
+    fn copy(&self, from: &Path, to: &Path) -> io::Result<u64> {
+        DISK_INDEX.forget(to);
+        fs::copy(from, to)
+    }
+}
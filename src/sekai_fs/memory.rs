@@ -0,0 +1,395 @@
+use super::{DirLock, DiskFs, EntryKind, FileVersion, SekaiFs, tree_entries};
+use crate::commands::cmds::normalize_path;
+use crate::metainfo::info_index::InfoIndex;
+use crate::rns::passlock::{check_dmk_magic, open_bytes};
+use flate2::read::ZlibDecoder;
+use std::collections::BTreeMap;
+use std::io::{self, Read};
+use std::path::{Component, Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use tar::{Archive, EntryType};
+
+/// Parent of the roots of Sekai's held in memory, it does not exist on the disk.
+const MEMORY_ROOT: &str = "/deemak_sekai";
+
+#[derive(Clone)]
+enum Node {
+    Dir,
+    /// Contents, and the write that made them, 0 for the archive
+    File(Arc<Vec<u8>>, u64),
+}
+
+impl Node {
+    fn kind(&self) -> EntryKind {
+        match self {
+            Node::Dir => EntryKind::Dir,
+            Node::File(..) => EntryKind::File,
+        }
+    }
+}
+
+fn error(kind: io::ErrorKind, path: &Path) -> io::Error {
+    io::Error::new(kind, format!("{}", path.display()))
+}
+
+/// A Sekai in memory with a copy-on-write overlay, see the explanation in [`super`].
+/// Paths inside the Sekai are kept relative to the root, which is the empty path.
+pub struct MemFs {
+    root: PathBuf,
+    /// The decrypted archive, never changed
+    base: BTreeMap<PathBuf, Node>,
+    /// Changes of the player, `None` hides the entry of the archive
+    overlay: RwLock<BTreeMap<PathBuf, Option<Node>>>,
+    /// Number of files written, for the versions of the files
+    writes: AtomicU64,
+    index: InfoIndex,
+    /// Held by `lock_dir`, one for the whole Sekai since nothing else shares it
+    dir_lock: Mutex<()>,
+}
+
+impl MemFs {
+    /// Empty Sekai with only the root directory.
+    pub fn new(root: &Path) -> Self {
+        let mut base = BTreeMap::new();
+        base.insert(PathBuf::new(), Node::Dir);
+        Self {
+            root: root.to_path_buf(),
+            base,
+            overlay: RwLock::new(BTreeMap::new()),
+            writes: AtomicU64::new(0),
+            index: InfoIndex::default(),
+            dir_lock: Mutex::new(()),
+        }
+    }
+
+    /// Root for the Sekai of the Deemak file, named after the file.
+    pub fn root_for(deemak_path: &Path) -> PathBuf {
+        let name = deemak_path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "sekai".to_string());
+        Path::new(MEMORY_ROOT).join(name)
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Builds the Sekai from an uncompressed tarball, as inside a Deemak file.
+    pub fn from_tarball(root: &Path, tarball: &[u8]) -> io::Result<Self> {
+        let mut mem = Self::new(root);
+        let mut archive = Archive::new(tarball);
+        for entry in archive.entries()? {
+            let mut entry = entry?;
+            let path = entry.path()?.into_owned();
+            // Entries are relative to the Sekai, anything leaving it is skipped
+            if path
+                .components()
+                .any(|c| !matches!(c, Component::Normal(_) | Component::CurDir))
+            {
+                continue;
+            }
+            let path = normalize_path(&path);
+            match entry.header().entry_type() {
+                EntryType::Directory => mem.insert_base(path, Node::Dir),
+                EntryType::Regular | EntryType::GNUSparse => {
+                    let mut contents = Vec::new();
+                    entry.read_to_end(&mut contents)?;
+                    mem.insert_base(path, Node::File(Arc::new(contents), 0));
+                }
+                _ => {}
+            }
+        }
+        Ok(mem)
+    }
+
+    /// Decrypts the Deemak file into memory, nothing is written to the disk.
+    pub fn open_deemak(deemak_path: &Path, root: &Path) -> Result<Self, String> {
+        if !check_dmk_magic(deemak_path)? {
+            return Err("File does not have the correct magic header".to_string());
+        }
+        let data = std::fs::read(deemak_path).map_err(|e| format!("Failed to read file: {e}"))?;
+        Self::open_deemak_bytes(&data, deemak_path, root)
+    }
+
+    /// Decrypts the contents of a Deemak file into memory, for files that are not on the disk,
+    /// like one the browser downloaded. The path is only used for the xattr of legacy files.
+    pub fn open_deemak_bytes(data: &[u8], deemak_path: &Path, root: &Path) -> Result<Self, String> {
+        let compressed = open_bytes(data, deemak_path)?;
+        let mut tarball = Vec::new();
+        ZlibDecoder::new(compressed.as_slice())
+            .read_to_end(&mut tarball)
+            .map_err(|e| format!("Decompression failed: {e}"))?;
+        Self::from_tarball(root, &tarball).map_err(|e| format!("Decompression failed: {e}"))
+    }
+
+    /// Copies a Sekai directory into memory, so that playing it leaves the directory untouched.
+    pub fn load_dir(dir: &Path, root: &Path) -> io::Result<Self> {
+        Ok(Self::from_entries(
+            root,
+            tree_entries(&DiskFs, dir, &|_| false)?,
+        ))
+    }
+
+    /// Builds the Sekai from relative paths, with the contents of files and None for
+    /// directories, as given by [`tree_entries`].
+    pub fn from_entries(root: &Path, entries: Vec<(PathBuf, Option<Vec<u8>>)>) -> Self {
+        let mut mem = Self::new(root);
+        for (path, contents) in entries {
+            let node = match contents {
+                Some(contents) => Node::File(Arc::new(contents), 0),
+                None => Node::Dir,
+            };
+            mem.insert_base(path, node);
+        }
+        mem
+    }
+
+    fn insert_base(&mut self, path: PathBuf, node: Node) {
+        for ancestor in path.ancestors().skip(1) {
+            self.base.entry(ancestor.to_path_buf()).or_insert(Node::Dir);
+        }
+        self.base.insert(path, node);
+    }
+
+    fn next_write(&self) -> u64 {
+        self.writes.fetch_add(1, Ordering::Relaxed) + 1
+    }
+
+    /// Path relative to the root, None if it is outside of the Sekai.
+    fn rel(&self, path: &Path) -> Option<PathBuf> {
+        normalize_path(path)
+            .strip_prefix(&self.root)
+            .ok()
+            .map(Path::to_path_buf)
+    }
+
+    fn rel_or_err(&self, path: &Path) -> io::Result<PathBuf> {
+        self.rel(path)
+            .ok_or_else(|| error(io::ErrorKind::NotFound, path))
+    }
+
+    fn node(&self, overlay: &BTreeMap<PathBuf, Option<Node>>, rel: &Path) -> Option<Node> {
+        match overlay.get(rel) {
+            Some(node) => node.clone(),
+            None => self.base.get(rel).cloned(),
+        }
+    }
+
+    /// Visible paths under the directory, at any depth.
+    fn descendants(&self, overlay: &BTreeMap<PathBuf, Option<Node>>, rel: &Path) -> Vec<PathBuf> {
+        let start = rel.to_path_buf();
+        let base = self.base.range(start.clone()..).map(|(path, _)| path);
+        let changed = overlay.range(start..).map(|(path, _)| path);
+        let mut paths: Vec<PathBuf> = base
+            .take_while(|path| path.starts_with(rel))
+            .chain(changed.take_while(|path| path.starts_with(rel)))
+            .filter(|path| path.as_path() != rel)
+            .cloned()
+            .collect();
+        paths.sort();
+        paths.dedup();
+        paths.retain(|path| self.node(overlay, path).is_some());
+        paths
+    }
+
+    /// Removes the entry, hiding it if it is in the archive.
+    fn hide(&self, overlay: &mut BTreeMap<PathBuf, Option<Node>>, rel: &Path) {
+        if self.base.contains_key(rel) {
+            overlay.insert(rel.to_path_buf(), None);
+        } else {
+            overlay.remove(rel);
+        }
+    }
+
+    fn check_parent_dir(
+        &self,
+        overlay: &BTreeMap<PathBuf, Option<Node>>,
+        rel: &Path,
+        path: &Path,
+    ) -> io::Result<()> {
+        match rel.parent() {
+            Some(parent) if matches!(self.node(overlay, parent), Some(Node::Dir)) => Ok(()),
+            _ => Err(error(io::ErrorKind::NotFound, path)),
+        }
+    }
+}
+
+impl SekaiFs for MemFs {
+    fn kind(&self, path: &Path) -> Option<EntryKind> {
+        let rel = self.rel(path)?;
+        let overlay = self.overlay.read().unwrap();
+        self.node(&overlay, &rel).map(|node| node.kind())
+    }
+
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        let rel = self.rel_or_err(path)?;
+        let overlay = self.overlay.read().unwrap();
+        match self.node(&overlay, &rel) {
+            Some(Node::File(contents, _)) => Ok(contents.as_ref().clone()),
+            Some(Node::Dir) => Err(error(io::ErrorKind::IsADirectory, path)),
+            None => Err(error(io::ErrorKind::NotFound, path)),
+        }
+    }
+
+    fn write(&self, path: &Path, contents: &[u8]) -> io::Result<()> {
+        let rel = self.rel_or_err(path)?;
+        let mut overlay = self.overlay.write().unwrap();
+        self.check_parent_dir(&overlay, &rel, path)?;
+        if let Some(Node::Dir) = self.node(&overlay, &rel) {
+            return Err(error(io::ErrorKind::IsADirectory, path));
+        }
+        overlay.insert(
+            rel,
+            Some(Node::File(Arc::new(contents.to_vec()), self.next_write())),
+        );
+        Ok(())
+    }
+
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<PathBuf>> {
+        let rel = self.rel_or_err(path)?;
+        let overlay = self.overlay.read().unwrap();
+        match self.node(&overlay, &rel) {
+            Some(Node::Dir) => {}
+            Some(Node::File(..)) => return Err(error(io::ErrorKind::NotADirectory, path)),
+            None => return Err(error(io::ErrorKind::NotFound, path)),
+        }
+        Ok(self
+            .descendants(&overlay, &rel)
+            .into_iter()
+            .filter(|child| child.parent() == Some(rel.as_path()))
+            .map(|child| self.root.join(child))
+            .collect())
+    }
+
+    fn create_dir(&self, path: &Path) -> io::Result<()> {
+        let rel = self.rel_or_err(path)?;
+        let mut overlay = self.overlay.write().unwrap();
+        if self.node(&overlay, &rel).is_some() {
+            return Err(error(io::ErrorKind::AlreadyExists, path));
+        }
+        self.check_parent_dir(&overlay, &rel, path)?;
+        overlay.insert(rel, Some(Node::Dir));
+        Ok(())
+    }
+
+    fn remove_file(&self, path: &Path) -> io::Result<()> {
+        let rel = self.rel_or_err(path)?;
+        let mut overlay = self.overlay.write().unwrap();
+        match self.node(&overlay, &rel) {
+            Some(Node::File(..)) => {
+                self.hide(&mut overlay, &rel);
+                Ok(())
+            }
+            Some(Node::Dir) => Err(error(io::ErrorKind::IsADirectory, path)),
+            None => Err(error(io::ErrorKind::NotFound, path)),
+        }
+    }
+
+    fn remove_dir(&self, path: &Path) -> io::Result<()> {
+        let rel = self.rel_or_err(path)?;
+        let mut overlay = self.overlay.write().unwrap();
+        match self.node(&overlay, &rel) {
+            Some(Node::Dir) if rel.as_os_str().is_empty() => {
+                Err(error(io::ErrorKind::PermissionDenied, path))
+            }
+            Some(Node::Dir) if !self.descendants(&overlay, &rel).is_empty() => {
+                Err(error(io::ErrorKind::DirectoryNotEmpty, path))
+            }
+            Some(Node::Dir) => {
+                self.hide(&mut overlay, &rel);
+                Ok(())
+            }
+            Some(Node::File(..)) => Err(error(io::ErrorKind::NotADirectory, path)),
+            None => Err(error(io::ErrorKind::NotFound, path)),
+        }
+    }
+
+    fn remove_dir_all(&self, path: &Path) -> io::Result<()> {
+        let rel = self.rel_or_err(path)?;
+        let mut overlay = self.overlay.write().unwrap();
+        match self.node(&overlay, &rel) {
+            Some(Node::Dir) if rel.as_os_str().is_empty() => {
+                Err(error(io::ErrorKind::PermissionDenied, path))
+            }
+            Some(Node::Dir) => {
+                for descendant in self.descendants(&overlay, &rel) {
+                    self.hide(&mut overlay, &descendant);
+                }
+                self.hide(&mut overlay, &rel);
+                Ok(())
+            }
+            Some(Node::File(..)) => Err(error(io::ErrorKind::NotADirectory, path)),
+            None => Err(error(io::ErrorKind::NotFound, path)),
+        }
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> io::Result<()> {
+        let from_rel = self.rel_or_err(from)?;
+        let to_rel = self.rel_or_err(to)?;
+        let mut overlay = self.overlay.write().unwrap();
+        let Some(node) = self.node(&overlay, &from_rel) else {
+            return Err(error(io::ErrorKind::NotFound, from));
+        };
+        if from_rel.as_os_str().is_empty() || to_rel.starts_with(&from_rel) {
+            return Err(error(io::ErrorKind::InvalidInput, to));
+        }
+        self.check_parent_dir(&overlay, &to_rel, to)?;
+        match (&node, self.node(&overlay, &to_rel)) {
+            (_, None) | (Node::File(..), Some(Node::File(..))) => {}
+            _ => return Err(error(io::ErrorKind::AlreadyExists, to)),
+        }
+
+        let mut moved = vec![(to_rel.clone(), node)];
+        for descendant in self.descendants(&overlay, &from_rel) {
+            let target = to_rel.join(descendant.strip_prefix(&from_rel).unwrap());
+            if let Some(node) = self.node(&overlay, &descendant) {
+                moved.push((target, node));
+            }
+            self.hide(&mut overlay, &descendant);
+        }
+        self.hide(&mut overlay, &from_rel);
+        for (target, node) in moved {
+            // Moved files are new at their path
+            let node = match node {
+                Node::File(contents, _) => Node::File(contents, self.next_write()),
+                Node::Dir => Node::Dir,
+            };
+            overlay.insert(target, Some(node));
+        }
+        Ok(())
+    }
+
+    fn version(&self, path: &Path) -> Option<FileVersion> {
+        let rel = self.rel(path)?;
+        let overlay = self.overlay.read().unwrap();
+        match self.node(&overlay, &rel)? {
+            Node::File(contents, write) => Some(FileVersion {
+                stamp: u128::from(write),
+                changed: 0,
+                inode: 0,
+                len: contents.len() as u64,
+            }),
+            Node::Dir => None,
+        }
+    }
+
+    fn info_index(&self) -> &InfoIndex {
+        &self.index
+    }
+
+    fn sync_file(&self, path: &Path) -> io::Result<()> {
+        match self.kind(path) {
+            Some(EntryKind::File) => Ok(()),
+            _ => Err(error(io::ErrorKind::NotFound, path)),
+        }
+    }
+
+    fn lock_dir(&self, _dir: &Path) -> io::Result<DirLock<'_>> {
+        Ok(DirLock {
+            _guard: self.dir_lock.lock().unwrap_or_else(|e| e.into_inner()),
+            _file: None,
+        })
+    }
+}
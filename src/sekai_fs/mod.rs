@@ -0,0 +1,147 @@
+use crate::metainfo::info_index::InfoIndex;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::MutexGuard;
+
+mod disk;
+pub use disk::DiskFs;
+
+mod memory;
+pub use memory::MemFs;
+
+mod tests;
+
+/*
+SEKAI FILESYSTEM EXPLANATION:
+
+Commands and metainfo never touch the disk directly, they go through a `SekaiFs`. Paths given to
+it are the same absolute paths the commands always worked with, HOME being the root of the Sekai.
+
+- `DiskFs` works on the real directory, for developers playing and editing a Sekai directory,
+  so changes can be seen in their editor.
+- `MemFs` holds a Sekai decrypted from its `.deemak` file in memory. Nothing is extracted to the
+  disk, so players cannot read locked objects with other programs. The decrypted archive is never
+  changed, everything the player creates, changes or deletes goes into a copy-on-write overlay on
+  top of it.
+
+The root of a `MemFs` is a path which does not exist on the disk, so that paths shown in logs
+and messages look the same as with `DiskFs`.
+*/
+
+/// Kind of an entry of a Sekai filesystem.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EntryKind {
+    File,
+    Dir,
+}
+
+/// Changes whenever the file is written, see [`SekaiFs::version`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FileVersion {
+    pub stamp: u128,
+    /// Inode change time on the disk, which moves even when the modification time is set back
+    pub changed: u128,
+    /// Inode number on the disk, a file replaced by another one gets a new one
+    pub inode: u64,
+    pub len: u64,
+}
+
+/// Advisory lock taken by [`SekaiFs::lock_dir`], released when dropped.
+pub struct DirLock<'a> {
+    _guard: MutexGuard<'a, ()>,
+    /// The locked directory, so other programs writing the Sekai wait as well
+    _file: Option<std::fs::File>,
+}
+
+/// Filesystem of a Sekai, see the explanation above. Methods work like their `std::fs`
+/// counterparts.
+pub trait SekaiFs: Send + Sync {
+    /// None if nothing exists at the path
+    fn kind(&self, path: &Path) -> Option<EntryKind>;
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>>;
+    fn write(&self, path: &Path, contents: &[u8]) -> io::Result<()>;
+    /// Paths of the entries in the directory, sorted by name
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<PathBuf>>;
+    /// Fails if the parent does not exist or the path already does
+    fn create_dir(&self, path: &Path) -> io::Result<()>;
+    fn remove_file(&self, path: &Path) -> io::Result<()>;
+    /// Fails if the directory is not empty
+    fn remove_dir(&self, path: &Path) -> io::Result<()>;
+    fn remove_dir_all(&self, path: &Path) -> io::Result<()>;
+    fn rename(&self, from: &Path, to: &Path) -> io::Result<()>;
+    /// Flushes the written contents of the file to the disk, like `File::sync_all`.
+    fn sync_file(&self, path: &Path) -> io::Result<()>;
+    /// Version of the file, None if it does not exist. Parsed info.json files are cached as long
+    /// as their version stays the same.
+    fn version(&self, path: &Path) -> Option<FileVersion>;
+    /// Parsed info.json files of this filesystem, see `metainfo::info_index`.
+    fn info_index(&self) -> &InfoIndex;
+    /// Waits for and takes the lock for writing the files of the directory. Only writes which
+    /// take it wait for each other, like the info.json mutators.
+    fn lock_dir(&self, dir: &Path) -> io::Result<DirLock<'_>>;
+
+    fn exists(&self, path: &Path) -> bool {
+        self.kind(path).is_some()
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        self.kind(path) == Some(EntryKind::Dir)
+    }
+
+    fn is_file(&self, path: &Path) -> bool {
+        self.kind(path) == Some(EntryKind::File)
+    }
+
+    fn read_to_string(&self, path: &Path) -> io::Result<String> {
+        String::from_utf8(self.read(path)?)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    fn create_dir_all(&self, path: &Path) -> io::Result<()> {
+        if self.is_dir(path) {
+            return Ok(());
+        }
+        if let Some(parent) = path.parent()
+            && !parent.as_os_str().is_empty()
+        {
+            self.create_dir_all(parent)?;
+        }
+        match self.create_dir(path) {
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists && self.is_dir(path) => Ok(()),
+            result => result,
+        }
+    }
+
+    fn copy(&self, from: &Path, to: &Path) -> io::Result<u64> {
+        let contents = self.read(from)?;
+        self.write(to, &contents)?;
+        Ok(contents.len() as u64)
+    }
+}
+
+/// Files under the directory, given relative to it with their contents, and the directories
+/// with `None`, like [`crate::rns::passlock::tree_entries`].
+pub fn tree_entries(
+    fs: &dyn SekaiFs,
+    dir: &Path,
+    skip: &dyn Fn(&Path) -> bool,
+) -> io::Result<Vec<(PathBuf, Option<Vec<u8>>)>> {
+    let mut entries = Vec::new();
+    let mut pending = vec![dir.to_path_buf()];
+    while let Some(current) = pending.pop() {
+        for path in fs.read_dir(&current)? {
+            let rel_path = path.strip_prefix(dir).unwrap_or(&path).to_path_buf();
+            match fs.kind(&path) {
+                Some(EntryKind::Dir) => {
+                    entries.push((rel_path, None));
+                    pending.push(path);
+                }
+                Some(EntryKind::File) if !skip(&rel_path) => {
+                    entries.push((rel_path, Some(fs.read(&path)?)));
+                }
+                _ => {}
+            }
+        }
+    }
+    Ok(entries)
+}
@@ -0,0 +1,114 @@
+#[cfg(test)]
+mod sekai_fs_tests {
+    use crate::rns::passlock::{NonceMode, encrypt_bytes, normalized_tarball, zlib_bytes};
+    use crate::rns::restore_comp::{backup_sekai, restore_sekai};
+    use crate::sekai_fs::{DiskFs, MemFs, SekaiFs, tree_entries};
+    use crate::utils::test_utils::setup_test_dir;
+    use std::fs;
+    use std::path::Path;
+
+    fn names(fs: &dyn SekaiFs, dir: &Path) -> Vec<String> {
+        fs.read_dir(dir)
+            .unwrap()
+            .iter()
+            .map(|path| path.file_name().unwrap().to_string_lossy().into_owned())
+            .collect()
+    }
+
+    /// Test that changes go into the overlay and never reach the loaded directory
+    #[test]
+    fn test_memfs_overlay() {
+        let (_temp_dir, root_path) = setup_test_dir(false);
+        let root = MemFs::root_for(Path::new("world.deemak"));
+        let mem = MemFs::load_dir(&root_path, &root).unwrap();
+
+        assert_eq!(
+            mem.read_to_string(&root.join("file1.txt")).unwrap().trim(),
+            "hello from file1"
+        );
+        assert_eq!(names(&mem, &root), ["file1.txt", "subdir1", "subdir2"]);
+        assert!(mem.is_dir(&root.join("subdir1/nested1")));
+        assert!(!mem.exists(&root_path.join("file1.txt")));
+
+        // Writes shadow the archive
+        mem.write(&root.join("file1.txt"), b"changed").unwrap();
+        mem.write(&root.join("subdir1/new.txt"), b"new").unwrap();
+        assert_eq!(mem.read(&root.join("file1.txt")).unwrap(), b"changed");
+        assert!(mem.write(&root.join("missing/new.txt"), b"new").is_err());
+
+        // Removed entries stay hidden, also when their directory is made again
+        mem.remove_dir_all(&root.join("subdir1")).unwrap();
+        assert_eq!(names(&mem, &root), ["file1.txt", "subdir2"]);
+        mem.create_dir(&root.join("subdir1")).unwrap();
+        assert!(names(&mem, &root.join("subdir1")).is_empty());
+        assert!(mem.remove_dir_all(&root).is_err());
+
+        // Renaming moves everything under the directory
+        mem.rename(&root.join("subdir2"), &root.join("moved"))
+            .unwrap();
+        assert!(!mem.exists(&root.join("subdir2/nested2/file6.txt")));
+        assert!(mem.is_file(&root.join("moved/nested2/file6.txt")));
+        assert!(
+            mem.rename(&root.join("moved"), &root.join("moved/nested2/inner"))
+                .is_err()
+        );
+
+        // The directory on the disk is untouched
+        assert_eq!(
+            fs::read_to_string(root_path.join("file1.txt"))
+                .unwrap()
+                .trim(),
+            "hello from file1"
+        );
+        assert!(root_path.join("subdir1/nested1/file4.txt").is_file());
+        assert!(root_path.join("subdir2").is_dir());
+        assert!(!root_path.join("moved").exists());
+    }
+
+    /// Test that a Deemak file opens into memory, and that saving and restoring stays there
+    #[test]
+    fn test_memfs_from_deemak_file() {
+        let (temp_dir, root_path) = setup_test_dir(true);
+        let entries = tree_entries(&DiskFs, &root_path, &|_| false).unwrap();
+        let compressed = zlib_bytes(&normalized_tarball(&entries).unwrap()).unwrap();
+        let deemak_path = temp_dir.path().join("world.deemak");
+        fs::write(
+            &deemak_path,
+            encrypt_bytes(&compressed, "password", NonceMode::Random).unwrap(),
+        )
+        .unwrap();
+
+        let root = MemFs::root_for(&deemak_path);
+        let mem = MemFs::open_deemak(&deemak_path, &root).unwrap();
+        assert_eq!(root.file_name().unwrap(), "world");
+        assert!(mem.is_file(&root.join(".dir_info/info.json")));
+        assert_eq!(
+            mem.read_to_string(&root.join("subdir2/nested2/file7.txt"))
+                .unwrap(),
+            fs::read_to_string(root_path.join("subdir2/nested2/file7.txt")).unwrap()
+        );
+        // The contents open the same without the file, as in the browser
+        let data = fs::read(&deemak_path).unwrap();
+        let from_bytes =
+            MemFs::open_deemak_bytes(&data, Path::new("picked.deemak"), &root).unwrap();
+        assert_eq!(names(&from_bytes, &root), names(&mem, &root));
+
+        backup_sekai(&mem, "restore", &root).unwrap();
+        mem.remove_file(&root.join("file1.txt")).unwrap();
+        mem.write(&root.join("subdir1/extra.txt"), b"extra")
+            .unwrap();
+        restore_sekai(&mem, "restore", &root).unwrap();
+        assert!(mem.is_file(&root.join("file1.txt")));
+        assert!(!mem.exists(&root.join("subdir1/extra.txt")));
+        assert!(mem.is_file(&root.join(".dir_info/restore_me.deemak")));
+        assert!(!Path::new(&root).exists());
+    }
+
+    #[test]
+    fn test_memfs_rejects_invalid_file() {
+        let (temp_dir, _root_path) = setup_test_dir(false);
+        let path = temp_dir.path().join("broken.deemak");
+        fs::write(&path, b"not a deemak file").unwrap();
+        assert!(MemFs::open_deemak(&path, &MemFs::root_for(&path)).is_err());
+    }
+}
@@ -1,66 +1,168 @@
 use std::env;
-use std::fs::File;
-use std::io::Write;
-use std::path::PathBuf;
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
 
 // === External Crates ===
 use dotenvy::dotenv;
 use rocket::fairing::{Fairing, Info, Kind};
-use rocket::fs::{FileServer, relative};
-use rocket::http::Header;
+use rocket::http::{ContentType, Header, Status};
+use rocket::response::Responder;
 use rocket::serde::{Serialize, json::Json};
-use rocket::{Config, Request, Response, get, options, routes};
+use rocket::{Config, Request, Response, State, get, options, routes};
 
 // === Local Modules ===
-use crate::commands::cmds;
-use crate::utils::auth;
-use crate::utils::globals::get_sekai_dir;
-use crate::utils::{find_root, prompt::DummyPrompter};
+use crate::commands::cmds::CommandResponse;
+use crate::metainfo::event_policy::EventStatus;
+use crate::metainfo::mime::is_inline_safe;
+use crate::session::{Sekai, Session};
+use crate::utils::{assets, auth, log, progress};
+
+/// Whether the server runs in Dev Mode, given to every session.
+struct DevMode(bool);
+
+/// Session of a request, played by the user of the token from the directory the client is in.
+fn request_session(
+    sekai: &Sekai,
+    dev_mode: &DevMode,
+    token: Option<&str>,
+    current_dir: &str,
+) -> Session {
+    let mut session = Session::new(sekai.clone());
+    session.user = token.and_then(auth::user_from_token);
+    session.dev_mode = dev_mode.0;
+    if !current_dir.is_empty() {
+        session.current_dir = PathBuf::from(current_dir);
+    }
+    session
+}
+
+// === Command Execution Endpoint ===
+#[get("/run?<command>&<current_dir>&<token>")]
+fn response(
+    command: &str,
+    current_dir: &str,
+    token: Option<&str>,
+    sekai: &State<Sekai>,
+    dev_mode: &State<DevMode>,
+) -> Json<CommandResponse> {
+    let mut session = request_session(sekai, dev_mode, token, current_dir);
+    Json(session.run(command).into())
+}
+
+// === Asset Endpoint ===
+/// Image or audio object, sent as a download unless its type is safe to show, see
+/// `mime::is_inline_safe`.
+struct AssetFile {
+    mime: String,
+    bytes: Vec<u8>,
+}
 
-// === Data Structures ===
+impl<'r> Responder<'r, 'static> for AssetFile {
+    fn respond_to(self, _: &'r Request<'_>) -> rocket::response::Result<'static> {
+        let mut response = Response::build();
+        response
+            .header(Header::new("X-Content-Type-Options", "nosniff"))
+            .header(Header::new(
+                "Content-Security-Policy",
+                "default-src 'none'; sandbox",
+            ));
+        match ContentType::parse_flexible(&self.mime).filter(|_| is_inline_safe(&self.mime)) {
+            Some(content_type) => response.header(content_type),
+            None => response
+                .header(ContentType::Binary)
+                .header(Header::new("Content-Disposition", "attachment")),
+        };
+        response
+            .sized_body(self.bytes.len(), Cursor::new(self.bytes))
+            .ok()
+    }
+}
+
+/// Serves an image or audio object shown by `read`, with the same checks as `read`.
+/// The path is relative to HOME, as `read` gives it.
+#[get("/asset?<path>&<token>")]
+fn asset(
+    path: &str,
+    token: Option<&str>,
+    sekai: &State<Sekai>,
+    dev_mode: &State<DevMode>,
+) -> Result<AssetFile, Status> {
+    let session = request_session(sekai, dev_mode, token, "");
+    let (mime, bytes) = session.read_asset(path).map_err(|e| {
+        log::log_warning("Asset", &e);
+        Status::Forbidden
+    })?;
+    Ok(AssetFile { mime, bytes })
+}
+
+// === Event Status Endpoint ===
 #[derive(Serialize)]
-struct CommandResponse {
-    output: String,
-    new_current_dir: Option<String>,
+struct EventResponse {
+    status: &'static str,
+    start: Option<String>,
+    end: Option<String>,
+    seconds_to_start: Option<i64>,
+    read_only: bool,
 }
 
-// === Command Execution Endpoint ===
-#[get("/run?<command>&<current_dir>")]
-fn response(command: &str, current_dir: &str) -> Json<CommandResponse> {
-    use cmds::{CommandResult, cmd_manager};
-
-    let world_dir = &get_sekai_dir();
-    let parts: Vec<&str> = command.split_whitespace().collect();
-    let root_dir = find_root::get_home(world_dir).expect("Could not find sekai home directory");
-    let mut current_dir = if current_dir.is_empty() {
-        root_dir.clone()
-    } else {
-        PathBuf::from(current_dir)
+#[get("/event")]
+fn event_status(sekai: &State<Sekai>) -> Json<EventResponse> {
+    let policy = match sekai.event_policy() {
+        Ok(policy) => policy,
+        Err(_) => {
+            return Json(EventResponse {
+                status: "closed",
+                start: None,
+                end: None,
+                seconds_to_start: None,
+                read_only: true,
+            });
+        }
+    };
+    let Some(policy) = policy else {
+        return Json(EventResponse {
+            status: "open",
+            start: None,
+            end: None,
+            seconds_to_start: None,
+            read_only: false,
+        });
+    };
+    let (status, seconds_to_start) = match policy.status() {
+        EventStatus::NotStarted(secs) => ("not_started", Some(secs)),
+        EventStatus::Running => ("running", None),
+        EventStatus::Ended => ("ended", None),
     };
+    Json(EventResponse {
+        status,
+        start: policy.start.clone(),
+        end: policy.end.clone(),
+        seconds_to_start,
+        read_only: status == "ended",
+    })
+}
 
-    let mut prompter = DummyPrompter;
-    match cmd_manager(&parts, &current_dir, &root_dir, &mut prompter) {
-        CommandResult::Output(output) => Json(CommandResponse {
-            output,
-            new_current_dir: None,
-        }),
-        CommandResult::ChangeDirectory(new_dir, message) => Json(CommandResponse {
-            output: message,
-            new_current_dir: Some(new_dir.display().to_string()),
-        }),
-        CommandResult::Clear => Json(CommandResponse {
-            output: "__CLEAR__".to_string(),
-            new_current_dir: None,
-        }),
-        CommandResult::Exit => Json(CommandResponse {
-            output: "__EXIT__".to_string(),
-            new_current_dir: None,
-        }),
-        CommandResult::NotFound => Json(CommandResponse {
-            output: "Command not found. Try `help`.".to_string(),
-            new_current_dir: None,
-        }),
-    }
+// === Web Frontend ===
+/// `config.js` of the frontend, made for the port the server runs on.
+#[get("/config.js")]
+fn web_config(config: &Config) -> (ContentType, String) {
+    (ContentType::JavaScript, assets::web_config_js(config.port))
+}
+
+/// Files of the frontend, compiled in unless overridden, see `utils::assets`.
+#[get("/<path..>", rank = 2)]
+fn web_file(path: PathBuf) -> Result<(ContentType, Vec<u8>), Status> {
+    let path = match path.to_str() {
+        Some("") => "index.html",
+        Some(path) => path,
+        None => return Err(Status::NotFound),
+    };
+    let contents = assets::web_file(path).ok_or(Status::NotFound)?;
+    let content_type = Path::new(path)
+        .extension()
+        .and_then(|ext| ContentType::from_extension(&ext.to_string_lossy()))
+        .unwrap_or(ContentType::Binary);
+    Ok((content_type, contents.into_owned()))
 }
 
 // === CORS Preflight Handler ===
@@ -92,20 +194,13 @@ impl Fairing for Cors {
     }
 }
 
-// === Write Frontend Config ===
-fn generate_config_js(port: u16) {
-    let js_content = format!(r#"export const BACKEND_URL = "http://localhost:{port}";"#);
-
-    let path = "static/config.js";
-    let mut file = File::create(path).expect("Failed to create config.js");
-    file.write_all(js_content.as_bytes())
-        .expect("Failed to write config.js");
-    println!("Generated static/config.js with port {port}");
+// === Rocket Entry Point ===
+/// Serves the Sekai, every request plays it in a session of its own.
+pub fn server(sekai: Sekai, dev_mode: bool) -> Option<Result<(), rocket::Error>> {
+    rocket::execute(launch(sekai, dev_mode))
 }
 
-// === Rocket Entry Point ===
-#[rocket::main]
-pub async fn server() -> Option<Result<(), rocket::Error>> {
+async fn launch(sekai: Sekai, dev_mode: bool) -> Option<Result<(), rocket::Error>> {
     dotenv().ok();
 
     let port: u16 = env::var("BACKEND_PORT")
@@ -113,8 +208,6 @@ pub async fn server() -> Option<Result<(), rocket::Error>> {
         .parse()
         .expect("Invalid port number");
 
-    generate_config_js(port);
-
     let config = Config {
         port,
         ..Config::default()
@@ -122,10 +215,23 @@ pub async fn server() -> Option<Result<(), rocket::Error>> {
 
     let _rocket = rocket::custom(config)
         .attach(Cors)
-        .mount("/", FileServer::from(relative!("static")))
+        .manage(sekai)
+        .manage(DevMode(dev_mode))
+        .mount("/", routes![web_config, web_file])
         .mount(
             "/backend",
-            routes![response, cors_preflight, auth::register, auth::login],
+            routes![
+                response,
+                asset,
+                event_status,
+                cors_preflight,
+                auth::register,
+                auth::login,
+                auth::guest,
+                auth::upgrade,
+                progress::leaderboard,
+                progress::my_progress
+            ],
         )
         .launch()
         .await
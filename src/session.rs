@@ -0,0 +1,146 @@
+use crate::commands::cmds::{CommandResult, cmd_manager};
+use crate::commands::read;
+use crate::metainfo::event_policy::{EventPolicy, read_event_policy};
+use crate::metainfo::info_reader::InfoError;
+use crate::metainfo::mime::{ObjectKind, object_mime};
+use crate::sekai_fs::SekaiFs;
+use crate::utils::globals::UserInfo;
+use crate::utils::prompt::{DummyPrompter, UserPrompter};
+use crate::utils::{log, shell_history};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/*
+SESSION EXPLANATION:
+
+The engine keeps no state of its own, a frontend playing a Sekai holds it in a `Session`:
+- The `Sekai`: its filesystem and HOME. It is shared by all sessions playing the same Sekai.
+- Where the player is, who they are, whether Dev Mode is on, and the commands they ran.
+- The prompter commands ask the player through, `DummyPrompter` unless set.
+
+The GUI has a session per shell, the web server makes one for every request from the token and
+directory the client sends, and tests make as many as they need. So one process can play several
+Sekai's, for several players.
+*/
+
+/// A Sekai being played, see the explanation above.
+#[derive(Clone)]
+pub struct Sekai {
+    pub fs: Arc<dyn SekaiFs>,
+    /// HOME of the Sekai
+    pub root: PathBuf,
+}
+
+impl Sekai {
+    pub fn new(fs: Arc<dyn SekaiFs>, root: PathBuf) -> Self {
+        Self { fs, root }
+    }
+
+    /// Event policy of the Sekai, if it has one. A policy that cannot be read is an error, the
+    /// Sekai is not played without the restrictions it was meant to have. `dev check` reports it.
+    pub fn event_policy(&self) -> Result<Option<EventPolicy>, String> {
+        match read_event_policy(self.fs.as_ref(), &self.root) {
+            Ok(policy) => Ok(policy),
+            Err(InfoError::NotFound(_)) => Ok(None),
+            Err(e) => {
+                log::log_error("EVENT", &format!("Cannot read the event policy: {e}"));
+                Err("The event policy of this Sekai is broken, it cannot be played.".to_string())
+            }
+        }
+    }
+}
+
+/// A player playing a Sekai, see the explanation above.
+pub struct Session {
+    pub sekai: Sekai,
+    pub current_dir: PathBuf,
+    /// None if nobody logged in
+    pub user: Option<UserInfo>,
+    pub dev_mode: bool,
+    /// Commands run, oldest first
+    pub history: Vec<String>,
+    pub prompter: Box<dyn UserPrompter>,
+}
+
+impl Session {
+    /// Session at HOME of the Sekai, with nobody logged in.
+    pub fn new(sekai: Sekai) -> Self {
+        Self {
+            current_dir: sekai.root.clone(),
+            sekai,
+            user: None,
+            dev_mode: false,
+            history: Vec::new(),
+            prompter: Box::new(DummyPrompter),
+        }
+    }
+
+    pub fn fs(&self) -> &dyn SekaiFs {
+        self.sekai.fs.as_ref()
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.sekai.root
+    }
+
+    pub fn username(&self) -> Option<&str> {
+        self.user.as_ref().map(|user| user.get_username())
+    }
+
+    /// Runs the command line, asking the player through the prompter of the session.
+    pub fn run(&mut self, input: &str) -> CommandResult {
+        let mut prompter = std::mem::replace(&mut self.prompter, Box::new(DummyPrompter));
+        let result = self.run_with(input, prompter.as_mut());
+        self.prompter = prompter;
+        result
+    }
+
+    /// Runs the command line with another prompter, for frontends whose prompts need their own
+    /// state. Going somewhere moves the session there.
+    pub fn run_with(&mut self, input: &str, prompter: &mut dyn UserPrompter) -> CommandResult {
+        let input = input.trim();
+        if input.is_empty() {
+            return CommandResult::NotFound;
+        }
+        shell_history::add_to_history(&mut self.history, input);
+        let parts: Vec<&str> = input.split_whitespace().collect();
+        let result = cmd_manager(self, &parts, prompter);
+        if let CommandResult::ChangeDirectory(new_dir, _) = &result {
+            self.current_dir = new_dir.clone();
+        }
+        result
+    }
+
+    /// MIME type and contents of the image or audio object shown by `read`, with the same checks
+    /// as `read`. Frontends fetch it after the command.
+    pub fn read_asset(&self, path: &str) -> Result<(String, Vec<u8>), String> {
+        let fs = self.fs();
+        let root_dir = self.root();
+        if let Some(policy) = self
+            .sekai
+            .event_policy()
+            .map_err(|e| format!("asset: {e}"))?
+        {
+            if policy.is_restricted()
+                && !self
+                    .username()
+                    .is_some_and(|name| policy.is_user_allowed(name))
+            {
+                return Err("asset: The user is not allowed to play this Sekai".to_string());
+            }
+            policy.check_command("read")?;
+        }
+        let file_path = read::resolve_readable(fs, path, root_dir, root_dir)?;
+        let mime = object_mime(fs, &file_path);
+        if matches!(
+            ObjectKind::from_mime(&mime),
+            ObjectKind::Text | ObjectKind::Binary
+        ) {
+            return Err(format!("asset: {path} is not an image or audio object"));
+        }
+        let bytes = fs
+            .read(&file_path)
+            .map_err(|e| format!("asset: Failed to read {path}: {e}"))?;
+        Ok((mime, bytes))
+    }
+}
@@ -0,0 +1,178 @@
+use crate::sekai_fs::MemFs;
+use crate::utils::config;
+use include_dir::{Dir, DirEntry, include_dir};
+use once_cell::sync::Lazy;
+#[cfg(any(feature = "gui", feature = "web"))]
+use std::borrow::Cow;
+#[cfg(any(feature = "gui", feature = "web", test))]
+use std::fs;
+use std::io;
+#[cfg(any(feature = "gui", feature = "web"))]
+use std::path::Component;
+use std::path::{Path, PathBuf};
+
+/*
+ASSETS EXPLANATION:
+
+The web frontend, the fonts and the Tutorial are compiled into the binary, so an installed `deemak`
+runs from any directory. An assets directory can override them without rebuilding, it is taken
+from the `DEEMAK_ASSETS` environment variable, else from `assets_dir` of the config. It has the
+layout of the repository, so a checkout can be used while working on the assets:
+- `static/` for the web frontend. `config.js` is not a file, the server makes it for its port.
+- `fontbook/fonts/ttf/` for the fonts, named by their file name.
+- `_tutorial/` for the Tutorial, which then replaces the compiled one as a whole.
+
+Files missing from the assets directory are taken from the binary. The web frontend is only
+compiled in with the `web` feature, and the fonts with the `gui` feature.
+*/
+
+#[cfg(feature = "web")]
+pub static WEB_DIR: Dir = include_dir!("$CARGO_MANIFEST_DIR/static");
+#[cfg(feature = "gui")]
+pub static FONTS_DIR: Dir = include_dir!("$CARGO_MANIFEST_DIR/fontbook/fonts/ttf");
+pub static TUTORIAL_DIR: Dir = include_dir!("$CARGO_MANIFEST_DIR/_tutorial");
+
+#[cfg(feature = "web")]
+const WEB_PREFIX: &str = "static";
+#[cfg(feature = "gui")]
+const FONTS_PREFIX: &str = "fontbook/fonts/ttf";
+const TUTORIAL_PREFIX: &str = "_tutorial";
+
+#[cfg(feature = "gui")]
+/// Font of the shell and the menus.
+pub const DEFAULT_FONT: &str = "JetBrainsMono-Medium.ttf";
+
+static OVERRIDE_DIR: Lazy<Option<PathBuf>> = Lazy::new(|| {
+    std::env::var("DEEMAK_ASSETS")
+        .ok()
+        .filter(|dir| !dir.is_empty())
+        .or_else(|| config::load_config().assets_dir)
+        .map(PathBuf::from)
+});
+
+/// The assets directory, see the explanation above.
+pub fn override_dir() -> Option<&'static Path> {
+    OVERRIDE_DIR.as_deref()
+}
+
+#[cfg(any(feature = "gui", feature = "web"))]
+/// Whether the path stays inside the directory it is relative to.
+fn is_contained(path: &Path) -> bool {
+    path.components().all(|c| matches!(c, Component::Normal(_)))
+}
+
+#[cfg(any(feature = "gui", feature = "web"))]
+/// Reads the file from the assets directory, or else from the compiled ones.
+fn lookup(
+    base: Option<&Path>,
+    prefix: &str,
+    embedded: &'static Dir,
+    path: &str,
+) -> Option<Cow<'static, [u8]>> {
+    let path = Path::new(path);
+    if !is_contained(path) {
+        return None;
+    }
+    if let Some(base) = base
+        && let Ok(contents) = fs::read(base.join(prefix).join(path))
+    {
+        return Some(Cow::Owned(contents));
+    }
+    embedded
+        .get_file(path)
+        .map(|file| Cow::Borrowed(file.contents()))
+}
+
+#[cfg(feature = "web")]
+/// File of the web frontend, relative to `static/`.
+pub fn web_file(path: &str) -> Option<Cow<'static, [u8]>> {
+    lookup(override_dir(), WEB_PREFIX, &WEB_DIR, path)
+}
+
+#[cfg(feature = "gui")]
+/// The font with the file name.
+pub fn font(name: &str) -> Option<Cow<'static, [u8]>> {
+    lookup(override_dir(), FONTS_PREFIX, &FONTS_DIR, name)
+}
+
+#[cfg(feature = "web")]
+/// `config.js` of the web frontend, pointing it to the server on the port.
+pub fn web_config_js(port: u16) -> String {
+    format!(r#"export const BACKEND_URL = "http://localhost:{port}";"#)
+}
+
+fn embedded_entries(dir: &'static Dir, entries: &mut Vec<(PathBuf, Option<Vec<u8>>)>) {
+    for entry in dir.entries() {
+        match entry {
+            DirEntry::Dir(sub) => {
+                entries.push((sub.path().to_path_buf(), None));
+                embedded_entries(sub, entries);
+            }
+            DirEntry::File(file) => {
+                entries.push((file.path().to_path_buf(), Some(file.contents().to_vec())))
+            }
+        }
+    }
+}
+
+/// The Tutorial in memory at the root, from the assets directory if it has one.
+pub fn tutorial_fs(root: &Path) -> io::Result<MemFs> {
+    tutorial_fs_from(override_dir(), root)
+}
+
+fn tutorial_fs_from(base: Option<&Path>, root: &Path) -> io::Result<MemFs> {
+    if let Some(dir) = base.map(|base| base.join(TUTORIAL_PREFIX))
+        && dir.is_dir()
+    {
+        return MemFs::load_dir(&dir, root);
+    }
+    let mut entries = Vec::new();
+    embedded_entries(&TUTORIAL_DIR, &mut entries);
+    Ok(MemFs::from_entries(root, entries))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sekai_fs::SekaiFs;
+    use tempfile::TempDir;
+
+    #[cfg(feature = "gui")]
+    #[test]
+    fn test_embedded_fonts() {
+        let font = lookup(None, FONTS_PREFIX, &FONTS_DIR, DEFAULT_FONT).unwrap();
+        assert!(ttf_parser::Face::parse(&font, 0).is_ok());
+        assert!(lookup(None, FONTS_PREFIX, &FONTS_DIR, "../static/index.js").is_none());
+    }
+
+    #[cfg(feature = "web")]
+    #[test]
+    fn test_embedded_and_overridden_assets() {
+        let index = lookup(None, WEB_PREFIX, &WEB_DIR, "index.html").unwrap();
+        assert!(String::from_utf8_lossy(&index).contains("config.js"));
+        assert!(lookup(None, WEB_PREFIX, &WEB_DIR, "config.js").is_none());
+        assert!(lookup(None, WEB_PREFIX, &WEB_DIR, "../Cargo.toml").is_none());
+
+        let temp = TempDir::new().unwrap();
+        fs::create_dir_all(temp.path().join(WEB_PREFIX)).unwrap();
+        fs::write(temp.path().join("static/index.css"), "body {}").unwrap();
+        let css = lookup(Some(temp.path()), WEB_PREFIX, &WEB_DIR, "index.css").unwrap();
+        assert_eq!(css.as_ref(), b"body {}");
+        // Files the directory lacks come from the binary
+        assert!(lookup(Some(temp.path()), WEB_PREFIX, &WEB_DIR, "index.js").is_some());
+    }
+
+    #[test]
+    fn test_tutorial_fs() {
+        let root = MemFs::root_for(Path::new("tutorial.deemak"));
+        let tutorial = tutorial_fs_from(None, &root).unwrap();
+        assert!(tutorial.is_file(&root.join(".dir_info/info.json")));
+        assert!(!tutorial.read_dir(&root).unwrap().is_empty());
+
+        let temp = TempDir::new().unwrap();
+        fs::create_dir_all(temp.path().join("_tutorial/.dir_info")).unwrap();
+        fs::write(temp.path().join("_tutorial/.dir_info/info.json"), "{}").unwrap();
+        let tutorial = tutorial_fs_from(Some(temp.path()), &root).unwrap();
+        assert_eq!(tutorial.read_dir(&root).unwrap(), [root.join(".dir_info")]);
+    }
+}
@@ -1,4 +1,13 @@
-use crate::utils::globals::{UserInfo, get_user_info, set_user_info};
+#[cfg(feature = "web")]
+use crate::metainfo::event_policy::check_user_access;
+use crate::session::Sekai;
+use crate::utils::config;
+use crate::utils::globals::UserInfo;
+#[cfg(feature = "web")]
+use crate::utils::user_mgr::set_user_password;
+use crate::utils::user_mgr::{validate_password, validate_username};
+#[cfg(feature = "web")]
+use crate::utils::{log, progress, throttle};
 use chrono::{Duration, Utc};
 use data_encoding::HEXUPPER;
 use jsonwebtoken::{DecodingKey, EncodingKey, Header, Validation, decode, encode};
@@ -6,11 +15,13 @@ use ring::{
     digest, pbkdf2,
     rand::{self, SecureRandom},
 };
-use rocket::form::Form;
-use rocket::serde::{Deserialize, Serialize, json::Json};
-use rocket::{FromForm, post};
+#[cfg(feature = "web")]
+use rocket::{FromForm, State, form::Form, post, serde::json::Json};
+use serde::{Deserialize, Serialize};
 use std::fs::File;
 use std::io::{Read, Write};
+#[cfg(feature = "web")]
+use std::net::IpAddr;
 use std::num::NonZeroU32;
 use std::path::Path;
 
@@ -18,25 +29,56 @@ const USER_FILE: &str = "database.json";
 const ITERATIONS: NonZeroU32 = NonZeroU32::new(100_000).unwrap();
 const CREDENTIAL_LEN: usize = digest::SHA512_OUTPUT_LEN;
 const JWT_SECRET: &[u8] = b"super-secret-key";
+/// Salt length for guest users, short enough to be used as an argon2 salt as well.
+const GUEST_SALT_LEN: usize = 16;
+pub const GUEST_PREFIX: &str = "guest_";
+/// Guests without any progress are removed this long after they were created.
+const GUEST_EXPIRY_SECS: i64 = 24 * 60 * 60;
+/// Tokens of registered users last a play session.
+const TOKEN_HOURS: i64 = 2;
+/// A guest has no password to log in again, so its token is the only way back
+/// to its profile. It lasts long, and a token login hands out a fresh one.
+pub const GUEST_TOKEN_DAYS: i64 = 90;
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct User {
     pub username: String,
     pub salt: String,
     pub password_hash: String,
+    /// Guest users have no password, and can be upgraded to a real account later.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub guest: bool,
+    /// Name the flags of the user are made for, if not the username. Flags are derived from it,
+    /// so a guest renamed when upgrading keeps it to have its flags still valid.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub flag_name: Option<String>,
+    /// Unix timestamp of when the guest was created, unused guests expire after a while.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created: Option<i64>,
+    /// The password was handed out by an admin, like the ones of `auth import`, so the user has
+    /// to choose a new one before playing.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub must_change_password: bool,
 }
-#[derive(FromForm, Deserialize, Serialize)]
+#[cfg_attr(feature = "web", derive(FromForm))]
+#[derive(Deserialize, Serialize)]
 pub struct AuthInput {
     pub username: String,
     pub password: String,
     pub token: Option<String>,
+    /// New password, for users who must change theirs when logging in
+    pub new_password: Option<String>,
 }
 
+#[cfg(feature = "web")]
 #[derive(Serialize)]
 pub struct AuthResponse {
     status: bool,
     message: String,
     token: Option<String>,
+    /// Login needs a `new_password` first, see `User::must_change_password`
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    must_change_password: bool,
 }
 #[derive(Debug, Serialize, Deserialize)]
 struct Claims {
@@ -69,6 +111,20 @@ pub fn hash_password(password: &str) -> Result<(String, String), ring::error::Un
     let mut salt = [0u8; CREDENTIAL_LEN];
     rng.fill(&mut salt)?;
 
+    let salt_hex = HEXUPPER.encode(&salt);
+    let hash = hash_password_with_salt(password, &salt_hex)?;
+    Ok((salt_hex, hash))
+}
+
+/// Hash the password with an existing hex encoded salt, returning the hex encoded hash.
+pub fn hash_password_with_salt(
+    password: &str,
+    salt_hex: &str,
+) -> Result<String, ring::error::Unspecified> {
+    let salt = HEXUPPER
+        .decode(salt_hex.as_bytes())
+        .map_err(|_| ring::error::Unspecified)?;
+
     let mut hash = [0u8; CREDENTIAL_LEN];
     pbkdf2::derive(
         pbkdf2::PBKDF2_HMAC_SHA512,
@@ -77,7 +133,130 @@ pub fn hash_password(password: &str) -> Result<(String, String), ring::error::Un
         password.as_bytes(),
         &mut hash,
     );
-    Ok((HEXUPPER.encode(&salt), HEXUPPER.encode(&hash)))
+    Ok(HEXUPPER.encode(&hash))
+}
+
+/// Creates a new guest user with a locally generated identity and salt.
+/// The salt is what makes the flags user specific, so it is kept when upgrading.
+pub fn create_guest_user(users: &mut Vec<User>) -> Result<User, ring::error::Unspecified> {
+    let rng = rand::SystemRandom::new();
+    let mut salt = [0u8; GUEST_SALT_LEN];
+    rng.fill(&mut salt)?;
+
+    let mut username;
+    loop {
+        let mut id = [0u8; 4];
+        rng.fill(&mut id)?;
+        username = format!("{GUEST_PREFIX}{}", HEXUPPER.encode(&id).to_lowercase());
+        if !users.iter().any(|u| u.username == username) {
+            break;
+        }
+    }
+
+    let guest = User {
+        username,
+        salt: HEXUPPER.encode(&salt),
+        password_hash: String::new(),
+        guest: true,
+        flag_name: None,
+        created: Some(Utc::now().timestamp()),
+        must_change_password: false,
+    };
+    users.push(guest.clone());
+    Ok(guest)
+}
+
+/// Removes the guests which were never used: created over a day before `now`, and without any
+/// progress. Returns the number of guests removed.
+pub fn expire_guests(
+    users: &mut Vec<User>,
+    now: i64,
+    has_progress: impl Fn(&str) -> bool,
+) -> usize {
+    let count = users.len();
+    users.retain(|u| {
+        !(u.guest
+            && u.created
+                .is_some_and(|created| now - created > GUEST_EXPIRY_SECS)
+            && !has_progress(&u.username))
+    });
+    count - users.len()
+}
+
+/// Upgrades a guest user to a real account with a password, optionally renaming it.
+/// The salt and the flag name are kept, so all the progress of the guest remains valid.
+pub fn upgrade_guest_user(
+    users: &mut [User],
+    guest_name: &str,
+    new_username: Option<&str>,
+    password: &str,
+) -> Result<User, String> {
+    if let Some(name) = new_username {
+        validate_username(name)?;
+        if name != guest_name && users.iter().any(|u| u.username == name) {
+            return Err(format!("Username '{name}' already exists"));
+        }
+    }
+    validate_password(password)?;
+    let user = users
+        .iter_mut()
+        .find(|u| u.username == guest_name)
+        .ok_or_else(|| format!("User '{guest_name}' does not exist"))?;
+    if !user.guest {
+        return Err(format!("User '{guest_name}' is not a guest"));
+    }
+
+    user.password_hash = hash_password_with_salt(password, &user.salt)
+        .map_err(|_| "Failed to hash password".to_string())?;
+    user.guest = false;
+    user.created = None;
+    if let Some(name) = new_username
+        && name != guest_name
+    {
+        user.flag_name = Some(guest_name.to_string());
+        user.username = name.to_string();
+    }
+    Ok(user.clone())
+}
+
+/// Create a JWT for the username, valid for `TOKEN_HOURS`.
+fn create_token(username: &str) -> String {
+    create_token_valid_for(username, Duration::hours(TOKEN_HOURS))
+}
+
+/// Create a JWT for a guest, valid for `GUEST_TOKEN_DAYS`.
+pub fn create_guest_token(username: &str) -> String {
+    create_token_valid_for(username, Duration::days(GUEST_TOKEN_DAYS))
+}
+
+fn create_token_valid_for(username: &str, valid_for: Duration) -> String {
+    let expiration = Utc::now()
+        .checked_add_signed(valid_for)
+        .expect("valid timestamp")
+        .timestamp();
+
+    let claims = Claims {
+        sub: username.to_string(),
+        exp: expiration as usize,
+    };
+
+    encode(
+        &Header::default(),
+        &claims,
+        &EncodingKey::from_secret(JWT_SECRET),
+    )
+    .expect("Failed to create token")
+}
+
+/// Get the username of a valid, unexpired token.
+pub fn username_from_token(token: &str) -> Option<String> {
+    decode::<Claims>(
+        token,
+        &DecodingKey::from_secret(JWT_SECRET),
+        &Validation::default(),
+    )
+    .ok()
+    .map(|token_data| token_data.claims.sub)
 }
 
 // Password verification
@@ -101,8 +280,52 @@ pub fn verify_password(password: &String, salt_hex: &str, hash_hex: &str) -> boo
     )
     .is_ok()
 }
+#[cfg(feature = "web")]
+/// Checks the event policy of the Sekai, returning the response if the user cannot play.
+fn event_access_denied(sekai: &Sekai, username: &str) -> Option<Json<AuthResponse>> {
+    let denied = match sekai.event_policy() {
+        Ok(policy) => check_user_access(&policy?, username).err(),
+        Err(message) => Some(message),
+    };
+    denied.map(|message| {
+        Json(AuthResponse {
+            status: false,
+            message,
+            token: None,
+            must_change_password: false,
+        })
+    })
+}
+
+#[cfg(feature = "web")]
 #[post("/register", data = "<input>")]
-pub fn register(input: Form<AuthInput>) -> Json<AuthResponse> {
+pub fn register(
+    input: Form<AuthInput>,
+    client_ip: Option<IpAddr>,
+    sekai: &State<Sekai>,
+) -> Json<AuthResponse> {
+    if let Some(denied) = event_access_denied(sekai, &input.username) {
+        return denied;
+    }
+    if let Err(message) =
+        validate_username(&input.username).and_then(|_| validate_password(&input.password))
+    {
+        return Json(AuthResponse {
+            status: false,
+            message,
+            token: None,
+            must_change_password: false,
+        });
+    }
+    let ip = client_ip.map(|ip| ip.to_string());
+    if let Err(wait) = throttle::try_signup(ip.as_deref()) {
+        return Json(AuthResponse {
+            status: false,
+            message: format!("Too many new accounts. Try again in {wait} seconds."),
+            token: None,
+            must_change_password: false,
+        });
+    }
     let mut users = load_users();
 
     if users.iter().any(|u| u.username == input.username) {
@@ -110,6 +333,7 @@ pub fn register(input: Form<AuthInput>) -> Json<AuthResponse> {
             status: false,
             message: "Username already exists".into(),
             token: None,
+            must_change_password: false,
         });
     }
 
@@ -120,6 +344,7 @@ pub fn register(input: Form<AuthInput>) -> Json<AuthResponse> {
                 status: false,
                 message: "Failed to hash password".into(),
                 token: None,
+                must_change_password: false,
             });
         }
     };
@@ -128,36 +353,31 @@ pub fn register(input: Form<AuthInput>) -> Json<AuthResponse> {
         username: input.username.clone(),
         salt,
         password_hash: hash,
+        guest: false,
+        flag_name: None,
+        created: None,
+        must_change_password: false,
     });
 
     save_users(&users);
 
-    let expiration = Utc::now()
-        .checked_add_signed(Duration::hours(2))
-        .expect("valid timestamp")
-        .timestamp();
-
-    let claims = Claims {
-        sub: input.username.clone(),
-        exp: expiration as usize,
-    };
-
-    let token = encode(
-        &Header::default(),
-        &claims,
-        &EncodingKey::from_secret(JWT_SECRET),
-    )
-    .expect("Failed to create token");
+    let token = create_token(&input.username);
 
     Json(AuthResponse {
         status: true,
         message: "User registered successfully".into(),
         token: Some(token),
+        must_change_password: false,
     })
 }
 
+#[cfg(feature = "web")]
 #[post("/login", data = "<input>")]
-pub fn login(input: Form<AuthInput>) -> Json<AuthResponse> {
+pub fn login(
+    input: Form<AuthInput>,
+    client_ip: Option<IpAddr>,
+    sekai: &State<Sekai>,
+) -> Json<AuthResponse> {
     let users = load_users();
 
     if let Some(token) = &input.token {
@@ -167,10 +387,22 @@ pub fn login(input: Form<AuthInput>) -> Json<AuthResponse> {
             &Validation::default(),
         ) {
             Ok(token_data) => {
+                let username = token_data.claims.sub;
+                if let Some(denied) = event_access_denied(sekai, &username) {
+                    return denied;
+                }
+                // A returning guest gets a fresh token, so it is never locked out of its profile
+                let is_guest = users.iter().any(|u| u.username == username && u.guest);
+                let token = if is_guest {
+                    create_guest_token(&username)
+                } else {
+                    token.clone()
+                };
                 return Json(AuthResponse {
                     status: true,
-                    message: format!("Token valid. Welcome, {}!", token_data.claims.sub),
-                    token: Some(token.clone()),
+                    message: format!("Token valid. Welcome, {username}!"),
+                    token: Some(token),
+                    must_change_password: false,
                 });
             }
             Err(err) => {
@@ -178,50 +410,47 @@ pub fn login(input: Form<AuthInput>) -> Json<AuthResponse> {
                     status: false,
                     message: format!("Invalid token: {err}"),
                     token: None,
+                    must_change_password: false,
                 });
             }
         }
     }
 
     if input.token.is_none() {
+        if let Some(denied) = event_access_denied(sekai, &input.username) {
+            return denied;
+        }
+        let ip = client_ip.map(|ip| ip.to_string());
+        let keys = throttle::login_keys(ip.as_deref(), &input.username);
+        if let Err(wait) = throttle::login_attempt(&keys) {
+            return Json(AuthResponse {
+                status: false,
+                message: format!("Too many failed attempts. Try again in {wait} seconds."),
+                token: None,
+                must_change_password: false,
+            });
+        }
         if let Some(user) = users.iter().find(|u| u.username == input.username) {
             if verify_password(&input.password, &user.salt, &user.password_hash) {
-                let expiration = Utc::now()
-                    .checked_add_signed(Duration::hours(2))
-                    .expect("valid timestamp")
-                    .timestamp();
-
-                let claims = Claims {
-                    sub: user.username.clone(),
-                    exp: expiration as usize,
-                };
-
-                let token = encode(
-                    &Header::default(),
-                    &claims,
-                    &EncodingKey::from_secret(JWT_SECRET),
-                )
-                .expect("Failed to create token");
-
-                // Create and set the global UserInfo
-                let mut user_info = UserInfo::new(
-                    user.username.clone(),
-                    user.salt.clone(),
-                    user.password_hash.clone(),
-                );
-                user_info.authenticate();
-                set_user_info(user_info).ok(); // Set the global user info, ignoring error if already set
-
+                throttle::login_succeeded(&keys);
+                if user.must_change_password {
+                    let username = user.username.clone();
+                    return change_password_on_login(users, &username, &input);
+                }
+                // The token is the session of the user, sent with every command
+                let token = create_token(&user.username);
                 return Json(AuthResponse {
                     status: true,
                     message: "Login successful".into(),
                     token: Some(token),
+                    must_change_password: false,
                 });
             } else {
                 return Json(AuthResponse {
                     status: false,
                     message: "Invalid password".into(),
                     token: None,
+                    must_change_password: false,
                 });
             }
         }
@@ -230,59 +459,206 @@ pub fn login(input: Form<AuthInput>) -> Json<AuthResponse> {
             status: false,
             message: "User not found".into(),
             token: None,
+            must_change_password: false,
         });
     }
     Json(AuthResponse {
         status: false,
         message: "Invalid request".into(),
         token: None,
+        must_change_password: false,
     })
 }
 
-// UserInfo integration functions
-/// Get current authenticated user info
-pub fn get_current_user() -> Option<&'static UserInfo> {
-    get_user_info()
-}
-
-/// Check if user is currently authenticated
-pub fn is_user_authenticated() -> bool {
-    if let Some(user_info) = get_user_info() {
-        user_info.is_authenticated()
+#[cfg(feature = "web")]
+/// Login of a user who must change the password first, done once a new password is sent.
+fn change_password_on_login(
+    mut users: Vec<User>,
+    username: &str,
+    input: &AuthInput,
+) -> Json<AuthResponse> {
+    let Some(new_password) = input.new_password.as_deref().filter(|p| !p.is_empty()) else {
+        return Json(AuthResponse {
+            status: false,
+            message: "Choose a new password to play".into(),
+            token: None,
+            must_change_password: true,
+        });
+    };
+    let changed = if new_password == input.password {
+        Err("The new password must differ from the one you were given".to_string())
     } else {
-        false
+        set_user_password(&mut users, username, new_password)
+    };
+    if let Err(message) = changed {
+        return Json(AuthResponse {
+            status: false,
+            message,
+            token: None,
+            must_change_password: true,
+        });
     }
+    save_users(&users);
+    Json(AuthResponse {
+        status: true,
+        message: "Password changed, login successful".into(),
+        token: Some(create_token(username)),
+        must_change_password: false,
+    })
 }
 
-/// Get current user's username safely
-pub fn get_current_username() -> Option<&'static str> {
-    get_user_info().map(|user| user.get_username())
+#[cfg(feature = "web")]
+#[post("/guest")]
+pub fn guest(client_ip: Option<IpAddr>, sekai: &State<Sekai>) -> Json<AuthResponse> {
+    if let Err(message) = guests_allowed(sekai) {
+        return Json(AuthResponse {
+            status: false,
+            message,
+            token: None,
+            must_change_password: false,
+        });
+    }
+    let ip = client_ip.map(|ip| ip.to_string());
+    if let Err(wait) = throttle::try_signup(ip.as_deref()) {
+        return Json(AuthResponse {
+            status: false,
+            message: format!("Too many new profiles. Try again in {wait} seconds."),
+            token: None,
+            must_change_password: false,
+        });
+    }
+    let mut users = load_users();
+    // Without the progress every guest looks unused, so none are removed then
+    if let Ok(progress) = progress::read_progress() {
+        expire_guests(&mut users, Utc::now().timestamp(), |name| {
+            !progress.user_events(name).is_empty()
+        });
+    }
+    let guest = match create_guest_user(&mut users) {
+        Ok(g) => g,
+        Err(_) => {
+            return Json(AuthResponse {
+                status: false,
+                message: "Failed to create guest profile".into(),
+                token: None,
+                must_change_password: false,
+            });
+        }
+    };
+    save_users(&users);
+
+    Json(AuthResponse {
+        status: true,
+        message: format!("Playing as guest {}", guest.username),
+        token: Some(create_guest_token(&guest.username)),
+        must_change_password: false,
+    })
 }
 
-/// Get user session duration
-pub fn get_session_duration() -> Option<std::time::Duration> {
-    get_user_info()?.get_login_duration()
+#[cfg(feature = "web")]
+/// Upgrade the guest of the token to a real account, keeping its progress.
+#[post("/upgrade", data = "<input>")]
+pub fn upgrade(input: Form<AuthInput>, sekai: &State<Sekai>) -> Json<AuthResponse> {
+    let guest_name = match input.token.as_deref().and_then(username_from_token) {
+        Some(name) => name,
+        None => {
+            return Json(AuthResponse {
+                status: false,
+                message: "Invalid or missing guest token".into(),
+                token: None,
+                must_change_password: false,
+            });
+        }
+    };
+
+    let new_username = (!input.username.is_empty()).then_some(input.username.as_str());
+    if let Some(denied) = event_access_denied(sekai, new_username.unwrap_or(&guest_name)) {
+        return denied;
+    }
+    let mut users = load_users();
+    match upgrade_guest_user(&mut users, &guest_name, new_username, &input.password) {
+        Ok(user) => {
+            save_users(&users);
+            if let Err(e) = progress::rename_user_progress(&guest_name, &user.username) {
+                log::log_error(
+                    "AUTH",
+                    &format!("Failed to move the progress of {guest_name}: {e}"),
+                );
+            }
+            Json(AuthResponse {
+                status: true,
+                message: format!("Guest upgraded to {}", user.username),
+                token: Some(create_token(&user.username)),
+                must_change_password: false,
+            })
+        }
+        Err(e) => Json(AuthResponse {
+            status: false,
+            message: e,
+            token: None,
+            must_change_password: false,
+        }),
+    }
 }
 
-/// Logout current user
-pub fn logout_user() {
-    // For now, we create a new  UserInfo to "logout"
-    let default_user = UserInfo::default();
-    let _ = set_user_info(default_user);
+/// The user of a valid token, logged in, for a session of the web server.
+pub fn user_from_token(token: &str) -> Option<UserInfo> {
+    let username = username_from_token(token)?;
+    let user = load_users().into_iter().find(|u| u.username == username)?;
+    Some(authenticate_user(&user))
 }
 
 /// Create a UserInfo from existing user data
 pub fn create_user_info_from_user(user: &User) -> UserInfo {
-    UserInfo::new(
+    let mut user_info = UserInfo::new(
         user.username.clone(),
         user.salt.clone(),
         user.password_hash.clone(),
-    )
+    );
+    user_info.is_guest = user.guest;
+    if let Some(flag_name) = &user.flag_name {
+        user_info.flag_name = flag_name.clone();
+    }
+    user_info
+}
+
+/// Guests cannot play a Sekai restricted to some users, nor one with a broken event policy.
+fn guests_allowed(sekai: &Sekai) -> Result<(), String> {
+    if sekai.event_policy()?.is_some_and(|p| p.is_restricted()) {
+        return Err("Guests are not allowed to play this Sekai".to_string());
+    }
+    Ok(())
+}
+
+/// Log in with the local guest profile, creating it on first use.
+/// The guest is remembered in the config, so progress carries over between runs.
+pub fn login_as_guest(sekai: &Sekai) -> Result<UserInfo, String> {
+    guests_allowed(sekai)?;
+    let mut cfg = config::load_config();
+    let mut users = load_users();
+    let existing = cfg
+        .guest_user
+        .as_ref()
+        .and_then(|name| users.iter().find(|u| u.guest && u.username == *name))
+        .cloned();
+
+    let guest = match existing {
+        Some(guest) => guest,
+        None => {
+            let guest = create_guest_user(&mut users)
+                .map_err(|_| "Failed to create guest profile".to_string())?;
+            save_users(&users);
+            cfg.guest_user = Some(guest.username.clone());
+            config::save_config(&cfg);
+            guest
+        }
+    };
+    Ok(authenticate_user(&guest))
 }
 
-/// Authenticate a user and set global UserInfo
-pub fn authenticate_user(user: &User) -> Result<(), UserInfo> {
+/// The UserInfo of the user, logged in now
+pub fn authenticate_user(user: &User) -> UserInfo {
     let mut user_info = create_user_info_from_user(user);
     user_info.authenticate();
-    set_user_info(user_info)
+    user_info
 }
@@ -2,11 +2,39 @@ use crate::epr_log_error;
 use crate::utils::log;
 use std::collections::HashSet;
 use std::fs::{self, read_dir};
-use std::io::Error;
-use std::path::Path;
+use std::io::{self, Error};
+use std::path::{Path, PathBuf};
+#[cfg(not(target_arch = "wasm32"))]
+use std::sync::OnceLock;
+#[cfg(not(target_arch = "wasm32"))]
+use std::sync::atomic::{AtomicI32, Ordering};
+use std::sync::{Mutex, Once};
 
 pub const LOCATIONS: [&str; 2] = ["/tmp", "/var/tmp"];
 pub const DEEMAK_PREFIX: [&str; 1] = ["deemak"];
+/// File in the temporary directory of an instance, holding the PID of the instance.
+pub const LOCK_FILE: &str = "deemak.lock";
+
+/*
+TEMPORARY FILES EXPLANATION:
+
+Every running instance of Deemak (the GUI, the web server or a dev command) keeps its temporary
+files in its own directory, `deemak_<pid>_<random>` in the system temporary directory. It is made
+on first use, with a lockfile holding the PID of the instance.
+
+- `generate_temp_path` gives a `TempDir` inside it, which deletes itself when dropped.
+- When the instance exits, normally through `exit_deemak` or by SIGINT/SIGTERM/SIGHUP, only its
+  own directory is deleted, so other running instances keep their files. If something else
+  handles the signal too, like the web server which shuts down gracefully on SIGINT and SIGTERM,
+  the directory is deleted once `main` returns instead.
+- Directories left behind by crashed instances are swept on exit, but only if the PID in their
+  lockfile is no longer running. Directories without a lockfile are never swept, since their
+  owner cannot be known.
+*/
+
+/// Temporary directory of this instance, made on first use and removed on exit.
+static INSTANCE_DIR: Mutex<Option<PathBuf>> = Mutex::new(None);
+static SIGNAL_HANDLERS: Once = Once::new();
 
 /// Collects all relevant cleanup locations, handling macOS specifics.
 pub fn get_all_cleanup_locations() -> Vec<String> {
@@ -17,8 +45,10 @@ pub fn get_all_cleanup_locations() -> Vec<String> {
         all_locs.insert(s.to_string());
     });
 
-    // Add the standard system temporary directory
-    all_locs.insert(std::env::temp_dir().to_string_lossy().to_string());
+    // Add the standard system temporary directory, the browser has none
+    if !cfg!(target_arch = "wasm32") {
+        all_locs.insert(std::env::temp_dir().to_string_lossy().to_string());
+    }
 
     // On macOS, symlinked /tmp and /var/tmp point to /private/tmp and /private/var/tmp
     // We add these canonical paths to ensure cleanup.
@@ -71,90 +101,264 @@ fn del_obj(path: &Path) -> Result<(), String> {
     })
 }
 
-/// Clean up all the temporary files created during deemak.
-fn cleanup_at_location(dir: &str) -> Result<(), String> {
-    let path = Path::new(dir);
+/// Temporary directory of this instance, see the explanation above.
+pub fn instance_dir() -> io::Result<PathBuf> {
+    let mut instance_dir = INSTANCE_DIR.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(dir) = instance_dir.as_ref()
+        && dir.is_dir()
+    {
+        return Ok(dir.clone());
+    }
+    let pid = std::process::id();
+    let dir = tempfile::Builder::new()
+        .prefix(&format!("{}_{pid}_", DEEMAK_PREFIX[0]))
+        .tempdir()?
+        .keep();
+    fs::write(dir.join(LOCK_FILE), pid.to_string())?;
+    log::log_info(
+        "Cleanup",
+        &format!("Temporary directory of this instance: {}", dir.display()),
+    );
+    *instance_dir = Some(dir.clone());
+    drop(instance_dir);
+    install_signal_handlers();
+    Ok(dir)
+}
 
-    for entry in read_dir(path).map_err(|e| {
-        let msg = format!("Failed to read directory {dir}: {e}");
-        log_cleanup_warning("read directory", dir, &e);
-        msg
-    })? {
-        let entry = entry.map_err(|e| {
-            let msg = format!("Failed to read directory entry in {dir}: {e}");
-            log_cleanup_warning("read directory entry", dir, &e);
-            msg
-        })?;
+/// Removes the temporary directory of this instance, if it was made.
+pub fn cleanup_instance() -> Result<(), String> {
+    let dir = INSTANCE_DIR
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .take();
+    match dir {
+        Some(dir) if dir.exists() => del_obj(&dir),
+        _ => Ok(()),
+    }
+}
 
-        let file_name = entry.file_name();
-        let file_name_str = file_name.to_string_lossy();
+/// Whether a process with the PID is running.
+#[cfg(not(target_arch = "wasm32"))]
+fn pid_alive(pid: u32) -> bool {
+    let Ok(pid) = libc::pid_t::try_from(pid) else {
+        return false;
+    };
+    if pid <= 0 {
+        return false;
+    }
+    // Signal 0 only checks the process, EPERM means it runs as another user
+    let running = unsafe { libc::kill(pid, 0) } == 0;
+    running || io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
+
+/// The browser runs a single instance, and it is this one.
+#[cfg(target_arch = "wasm32")]
+fn pid_alive(pid: u32) -> bool {
+    pid == std::process::id()
+}
 
-        if DEEMAK_PREFIX
+/// PID of the instance owning the temporary directory, None if it has no lockfile.
+fn owner_pid(dir: &Path) -> Option<u32> {
+    fs::read_to_string(dir.join(LOCK_FILE))
+        .ok()?
+        .trim()
+        .parse()
+        .ok()
+}
+
+/// Removes the temporary directories of Deemak instances which are no longer running, returning
+/// how many were removed.
+pub fn sweep_stale_at(dir: &Path) -> Result<usize, String> {
+    let mut removed = 0;
+    for entry in read_dir(dir).map_err(|e| {
+        log_cleanup_warning("read directory", &dir.display().to_string(), &e);
+        format!("Failed to read directory {}: {e}", dir.display())
+    })? {
+        let Ok(entry) = entry else { continue };
+        let path = entry.path();
+        let file_name = entry.file_name();
+        if !DEEMAK_PREFIX
             .iter()
-            .any(|prefix| file_name_str.starts_with(prefix))
+            .any(|prefix| file_name.to_string_lossy().starts_with(prefix))
+            || !path.is_dir()
         {
-            let path_to_remove = entry.path();
-            if let Err(e) = del_obj(&path_to_remove) {
-                let _ = e;
+            continue;
+        }
+        match owner_pid(&path) {
+            Some(pid) if pid != std::process::id() && !pid_alive(pid) && del_obj(&path).is_ok() => {
+                removed += 1;
             }
+            _ => {}
         }
     }
-    Ok(())
+    Ok(removed)
 }
 
-/// Clean up only `.tmp.zlib` in the current working directory, just in case present
-fn clean_cwd() -> Result<(), String> {
-    let cwd = std::env::current_dir().map_err(|e| {
-        log_cleanup_warning("get current directory", "current", &e);
-        format!("Failed to get current working directory: {e}")
-    })?;
+/// Removes this instance's temporary files, and those of crashed instances.
+pub fn cleanup_deemak() -> Result<(), String> {
+    let result = cleanup_instance();
 
-    for entry in read_dir(&cwd).map_err(|e| {
-        log_cleanup_warning("read current directory", "current", &e);
-        format!("Failed to read current directory: {e}")
-    })? {
-        let entry = entry.map_err(|e| {
-            log_cleanup_warning("read directory entry", "current", &e);
-            format!("Failed to read directory entry in current directory: {e}")
-        })?;
-
-        let path_to_remove = entry.path();
-        let file_name_str = path_to_remove
-            .file_name()
-            .map(|s| s.to_string_lossy())
-            .unwrap_or_default();
-
-        if file_name_str.ends_with(".tmp.zlib")
-            && path_to_remove.is_file()
-            && let Err(e) = fs::remove_file(&path_to_remove)
-        {
-            log_cleanup_warning("remove file", &file_name_str, &e);
+    // Clean up all specified locations
+    for location in get_all_cleanup_locations().iter() {
+        if let Err(e) = sweep_stale_at(Path::new(location)) {
+            log_cleanup_warning("cleanup at location", location, &Error::other(e));
         }
     }
-    Ok(())
+    result
 }
 
-/// Clean up all temporary files in all locations.
-pub fn cleanup_deemak() -> Result<(), String> {
-    // clean up the current working directory first
-    if let Err(e) = clean_cwd() {
-        log_cleanup_warning(
-            "cleanup current working directory",
-            "current",
-            &Error::other(e),
-        );
+/// Removes the temporary directory of this instance when dropped, for exits which return from
+/// `main` instead of going through `exit_deemak`.
+pub struct InstanceGuard(());
+
+impl InstanceGuard {
+    /// Also installs the signal handlers, so that the instance is cleaned up when interrupted.
+    pub fn install() -> Self {
+        install_signal_handlers();
+        InstanceGuard(())
     }
+}
 
-    let all_locs = get_all_cleanup_locations();
-    // Clean up all specified locations
-    for location in all_locs.iter() {
-        if let Err(e) = cleanup_at_location(location) {
-            log_cleanup_warning("cleanup at location", location, &Error::other(e));
+impl Drop for InstanceGuard {
+    fn drop(&mut self) {
+        if let Err(e) = cleanup_instance() {
+            log::log_warning("Cleanup", &e);
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+/// Write end of the pipe the signal handler writes to, -1 until the handlers are installed.
+static SIGNAL_PIPE: AtomicI32 = AtomicI32::new(-1);
+
+#[cfg(not(target_arch = "wasm32"))]
+const CLEANUP_SIGNALS: [libc::c_int; 3] = [libc::SIGINT, libc::SIGTERM, libc::SIGHUP];
+
+#[cfg(not(target_arch = "wasm32"))]
+/// Handlers of the signals from before ours were installed, which ours calls after it.
+static PREVIOUS_ACTIONS: OnceLock<Vec<(libc::c_int, libc::sigaction)>> = OnceLock::new();
+
+#[cfg(not(target_arch = "wasm32"))]
+extern "C" fn on_signal(
+    signal: libc::c_int,
+    info: *mut libc::siginfo_t,
+    context: *mut libc::c_void,
+) {
+    // Only async-signal-safe calls here, the watcher thread does the cleanup
+    let fd = SIGNAL_PIPE.load(Ordering::Relaxed);
+    if fd >= 0 {
+        let byte = signal as u8;
+        unsafe { libc::write(fd, (&byte as *const u8).cast(), 1) };
+    }
+    if let Some(action) = previous_handler(signal) {
+        unsafe {
+            if action.sa_flags & libc::SA_SIGINFO != 0 {
+                let handler: extern "C" fn(libc::c_int, *mut libc::siginfo_t, *mut libc::c_void) =
+                    std::mem::transmute(action.sa_sigaction);
+                handler(signal, info, context);
+            } else {
+                let handler: extern "C" fn(libc::c_int) = std::mem::transmute(action.sa_sigaction);
+                handler(signal);
+            }
         }
     }
-    Ok(())
 }
 
+/// Handler of the signal from before ours, unless it was the default or ignored.
+#[cfg(not(target_arch = "wasm32"))]
+fn previous_handler(signal: libc::c_int) -> Option<&'static libc::sigaction> {
+    PREVIOUS_ACTIONS
+        .get()?
+        .iter()
+        .find(|(s, action)| {
+            *s == signal
+                && action.sa_sigaction != libc::SIG_DFL
+                && action.sa_sigaction != libc::SIG_IGN
+        })
+        .map(|(_, action)| action)
+}
+
+/// Whether another handler of the signal was installed before or after ours, like the one of the
+/// web server. Then that handler shuts the program down, and the instance is cleaned up as it
+/// returns from `main`.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn signal_handled_elsewhere(signal: libc::c_int) -> bool {
+    if previous_handler(signal).is_some() {
+        return true;
+    }
+    let mut current: libc::sigaction = unsafe { std::mem::zeroed() };
+    unsafe { libc::sigaction(signal, std::ptr::null(), &mut current) };
+    current.sa_sigaction != on_signal as *const () as libc::sighandler_t
+}
+
+/// Removes the temporary directory of this instance on SIGINT, SIGTERM and SIGHUP, then exits,
+/// unless another handler takes care of the signal, see `signal_handled_elsewhere`.
+/// The handler wakes a thread through a pipe, which does the cleanup outside of the handler.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn install_signal_handlers() {
+    SIGNAL_HANDLERS.call_once(|| {
+        let mut fds = [0 as libc::c_int; 2];
+        if unsafe { libc::pipe(fds.as_mut_ptr()) } != 0 {
+            log::log_warning("Cleanup", "Failed to create the signal pipe");
+            return;
+        }
+        let [read_fd, write_fd] = fds;
+        SIGNAL_PIPE.store(write_fd, Ordering::Relaxed);
+
+        let spawned = std::thread::Builder::new()
+            .name("deemak-signals".to_string())
+            .spawn(move || {
+                let mut byte = 0u8;
+                loop {
+                    let read = unsafe { libc::read(read_fd, (&mut byte as *mut u8).cast(), 1) };
+                    if read == 1 {
+                        if signal_handled_elsewhere(libc::c_int::from(byte)) {
+                            log::log_info(
+                                "Cleanup",
+                                &format!("Received signal {byte}, leaving it to its handler"),
+                            );
+                            continue;
+                        }
+                        break;
+                    }
+                    if read == 0 || io::Error::last_os_error().kind() != io::ErrorKind::Interrupted
+                    {
+                        return;
+                    }
+                }
+                log::log_info("Cleanup", &format!("Received signal {byte}, cleaning up"));
+                if let Err(e) = cleanup_instance() {
+                    eprintln!("Error during cleanup: {e}");
+                }
+                std::process::exit(128 + i32::from(byte));
+            });
+        if let Err(e) = spawned {
+            log::log_warning("Cleanup", &format!("Failed to start signal thread: {e}"));
+            return;
+        }
+
+        let mut action: libc::sigaction = unsafe { std::mem::zeroed() };
+        action.sa_sigaction = on_signal as *const () as libc::sighandler_t;
+        action.sa_flags = libc::SA_SIGINFO | libc::SA_RESTART;
+        unsafe { libc::sigemptyset(&mut action.sa_mask) };
+        let mut previous = Vec::new();
+        for signal in CLEANUP_SIGNALS {
+            let mut old: libc::sigaction = unsafe { std::mem::zeroed() };
+            unsafe { libc::sigaction(signal, std::ptr::null(), &mut old) };
+            previous.push((signal, old));
+        }
+        // Set before installing, so the handler never misses a previous one
+        let _ = PREVIOUS_ACTIONS.set(previous);
+        for signal in CLEANUP_SIGNALS {
+            unsafe { libc::sigaction(signal, &action, std::ptr::null_mut()) };
+        }
+    });
+}
+
+/// There are no signals in the browser.
+#[cfg(target_arch = "wasm32")]
+pub fn install_signal_handlers() {}
+
 /// Cleanup all temporary files and exit the DEEMAK shell with the specified exit code.
 pub fn exit_deemak(code: i32) -> ! {
     log::log_info("Application", "Exiting DEEMAK Shell");
@@ -1,11 +1,81 @@
+#[cfg(feature = "gui")]
+use crate::keymap::Keymap;
 use serde::{Deserialize, Serialize};
 use std::fs::{self, File};
 use std::io::{Read, Write};
 use std::path::PathBuf;
 
-#[derive(Serialize, Deserialize, Default)]
+/// Share of the window width used by the terminal, the rest is the sidebar.
+pub const DEFAULT_SPLIT_RATIO: f32 = 2.0 / 3.0;
+pub const SPLIT_RATIO_RANGE: (f32, f32) = (0.5, 0.85);
+pub const FONT_SIZE_RANGE: (f32, f32) = (12.0, 48.0);
+
+#[derive(Serialize, Deserialize)]
 pub struct DeemakConfig {
     pub font_index: usize,
+    /// Username of the local guest profile, reused across runs.
+    #[serde(default)]
+    pub guest_user: Option<String>,
+    /// Name of the theme, see `utils::theme`.
+    #[serde(default = "default_theme")]
+    pub theme: String,
+    /// Font size of the shell, None to size it by the monitor width.
+    #[serde(default)]
+    pub font_size: Option<f32>,
+    #[serde(default = "default_split_ratio")]
+    pub term_split_ratio: f32,
+    /// Keys of the GUI, see `keymap`.
+    #[cfg(feature = "gui")]
+    #[serde(default)]
+    pub keymap: Keymap,
+    /// Kept as it is by builds without the GUI, so saving the config does not drop it.
+    #[cfg(not(feature = "gui"))]
+    #[serde(default, skip_serializing_if = "serde_json::Value::is_null")]
+    pub keymap: serde_json::Value,
+    /// Fonts tried before the bundled and system ones for characters the shell font lacks,
+    /// see `gui_fonts`.
+    #[serde(default)]
+    pub fallback_fonts: Vec<String>,
+    /// Directory overriding the compiled web frontend, fonts and Tutorial, see `utils::assets`.
+    #[serde(default)]
+    pub assets_dir: Option<String>,
+}
+
+fn default_theme() -> String {
+    "dark".to_string()
+}
+
+fn default_split_ratio() -> f32 {
+    DEFAULT_SPLIT_RATIO
+}
+
+impl Default for DeemakConfig {
+    fn default() -> Self {
+        Self {
+            font_index: 0,
+            guest_user: None,
+            theme: default_theme(),
+            font_size: None,
+            term_split_ratio: DEFAULT_SPLIT_RATIO,
+            keymap: Default::default(),
+            fallback_fonts: Vec::new(),
+            assets_dir: None,
+        }
+    }
+}
+
+impl DeemakConfig {
+    /// Font size of the shell, for a monitor of the given width.
+    pub fn shell_font_size(&self, monitor_width: i32) -> f32 {
+        self.font_size
+            .unwrap_or(monitor_width as f32 / 73.5)
+            .clamp(FONT_SIZE_RANGE.0, FONT_SIZE_RANGE.1)
+    }
+
+    pub fn split_ratio(&self) -> f32 {
+        self.term_split_ratio
+            .clamp(SPLIT_RATIO_RANGE.0, SPLIT_RATIO_RANGE.1)
+    }
 }
 
 pub fn get_config_path() -> PathBuf {
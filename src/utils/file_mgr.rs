@@ -1,7 +1,8 @@
-use super::{globals::set_sekai_dir, log};
 use crate::metainfo::valid_sekai::validate_or_create_sekai;
-use crate::rns::{passlock::check_dmk_magic, restore_comp::generate_temp_path};
-use crate::{DEV_MODE, epr_log_error, fatal_error};
+use crate::rns::passlock::{self, check_dmk_magic};
+use crate::sekai_fs::DiskFs;
+use crate::{epr_log_error, fatal_error};
+use std::io::IsTerminal;
 use std::path::{Path, PathBuf};
 
 #[derive(Debug, Clone, Default)]
@@ -10,7 +11,6 @@ pub struct DeemakSekaiMgr {
     pub password: Option<String>,
     pub is_directory: bool,
     pub valid_sekai: bool,
-    pub temp_location: PathBuf,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -47,8 +47,16 @@ impl SekaiOperation {
     }
 }
 
-/// Input password, optionally confirming it if `confirm` is true.
+/// Input password, optionally confirming it if `confirm` is true. If stdin is not a terminal,
+/// the password is its first line instead, so scripts never pass it as an argument.
 pub fn input_file_password(confirm: bool) -> String {
+    if !std::io::stdin().is_terminal() {
+        let mut line = String::new();
+        if let Err(e) = std::io::stdin().read_line(&mut line) {
+            fatal_error!("SEKAI", "Password input failed: {e}");
+        }
+        return line.trim_end_matches(['\r', '\n']).to_string();
+    }
     let mut pwd = dialoguer::Password::new().with_prompt("Enter password");
     if confirm {
         pwd = pwd.with_confirmation("Confirm password", "Passwords don't match!");
@@ -68,7 +76,6 @@ impl DeemakSekaiMgr {
             password,
             is_directory: sekai_path.is_dir(),
             valid_sekai: Self::check_validity(&sekai_path),
-            temp_location: Self::get_absolute_path(&sekai_path),
         }
     }
 
@@ -86,10 +93,8 @@ impl DeemakSekaiMgr {
             // File extension should be `.deemak`
             check_dmk_magic(&path).unwrap_or(false)
         } else {
-            let home_ok = validate_or_create_sekai(&path, true);
-            set_sekai_dir(path.clone()); // This line HAS to be sandwiched between the two calls
-            let rest_ok = validate_or_create_sekai(&path, false);
-            home_ok && rest_ok
+            validate_or_create_sekai(&DiskFs, &path, true)
+                && validate_or_create_sekai(&DiskFs, &path, false)
         }
     }
 
@@ -105,8 +110,7 @@ impl DeemakSekaiMgr {
     }
 
     /// Manage if the sekai_path is a file or a directory.
-    pub fn oper_allowed(&mut self) -> (Vec<SekaiOperation>, String) {
-        let dev_mode = *DEV_MODE.get().unwrap_or(&false);
+    pub fn oper_allowed(&mut self, dev_mode: bool) -> (Vec<SekaiOperation>, String) {
         println!("{dev_mode:?}");
         let mut allowed_opers: Vec<SekaiOperation> = Vec::new();
         let mut crit_msg = String::new();
@@ -124,7 +128,6 @@ impl DeemakSekaiMgr {
                 crit_msg += "Restore ";
                 allowed_opers.push(SekaiOperation::Restore);
                 if !dev_mode {
-                    self.temp_location = generate_temp_path("sekai_wd");
                     crit_msg += "Play for User ";
                     allowed_opers.push(SekaiOperation::Play);
                 }
@@ -139,48 +142,24 @@ impl DeemakSekaiMgr {
         (allowed_opers, crit_msg)
     }
 
-    /// Set Xattr password to the file
+    /// Sets the password to encrypt the Sekai with.
     /// Sets a random password if the provided password is not provided
-    pub fn set_password(&mut self, get_password: bool) -> Result<(), String> {
+    pub fn set_password(&mut self, get_password: bool) {
         if get_password {
             self.password = input_file_password(true).into();
         }
-
-        log::log_debug(
-            "Xattr Set",
-            &format!(
-                "Setting xattr password for file: {}",
-                self.abs_path.display()
-            ),
-        );
         if self.password.is_none() {
             // Generate a random password if not provided
             self.password = Some(format!("sekai_{}", rand::random::<u64>()));
         }
-
-        xattr::set(
-            &self.abs_path,
-            "pass.deemak",
-            self.password.as_ref().unwrap().as_bytes(),
-        )
-        .map_err(|e| format!("Failed to set xattr metadata: {e}"))?;
-        Ok(())
     }
 
-    /// Get Xattr password from the file
-    pub fn get_password(&mut self) -> String {
-        let password_bytes =
-            xattr::get(&self.abs_path, "pass.deemak").expect("Failed to read xattr metadata");
-        match password_bytes {
-            Some(bytes) => String::from_utf8(bytes).expect("Password metadata is not valid UTF-8"),
-            None => {
-                // If no password is set, generate a new one
-                log::log_warning("Xattr Set", "No password found, generating a random one.");
-                let new_pass = format!("sekai_{}", rand::random::<u64>());
-                self.password = Some(new_pass.clone());
-                let _ = xattr::set(&self.abs_path, "pass.deemak", new_pass.as_bytes());
-                new_pass
-            }
+    /// Checks the password of the Deemak file, and keeps it if it matches.
+    pub fn check_password(&mut self, password: &str) -> Result<bool, String> {
+        let matches = passlock::check_password(&self.abs_path, password)?;
+        if matches {
+            self.password = Some(password.to_string());
         }
+        Ok(matches)
     }
 }
@@ -1,14 +1,15 @@
 use crate::metainfo::info_reader::{InfoError, read_validate_info};
-use crate::utils::{globals, log};
+use crate::sekai_fs::SekaiFs;
+use crate::utils::log;
 use std::path::{Path, PathBuf};
 
 /// Find the root directory of a sekai by finding "location": "home"
 /// in nearest `.dir_info/info.json`
-pub fn check_home(sekai_path: &Path) -> Result<Option<PathBuf>, InfoError> {
+pub fn check_home(fs: &dyn SekaiFs, sekai_path: &Path) -> Result<Option<PathBuf>, InfoError> {
     let mut current = sekai_path.to_path_buf();
     // Check for info.json in current directory
     let info_path = current.join(".dir_info/info.json");
-    match read_validate_info(&info_path) {
+    match read_validate_info(fs, &info_path) {
         Ok(info) => {
             if info.location == "HOME" {
                 Ok(Some(current))
@@ -39,8 +40,8 @@ pub fn check_home(sekai_path: &Path) -> Result<Option<PathBuf>, InfoError> {
 }
 
 /// Returns the home directory
-pub fn get_home(sekai_path: &Path) -> Option<PathBuf> {
-    match check_home(sekai_path) {
+pub fn get_home(fs: &dyn SekaiFs, sekai_path: &Path) -> Option<PathBuf> {
+    match check_home(fs, sekai_path) {
         Ok(Some(home)) => Some(home),
         Ok(None) => None,
         Err(e) => {
@@ -51,17 +52,13 @@ pub fn get_home(sekai_path: &Path) -> Option<PathBuf> {
 }
 
 /// Get the relative deemak path for a given path w.r.t sekai root directory.
+/// Without a root directory, only temporary paths are replaced.
 pub fn relative_deemak_path(path: &Path, sekai_root_dir: Option<&Path>) -> PathBuf {
-    let _sekai_dir = if let Some(dir) = sekai_root_dir {
-        dir
-    } else {
-        &globals::get_sekai_dir()
-    };
     let all_temp_locs = super::cleanup::get_all_cleanup_locations();
     let temp_dir_prefix = PathBuf::from("/tmp");
 
     // Check if it's prefixed by world_dir
-    if let Ok(relative_path) = path.strip_prefix(_sekai_dir) {
+    if let Some(relative_path) = sekai_root_dir.and_then(|dir| path.strip_prefix(dir).ok()) {
         if relative_path.components().count() == 0 {
             // Path is exactly world_dir, represent as "HOME"
             PathBuf::from("HOME")
@@ -1,65 +1,44 @@
-use crate::SEKAI_DIR;
-use once_cell::sync::{Lazy, OnceCell};
-use std::path::PathBuf;
-use std::sync::Mutex;
-
-/// Shell history to store the commands executed by the user.
-pub static SHELL_HISTORY: Lazy<Mutex<Vec<String>>> = Lazy::new(|| Mutex::new(Vec::new()));
-/// Global user information instance
-pub static USER_INFO: OnceCell<UserInfo> = OnceCell::new();
-
-/// Font options available in the application.
+/// Banner shown when Deemak starts.
+pub const DEEMAK_BANNER: &str = r#"
+ _____                            _
+|  __ \                          | |
+| |  | | ___  ___ _ __ ___   __ _| | __
+| |  | |/ _ \/ _ \ '_ ` _ \ / _` | |/ /
+| |__| |  __/  __/ | | | | | (_| |   <
+|_____/ \___|\___|_| |_|_| |\__,_|_|\_|
+
+Developed by Databased Club, Indian Institute of Science, Bangalore.
+Official Github Repo: https://github.com/databasedIISc/deemak
+"#;
+
+/// Font options available in the application, with the file names of the bundled fonts.
 pub const FONT_OPTIONS: [(&str, &str); 11] = [
-    (
-        "JetBrains Mono Medium",
-        "fontbook/fonts/ttf/JetBrainsMono-Medium.ttf",
-    ),
-    (
-        "JetBrains Mono Regular",
-        "fontbook/fonts/ttf/JetBrainsMono-Regular.ttf",
-    ),
-    (
-        "JetBrains Mono NL Light",
-        "fontbook/fonts/ttf/JetBrainsMonoNL-Light.ttf",
-    ),
-    (
-        "JetBrains Mono NL Medium",
-        "fontbook/fonts/ttf/JetBrainsMonoNL-Medium.ttf",
-    ),
-    (
-        "JetBrains Mono NL Regular",
-        "fontbook/fonts/ttf/JetBrainsMonoNL-Regular.ttf",
-    ),
-    (
-        "JetBrains Mono NL Thin",
-        "fontbook/fonts/ttf/JetBrainsMonoNL-Thin.ttf",
-    ),
+    ("JetBrains Mono Medium", "JetBrainsMono-Medium.ttf"),
+    ("JetBrains Mono Regular", "JetBrainsMono-Regular.ttf"),
+    ("JetBrains Mono NL Light", "JetBrainsMonoNL-Light.ttf"),
+    ("JetBrains Mono NL Medium", "JetBrainsMonoNL-Medium.ttf"),
+    ("JetBrains Mono NL Regular", "JetBrainsMonoNL-Regular.ttf"),
+    ("JetBrains Mono NL Thin", "JetBrainsMonoNL-Thin.ttf"),
     (
         "JetBrains Mono NL Thin Italic",
-        "fontbook/fonts/ttf/JetBrainsMonoNL-ThinItalic.ttf",
-    ),
-    ("Hack Nerd", "fontbook/fonts/ttf/HackNerdFont-Regular.ttf"),
-    (
-        "Hack Nerd Mono",
-        "fontbook/fonts/ttf/HackNerdFontMono-Regular.ttf",
-    ),
-    (
-        "Hack Nerd Propo",
-        "fontbook/fonts/ttf/HackNerdFontPropo-Regular.ttf",
-    ),
-    (
-        "Meslo LGS NF Regular",
-        "fontbook/fonts/ttf/MesloLGS NF Regular.ttf",
+        "JetBrainsMonoNL-ThinItalic.ttf",
     ),
+    ("Hack Nerd", "HackNerdFont-Regular.ttf"),
+    ("Hack Nerd Mono", "HackNerdFontMono-Regular.ttf"),
+    ("Hack Nerd Propo", "HackNerdFontPropo-Regular.ttf"),
+    ("Meslo LGS NF Regular", "MesloLGS NF Regular.ttf"),
 ];
 
 /// User information structure with expandable functionality
 #[derive(Debug, Clone, Default)]
 pub struct UserInfo {
     pub username: String,
+    /// Name the flags of the user are made for, see `auth::User::flag_name`
+    pub flag_name: String,
     pub salt: String,
     pub password_hash: String,
     pub is_authenticated: bool,
+    pub is_guest: bool,
     pub login_time: Option<std::time::SystemTime>,
 }
 
@@ -67,10 +46,12 @@ impl UserInfo {
     /// Create a new UserInfo instance
     pub fn new(username: String, salt: String, password_hash: String) -> Self {
         Self {
+            flag_name: username.clone(),
             username,
             salt,
             password_hash,
             is_authenticated: false,
+            is_guest: false,
             login_time: None,
         }
     }
@@ -86,13 +67,24 @@ impl UserInfo {
         self.is_authenticated
     }
 
+    /// Check if user is playing as a guest
+    pub fn is_guest(&self) -> bool {
+        self.is_guest
+    }
+
     /// Get username
     pub fn get_username(&self) -> &str {
         &self.username
     }
 
+    /// Name the flags of the user are made for
+    pub fn flag_name(&self) -> &str {
+        &self.flag_name
+    }
+
     /// Set user credentials
     pub fn set_credentials(&mut self, username: String, salt: String, password_hash: String) {
+        self.flag_name = username.clone();
         self.username = username;
         self.salt = salt;
         self.password_hash = password_hash;
@@ -101,9 +93,11 @@ impl UserInfo {
     /// Clear user information (logout)
     pub fn clear(&mut self) {
         self.username.clear();
+        self.flag_name.clear();
         self.salt.clear();
         self.password_hash.clear();
         self.is_authenticated = false;
+        self.is_guest = false;
         self.login_time = None;
     }
 
@@ -116,54 +110,3 @@ impl UserInfo {
         }
     }
 }
-
-/// Gets the global if it has been set, otherwise returns None.
-pub fn get_global_once<T: Clone>(cell: &OnceCell<T>) -> &T {
-    cell.get().unwrap()
-}
-
-/// Sets the global variable if it has not been set yet.
-pub fn set_global_once<T>(cell: &OnceCell<T>, value: T) -> Result<(), T> {
-    cell.set(value)
-}
-
-/// Get global user info
-pub fn get_user_info() -> Option<&'static UserInfo> {
-    USER_INFO.get()
-}
-
-/// Set global user info
-pub fn set_user_info(user_info: UserInfo) -> Result<(), UserInfo> {
-    USER_INFO.set(user_info)
-}
-
-/// Initialize or update global user info
-pub fn init_user_info(username: String, salt: String, password_hash: String) {
-    let user_info = UserInfo::new(username, salt, password_hash);
-    // The `set` method returns Ok(()) if the cell was empty and is now filled,
-    // and Err(value) if the cell was already filled.
-    // We can ignore the result if we don't need to handle the case where it's already set.
-    let _ = USER_INFO.set(user_info);
-}
-
-// Specialized getter/setter for WORLD_DIR
-pub fn get_sekai_dir() -> PathBuf {
-    let world_dir = SEKAI_DIR.get();
-
-    // Return original path if WORLD_DIR is empty
-    if let Some(dir) = world_dir {
-        if dir.is_empty() {
-            return PathBuf::new();
-        }
-        return PathBuf::from(dir);
-    }
-    // `!` is the default path if WORLD_DIR is not set, something you wont expect initially.
-    PathBuf::from("!")
-}
-
-/// Set the WORLD_DIR global variable.
-pub fn set_sekai_dir(path: PathBuf) {
-    SEKAI_DIR
-        .set(path.to_string_lossy().to_string())
-        .expect("WORLD_DIR already set");
-}
@@ -1,9 +1,16 @@
-use crate::DEV_MODE;
 use crate::utils::relative_deemak_path;
 use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Whether the loggers print, on in Dev Mode. Logging is the same for the whole process.
+static DEBUG_MODE: AtomicBool = AtomicBool::new(false);
 
 pub fn debug_mode() -> bool {
-    *DEV_MODE.get().unwrap_or(&false)
+    DEBUG_MODE.load(Ordering::Relaxed)
+}
+
+pub fn set_debug_mode(on: bool) {
+    DEBUG_MODE.store(on, Ordering::Relaxed);
 }
 
 /// Replaces the Paths in the message with their relative paths.
@@ -3,13 +3,21 @@ mod tests;
 pub use find_root::relative_deemak_path;
 pub mod log;
 pub use log::debug_mode;
+pub mod assets;
+#[cfg(not(target_arch = "wasm32"))]
 pub mod auth;
 pub mod cleanup;
 pub mod config;
 pub mod file_mgr;
 pub mod globals;
+pub mod progress;
 pub mod prompt;
 pub mod shell_history;
 pub mod tab_completion;
 pub mod test_utils;
+pub mod text_input;
+pub mod theme;
+pub mod throttle;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod user_mgr;
 pub mod wrapit;
@@ -0,0 +1,440 @@
+#[cfg(feature = "web")]
+use crate::utils::auth::username_from_token;
+use crate::utils::log;
+use crate::utils::throttle::{AttemptCounter, UNLOCK_BACKOFF};
+use chrono::{DateTime, Utc};
+#[cfg(feature = "web")]
+use rocket::{get, serde::json::Json};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+#[cfg(not(target_arch = "wasm32"))]
+use std::fs;
+#[cfg(not(target_arch = "wasm32"))]
+use std::io::Write;
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::Path;
+use std::sync::Mutex;
+
+/// File-based progress store, kept next to the user database.
+const PROGRESS_FILE: &str = "progress.json";
+/// Environment variable holding the leaderboard freeze time, in RFC 3339 format.
+pub const FREEZE_ENV: &str = "LEADERBOARD_FREEZE";
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum EventKind {
+    Solve,
+    Unlock,
+}
+
+/// A single solve/unlock event of a user.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ProgressEvent {
+    pub username: String,
+    pub kind: EventKind,
+    /// Object path relative to the Sekai, like `HOME/opb/level_1`
+    pub object: String,
+    pub points: u64,
+    /// Unix timestamp in seconds
+    pub timestamp: i64,
+}
+
+/// Failed unlock attempts of a user on an object.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct UnlockAttempts {
+    pub username: String,
+    pub object: String,
+    #[serde(flatten)]
+    pub counter: AttemptCounter,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct ProgressData {
+    #[serde(default)]
+    pub events: Vec<ProgressEvent>,
+    /// Kept with the progress, so restarting does not reset the backoff
+    #[serde(default)]
+    pub attempts: Vec<UnlockAttempts>,
+}
+
+/// Standing of a user in the leaderboard.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ScoreEntry {
+    pub rank: usize,
+    pub username: String,
+    pub points: u64,
+    pub objects: usize,
+    /// Time of the last event that counted towards the score
+    pub last_event: i64,
+}
+
+impl ProgressData {
+    /// Records an event, returns false if the same event was already recorded.
+    pub fn record(
+        &mut self,
+        username: &str,
+        kind: EventKind,
+        object: &str,
+        points: u64,
+        timestamp: i64,
+    ) -> bool {
+        if self
+            .events
+            .iter()
+            .any(|e| e.username == username && e.kind == kind && e.object == object)
+        {
+            return false;
+        }
+        self.events.push(ProgressEvent {
+            username: username.to_string(),
+            kind,
+            object: object.to_string(),
+            points,
+            timestamp,
+        });
+        true
+    }
+
+    /// All events of the user, in the order they happened.
+    pub fn user_events(&self, username: &str) -> Vec<&ProgressEvent> {
+        let mut events: Vec<&ProgressEvent> = self
+            .events
+            .iter()
+            .filter(|e| e.username == username)
+            .collect();
+        events.sort_by_key(|e| e.timestamp);
+        events
+    }
+
+    /// Moves all the events of a user to a new username, used when upgrading a guest.
+    pub fn rename_user(&mut self, old: &str, new: &str) {
+        for event in self.events.iter_mut().filter(|e| e.username == old) {
+            event.username = new.to_string();
+        }
+        for attempt in self.attempts.iter_mut().filter(|a| a.username == old) {
+            attempt.username = new.to_string();
+        }
+    }
+
+    /// Seconds the user has to wait before trying to unlock the object again.
+    pub fn unlock_wait(&self, username: &str, object: &str, now: i64) -> i64 {
+        self.attempts
+            .iter()
+            .find(|a| a.username == username && a.object == object)
+            .map(|a| UNLOCK_BACKOFF.wait(&a.counter, now))
+            .unwrap_or(0)
+    }
+
+    /// Counts an attempt to unlock the object, see `BackoffPolicy::attempt`.
+    pub fn unlock_attempt(&mut self, username: &str, object: &str, now: i64) -> Result<(), i64> {
+        match self
+            .attempts
+            .iter_mut()
+            .find(|a| a.username == username && a.object == object)
+        {
+            Some(attempt) => UNLOCK_BACKOFF.attempt(&mut attempt.counter, now),
+            None => {
+                let mut counter = AttemptCounter::default();
+                UNLOCK_BACKOFF.attempt(&mut counter, now)?;
+                self.attempts.push(UnlockAttempts {
+                    username: username.to_string(),
+                    object: object.to_string(),
+                    counter,
+                });
+                Ok(())
+            }
+        }
+    }
+
+    pub fn unlock_succeeded(&mut self, username: &str, object: &str) {
+        self.attempts
+            .retain(|a| !(a.username == username && a.object == object));
+    }
+
+    /// Computes the leaderboard, ignoring events after the freeze time.
+    /// Each object counts only once per user. Ties are broken by who got there first.
+    pub fn leaderboard(&self, freeze: Option<i64>) -> Vec<ScoreEntry> {
+        // username -> (object -> (points, timestamp))
+        let mut per_user: HashMap<&str, HashMap<&str, (u64, i64)>> = HashMap::new();
+        for event in &self.events {
+            if freeze.is_some_and(|f| event.timestamp > f) {
+                continue;
+            }
+            let objects = per_user.entry(&event.username).or_default();
+            let entry = objects
+                .entry(&event.object)
+                .or_insert((event.points, event.timestamp));
+            if event.points > entry.0 {
+                *entry = (event.points, event.timestamp);
+            }
+        }
+
+        let mut board: Vec<ScoreEntry> = per_user
+            .into_iter()
+            .map(|(username, objects)| ScoreEntry {
+                rank: 0,
+                username: username.to_string(),
+                points: objects.values().map(|(p, _)| p).sum(),
+                objects: objects.len(),
+                last_event: objects.values().map(|(_, t)| *t).max().unwrap_or(0),
+            })
+            .collect();
+        board.sort_by(|a, b| {
+            b.points
+                .cmp(&a.points)
+                .then(a.last_event.cmp(&b.last_event))
+                .then(a.username.cmp(&b.username))
+        });
+        for (i, entry) in board.iter_mut().enumerate() {
+            entry.rank = i + 1;
+        }
+        board
+    }
+}
+
+/// The progress to show, empty if it cannot be read. Changes go through `update_progress`.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn load_progress() -> ProgressData {
+    read_progress().unwrap_or_else(|e| {
+        log::log_error("PROGRESS", &e);
+        ProgressData::default()
+    })
+}
+
+/// The saved progress, or why it cannot be read. No file yet is no progress.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn read_progress() -> Result<ProgressData, String> {
+    read_progress_file(Path::new(PROGRESS_FILE))
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn read_progress_file(path: &Path) -> Result<ProgressData, String> {
+    if !path.exists() {
+        return Ok(ProgressData::default());
+    }
+    let data =
+        fs::read_to_string(path).map_err(|e| format!("Failed to read progress data: {e}"))?;
+    serde_json::from_str(&data).map_err(|e| format!("Failed to parse progress data: {e}"))
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn save_progress(progress: &ProgressData) -> Result<(), String> {
+    write_progress_file(Path::new(PROGRESS_FILE), progress)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn write_progress_file(path: &Path, progress: &ProgressData) -> Result<(), String> {
+    let data = serde_json::to_string_pretty(progress)
+        .map_err(|e| format!("Failed to serialize progress data: {e}"))?;
+    /* XXX EXPLANATION:
+    The progress is written next to the file and renamed over it, so a crash while writing leaves
+    the previous progress instead of a truncated file. The temporary name has the process id, as
+    another server of the same Sekai may be writing its own at the same time.
+    */
+    let temp_path = path.with_extension(format!("json.{}.tmp", std::process::id()));
+    let written = fs::File::create(&temp_path)
+        .and_then(|mut file| {
+            file.write_all(data.as_bytes())?;
+            file.sync_all()
+        })
+        .and_then(|_| fs::rename(&temp_path, path));
+    if let Err(e) = written {
+        let _ = fs::remove_file(&temp_path);
+        return Err(format!("Failed to write progress data: {e}"));
+    }
+    Ok(())
+}
+
+/// The browser has no files, progress is kept here and in localStorage by the JS side, see `wasm`.
+#[cfg(target_arch = "wasm32")]
+static BROWSER_PROGRESS: std::sync::Mutex<Option<ProgressData>> = std::sync::Mutex::new(None);
+
+#[cfg(target_arch = "wasm32")]
+pub fn load_progress() -> ProgressData {
+    BROWSER_PROGRESS
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone()
+        .unwrap_or_default()
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn save_progress(progress: &ProgressData) -> Result<(), String> {
+    *BROWSER_PROGRESS.lock().unwrap_or_else(|e| e.into_inner()) = Some(progress.clone());
+    Ok(())
+}
+
+/// Serializes the changes of the progress, so they are not lost or checked twice.
+static PROGRESS_LOCK: Mutex<()> = Mutex::new(());
+
+/// Loads the progress, changes it and saves it, without other changes in between.
+/// Progress that cannot be read fails the change, rather than being replaced by an empty one.
+fn update_progress<T>(change: impl FnOnce(&mut ProgressData) -> T) -> Result<T, String> {
+    let _guard = PROGRESS_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    #[cfg(not(target_arch = "wasm32"))]
+    return update_progress_file(Path::new(PROGRESS_FILE), change);
+    #[cfg(target_arch = "wasm32")]
+    {
+        let mut progress = load_progress();
+        let result = change(&mut progress);
+        save_progress(&progress)?;
+        Ok(result)
+    }
+}
+
+/// `update_progress` on the progress file at the path, without taking the lock.
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn update_progress_file<T>(
+    path: &Path,
+    change: impl FnOnce(&mut ProgressData) -> T,
+) -> Result<T, String> {
+    let mut progress = read_progress_file(path)?;
+    let result = change(&mut progress);
+    write_progress_file(path, &progress)?;
+    Ok(result)
+}
+
+/// Records a solve/unlock event of the user with the current time.
+pub fn record_event(username: &str, kind: EventKind, object: &str, points: u64) {
+    let recorded = update_progress(|progress| {
+        progress.record(username, kind, object, points, Utc::now().timestamp())
+    });
+    match recorded {
+        Ok(true) => log::log_info(
+            "PROGRESS",
+            &format!("Recorded {kind:?} of {object} by {username} for {points} points"),
+        ),
+        Ok(false) => {}
+        Err(e) => log::log_error(
+            "PROGRESS",
+            &format!("Failed to record {kind:?} of {object} by {username}: {e}"),
+        ),
+    }
+}
+
+/// Seconds the user has to wait before trying to unlock the object again.
+pub fn unlock_wait(username: &str, object: &str) -> i64 {
+    load_progress().unlock_wait(username, object, Utc::now().timestamp())
+}
+
+/// Counts an attempt of the user to unlock the object, if allowed now. It counts as a wrong flag
+/// until `clear_unlock_failures`. Returns why the attempt is refused otherwise, an attempt that
+/// cannot be counted is refused as well.
+pub fn start_unlock_attempt(username: &str, object: &str) -> Result<(), String> {
+    match update_progress(|progress| {
+        progress.unlock_attempt(username, object, Utc::now().timestamp())
+    }) {
+        Ok(Ok(())) => Ok(()),
+        Ok(Err(wait)) => Err(format!(
+            "Too many wrong attempts. Try again in {wait} seconds."
+        )),
+        Err(e) => {
+            log::log_error("PROGRESS", &e);
+            Err("Progress cannot be saved right now. Try again later.".to_string())
+        }
+    }
+}
+
+/// Resets the failed attempts once the object is unlocked.
+pub fn clear_unlock_failures(username: &str, object: &str) {
+    if let Err(e) = update_progress(|progress| progress.unlock_succeeded(username, object)) {
+        log::log_error("PROGRESS", &e);
+    }
+}
+
+/// Moves the recorded progress of a user to a new username.
+pub fn rename_user_progress(old: &str, new: &str) -> Result<(), String> {
+    if old == new {
+        return Ok(());
+    }
+    update_progress(|progress| progress.rename_user(old, new))
+}
+
+/// Reads the leaderboard freeze time from the environment, if set.
+pub fn get_freeze_time() -> Option<i64> {
+    let value = std::env::var(FREEZE_ENV).ok()?;
+    match DateTime::parse_from_rfc3339(value.trim()) {
+        Ok(time) => Some(time.timestamp()),
+        Err(e) => {
+            log::log_warning(
+                "PROGRESS",
+                &format!("Invalid {FREEZE_ENV} '{value}', expected RFC 3339: {e}"),
+            );
+            None
+        }
+    }
+}
+
+#[cfg(feature = "web")]
+#[derive(Serialize)]
+pub struct LeaderboardResponse {
+    frozen: bool,
+    freeze_time: Option<String>,
+    entries: Vec<ScoreEntry>,
+}
+
+#[cfg(feature = "web")]
+#[derive(Serialize)]
+pub struct MyProgressResponse {
+    status: bool,
+    message: String,
+    /// Standing in the leaderboard, as everyone sees it after the freeze
+    standing: Option<ScoreEntry>,
+    /// Live points of the user, counting the events after the freeze as well
+    points: u64,
+    events: Vec<ProgressEvent>,
+}
+
+#[cfg(feature = "web")]
+fn format_time(timestamp: i64) -> Option<String> {
+    DateTime::<Utc>::from_timestamp(timestamp, 0).map(|t| t.to_rfc3339())
+}
+
+#[cfg(feature = "web")]
+#[get("/leaderboard")]
+pub fn leaderboard() -> Json<LeaderboardResponse> {
+    let freeze = get_freeze_time();
+    let frozen = freeze.is_some_and(|f| Utc::now().timestamp() > f);
+    Json(LeaderboardResponse {
+        frozen,
+        freeze_time: freeze.and_then(format_time),
+        entries: load_progress().leaderboard(freeze),
+    })
+}
+
+#[cfg(feature = "web")]
+/// Progress of the user of the token. The standing is frozen like the leaderboard, so it does not
+/// give away the ranks after the freeze, the user still sees their own live points.
+#[get("/me/progress?<token>")]
+pub fn my_progress(token: &str) -> Json<MyProgressResponse> {
+    let Some(username) = username_from_token(token) else {
+        return Json(MyProgressResponse {
+            status: false,
+            message: "Invalid or expired token".into(),
+            standing: None,
+            points: 0,
+            events: vec![],
+        });
+    };
+    let progress = load_progress();
+    let standing = progress
+        .leaderboard(get_freeze_time())
+        .into_iter()
+        .find(|e| e.username == username);
+    let points = progress
+        .leaderboard(None)
+        .into_iter()
+        .find(|e| e.username == username)
+        .map_or(0, |e| e.points);
+    Json(MyProgressResponse {
+        status: true,
+        message: format!("Progress of {username}"),
+        standing,
+        points,
+        events: progress
+            .user_events(&username)
+            .into_iter()
+            .cloned()
+            .collect(),
+    })
+}
@@ -1,5 +1,7 @@
 use crate::metainfo::valid_sekai::create_dir_info;
-use std::collections::HashMap;
+use crate::sekai_fs::DiskFs;
+use crate::utils::prompt::UserPrompter;
+use std::collections::{HashMap, VecDeque};
 use std::fs::{self, File};
 use std::io::{Read, Write};
 use std::path::{Path, PathBuf};
@@ -17,6 +19,31 @@ pub fn remove_file<P: AsRef<Path>>(path: P) {
     fs::remove_file(path).unwrap();
 }
 
+/// Prompter giving the answers in order, for commands which ask for input.
+pub struct ScriptedPrompter {
+    answers: VecDeque<String>,
+}
+
+impl ScriptedPrompter {
+    pub fn new(answers: &[&str]) -> Self {
+        Self {
+            answers: answers.iter().map(|a| a.to_string()).collect(),
+        }
+    }
+}
+
+impl UserPrompter for ScriptedPrompter {
+    fn confirm(&mut self, _message: &str) -> bool {
+        true
+    }
+    fn input(&mut self, _message: &str) -> String {
+        self.answers.pop_front().unwrap_or_default()
+    }
+    fn write(&mut self, message: &str) -> String {
+        message.to_string()
+    }
+}
+
 /// Helper to create a test directory wi/// Creates a temporary directory with the following structure:
 /// Creates a temporary directory with the following structure:
 /// temp_dir/
@@ -59,7 +86,7 @@ pub fn setup_test_dir(make_dirinfo: bool) -> (TempDir, PathBuf) {
     // NOTE: We assume that create_dir_info is correctly implemented. It's tests are elsewhere.
     // If you find that there is issue in the dir_info creation, please check first that those
     // tests are passing.
-    if make_dirinfo && !create_dir_info(&root_path, true) {
+    if make_dirinfo && !create_dir_info(&DiskFs, &root_path, &root_path) {
         panic!(
             "Failed to create valid .dir_info for root directory: {}",
             root_path.display()
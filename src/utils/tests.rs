@@ -69,4 +69,438 @@ mod utils_tests {
             "Multiple paths: HOME/some/file.txt, HOME/other_some, DEEMAK_TEMP/temp_file.log, and /var/log/system.log."
         );
     }
+
+    #[test]
+    fn test_user_mgr_import_and_manage() {
+        use crate::utils::auth::verify_password;
+        use crate::utils::user_mgr::*;
+        let csv = "username,password\nalice,wonderland\nbob\n\n# comment\ncarol,\n";
+        let entries = parse_users_csv(csv).unwrap();
+        assert_eq!(entries.len(), 3);
+        assert_eq!(
+            entries[0],
+            CsvUser::Plain("alice".to_string(), Some("wonderland".to_string()))
+        );
+        assert_eq!(entries[1], CsvUser::Plain("bob".to_string(), None));
+
+        let mut users = Vec::new();
+        let generated = import_users(&mut users, entries).unwrap();
+        assert_eq!(users.len(), 3);
+        assert_eq!(generated.len(), 2);
+        let (name, generated_password) = &generated[0];
+        let bob = users.iter().find(|u| u.username == *name).unwrap();
+        assert!(verify_password(
+            generated_password,
+            &bob.salt,
+            &bob.password_hash
+        ));
+        // Imported passwords are known to the admin, they must be changed at the first login
+        assert!(users.iter().all(|u| u.must_change_password));
+
+        // Re-importing an existing user must not change anything
+        let dup = parse_users_csv("dave\nalice").unwrap();
+        assert!(import_users(&mut users, dup).is_err());
+        assert_eq!(users.len(), 3);
+        assert!(parse_users_csv("eve\neve").is_err());
+        assert!(parse_users_csv("bad name,pw").is_err());
+
+        assert!(set_user_password(&mut users, "alice", "").is_err());
+        set_user_password(&mut users, "alice", "new_pass").unwrap();
+        let alice = users.iter().find(|u| u.username == "alice").unwrap();
+        assert!(!alice.must_change_password);
+        assert!(verify_password(
+            &"new_pass".to_string(),
+            &alice.salt,
+            &alice.password_hash
+        ));
+        assert!(add_user(&mut users, "alice", "x").is_err());
+        remove_user(&mut users, "alice").unwrap();
+        assert!(remove_user(&mut users, "alice").is_err());
+        assert_eq!(export_users_csv(&users).lines().count(), 3);
+    }
+
+    #[test]
+    fn test_user_mgr_export_round_trip() {
+        use crate::utils::auth::verify_password;
+        use crate::utils::user_mgr::*;
+        let mut users = Vec::new();
+        add_user(&mut users, "alice", "wonderland").unwrap();
+        add_user(&mut users, "bob", "builder").unwrap();
+        let csv = export_users_csv(&users);
+
+        // The hashes are kept, not taken as passwords, and no user is named after the header
+        let mut imported = Vec::new();
+        let generated = import_users(&mut imported, parse_users_csv(&csv).unwrap()).unwrap();
+        assert!(generated.is_empty());
+        assert_eq!(imported.len(), 2);
+        let alice = imported.iter().find(|u| u.username == "alice").unwrap();
+        assert!(verify_password(
+            &"wonderland".to_string(),
+            &alice.salt,
+            &alice.password_hash
+        ));
+        assert_eq!(export_users_csv(&imported), csv);
+
+        let bad = format!("{CSV_EXPORT_HEADER}\nalice,salt");
+        assert!(parse_users_csv(&bad).is_err());
+        let bad = format!("{CSV_EXPORT_HEADER}\nalice,salt,not hex");
+        assert!(parse_users_csv(&bad).is_err());
+    }
+
+    #[test]
+    fn test_guest_upgrade_keeps_salt() {
+        use crate::utils::auth::{create_guest_user, upgrade_guest_user, verify_password};
+        use argon2::password_hash::SaltString;
+        let mut users = Vec::new();
+        let guest = create_guest_user(&mut users).unwrap();
+        assert!(guest.guest);
+        assert!(guest.username.starts_with("guest_"));
+        // Guest salt must be usable for the user specific flags
+        assert!(SaltString::from_b64(&guest.salt).is_ok());
+        assert!(!verify_password(
+            &String::new(),
+            &guest.salt,
+            &guest.password_hash
+        ));
+
+        let user = upgrade_guest_user(&mut users, &guest.username, Some("player"), "pw").unwrap();
+        assert!(!user.guest);
+        assert_eq!(user.username, "player");
+        assert_eq!(user.salt, guest.salt);
+        assert!(verify_password(
+            &"pw".to_string(),
+            &user.salt,
+            &user.password_hash
+        ));
+        assert!(upgrade_guest_user(&mut users, "player", None, "pw").is_err());
+
+        let other = create_guest_user(&mut users).unwrap();
+        assert!(upgrade_guest_user(&mut users, &other.username, Some("bad name"), "pw").is_err());
+        assert!(upgrade_guest_user(&mut users, &other.username, None, "").is_err());
+        assert!(
+            users
+                .iter()
+                .any(|u| u.username == other.username && u.guest)
+        );
+    }
+
+    #[test]
+    fn test_guest_flags_survive_upgrade() {
+        use crate::rns::security::{read_user_flag, user_flag};
+        use crate::utils::auth::{authenticate_user, create_guest_user, upgrade_guest_user};
+        let mut users = Vec::new();
+        let guest = create_guest_user(&mut users).unwrap();
+        let before = authenticate_user(&guest);
+        let flag = user_flag(before.flag_name(), "level1", "FLAG{first_level}");
+
+        // The flag solved as a guest still unlocks after renaming the account
+        let user = upgrade_guest_user(&mut users, &guest.username, Some("player"), "pw").unwrap();
+        let after = authenticate_user(&user);
+        assert_eq!(after.get_username(), "player");
+        assert_eq!(after.flag_name(), guest.username);
+        assert_eq!(
+            read_user_flag(after.flag_name(), "level1", &flag),
+            "FLAG{first_level}"
+        );
+        let saved: Vec<crate::utils::auth::User> =
+            serde_json::from_str(&serde_json::to_string(&users).unwrap()).unwrap();
+        assert_eq!(authenticate_user(&saved[0]).flag_name(), guest.username);
+    }
+
+    #[test]
+    fn test_expire_unused_guests() {
+        use crate::utils::auth::{create_guest_user, expire_guests};
+        use crate::utils::user_mgr::add_user;
+        let mut users = Vec::new();
+        add_user(&mut users, "alice", "pw").unwrap();
+        let idle = create_guest_user(&mut users).unwrap();
+        let playing = create_guest_user(&mut users).unwrap();
+        let created = idle.created.unwrap();
+
+        assert_eq!(expire_guests(&mut users, created + 60, |_| false), 0);
+        let day_later = created + 2 * 24 * 60 * 60;
+        let removed = expire_guests(&mut users, day_later, |name| name == playing.username);
+        assert_eq!(removed, 1);
+        let names: Vec<&str> = users.iter().map(|u| u.username.as_str()).collect();
+        assert_eq!(names, ["alice", playing.username.as_str()]);
+    }
+
+    #[test]
+    fn test_old_guest_can_upgrade() {
+        use crate::utils::auth::{
+            create_guest_token, create_guest_user, expire_guests, upgrade_guest_user,
+            username_from_token,
+        };
+        use data_encoding::BASE64URL_NOPAD;
+        let mut users = Vec::new();
+        let guest = create_guest_user(&mut users).unwrap();
+        let token = create_guest_token(&guest.username);
+        assert_eq!(
+            username_from_token(&token).as_deref(),
+            Some(guest.username.as_str())
+        );
+        let payload = token.split('.').nth(1).unwrap();
+        let claims: serde_json::Value =
+            serde_json::from_slice(&BASE64URL_NOPAD.decode(payload.as_bytes()).unwrap()).unwrap();
+        let expires = claims["exp"].as_i64().unwrap();
+        let month_later = guest.created.unwrap() + 30 * 24 * 60 * 60;
+        assert!(expires > month_later);
+
+        // A month later the guest with progress is still there, and can still upgrade
+        assert_eq!(expire_guests(&mut users, month_later, |_| true), 0);
+        let user = upgrade_guest_user(&mut users, &guest.username, Some("player"), "pw").unwrap();
+        assert_eq!(user.username, "player");
+        assert_eq!(user.flag_name.as_deref(), Some(guest.username.as_str()));
+    }
+
+    #[test]
+    fn test_progress_leaderboard() {
+        use crate::utils::progress::{EventKind, ProgressData};
+        let mut progress = ProgressData::default();
+        assert!(progress.record("alice", EventKind::Solve, "HOME/l1", 10, 100));
+        assert!(!progress.record("alice", EventKind::Solve, "HOME/l1", 10, 150));
+        // Unlocking the same object does not count twice
+        assert!(progress.record("alice", EventKind::Unlock, "HOME/l1", 10, 200));
+        assert!(progress.record("bob", EventKind::Unlock, "HOME/l1", 10, 120));
+        assert!(progress.record("bob", EventKind::Unlock, "HOME/c1", 5, 300));
+
+        let board = progress.leaderboard(None);
+        assert_eq!(board[0].username, "bob");
+        assert_eq!(board[0].points, 15);
+        assert_eq!(board[1].username, "alice");
+        assert_eq!(board[1].points, 10);
+
+        // Frozen before bob's chest, alice got there first
+        let frozen = progress.leaderboard(Some(250));
+        assert_eq!(frozen[0].username, "alice");
+        assert_eq!(frozen[0].rank, 1);
+        assert_eq!(frozen[1].points, 10);
+
+        progress.rename_user("alice", "carol");
+        assert!(progress.user_events("alice").is_empty());
+        assert_eq!(progress.user_events("carol").len(), 2);
+    }
+
+    #[test]
+    fn test_unlock_backoff() {
+        use crate::utils::progress::ProgressData;
+        use crate::utils::throttle::UNLOCK_BACKOFF;
+        assert_eq!(UNLOCK_BACKOFF.delay(2), 0);
+        assert_eq!(UNLOCK_BACKOFF.delay(3), 5);
+        assert_eq!(UNLOCK_BACKOFF.delay(5), 20);
+        assert_eq!(UNLOCK_BACKOFF.delay(100), UNLOCK_BACKOFF.max_secs);
+
+        let mut progress = ProgressData::default();
+        for _ in 0..3 {
+            assert!(progress.unlock_attempt("alice", "HOME/l1", 100).is_ok());
+        }
+        // Attempts made before the others finish are refused too
+        assert_eq!(progress.unlock_attempt("alice", "HOME/l1", 100), Err(5));
+        assert_eq!(progress.unlock_wait("alice", "HOME/l1", 100), 5);
+        assert_eq!(progress.unlock_wait("alice", "HOME/l1", 105), 0);
+        // Other users and objects are not affected
+        assert_eq!(progress.unlock_wait("bob", "HOME/l1", 100), 0);
+        assert_eq!(progress.unlock_wait("alice", "HOME/c1", 100), 0);
+
+        // Counters survive a save and load
+        let data = serde_json::to_string(&progress).unwrap();
+        let mut progress: ProgressData = serde_json::from_str(&data).unwrap();
+        assert_eq!(progress.unlock_wait("alice", "HOME/l1", 100), 5);
+
+        progress.unlock_succeeded("alice", "HOME/l1");
+        assert_eq!(progress.unlock_wait("alice", "HOME/l1", 100), 0);
+
+        // Failures are forgotten after a quiet while
+        for _ in 0..4 {
+            let _ = progress.unlock_attempt("alice", "HOME/l1", 200);
+        }
+        assert!(progress.unlock_wait("alice", "HOME/l1", 201) > 0);
+        let later = 200 + UNLOCK_BACKOFF.forget_secs;
+        assert_eq!(progress.unlock_wait("alice", "HOME/l1", later), 0);
+        assert!(progress.unlock_attempt("alice", "HOME/l1", later).is_ok());
+        assert_eq!(progress.unlock_wait("alice", "HOME/l1", later), 0);
+    }
+
+    #[test]
+    fn test_login_throttle() {
+        use crate::utils::throttle::{LOGIN_BACKOFF, login_attempt, login_keys, login_succeeded};
+        let keys = login_keys(Some("192.0.2.7"), "throttled_user");
+        // Successful logins do not count
+        for _ in 0..2 * LOGIN_BACKOFF.free_attempts {
+            assert!(login_attempt(&keys).is_ok());
+            login_succeeded(&keys);
+        }
+        for _ in 0..LOGIN_BACKOFF.free_attempts {
+            assert!(login_attempt(&keys).is_ok());
+        }
+        // Attempts are counted when they start, so a burst cannot get past the check
+        assert!(login_attempt(&keys).is_err());
+        // The address is throttled for other users as well
+        assert!(login_attempt(&login_keys(Some("192.0.2.7"), "other_user")).is_err());
+        assert!(login_attempt(&login_keys(Some("192.0.2.8"), "other_user")).is_ok());
+    }
+
+    #[test]
+    fn test_corrupt_progress_not_overwritten() {
+        use crate::utils::progress::{EventKind, update_progress_file};
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("progress.json");
+
+        let recorded = update_progress_file(&path, |progress| {
+            progress.record("alice", EventKind::Solve, "HOME/l1", 10, 100)
+        });
+        assert_eq!(recorded, Ok(true));
+        let entries = std::fs::read_dir(dir.path()).unwrap().count();
+        assert_eq!(
+            entries, 1,
+            "the temporary file is renamed over the progress"
+        );
+
+        std::fs::write(&path, "{\"events\": [").unwrap();
+        let changed = update_progress_file(&path, |progress| {
+            progress.record("bob", EventKind::Solve, "HOME/l1", 10, 200)
+        });
+        assert!(changed.is_err());
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "{\"events\": [");
+    }
+
+    #[test]
+    fn test_load_themes() {
+        use crate::utils::theme::{Rgb, Theme, load_themes};
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("solarized.toml"),
+            "background = \"#002b36\"\naccent = \"#B58900\"\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.path().join("light.toml"),
+            "name = \"light\"\ntext = \"#102030\"\n",
+        )
+        .unwrap();
+        std::fs::write(dir.path().join("broken.toml"), "text = \"red\"\n").unwrap();
+
+        let themes = load_themes(dir.path());
+        let names: Vec<&str> = themes.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(names, ["dark", "light", "high-contrast", "solarized"]);
+
+        // Colours left out come from the dark theme
+        let solarized = &themes[3];
+        assert_eq!(solarized.background, Rgb(0x00, 0x2b, 0x36));
+        assert_eq!(solarized.accent, Rgb(0xb5, 0x89, 0x00));
+        assert_eq!(solarized.text, Theme::dark().text);
+        // A file with the name of a built-in theme replaces it
+        assert_eq!(themes[1].text, Rgb(0x10, 0x20, 0x30));
+        assert!("#12345".parse::<Rgb>().is_err());
+    }
+
+    #[test]
+    fn test_text_input_graphemes() {
+        use crate::utils::text_input::{
+            char_to_byte, delete_back, insert, next_boundary, prev_boundary,
+        };
+        // "नमस्ते" is the graphemes "न", "म", "स्ते"
+        let mut buffer = String::new();
+        let mut cursor = insert(&mut buffer, 0, "नमस्ते");
+        assert_eq!(cursor, buffer.len());
+        cursor = prev_boundary(&buffer, cursor);
+        assert_eq!(&buffer[cursor..], "स्ते");
+        cursor = prev_boundary(&buffer, cursor);
+        assert_eq!(next_boundary(&buffer, cursor), "नम".len());
+
+        // Typed in the middle, control characters are keys and are dropped
+        cursor = insert(&mut buffer, cursor, "ಕನ್ನಡ\t\u{8}");
+        assert_eq!(buffer, "नಕನ್ನಡमस्ते");
+        // Kannada has no conjunct graphemes, "ನ್ನ" is "ನ್" and "ನ"
+        cursor = delete_back(&mut buffer, cursor);
+        cursor = delete_back(&mut buffer, cursor);
+        assert_eq!(buffer, "नಕನ್मस्ते");
+        assert_eq!(cursor, "नಕನ್".len());
+
+        // A combining accent is deleted with its letter
+        let mut buffer = "cafe\u{301}".to_string();
+        assert_eq!(delete_back(&mut buffer, 6), 3);
+        assert_eq!(buffer, "caf");
+        assert_eq!(delete_back(&mut buffer, 0), 0);
+        assert_eq!(next_boundary(&buffer, 3), 3);
+
+        assert_eq!(char_to_byte("aमb", 2), 4);
+        assert_eq!(char_to_byte("aमb", 9), 5);
+    }
+
+    #[test]
+    fn test_instance_temp_dirs() {
+        use crate::rns::restore_comp::generate_temp_path;
+        use crate::utils::cleanup::{LOCK_FILE, cleanup_instance, instance_dir};
+
+        let temp = generate_temp_path("test").unwrap();
+        let path = temp.path().to_path_buf();
+        assert!(path.is_dir());
+        assert!(path.starts_with(instance_dir().unwrap()));
+        let lock = std::fs::read_to_string(instance_dir().unwrap().join(LOCK_FILE)).unwrap();
+        assert_eq!(lock, std::process::id().to_string());
+
+        // The directory lives as long as its owner
+        drop(temp);
+        assert!(!path.exists());
+        let instance = instance_dir().unwrap();
+        assert!(instance.is_dir());
+
+        // Nothing else of the tests uses the instance directory, remove it as `main` would
+        cleanup_instance().unwrap();
+        assert!(!instance.exists());
+    }
+
+    #[test]
+    fn test_signal_left_to_other_handler() {
+        use crate::utils::cleanup::{install_signal_handlers, signal_handled_elsewhere};
+
+        extern "C" fn other_handler(_: libc::c_int) {}
+
+        install_signal_handlers();
+        assert!(!signal_handled_elsewhere(libc::SIGHUP));
+
+        // A handler installed later, like the one of the web server, takes over the shutdown
+        let mut ours: libc::sigaction = unsafe { std::mem::zeroed() };
+        unsafe {
+            libc::sigaction(libc::SIGHUP, std::ptr::null(), &mut ours);
+            libc::signal(
+                libc::SIGHUP,
+                other_handler as extern "C" fn(libc::c_int) as libc::sighandler_t,
+            );
+        }
+        assert!(signal_handled_elsewhere(libc::SIGHUP));
+        unsafe { libc::sigaction(libc::SIGHUP, &ours, std::ptr::null_mut()) };
+        assert!(!signal_handled_elsewhere(libc::SIGHUP));
+    }
+
+    #[test]
+    fn test_sweep_only_dead_instances() {
+        use crate::utils::cleanup::{LOCK_FILE, sweep_stale_at};
+        use std::fs;
+
+        let location = tempfile::TempDir::new().unwrap();
+        let mut child = std::process::Command::new("true").spawn().unwrap();
+        let dead_pid = child.id();
+        child.wait().unwrap();
+
+        for (name, pid) in [
+            ("deemak_dead", Some(dead_pid)),
+            ("deemak_running", Some(std::process::id())),
+            ("deemak_unknown", None),
+            ("other_dead", Some(dead_pid)),
+        ] {
+            let dir = location.path().join(name);
+            fs::create_dir(&dir).unwrap();
+            if let Some(pid) = pid {
+                fs::write(dir.join(LOCK_FILE), pid.to_string()).unwrap();
+            }
+        }
+
+        assert_eq!(sweep_stale_at(location.path()), Ok(1));
+        assert!(!location.path().join("deemak_dead").exists());
+        assert!(location.path().join("deemak_running").exists());
+        assert!(location.path().join("deemak_unknown").exists());
+        assert!(location.path().join("other_dead").exists());
+    }
 }
@@ -0,0 +1,55 @@
+use unicode_segmentation::UnicodeSegmentation;
+
+/*
+TEXT INPUT EXPLANATION:
+
+Typed text comes from the characters the keyboard layout or the input method produced, not from
+the keys, so any script can be typed. The cursor is a byte offset into the input, which is always
+at the boundary of a grapheme: what the player sees as one character, like "क्षि" or "é" written
+as `e` and a combining accent. Moving and deleting go over whole graphemes, so the cursor never
+ends up inside one.
+*/
+
+/// Whether a typed character goes into the input, control characters are handled as keys.
+pub fn is_typed(c: char) -> bool {
+    !c.is_control()
+}
+
+/// Inserts the typed text at the cursor, returning the new cursor.
+pub fn insert(buffer: &mut String, cursor: usize, text: &str) -> usize {
+    let text: String = text.chars().filter(|c| is_typed(*c)).collect();
+    let cursor = cursor.min(buffer.len());
+    buffer.insert_str(cursor, &text);
+    cursor + text.len()
+}
+
+/// Start of the grapheme before the cursor.
+pub fn prev_boundary(buffer: &str, cursor: usize) -> usize {
+    buffer[..cursor.min(buffer.len())]
+        .grapheme_indices(true)
+        .next_back()
+        .map_or(0, |(i, _)| i)
+}
+
+/// End of the grapheme after the cursor.
+pub fn next_boundary(buffer: &str, cursor: usize) -> usize {
+    let cursor = cursor.min(buffer.len());
+    buffer[cursor..]
+        .graphemes(true)
+        .next()
+        .map_or(cursor, |g| cursor + g.len())
+}
+
+/// Deletes the grapheme before the cursor, returning the new cursor.
+pub fn delete_back(buffer: &mut String, cursor: usize) -> usize {
+    let start = prev_boundary(buffer, cursor);
+    buffer.replace_range(start..cursor.min(buffer.len()), "");
+    start
+}
+
+/// Byte offset of the character at the index, for slicing lines by column.
+pub fn char_to_byte(text: &str, index: usize) -> usize {
+    text.char_indices()
+        .nth(index)
+        .map_or(text.len(), |(i, _)| i)
+}
@@ -0,0 +1,234 @@
+use crate::commands::output::Style;
+use crate::utils::{config, log};
+#[cfg(feature = "gui")]
+use raylib::prelude::Color;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+
+/*
+THEMES EXPLANATION:
+
+Every screen of the GUI takes its colours from the current theme, so that changing the theme in
+the settings applies at once. The built-in themes are `dark` (the default), `light` and
+`high-contrast`.
+
+More themes can be added as TOML files in `~/.config/deemak/themes/`, with colours given as
+`"#rrggbb"`. Colours which are left out are taken from the dark theme, and the name of the file
+is used if there is no `name`. A file with the name of a built-in theme replaces it.
+
+    name = "solarized"
+    background = "#002b36"
+    text = "#eee8d5"
+    accent = "#b58900"
+*/
+
+/// A colour of a theme, written as `"#rrggbb"`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rgb(pub u8, pub u8, pub u8);
+
+#[cfg(feature = "gui")]
+impl Rgb {
+    pub fn color(self) -> Color {
+        Color::new(self.0, self.1, self.2, 255)
+    }
+
+    /// The colour with an opacity between 0 and 1, for fading in.
+    pub fn fade(self, alpha: f32) -> Color {
+        Color::new(
+            self.0,
+            self.1,
+            self.2,
+            (alpha.clamp(0.0, 1.0) * 255.0) as u8,
+        )
+    }
+}
+
+impl std::str::FromStr for Rgb {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let hex = s
+            .strip_prefix('#')
+            .filter(|hex| hex.len() == 6 && hex.is_ascii())
+            .ok_or_else(|| format!("Invalid colour '{s}', expected \"#rrggbb\""))?;
+        let channel = |i: usize| {
+            u8::from_str_radix(&hex[i..i + 2], 16).map_err(|_| format!("Invalid colour '{s}'"))
+        };
+        Ok(Rgb(channel(0)?, channel(2)?, channel(4)?))
+    }
+}
+
+impl Serialize for Rgb {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&format!("#{:02x}{:02x}{:02x}", self.0, self.1, self.2))
+    }
+}
+
+impl<'de> Deserialize<'de> for Rgb {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Theme {
+    pub name: String,
+    pub background: Rgb,
+    /// Terminal output, titles and the cursor
+    pub text: Rgb,
+    /// Options which are not selected
+    pub muted: Rgb,
+    /// Footers and hints
+    pub dim: Rgb,
+    /// Selected options and highlights
+    pub accent: Rgb,
+    /// Background of selected text
+    pub selection: Rgb,
+    /// Line between the terminal and the sidebar
+    pub divider: Rgb,
+    pub success: Rgb,
+    pub error: Rgb,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self::dark()
+    }
+}
+
+impl Theme {
+    pub fn dark() -> Self {
+        Self {
+            name: "dark".to_string(),
+            background: Rgb(0, 0, 0),
+            text: Rgb(255, 255, 255),
+            muted: Rgb(200, 200, 200),
+            dim: Rgb(130, 130, 130),
+            accent: Rgb(255, 203, 0),
+            selection: Rgb(64, 96, 128),
+            divider: Rgb(77, 77, 77),
+            success: Rgb(0, 228, 48),
+            error: Rgb(230, 41, 55),
+        }
+    }
+
+    pub fn light() -> Self {
+        Self {
+            name: "light".to_string(),
+            background: Rgb(245, 245, 240),
+            text: Rgb(20, 20, 20),
+            muted: Rgb(70, 70, 70),
+            dim: Rgb(120, 120, 120),
+            accent: Rgb(180, 100, 0),
+            selection: Rgb(170, 200, 235),
+            divider: Rgb(190, 190, 190),
+            success: Rgb(0, 130, 40),
+            error: Rgb(190, 30, 40),
+        }
+    }
+
+    pub fn high_contrast() -> Self {
+        Self {
+            name: "high-contrast".to_string(),
+            background: Rgb(0, 0, 0),
+            text: Rgb(255, 255, 255),
+            muted: Rgb(255, 255, 255),
+            dim: Rgb(220, 220, 220),
+            accent: Rgb(255, 255, 0),
+            selection: Rgb(0, 0, 255),
+            divider: Rgb(255, 255, 255),
+            success: Rgb(0, 255, 0),
+            error: Rgb(255, 80, 80),
+        }
+    }
+
+    /// Colour of a segment of the output of a command.
+    pub fn style(&self, style: Style) -> Rgb {
+        match style {
+            Style::Text => self.text,
+            Style::Path => self.success,
+            Style::Locked | Style::Error => self.error,
+            Style::Hint => self.dim,
+            Style::Heading => self.accent,
+        }
+    }
+
+    /// Reads a theme file, see the explanation above.
+    pub fn from_file(path: &Path) -> Result<Self, String> {
+        let contents = fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read {}: {e}", path.display()))?;
+        let table: toml::Table =
+            toml::from_str(&contents).map_err(|e| format!("Invalid {}: {e}", path.display()))?;
+        let has_name = table.contains_key("name");
+        let mut theme: Theme = table
+            .try_into()
+            .map_err(|e| format!("Invalid {}: {e}", path.display()))?;
+        if !has_name {
+            theme.name = path
+                .file_stem()
+                .map(|s| s.to_string_lossy().into_owned())
+                .unwrap_or_default();
+        }
+        Ok(theme)
+    }
+}
+
+pub fn get_themes_dir() -> PathBuf {
+    config::get_config_path().with_file_name("themes")
+}
+
+/// Built-in themes, followed by the themes in the directory. Invalid theme files are skipped.
+pub fn load_themes(themes_dir: &Path) -> Vec<Theme> {
+    let mut themes = vec![Theme::dark(), Theme::light(), Theme::high_contrast()];
+    let Ok(entries) = fs::read_dir(themes_dir) else {
+        return themes;
+    };
+    let mut paths: Vec<PathBuf> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "toml"))
+        .collect();
+    paths.sort();
+    for path in paths {
+        match Theme::from_file(&path) {
+            Ok(theme) => match themes.iter_mut().find(|t| t.name == theme.name) {
+                Some(existing) => *existing = theme,
+                None => themes.push(theme),
+            },
+            Err(e) => log::log_warning("Theme", &e),
+        }
+    }
+    themes
+}
+
+/// All the themes which can be picked in the settings.
+pub fn available_themes() -> Vec<Theme> {
+    load_themes(&get_themes_dir())
+}
+
+static CURRENT_THEME: RwLock<Option<Theme>> = RwLock::new(None);
+
+/// Theme to draw with, the one saved in the config until another one is picked.
+pub fn current() -> Theme {
+    if let Some(theme) = CURRENT_THEME.read().ok().and_then(|t| t.clone()) {
+        return theme;
+    }
+    let name = config::load_config().theme;
+    let theme = available_themes()
+        .into_iter()
+        .find(|t| t.name == name)
+        .unwrap_or_default();
+    set_current(theme.clone());
+    theme
+}
+
+/// Switches the theme of every screen, without saving it.
+pub fn set_current(theme: Theme) {
+    if let Ok(mut current) = CURRENT_THEME.write() {
+        *current = Some(theme);
+    }
+}
@@ -0,0 +1,154 @@
+use chrono::Utc;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+/// Failed attempts of something, like unlocking an object or logging in.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct AttemptCounter {
+    pub failures: u32,
+    /// Unix timestamp in seconds of the last failure
+    pub last_failure: i64,
+}
+
+/// Exponential backoff, the wait doubles with every failure after the free attempts.
+pub struct BackoffPolicy {
+    pub free_attempts: u32,
+    pub base_secs: i64,
+    pub max_secs: i64,
+    /// Failures are forgotten after this long without another one
+    pub forget_secs: i64,
+}
+
+/// Flags are short, so only a few tries are allowed before the wait starts growing.
+pub const UNLOCK_BACKOFF: BackoffPolicy = BackoffPolicy {
+    free_attempts: 3,
+    base_secs: 5,
+    max_secs: 60 * 60,
+    forget_secs: 24 * 60 * 60,
+};
+
+pub const LOGIN_BACKOFF: BackoffPolicy = BackoffPolicy {
+    free_attempts: 5,
+    base_secs: 2,
+    max_secs: 15 * 60,
+    forget_secs: 60 * 60,
+};
+
+/// Failed login attempts, by `ip:<addr>` and `user:<name>`. Kept in memory by the server.
+static LOGIN_ATTEMPTS: Lazy<Mutex<HashMap<String, AttemptCounter>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+impl BackoffPolicy {
+    /// Delay required after the given number of failures.
+    pub fn delay(&self, failures: u32) -> i64 {
+        if failures < self.free_attempts {
+            return 0;
+        }
+        let exp = (failures - self.free_attempts).min(30);
+        self.base_secs
+            .saturating_mul(1i64 << exp)
+            .min(self.max_secs)
+    }
+
+    /// The counter without the failures forgotten by now.
+    fn current(&self, counter: &AttemptCounter, now: i64) -> AttemptCounter {
+        if now - counter.last_failure >= self.forget_secs {
+            AttemptCounter::default()
+        } else {
+            *counter
+        }
+    }
+
+    /// Seconds left before another attempt is allowed, 0 if allowed now.
+    pub fn wait(&self, counter: &AttemptCounter, now: i64) -> i64 {
+        let counter = self.current(counter, now);
+        let ready_at = counter.last_failure + self.delay(counter.failures);
+        (ready_at - now).max(0)
+    }
+
+    /// Counts an attempt as failed if it is allowed now, until it is known to have succeeded.
+    /// Checking and counting at once keeps attempts made at the same time from all passing.
+    /// Returns the seconds left before another attempt is allowed otherwise.
+    pub fn attempt(&self, counter: &mut AttemptCounter, now: i64) -> Result<(), i64> {
+        let wait = self.wait(counter, now);
+        if wait > 0 {
+            return Err(wait);
+        }
+        *counter = self.current(counter, now);
+        counter.fail(now);
+        Ok(())
+    }
+}
+
+impl AttemptCounter {
+    pub fn fail(&mut self, now: i64) {
+        self.failures = self.failures.saturating_add(1);
+        self.last_failure = now;
+    }
+}
+
+/// Keys for throttling the login of a user from an address.
+pub fn login_keys(ip: Option<&str>, username: &str) -> Vec<String> {
+    let mut keys = vec![format!("user:{username}")];
+    if let Some(ip) = ip {
+        keys.push(format!("ip:{ip}"));
+    }
+    keys
+}
+
+/// Counts a login with the keys, if none of them has to wait. It counts as failed until
+/// `login_succeeded`. Returns the seconds left to wait otherwise.
+pub fn login_attempt(keys: &[String]) -> Result<(), i64> {
+    let now = Utc::now().timestamp();
+    let mut attempts = LOGIN_ATTEMPTS.lock().unwrap();
+    let wait = keys
+        .iter()
+        .filter_map(|k| attempts.get(k))
+        .map(|c| LOGIN_BACKOFF.wait(c, now))
+        .max()
+        .unwrap_or(0);
+    if wait > 0 {
+        return Err(wait);
+    }
+    for key in keys {
+        LOGIN_BACKOFF.attempt(attempts.entry(key.clone()).or_default(), now)?;
+    }
+    Ok(())
+}
+
+/// Resets the user's counter, and takes the attempt back from the address. The address keeps
+/// its other failures until they are forgotten, so logging into another account does not reset
+/// the throttle for guessing passwords.
+pub fn login_succeeded(keys: &[String]) {
+    let mut attempts = LOGIN_ATTEMPTS.lock().unwrap();
+    for key in keys {
+        if key.starts_with("user:") {
+            attempts.remove(key);
+        } else if let Some(counter) = attempts.get_mut(key) {
+            counter.failures = counter.failures.saturating_sub(1);
+        }
+    }
+}
+
+/// Every new account counts, not only failures, so a few are allowed before the wait starts.
+pub const SIGNUP_BACKOFF: BackoffPolicy = BackoffPolicy {
+    free_attempts: 5,
+    base_secs: 60,
+    max_secs: 60 * 60,
+    forget_secs: 24 * 60 * 60,
+};
+
+/// Accounts created, by `ip:<addr>`. Kept in memory by the server.
+static SIGNUPS: Lazy<Mutex<HashMap<String, AttemptCounter>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Counts a new account from the address, if it does not have to wait.
+/// Returns the seconds left to wait otherwise.
+pub fn try_signup(ip: Option<&str>) -> Result<(), i64> {
+    let now = Utc::now().timestamp();
+    let key = format!("ip:{}", ip.unwrap_or("unknown"));
+    let mut signups = SIGNUPS.lock().unwrap();
+    SIGNUP_BACKOFF.attempt(signups.entry(key).or_default(), now)
+}
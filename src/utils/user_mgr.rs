@@ -0,0 +1,234 @@
+use super::auth::{User, hash_password};
+use data_encoding::{BASE32_NOPAD, HEXUPPER};
+use ring::rand::{SecureRandom, SystemRandom};
+
+/// Length of the random bytes used for generated passwords.
+const GENERATED_PASSWORD_BYTES: usize = 10;
+/// Header used for CSV import and export of users.
+pub const CSV_HEADER: &str = "username,password";
+pub const CSV_EXPORT_HEADER: &str = "username,salt,password_hash";
+
+/// A user read from a CSV file by `parse_users_csv`.
+#[derive(Debug, PartialEq)]
+pub enum CsvUser {
+    /// A `username[,password]` line, the password is generated if not given
+    Plain(String, Option<String>),
+    /// A line of `export_users_csv`, the password stays hashed
+    Hashed {
+        username: String,
+        salt: String,
+        password_hash: String,
+    },
+}
+
+impl CsvUser {
+    pub fn username(&self) -> &str {
+        match self {
+            CsvUser::Plain(username, _) | CsvUser::Hashed { username, .. } => username,
+        }
+    }
+}
+
+/// Checks if the username is acceptable for the user database.
+pub fn validate_username(username: &str) -> Result<(), String> {
+    if username.is_empty() {
+        return Err("Username cannot be empty".to_string());
+    }
+    if username
+        .chars()
+        .any(|c| c.is_whitespace() || c == ',' || c.is_control())
+    {
+        return Err(format!(
+            "Invalid username '{username}': whitespace and commas are not allowed"
+        ));
+    }
+    Ok(())
+}
+
+/// Checks if the password is acceptable for an account.
+pub fn validate_password(password: &str) -> Result<(), String> {
+    if password.is_empty() {
+        return Err("Password cannot be empty".to_string());
+    }
+    Ok(())
+}
+
+/// Generates a random password to be handed out to a player.
+pub fn generate_password() -> String {
+    let mut bytes = [0u8; GENERATED_PASSWORD_BYTES];
+    SystemRandom::new()
+        .fill(&mut bytes)
+        .expect("Failed to generate random bytes");
+    BASE32_NOPAD.encode(&bytes).to_lowercase()
+}
+
+/// Adds a new user with the given password to the list of users.
+pub fn add_user(users: &mut Vec<User>, username: &str, password: &str) -> Result<(), String> {
+    validate_username(username)?;
+    validate_password(password)?;
+    if users.iter().any(|u| u.username == username) {
+        return Err(format!("User '{username}' already exists"));
+    }
+    let (salt, password_hash) =
+        hash_password(password).map_err(|_| "Failed to hash password".to_string())?;
+    users.push(User {
+        username: username.to_string(),
+        salt,
+        password_hash,
+        guest: false,
+        flag_name: None,
+        created: None,
+        must_change_password: false,
+    });
+    Ok(())
+}
+
+/// Removes the user from the list of users.
+pub fn remove_user(users: &mut Vec<User>, username: &str) -> Result<(), String> {
+    let count = users.len();
+    users.retain(|u| u.username != username);
+    if users.len() == count {
+        return Err(format!("User '{username}' does not exist"));
+    }
+    Ok(())
+}
+
+/// Changes the password of an existing user, generating a new salt. The user no longer has to
+/// change it.
+pub fn set_user_password(users: &mut [User], username: &str, password: &str) -> Result<(), String> {
+    validate_password(password)?;
+    let user = users
+        .iter_mut()
+        .find(|u| u.username == username)
+        .ok_or_else(|| format!("User '{username}' does not exist"))?;
+    if user.guest {
+        return Err(format!(
+            "User '{username}' is a guest, upgrade it to keep its progress instead"
+        ));
+    }
+    let (salt, password_hash) =
+        hash_password(password).map_err(|_| "Failed to hash password".to_string())?;
+    user.salt = salt;
+    user.password_hash = password_hash;
+    user.must_change_password = false;
+    Ok(())
+}
+
+/// Parses a CSV of users, one `username[,password]` per line, or the output of
+/// `export_users_csv` if it starts with its header.
+/// The header line, empty lines and lines starting with `#` are skipped.
+pub fn parse_users_csv(content: &str) -> Result<Vec<CsvUser>, String> {
+    let exported = content.lines().next().map(str::trim) == Some(CSV_EXPORT_HEADER);
+    let mut entries: Vec<CsvUser> = Vec::new();
+    for (line_no, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if line_no == 0 && (exported || line == CSV_HEADER || line == "username") {
+            continue;
+        }
+        let entry = if exported {
+            parse_exported_line(line)
+        } else {
+            let mut fields = line.splitn(2, ',').map(str::trim);
+            let username = fields.next().unwrap_or_default().to_string();
+            let password = fields
+                .next()
+                .filter(|p| !p.is_empty())
+                .map(|p| p.to_string());
+            Ok(CsvUser::Plain(username, password))
+        }
+        .map_err(|e| format!("Line {}: {e}", line_no + 1))?;
+        let username = entry.username();
+        validate_username(username).map_err(|e| format!("Line {}: {e}", line_no + 1))?;
+        if entries.iter().any(|u| u.username() == username) {
+            return Err(format!(
+                "Line {}: Duplicate username '{username}'",
+                line_no + 1
+            ));
+        }
+        entries.push(entry);
+    }
+    Ok(entries)
+}
+
+/// Parses a `username,salt,password_hash` line of an export.
+fn parse_exported_line(line: &str) -> Result<CsvUser, String> {
+    let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+    let [username, salt, password_hash] = fields[..] else {
+        return Err(format!("Expected {CSV_EXPORT_HEADER}"));
+    };
+    if salt.is_empty() || HEXUPPER.decode(password_hash.as_bytes()).is_err() {
+        return Err(format!("Invalid salt or password hash for '{username}'"));
+    }
+    Ok(CsvUser::Hashed {
+        username: username.to_string(),
+        salt: salt.to_string(),
+        password_hash: password_hash.to_string(),
+    })
+}
+
+/// Imports the parsed entries into the list of users. Nothing is added if any user already exists.
+/// Users given in plain must change their password when they first log in.
+/// Returns the credentials of users whose password was generated, for distribution.
+pub fn import_users(
+    users: &mut Vec<User>,
+    entries: Vec<CsvUser>,
+) -> Result<Vec<(String, String)>, String> {
+    let existing: Vec<&str> = entries
+        .iter()
+        .map(CsvUser::username)
+        .filter(|name| users.iter().any(|u| u.username == *name))
+        .collect();
+    if !existing.is_empty() {
+        return Err(format!("User(s) already exist: {}", existing.join(", ")));
+    }
+
+    let mut generated = Vec::new();
+    for entry in entries {
+        match entry {
+            CsvUser::Plain(username, password) => {
+                let password = match password {
+                    Some(password) => password,
+                    None => {
+                        let password = generate_password();
+                        generated.push((username.clone(), password.clone()));
+                        password
+                    }
+                };
+                add_user(users, &username, &password)?;
+                if let Some(user) = users.last_mut() {
+                    user.must_change_password = true;
+                }
+            }
+            CsvUser::Hashed {
+                username,
+                salt,
+                password_hash,
+            } => users.push(User {
+                username,
+                salt,
+                password_hash,
+                guest: false,
+                flag_name: None,
+                created: None,
+                must_change_password: false,
+            }),
+        }
+    }
+    Ok(generated)
+}
+
+/// Exports the users as CSV, without any plaintext passwords. `parse_users_csv` reads it back.
+pub fn export_users_csv(users: &[User]) -> String {
+    let mut out = String::from(CSV_EXPORT_HEADER);
+    out.push('\n');
+    for user in users {
+        out.push_str(&format!(
+            "{},{},{}\n",
+            user.username, user.salt, user.password_hash
+        ));
+    }
+    out
+}
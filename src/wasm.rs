@@ -0,0 +1,75 @@
+use crate::commands::cmds::CommandResponse;
+use crate::metainfo::valid_sekai::sekai_initialize;
+use crate::sekai_fs::MemFs;
+use crate::session::{Sekai, Session};
+use crate::utils::globals::UserInfo;
+use crate::utils::progress::{self, ProgressData};
+use std::path::Path;
+use std::sync::Arc;
+use wasm_bindgen::prelude::*;
+
+/*
+WASM EXPLANATION:
+
+With the `wasm` feature, a wasm32 build of the engine plays a Sekai in the browser, without a
+server. `static/engine.js` uses it when `config.js` has no `BACKEND_URL`:
+- The page downloads a Deemak file, or the player picks one, and opens it with `BrowserSession`.
+  It is decrypted into a `MemFs`, as the desktop does, so saving and restoring stay in memory.
+- Commands give the same JSON as `/backend/run`, and `asset` the bytes `/backend/asset` would.
+- There are no accounts, the player is a local guest with the name the page gives. Their progress
+  is kept in memory, the page stores it in localStorage with `progress` and `load_progress`.
+*/
+
+/// A Sekai played in the browser, see the explanation above.
+#[wasm_bindgen]
+pub struct BrowserSession {
+    session: Session,
+}
+
+#[wasm_bindgen]
+impl BrowserSession {
+    /// Opens the contents of the Deemak file with the name, played by the player.
+    #[wasm_bindgen(constructor)]
+    pub fn new(name: &str, data: &[u8], player: &str) -> Result<BrowserSession, String> {
+        let deemak_path = Path::new(name);
+        let root = MemFs::root_for(deemak_path);
+        let fs = MemFs::open_deemak_bytes(data, deemak_path, &root)?;
+        let sekai = Sekai::new(Arc::new(fs), root);
+        sekai_initialize(sekai.fs.as_ref(), &sekai.root);
+
+        let mut user = UserInfo::new(player.to_string(), String::new(), String::new());
+        user.is_guest = true;
+        let mut session = Session::new(sekai);
+        session.user = Some(user);
+        Ok(BrowserSession { session })
+    }
+
+    /// Runs the command line, returning the JSON of `CommandResponse`.
+    pub fn run(&mut self, command: &str) -> String {
+        let response = CommandResponse::from(self.session.run(command));
+        serde_json::to_string(&response).unwrap_or_default()
+    }
+
+    /// Contents of the image or audio object shown by `read`.
+    pub fn asset(&self, path: &str) -> Result<Vec<u8>, String> {
+        self.session.read_asset(path).map(|(_, bytes)| bytes)
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn current_dir(&self) -> String {
+        self.session.current_dir.display().to_string()
+    }
+}
+
+/// Progress of the players, as JSON to keep in localStorage.
+#[wasm_bindgen]
+pub fn progress() -> String {
+    serde_json::to_string(&progress::load_progress()).unwrap_or_default()
+}
+
+/// Restores the progress kept by `progress`. Invalid JSON starts over.
+#[wasm_bindgen]
+pub fn load_progress(json: &str) {
+    let data: ProgressData = serde_json::from_str(json).unwrap_or_default();
+    let _ = progress::save_progress(&data);
+}
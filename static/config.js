@@ -1 +0,0 @@
-export const BACKEND_URL = "http://localhost:8001";
\ No newline at end of file
@@ -0,0 +1,50 @@
+// engine.js
+// Runs commands on the server, or in the browser with the WebAssembly build of the engine when
+// config.js has no BACKEND_URL. See `src/wasm.rs` and the README for the browser build.
+let wasm    = null;
+let browser = null;
+
+export function isBrowserOnly() {
+  return !window.BACKEND_URL;
+}
+
+// Opens the Deemak file in the browser, with the progress kept in localStorage.
+export async function openSekai(name, bytes) {
+  if (!wasm) {
+    wasm = await import('./pkg/deemak.js');
+    await wasm.default();
+  }
+  wasm.load_progress(localStorage.getItem('progress') || '');
+  const player = localStorage.getItem('player') || 'player';
+  browser = new wasm.BrowserSession(name, new Uint8Array(bytes), player);
+}
+
+export function isSekaiOpen() {
+  return browser !== null;
+}
+
+// Result of the command, as `/backend/run` gives it.
+export async function runCommand(command, currentDir) {
+  if (!isBrowserOnly()) {
+    const response = await fetch(
+      `${window.BACKEND_URL}/backend/run?command=${encodeURIComponent(command)}&current_dir=${encodeURIComponent(currentDir)}&token=${encodeURIComponent(localStorage.getItem('token') || '')}`
+    );
+    return response.json();
+  }
+  const result = JSON.parse(browser.run(command));
+  localStorage.setItem('progress', wasm.progress());
+  return result;
+}
+
+// URL of an image or audio object opened by `read`.
+export function assetUrl(asset) {
+  if (!isBrowserOnly()) {
+    return `${window.BACKEND_URL}/backend/asset?path=${encodeURIComponent(asset.path)}&token=${encodeURIComponent(localStorage.getItem('token') || '')}`;
+  }
+  try {
+    return URL.createObjectURL(new Blob([browser.asset(asset.path)], { type: asset.mime }));
+  } catch (error) {
+    console.error("Asset error:", error);
+    return '';
+  }
+}
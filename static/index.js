@@ -1,4 +1,6 @@
 // index.js
+import { assetUrl, isBrowserOnly, isSekaiOpen, openSekai, runCommand } from './engine.js';
+
 const menuContainer      = document.getElementById('menu_container');
 const terminalContainer  = document.getElementById('terminal_container');
 const authContainer      = document.getElementById('auth_container');
@@ -9,6 +11,12 @@ const terminal           = document.getElementById('terminal');
 const loading            = document.getElementById('loading');
 const loginMessage       = document.getElementById('login_message');
 const registerMessage    = document.getElementById('register_message');
+const upgradeContainer   = document.getElementById('upgrade_container');
+const upgradeMessage     = document.getElementById('upgrade_message');
+const upgradeMenuItem    = document.getElementById('upgrade_menu_item');
+const logoutMenuItem     = document.getElementById('logout_menu_item');
+const sekaiContainer     = document.getElementById('sekai_container');
+const sekaiMessage       = document.getElementById('sekai_message');
 
 let authenticated           = false;
 let registered              = false;
@@ -19,6 +27,10 @@ let commandHistory = [];
 let historyIndex   = -1;
 
 async function checkSessionOnLoad() {
+  if (isBrowserOnly()) {
+    await showSekaiPicker();
+    return;
+  }
   const token = localStorage.getItem('token')
   if (token) {
     await verifySession(token);
@@ -39,9 +51,12 @@ async function verifySession(existingToken) {
     const tokenResult = await tokenResponse.json();
 
     if (tokenResult.status) {
+      if (tokenResult.token) {
+        localStorage.setItem('token', tokenResult.token);
+      }
       authenticated = true;
       authContainer.style.display = "none";
-      menuContainer.style.display = "flex";
+      showMenu();
     } else {
       localStorage.removeItem('token');
       showAuthScreen();
@@ -56,21 +71,73 @@ async function verifySession(existingToken) {
 }
 
 
+// Without a server there are no accounts, the player opens the Sekai of the page or picks one.
+async function showSekaiPicker() {
+  if (window.SEKAI_URL) {
+    loading.style.display = "flex";
+    try {
+      const response = await fetch(window.SEKAI_URL);
+      if (!response.ok) throw new Error(`${response.status} ${response.statusText}`);
+      await openSekai(window.SEKAI_URL.split('/').pop(), await response.arrayBuffer());
+      showMenu();
+      return;
+    } catch (error) {
+      console.error("Sekai download error:", error);
+      sekaiMessage.textContent = "Could not download the Sekai, please pick the file.";
+    } finally {
+      loading.style.display = "none";
+    }
+  }
+  document.getElementById('player_name_input').value = localStorage.getItem('player') || '';
+  authContainer.style.display     = "flex";
+  loginContainer.style.display    = "none";
+  registerContainer.style.display = "none";
+  upgradeContainer.style.display  = "none";
+  sekaiContainer.style.display    = "flex";
+}
+
+async function openPickedSekai() {
+  const player = document.getElementById('player_name_input').value.trim();
+  const file   = document.getElementById('sekai_file_input').files[0];
+  if (!file) {
+    sekaiMessage.textContent = 'Please pick a Deemak file.';
+    return;
+  }
+  if (player) localStorage.setItem('player', player);
+
+  loading.style.display = "flex";
+  try {
+    await openSekai(file.name, await file.arrayBuffer());
+    authContainer.style.display  = "none";
+    sekaiContainer.style.display = "none";
+    showMenu();
+  } catch (error) {
+    console.error("Sekai open error:", error);
+    sekaiMessage.textContent = `Could not open the Sekai: ${error}`;
+  } finally {
+    loading.style.display = "none";
+  }
+}
+
 function showAuthScreen(errorMsg = "") {
   authenticated = false;
   registered    = false;
   authContainer.style.display     = "flex";
   loginContainer.style.display    = "flex";
   registerContainer.style.display = "none";
+  upgradeContainer.style.display  = "none";
   if (errorMsg) loginMessage.textContent = errorMsg;
 }
 
 async function login() {
-  const usernameInput = document.getElementById('login_username_input');
-  const passwordInput = document.getElementById('login_password_input');
+  const usernameInput    = document.getElementById('login_username_input');
+  const passwordInput    = document.getElementById('login_password_input');
+  const newPasswordBox   = document.getElementById('login_new_password');
+  const newPasswordInput = document.getElementById('login_new_password_input');
 
-  const username = usernameInput.value.trim();
-  const password = passwordInput.value.trim();
+  const username    = usernameInput.value.trim();
+  const password    = passwordInput.value.trim();
+  const newPassword = newPasswordInput.value.trim();
 
   if (!username || !password) {
     loginMessage.textContent = 'Please enter both username and password.';
@@ -85,18 +152,24 @@ async function login() {
       method: 'POST',
       headers: { 'Content-Type': 'application/x-www-form-urlencoded' },
       body: `username=${encodeURIComponent(username)}&password=${encodeURIComponent(password)}`
+        + (newPassword ? `&new_password=${encodeURIComponent(newPassword)}` : '')
     });
     const result = await response.json();
 
     if (result.status) {
       authenticated = true;
       localStorage.setItem('token', result.token);
+      localStorage.removeItem('guest');
       loginMessage.textContent = '';
       usernameInput.value = '';
       passwordInput.value = '';
+      newPasswordInput.value = '';
+      newPasswordBox.style.display = "none";
       authContainer.style.display = "none";
-      menuContainer.style.display = "flex";
+      showMenu();
     } else {
+      // Passwords handed out by the admin must be changed before playing
+      newPasswordBox.style.display = result.must_change_password ? "block" : "none";
       loginMessage.textContent = result.message;
       showAuthScreen();
     }
@@ -133,12 +206,13 @@ async function register() {
 
     if (result.status) {
       localStorage.setItem('token', result.token);
+      localStorage.removeItem('guest');
       authenticated = true;
       registerMessage.textContent = '';
       usernameInput.value = '';
       passwordInput.value = '';
       authContainer.style.display = "none";
-      menuContainer.style.display = "flex";
+      showMenu();
     } else {
       registerMessage.textContent = result.message;
       authContainer.style.display = "flex";
@@ -153,8 +227,136 @@ async function register() {
     loading.style.display = "none";
   }
 }
+async function playAsGuest() {
+  loading.style.display = "flex";
+  authContainer.style.display = "none";
+
+  try {
+    const response = await fetch(`${window.BACKEND_URL}/backend/guest`, { method: 'POST' });
+    const result = await response.json();
+
+    if (result.status) {
+      authenticated = true;
+      localStorage.setItem('token', result.token);
+      localStorage.setItem('guest', '1');
+      loginMessage.textContent = '';
+      showMenu();
+    } else {
+      showAuthScreen(result.message);
+    }
+  } catch (error) {
+    console.error("Guest login error:", error);
+    showAuthScreen("Server error. Please try again.");
+  } finally {
+    loading.style.display = "none";
+  }
+}
+
+function showUpgrade() {
+  menuContainer.style.display     = "none";
+  authContainer.style.display     = "flex";
+  loginContainer.style.display    = "none";
+  registerContainer.style.display = "none";
+  upgradeContainer.style.display  = "flex";
+  upgradeMessage.textContent      = '';
+}
+
+async function upgradeGuest() {
+  const usernameInput = document.getElementById('upgrade_username_input');
+  const passwordInput = document.getElementById('upgrade_password_input');
+
+  const username = usernameInput.value.trim();
+  const password = passwordInput.value.trim();
+
+  if (!password) {
+    upgradeMessage.textContent = 'Please enter a password.';
+    return;
+  }
+
+  loading.style.display = "flex";
+  try {
+    const token = localStorage.getItem('token') || '';
+    const response = await fetch(`${window.BACKEND_URL}/backend/upgrade`, {
+      method: 'POST',
+      headers: { 'Content-Type': 'application/x-www-form-urlencoded' },
+      body: `token=${encodeURIComponent(token)}&username=${encodeURIComponent(username)}&password=${encodeURIComponent(password)}`
+    });
+    const result = await response.json();
+
+    if (result.status) {
+      localStorage.setItem('token', result.token);
+      localStorage.removeItem('guest');
+      usernameInput.value = '';
+      passwordInput.value = '';
+      backToMenu();
+    } else {
+      upgradeMessage.textContent = result.message;
+    }
+  } catch (error) {
+    console.error("Upgrade error:", error);
+    upgradeMessage.textContent = "Server error. Please try again.";
+  } finally {
+    loading.style.display = "none";
+  }
+}
+
+let eventCountdown = null;
+
+function showMenu() {
+  upgradeMenuItem.style.display = localStorage.getItem('guest') && !isBrowserOnly() ? "block" : "none";
+  logoutMenuItem.style.display  = isBrowserOnly() ? "none" : "block";
+  menuContainer.style.display   = "flex";
+  checkEventStatus();
+}
+
+function formatCountdown(secs) {
+  const days  = Math.floor(secs / 86400);
+  const clock = [Math.floor(secs % 86400 / 3600), Math.floor(secs % 3600 / 60), secs % 60]
+    .map(n => String(n).padStart(2, '0')).join(':');
+  return days > 0 ? `${days}d ${clock}` : clock;
+}
+
+// Shows a countdown before a timed event starts, and disables the shell until then.
+async function checkEventStatus() {
+  const eventStatus = document.getElementById('event_status');
+  const startButton = document.getElementById('start_shell_button');
+  clearInterval(eventCountdown);
+  eventStatus.textContent = '';
+  startButton.disabled = false;
+  // The commands check the event themselves in the browser
+  if (isBrowserOnly()) return;
+
+  try {
+    const response = await fetch(`${window.BACKEND_URL}/backend/event`);
+    const result = await response.json();
+
+    if (result.status === "not_started") {
+      let remaining = result.seconds_to_start;
+      startButton.disabled = true;
+      eventStatus.textContent = `The event starts in ${formatCountdown(remaining)}`;
+      eventCountdown = setInterval(() => {
+        remaining -= 1;
+        if (remaining <= 0) {
+          clearInterval(eventCountdown);
+          checkEventStatus();
+        } else {
+          eventStatus.textContent = `The event starts in ${formatCountdown(remaining)}`;
+        }
+      }, 1000);
+    } else if (result.status === "ended") {
+      eventStatus.textContent = "The event has ended. The Sekai is read-only now.";
+    } else if (result.status === "closed") {
+      startButton.disabled = true;
+      eventStatus.textContent = "The event policy of this Sekai is broken, it cannot be played.";
+    }
+  } catch (error) {
+    console.error("Event status error:", error);
+  }
+}
+
 function logout() {
   localStorage.removeItem('token');
+  localStorage.removeItem('guest');
   authenticated = false
   showAuthScreen();
   terminalContainer.style.display  = "none";
@@ -228,7 +430,9 @@ function about() {
 function backToMenu() {
   aboutContainer.style.display    = "none";
   terminalContainer.style.display = "none";
-  menuContainer.style.display     = "flex";
+  authContainer.style.display     = "none";
+  upgradeContainer.style.display  = "none";
+  showMenu();
   clearTerminal();
 }
 
@@ -284,23 +488,56 @@ async function processCommand(input) {
   }
 
   try {
-    const response = await fetch(
-      `${window.BACKEND_URL}/backend/run?command=${encodeURIComponent(command)}&current_dir=${encodeURIComponent(currentDir)}`
-    );
-    const result = await response.json();
-    output.innerText = result.output;
+    const result = await runCommand(command, currentDir);
+    showOutput(output, result);
+    if (result.asset) {
+      output.appendChild(assetElement(result.asset));
+    }
     if (result.new_current_dir) {
       currentDir = result.new_current_dir;
     }
   } catch (error) {
     console.error("Error:", error);
-    output.innerText = "Error: Could not reach server.";
+    output.innerText = !isBrowserOnly()
+      ? "Error: Could not reach server."
+      : isSekaiOpen() ? `Error: ${error}` : "Error: No Sekai is open.";
   }
 
   terminal.appendChild(output);
   addNewInput();
 }
 
+// Shows the output of the command with a span for each styled segment, see
+// `src/commands/output.rs`. The text of failed commands is shown as an error.
+function showOutput(output, result) {
+  if (result.error) {
+    output.classList.add("error");
+  }
+  const segments = result.segments || [{ style: "text", text: result.output }];
+  for (const segment of segments) {
+    const span = document.createElement('span');
+    span.classList.add(`segment_${segment.style}`);
+    span.textContent = segment.text;
+    output.appendChild(span);
+  }
+}
+
+// Shows an image or audio object opened by `read` inline.
+function assetElement(asset) {
+  const url = assetUrl(asset);
+  let element;
+  if (asset.mime.startsWith('image/')) {
+    element = document.createElement('img');
+    element.alt = asset.path.split('/').pop();
+  } else {
+    element = document.createElement('audio');
+    element.controls = true;
+  }
+  element.src = url;
+  element.classList.add('asset');
+  return element;
+}
+
 function addNewInput() {
   const line = document.createElement('div');
   line.className = 'terminal_line';
@@ -349,4 +586,8 @@ window.showRegister   = showRegister;
 window.showLogin      = showLogin;
 window.login          = login;
 window.register       = register;
-window.logout         = logout;
\ No newline at end of file
+window.playAsGuest    = playAsGuest;
+window.showUpgrade    = showUpgrade;
+window.upgradeGuest   = upgradeGuest;
+window.logout         = logout;
+window.openPickedSekai = openPickedSekai;
\ No newline at end of file